@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
+use std::fmt;
+
 #[test]
 fn indice() {
     value_ownership::value_ownership();
@@ -42,11 +44,34 @@ RELATED ENTITIES: id vs value vs Rc vs Arc
 struct Product {
     id: u64,
     name: String,
-    price: f64,
+    price: Cents, // Never f64 for money: see ecs_contiguous_demo
+}
+
+// Just enough money for the demos: whole cents in an i64, always USD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cents(i64);
+
+impl Cents {
+    // basis_points / 10_000 of the amount, rounding half a cent up
+    fn percent(self, basis_points: i64) -> Self {
+        Cents((self.0 * basis_points + 5_000).div_euclid(10_000))
+    }
+}
+
+impl fmt::Display for Cents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02} USD", self.0 / 100, self.0 % 100)
+    }
+}
+
+// Helper for the demos: usd("999.99")
+fn usd(amount: &str) -> Cents {
+    let (whole, cents) = amount.split_once('.').unwrap_or((amount, "00"));
+    Cents(whole.parse::<i64>().unwrap() * 100 + cents.parse::<i64>().unwrap())
 }
 
 impl Product {
-    fn new(id: u64, name: &str, price: f64) -> Self {
+    fn new(id: u64, name: &str, price: Cents) -> Self {
         Product {
             id,
             name: name.to_string(),
@@ -90,7 +115,7 @@ VALUE_OWNERSHIP
 */
 #[cfg(test)]
 mod value_ownership {
-    use super::Product;
+    use super::{Product, usd};
 
     #[derive(Debug, Clone)]
     pub struct CartItem {
@@ -117,7 +142,7 @@ mod value_ownership {
 
     #[test]
     pub fn value_ownership() {
-        let product = Product::new(1, "Laptop", 999.99);
+        let product = Product::new(1, "Laptop", usd("999.99"));
 
         // Each entity has its OWN COPY
         let cart = Cart {
@@ -194,7 +219,7 @@ ID_REFERENCE
 */
 #[cfg(test)]
 mod id_reference {
-    use super::Product;
+    use super::{Cents, Product, usd};
     use std::collections::HashMap;
 
     #[derive(Debug, Clone)]
@@ -240,7 +265,7 @@ mod id_reference {
             self.products.get(&id)
         }
 
-        pub fn update_price(&mut self, id: u64, new_price: f64) {
+        pub fn update_price(&mut self, id: u64, new_price: Cents) {
             if let Some(product) = self.products.get_mut(&id) {
                 product.price = new_price;
             }
@@ -249,8 +274,8 @@ mod id_reference {
 
     #[test]
     pub fn id_reference() {
-        let product1 = Product::new(1, "Laptop", 999.99);
-        let product2 = Product::new(2, "Mouse", 29.99);
+        let product1 = Product::new(1, "Laptop", usd("999.99"));
+        let product2 = Product::new(2, "Mouse", usd("29.99"));
 
         let mut repo = ProductRepository::new();
         repo.add(product1.clone());
@@ -283,7 +308,7 @@ mod id_reference {
         for item in &cart.items {
             if let Some(product) = repo.get(item.product_id) {
                 println!(
-                    "    - {} x{} = ${:.2}",
+                    "    - {} x{} = {}",
                     product.name, item.quantity, product.price
                 );
             }
@@ -293,22 +318,19 @@ mod id_reference {
         for item in &order.items {
             if let Some(product) = repo.get(item.product_id) {
                 println!(
-                    "    - {} x{} = ${:.2}",
+                    "    - {} x{} = {}",
                     product.name, item.quantity, product.price
                 );
             }
         }
 
         // Changing price in repo affects everyone
-        println!("\n  → Updating Laptop price to $899.99...");
-        repo.update_price(1, 899.99);
+        println!("\n  → Updating Laptop price to 899.99 USD...");
+        repo.update_price(1, usd("899.99"));
 
         println!("  Now in Cart:");
         if let Some(product) = repo.get(1) {
-            println!(
-                "    - {} = ${:.2} (updated!)",
-                product.name, product.price
-            );
+            println!("    - {} = {} (updated!)", product.name, product.price);
         }
 
         println!("  ✅ id_reference::id_reference");
@@ -371,7 +393,7 @@ RC_SHARED
 */
 #[cfg(test)]
 mod rc_shared {
-    use super::Product;
+    use super::{Product, usd};
     use std::cell::RefCell;
     use std::rc::Rc;
 
@@ -401,7 +423,7 @@ mod rc_shared {
     #[test]
     pub fn rc_shared() {
         // Create product with Rc<RefCell<T>>
-        let product = Rc::new(RefCell::new(Product::new(1, "Laptop", 999.99)));
+        let product = Rc::new(RefCell::new(Product::new(1, "Laptop", usd("999.99"))));
 
         println!("  Rc strong_count initial: {}", Rc::strong_count(&product));
 
@@ -448,15 +470,12 @@ mod rc_shared {
         );
 
         // Modify from anywhere affects everyone
-        println!("\n  Original price: ${:.2}", product.borrow().price);
-        product.borrow_mut().price = 899.99;
-        println!("  Modified price: ${:.2}", product.borrow().price);
-        println!(
-            "  Seen from Cart: ${:.2}",
-            cart.items[0].product.borrow().price
-        );
+        println!("\n  Original price: {}", product.borrow().price);
+        product.borrow_mut().price = usd("899.99");
+        println!("  Modified price: {}", product.borrow().price);
+        println!("  Seen from Cart: {}", cart.items[0].product.borrow().price);
         println!(
-            "  Seen from Order: ${:.2}",
+            "  Seen from Order: {}",
             order.items[0].product.borrow().price
         );
 
@@ -519,7 +538,7 @@ ARC_SHARED
 */
 #[cfg(test)]
 mod arc_shared {
-    use super::Product;
+    use super::{Product, usd};
     use std::sync::{Arc, RwLock};
 
     #[derive(Debug, Clone)]
@@ -548,7 +567,7 @@ mod arc_shared {
     #[test]
     pub fn arc_shared() {
         // Create product with Arc<RwLock<T>>
-        let product = Arc::new(RwLock::new(Product::new(1, "Laptop", 999.99)));
+        let product = Arc::new(RwLock::new(Product::new(1, "Laptop", usd("999.99"))));
 
         println!(
             "  Arc strong_count initial: {}",
@@ -581,19 +600,16 @@ mod arc_shared {
         let handle = std::thread::spawn(move || {
             // Modify price from another thread
             let mut p = product_for_thread.write().unwrap();
-            p.price = 849.99;
-            println!("  [Thread] Price modified to: ${:.2}", p.price);
+            p.price = usd("849.99");
+            println!("  [Thread] Price modified to: {}", p.price);
         });
 
         handle.join().unwrap();
 
         // See change from main thread
+        println!("  [Main] Price seen: {}", product.read().unwrap().price);
         println!(
-            "  [Main] Price seen: ${:.2}",
-            product.read().unwrap().price
-        );
-        println!(
-            "  [Cart] Price seen: ${:.2}",
+            "  [Cart] Price seen: {}",
             cart.items[0].product.read().unwrap().price
         );
 
@@ -616,7 +632,7 @@ LIFETIMES_REF: REFERENCES WITH LIFETIMES (&'a T)
 
 #[cfg(test)]
 mod lifetimes_ref {
-    use super::Product;
+    use super::{Product, usd};
 
     pub struct CartItem<'a> {
         pub product: &'a Product,
@@ -625,7 +641,7 @@ mod lifetimes_ref {
 
     #[test]
     pub fn lifetimes_ref() {
-        let product = Product::new(1, "Laptop", 999.99);
+        let product = Product::new(1, "Laptop", usd("999.99"));
 
         // The CartItem only lives as long as 'product'
         let item = CartItem {
@@ -666,14 +682,14 @@ WEAK_REFERENCES: WEAK REFERENCES (Weak<T>)
 
 #[cfg(test)]
 mod weak_references {
-    use super::Product;
+    use super::{Product, usd};
     use std::cell::RefCell;
     use std::rc::{Rc, Weak};
 
     #[test]
     pub fn weak_references() {
         let product = Rc::new(RefCell::new(
-            Product::new(1, "Laptop", 999.99)
+            Product::new(1, "Laptop", usd("999.99"))
         ));
 
         // Create a weak reference
@@ -712,7 +728,7 @@ ARENA_ALLOCATION: ARENA ALLOCATION
 #[cfg(test)]
 mod arena_allocation {
     // Nota: En producción usarías crates como 'bumpalo' o 'typed-arena'
-    use super::Product;
+    use super::{Product, usd};
     use std::cell::RefCell;
 
     pub struct Arena {
@@ -742,8 +758,8 @@ mod arena_allocation {
         };
 
         // The arena owns the data
-        arena.alloc(Product::new(1, "Laptop", 999.99));
-        arena.alloc(Product::new(2, "Mouse", 25.00));
+        arena.alloc(Product::new(1, "Laptop", usd("999.99")));
+        arena.alloc(Product::new(2, "Mouse", usd("25.00")));
 
         arena.get_all();
         assert_eq!(arena.products.borrow().len(), 2);
@@ -858,12 +874,14 @@ mod ecs_pattern {
     */
 
     pub mod ecs_contiguous {
+        use super::super::{Cents, usd};
+
         // Instead of using object, one single World struct with Vecs for components
         // with data from all the entities
         pub struct World {
             // Each index is an entity. Option allows not all to have everything.
             names: Vec<Option<String>>,
-            prices: Vec<Option<f64>>,
+            prices: Vec<Option<Cents>>, // Cents is Copy (a bare i64): still contiguous
         }
 
         impl World {
@@ -874,7 +892,7 @@ mod ecs_pattern {
                 }
             }
 
-            fn spawn(&mut self, name: &str, price: f64) -> usize {
+            fn spawn(&mut self, name: &str, price: Cents) -> usize {
                 let id = self.names.len();
                 self.names.push(Some(name.to_string()));
                 self.prices.push(Some(price));
@@ -885,20 +903,20 @@ mod ecs_pattern {
         #[test]
         pub fn ecs_contiguous_demo() {
             let mut world = World::new();
-            world.spawn("Laptop", 999.99);
-            world.spawn("Mouse", 25.00);
-            world.spawn("Keyboard", 50.00);
+            world.spawn("Laptop", usd("999.99"));
+            world.spawn("Mouse", usd("25.00"));
+            world.spawn("Keyboard", usd("50.00"));
 
             // SYSTEM: Apply 10% discount to EVERYTHING
-            // The CPU flies here because it traverses a pure f64 array.
-            for price_opt in world.prices.iter_mut() {
-                if let Some(price) = price_opt {
-                    *price *= 0.9;
-                }
+            // The CPU flies here because it traverses a contiguous array.
+            // With f64 this gave 899.991 (not a real price); Cents rounds explicitly.
+            for price in world.prices.iter_mut().flatten() {
+                *price = price.percent(9000);
             }
 
             println!("  [ECS Contiguous] Prices updated in bulk.");
-            assert_eq!(world.prices[0], Some(899.991));
+            assert_eq!(world.prices[0], Some(usd("899.99")));
+            assert_eq!(world.prices[1], Some(usd("22.50")));
         }
     }
 
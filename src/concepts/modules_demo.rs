@@ -180,16 +180,30 @@ fn index() {
 
     // Create order using domain::order
     let mut order_service = domain::OrderService::new();
-    let items = vec![domain::OrderItem {
-        product_id: 101,
-        quantity: 2,
-        price: 25.50,
-    }];
+    let book = order_service
+        .catalog_mut()
+        .add_product(
+            "BOOK-101",
+            "Rust Book".to_string(),
+            "25.50 USD".parse().unwrap(),
+            10,
+        )
+        .unwrap();
+    let lines = vec![domain::OrderLine::new(book.id, 2)];
+
+    let order = order_service.create_order(user1.id, lines).unwrap();
+    println!("✓ Order created: ID={}, Total={}", order.id, order.total);
 
-    let order = order_service.create_order(user1.id, items).unwrap();
-    println!("✓ Order created: ID={}, Total=${:.2}", order.id, order.total);
+    // Payment: autoriza contra el total real de la orden y captura
+    let mut payment_service = domain::PaymentService::new();
+    let payment = payment_service
+        .authorize(&order_service, order.id, order.total)
+        .unwrap();
+    payment_service.capture(payment.id).unwrap();
+    let summary = payment_service.reconcile(&order_service, order.id).unwrap();
+    println!("✓ Payment captured: settled={}", summary.is_settled());
 
-    let all_users = user_service.get_all_users();
+    let all_users = user_service.get_all_users().unwrap();
     println!("\n📋 Total users: {}", all_users.len());
 
     // ============================================================
@@ -210,12 +224,12 @@ fn index() {
         .unwrap();
     println!("✓ Email updated");
 
-    let updated_user = hybrid_service.get_user(user3.id).unwrap();
+    let updated_user = hybrid_service.get_user(user3.id).unwrap().unwrap();
     println!("✓ User after update: {:?}", updated_user);
 
     println!(
         "\n📋 Total users (hybrid): {}",
-        hybrid_service.user_count()
+        hybrid_service.user_count().unwrap()
     );
 
     // ============================================================
@@ -0,0 +1,603 @@
+// Dominio: Cart
+// Carrito de compras: vive antes de la orden y se convierte en ella en checkout
+
+use super::order::{Order, OrderError, OrderLine, OrderRepository, OrderService};
+use super::product::{ProductError, ProductRepository, ProductService};
+use crate::modules_demo::shared::validation::at_least;
+use crate::modules_demo::shared::{
+    CartId, Entity, ErrorCode, ErrorKind, IdGenerator, InMemoryRepository, Money, MoneyError,
+    ProductId, RepoResult, Repository, RepositoryError, SequentialIdGenerator, Transactional,
+    UnitOfWork, UserId, ValidationErrors, Validator,
+};
+use thiserror::Error;
+
+// ============================================================
+// MODEL
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cart {
+    pub id: CartId,
+    /// None = carrito de invitado (sin login)
+    pub user_id: Option<UserId>,
+    pub items: Vec<CartItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartItem {
+    pub product_id: ProductId,
+    pub quantity: u32,
+    /// Precio de catálogo al momento de agregar (o del último reprice)
+    pub unit_price: Money,
+}
+
+/// Diferencia detectada entre el precio del carrito y el del catálogo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceChange {
+    pub product_id: ProductId,
+    pub old: Money,
+    pub new: Money,
+}
+
+impl Cart {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Total con los precios guardados en el carrito. None si está vacío.
+    pub fn total(&self) -> Result<Option<Money>, MoneyError> {
+        let Some(first) = self.items.first() else {
+            return Ok(None);
+        };
+
+        let subtotals = self
+            .items
+            .iter()
+            .map(|i| i.unit_price.checked_mul(i.quantity))
+            .collect::<Result<Vec<_>, _>>()?;
+        Money::sum(first.unit_price.currency(), subtotals).map(Some)
+    }
+
+    fn item_mut(&mut self, product_id: ProductId) -> Option<&mut CartItem> {
+        self.items.iter_mut().find(|i| i.product_id == product_id)
+    }
+
+    // Suma la cantidad si el producto ya está en el carrito
+    fn add(&mut self, product_id: ProductId, quantity: u32, unit_price: Money) {
+        match self.item_mut(product_id) {
+            Some(item) => item.quantity = item.quantity.saturating_add(quantity),
+            None => self.items.push(CartItem {
+                product_id,
+                quantity,
+                unit_price,
+            }),
+        }
+    }
+}
+
+impl Entity for Cart {
+    type Id = CartId;
+
+    fn id(&self) -> CartId {
+        self.id
+    }
+}
+
+// ============================================================
+// ERRORS
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CartError {
+    #[error("Cart {id} not found")]
+    NotFound { id: CartId },
+
+    #[error("Cart {id} is empty")]
+    EmptyCart { id: CartId },
+
+    #[error("Invalid cart item")]
+    Invalid(#[from] ValidationErrors),
+
+    #[error("Product {product_id} is not in cart {id}")]
+    ItemNotFound { id: CartId, product_id: ProductId },
+
+    #[error("Guest cart {id} must be merged into a user cart before checkout")]
+    GuestCheckout { id: CartId },
+
+    #[error("Prices in cart {id} changed since they were added")]
+    PricesChanged {
+        id: CartId,
+        changes: Vec<PriceChange>,
+    },
+
+    #[error("Catalog operation failed")]
+    Catalog(#[from] ProductError),
+
+    #[error("Checkout failed")]
+    Order(#[from] OrderError),
+
+    #[error(transparent)]
+    Money(#[from] MoneyError),
+
+    #[error("Cart storage failed")]
+    Repository(#[from] RepositoryError),
+}
+
+impl ErrorCode for CartError {
+    fn code(&self) -> &'static str {
+        match self {
+            CartError::NotFound { .. } => "CART_NOT_FOUND",
+            CartError::EmptyCart { .. } => "CART_EMPTY",
+            CartError::Invalid(_) => "CART_INVALID",
+            CartError::ItemNotFound { .. } => "CART_ITEM_NOT_FOUND",
+            CartError::GuestCheckout { .. } => "CART_GUEST_CHECKOUT",
+            CartError::PricesChanged { .. } => "CART_PRICES_CHANGED",
+            CartError::Catalog(e) => e.code(),
+            CartError::Order(e) => e.code(),
+            CartError::Money(e) => e.code(),
+            CartError::Repository(e) => e.code(),
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            CartError::Invalid(_) => ErrorKind::Validation,
+            CartError::NotFound { .. } | CartError::ItemNotFound { .. } => ErrorKind::NotFound,
+            CartError::EmptyCart { .. } | CartError::GuestCheckout { .. } => {
+                ErrorKind::InvalidState
+            }
+            CartError::PricesChanged { .. } => ErrorKind::Conflict,
+            CartError::Catalog(e) => e.kind(),
+            CartError::Order(e) => e.kind(),
+            CartError::Money(e) => e.kind(),
+            CartError::Repository(e) => e.kind(),
+        }
+    }
+}
+
+// ============================================================
+// REPOSITORY
+// ============================================================
+
+pub trait CartRepository: Repository<Cart, CartId> {
+    fn find_by_user(&self, user_id: UserId) -> RepoResult<Option<Cart>> {
+        Ok(self.iter()?.find(|c| c.user_id == Some(user_id)))
+    }
+}
+
+pub type InMemoryCartRepository = InMemoryRepository<Cart>;
+
+impl CartRepository for InMemoryCartRepository {}
+
+// ============================================================
+// SERVICE
+// ============================================================
+
+pub struct CartService<R = InMemoryCartRepository> {
+    repo: R,
+    ids: Box<dyn IdGenerator>,
+}
+
+impl CartService {
+    pub fn new() -> Self {
+        Self::with_repository(InMemoryCartRepository::new())
+            .expect("in-memory repository is always readable")
+    }
+}
+
+impl Default for CartService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: CartRepository> CartService<R> {
+    pub fn with_repository(repo: R) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
+
+        Ok(Self {
+            repo,
+            ids: Box::new(ids),
+        })
+    }
+
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    pub fn create_guest_cart(&mut self) -> Result<Cart, CartError> {
+        self.create(None)
+    }
+
+    /// Un usuario tiene a lo sumo un carrito: lo devuelve o lo crea.
+    pub fn cart_for_user(&mut self, user_id: UserId) -> Result<Cart, CartError> {
+        match self.repo.find_by_user(user_id)? {
+            Some(cart) => Ok(cart),
+            None => self.create(Some(user_id)),
+        }
+    }
+
+    pub fn get_cart(&self, id: CartId) -> Result<Option<Cart>, CartError> {
+        Ok(self.repo.find_by_id(id)?)
+    }
+
+    /// El precio se toma del catálogo, nunca del cliente.
+    pub fn add_item<P: ProductRepository>(
+        &mut self,
+        id: CartId,
+        catalog: &ProductService<P>,
+        product_id: ProductId,
+        quantity: u32,
+    ) -> Result<Cart, CartError> {
+        Validator::check("quantity", &quantity, &[at_least(1)])?;
+
+        let price = catalog.price_of(product_id)?;
+        self.apply(id, |cart| {
+            cart.add(product_id, quantity, price);
+            Ok(())
+        })
+    }
+
+    /// Cantidad 0 = quitar el producto.
+    pub fn update_quantity(
+        &mut self,
+        id: CartId,
+        product_id: ProductId,
+        quantity: u32,
+    ) -> Result<Cart, CartError> {
+        if quantity == 0 {
+            return self.remove_item(id, product_id);
+        }
+
+        self.apply(id, |cart| {
+            let item = cart
+                .item_mut(product_id)
+                .ok_or(CartError::ItemNotFound { id, product_id })?;
+            item.quantity = quantity;
+            Ok(())
+        })
+    }
+
+    pub fn remove_item(&mut self, id: CartId, product_id: ProductId) -> Result<Cart, CartError> {
+        self.apply(id, |cart| {
+            let before = cart.items.len();
+            cart.items.retain(|i| i.product_id != product_id);
+            if cart.items.len() == before {
+                return Err(CartError::ItemNotFound { id, product_id });
+            }
+            Ok(())
+        })
+    }
+
+    /// Al hacer login: los items del carrito de invitado pasan al del
+    /// usuario (sumando cantidades) y el de invitado desaparece.
+    pub fn merge_guest_cart(&mut self, guest: CartId, user_id: UserId) -> Result<Cart, CartError> {
+        let guest_cart = self.require(guest)?;
+        let mut user_cart = self.cart_for_user(user_id)?;
+
+        if guest_cart.user_id == Some(user_id) {
+            return Ok(user_cart);
+        }
+
+        for item in guest_cart.items {
+            user_cart.add(item.product_id, item.quantity, item.unit_price);
+        }
+
+        self.repo.save(user_cart.clone())?;
+        self.repo.delete(guest)?;
+        Ok(user_cart)
+    }
+
+    /// Actualiza los precios del carrito con los del catálogo y devuelve
+    /// lo que cambió.
+    pub fn reprice<P: ProductRepository>(
+        &mut self,
+        id: CartId,
+        catalog: &ProductService<P>,
+    ) -> Result<Vec<PriceChange>, CartError> {
+        let mut cart = self.require(id)?;
+        let mut changes = Vec::new();
+
+        for item in &mut cart.items {
+            let current = catalog.price_of(item.product_id)?;
+            if current != item.unit_price {
+                changes.push(PriceChange {
+                    product_id: item.product_id,
+                    old: item.unit_price,
+                    new: current,
+                });
+                item.unit_price = current;
+            }
+        }
+
+        if !changes.is_empty() {
+            self.repo.save(cart)?;
+        }
+        Ok(changes)
+    }
+
+    /// Convierte el carrito en una Order y lo vacía, en un `UnitOfWork`
+    /// sobre ambos servicios: si no se puede vaciar el carrito, la orden,
+    /// su reserva y su auditoría se deshacen (no queda una orden suelta ni
+    /// se pierde el carrito). Los eventos solo se deshacen si OrderService
+    /// usa outbox.
+    ///
+    /// Si el catálogo cambió precios, el carrito se actualiza y el checkout
+    /// falla con `PricesChanged` para que el cliente vea los nuevos montos.
+    pub fn checkout<O, P>(
+        &mut self,
+        id: CartId,
+        orders: &mut OrderService<O, P>,
+    ) -> Result<Order, CartError>
+    where
+        R: Transactional,
+        O: OrderRepository + Transactional,
+        P: ProductRepository + Transactional,
+    {
+        let cart = self.require(id)?;
+        let user_id = cart.user_id.ok_or(CartError::GuestCheckout { id })?;
+
+        if cart.is_empty() {
+            return Err(CartError::EmptyCart { id });
+        }
+
+        // Fuera de la unidad: el carrito repreciado queda aunque falle
+        let changes = self.reprice(id, orders.catalog())?;
+        if !changes.is_empty() {
+            return Err(CartError::PricesChanged { id, changes });
+        }
+
+        let lines = cart
+            .items
+            .iter()
+            .map(|i| OrderLine::new(i.product_id, i.quantity))
+            .collect();
+        let cleared = Cart {
+            items: Vec::new(),
+            ..cart
+        };
+
+        UnitOfWork::new((self, orders)).run(|(carts, orders)| {
+            let order = orders.create_order(user_id, lines)?;
+            carts.repo.save(cleared)?;
+            Ok(order)
+        })
+    }
+
+    fn create(&mut self, user_id: Option<UserId>) -> Result<Cart, CartError> {
+        let cart = Cart {
+            id: CartId(self.ids.next_id()?),
+            user_id,
+            items: Vec::new(),
+        };
+
+        self.repo.insert(cart.clone())?;
+        Ok(cart)
+    }
+
+    fn require(&self, id: CartId) -> Result<Cart, CartError> {
+        self.repo.find_by_id(id)?.ok_or(CartError::NotFound { id })
+    }
+
+    // Carga → muta → guarda (mismo patrón que OrderService::apply)
+    fn apply<F>(&mut self, id: CartId, command: F) -> Result<Cart, CartError>
+    where
+        F: FnOnce(&mut Cart) -> Result<(), CartError>,
+    {
+        let mut cart = self.require(id)?;
+        command(&mut cart)?;
+
+        self.repo.save(cart.clone())?;
+        Ok(cart)
+    }
+}
+
+impl<R: CartRepository + Transactional> Transactional for CartService<R> {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.repo.begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.repo.commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.repo.rollback()
+    }
+}
+
+/*
+CARRITO → ORDEN:
+
+  invitado                 login                    checkout
+  ┌────────────┐     merge_guest_cart     ┌────────────┐     ┌───────────┐
+  │ Cart (None)│ ───────────────────────▶ │ Cart (user)│ ──▶ │ Order     │
+  └────────────┘   suma cantidades,       └────────────┘     │ (Pending) │
+                   borra el de invitado     │ vacío          └───────────┘
+
+- El carrito guarda un precio "de vidriera"; la orden usa el de catálogo
+- reprice sincroniza ambos; checkout se niega si difieren
+- checkout = create_order (reserva stock) + vaciar carrito en un
+  UnitOfWork: si lo segundo falla, la orden y la reserva se deshacen
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::domain::OrderStatus;
+    use crate::modules_demo::shared::testing::FaultyRepository;
+
+    fn usd(amount: &str) -> Money {
+        format!("{} USD", amount).parse().unwrap()
+    }
+
+    fn setup() -> (CartService, OrderService, ProductId, ProductId) {
+        let mut orders = OrderService::new();
+        let book = orders
+            .catalog_mut()
+            .add_product("BOOK", "Book".to_string(), usd("20.00"), 10)
+            .unwrap();
+        let pen = orders
+            .catalog_mut()
+            .add_product("PEN", "Pen".to_string(), usd("1.50"), 10)
+            .unwrap();
+        (CartService::new(), orders, book.id, pen.id)
+    }
+
+    #[test]
+    fn test_add_update_remove() {
+        let (mut carts, orders, book, pen) = setup();
+        let cart = carts.cart_for_user(UserId(1)).unwrap();
+
+        carts.add_item(cart.id, orders.catalog(), book, 1).unwrap();
+        carts.add_item(cart.id, orders.catalog(), book, 1).unwrap();
+        let cart = carts.add_item(cart.id, orders.catalog(), pen, 4).unwrap();
+        assert_eq!(cart.items[0].quantity, 2);
+        assert_eq!(cart.total().unwrap(), Some(usd("46.00")));
+
+        let cart = carts.update_quantity(cart.id, pen, 0).unwrap();
+        assert_eq!(cart.items.len(), 1);
+
+        assert_eq!(
+            carts.remove_item(cart.id, pen).unwrap_err(),
+            CartError::ItemNotFound {
+                id: cart.id,
+                product_id: pen
+            }
+        );
+    }
+
+    #[test]
+    fn test_cart_changes_roll_back_in_a_unit_of_work() {
+        let (mut carts, orders, book, pen) = setup();
+        let cart = carts.cart_for_user(UserId(1)).unwrap();
+
+        let result = UnitOfWork::new(&mut carts).run(|carts| {
+            carts.add_item(cart.id, orders.catalog(), book, 1)?;
+            carts.add_item(cart.id, orders.catalog(), pen, 0)
+        });
+
+        assert!(matches!(result, Err(CartError::Invalid(_))));
+        assert!(carts.get_cart(cart.id).unwrap().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_guest_into_user_cart() {
+        let (mut carts, orders, book, pen) = setup();
+
+        let guest = carts.create_guest_cart().unwrap();
+        carts.add_item(guest.id, orders.catalog(), book, 1).unwrap();
+        carts.add_item(guest.id, orders.catalog(), pen, 2).unwrap();
+
+        let user = carts.cart_for_user(UserId(1)).unwrap();
+        carts.add_item(user.id, orders.catalog(), book, 2).unwrap();
+
+        let merged = carts.merge_guest_cart(guest.id, UserId(1)).unwrap();
+        let quantities: Vec<_> = merged
+            .items
+            .iter()
+            .map(|i| (i.product_id, i.quantity))
+            .collect();
+
+        assert_eq!(merged.id, user.id);
+        assert_eq!(quantities, vec![(book, 3), (pen, 2)]);
+        assert!(carts.get_cart(guest.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_reprice_against_catalog() {
+        let (mut carts, mut orders, book, _) = setup();
+        let cart = carts.cart_for_user(UserId(1)).unwrap();
+        carts.add_item(cart.id, orders.catalog(), book, 1).unwrap();
+
+        orders
+            .catalog_mut()
+            .update_price(book, usd("18.00"))
+            .unwrap();
+
+        let changes = carts.reprice(cart.id, orders.catalog()).unwrap();
+        assert_eq!(
+            changes,
+            vec![PriceChange {
+                product_id: book,
+                old: usd("20.00"),
+                new: usd("18.00")
+            }]
+        );
+        assert!(carts.reprice(cart.id, orders.catalog()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_checkout_creates_order_and_clears_cart() {
+        let (mut carts, mut orders, book, pen) = setup();
+        let cart = carts.cart_for_user(UserId(7)).unwrap();
+        carts.add_item(cart.id, orders.catalog(), book, 2).unwrap();
+        carts.add_item(cart.id, orders.catalog(), pen, 1).unwrap();
+
+        let order = carts.checkout(cart.id, &mut orders).unwrap();
+
+        assert_eq!(order.user_id, UserId(7));
+        assert_eq!(order.total, usd("41.50"));
+        assert_eq!(order.status, OrderStatus::Pending);
+        assert!(carts.get_cart(cart.id).unwrap().unwrap().is_empty());
+        assert_eq!(orders.catalog().available(book).unwrap(), 8);
+    }
+
+    #[test]
+    fn test_failed_checkout_keeps_cart() {
+        let (mut carts, mut orders, book, _) = setup();
+        let cart = carts.cart_for_user(UserId(1)).unwrap();
+        carts.add_item(cart.id, orders.catalog(), book, 11).unwrap();
+
+        let err = carts.checkout(cart.id, &mut orders).unwrap_err();
+        assert_eq!(err.code(), "PRODUCT_INSUFFICIENT_STOCK");
+
+        assert_eq!(carts.get_cart(cart.id).unwrap().unwrap().items.len(), 1);
+        assert!(orders.get_user_orders(UserId(1)).unwrap().is_empty());
+    }
+
+    impl CartRepository for FaultyRepository<Cart> {}
+
+    #[test]
+    fn test_checkout_rolls_back_the_order_if_the_cart_cannot_be_cleared() {
+        let (_, mut orders, book, _) = setup();
+        let (repo, faults) = FaultyRepository::new();
+        let mut carts = CartService::with_repository(repo).unwrap();
+        let cart = carts.cart_for_user(UserId(1)).unwrap();
+        carts.add_item(cart.id, orders.catalog(), book, 2).unwrap();
+
+        faults.fail_writes(true);
+        let err = carts.checkout(cart.id, &mut orders).unwrap_err();
+        assert!(matches!(err, CartError::Repository(_)));
+
+        assert!(orders.get_user_orders(UserId(1)).unwrap().is_empty());
+        assert_eq!(orders.catalog().available(book).unwrap(), 10);
+        assert_eq!(carts.get_cart(cart.id).unwrap().unwrap().items.len(), 1);
+    }
+
+    #[test]
+    fn test_checkout_rejects_guest_and_stale_prices() {
+        let (mut carts, mut orders, book, _) = setup();
+
+        let guest = carts.create_guest_cart().unwrap();
+        carts.add_item(guest.id, orders.catalog(), book, 1).unwrap();
+        assert_eq!(
+            carts.checkout(guest.id, &mut orders).unwrap_err(),
+            CartError::GuestCheckout { id: guest.id }
+        );
+
+        let cart = carts.merge_guest_cart(guest.id, UserId(1)).unwrap();
+        orders
+            .catalog_mut()
+            .update_price(book, usd("25.00"))
+            .unwrap();
+
+        let err = carts.checkout(cart.id, &mut orders).unwrap_err();
+        assert_eq!(err.code(), "CART_PRICES_CHANGED");
+
+        // El carrito ya quedó repreciado: el segundo intento pasa
+        let order = carts.checkout(cart.id, &mut orders).unwrap();
+        assert_eq!(order.total, usd("25.00"));
+    }
+}
@@ -0,0 +1,216 @@
+// Eventos de dominio: hechos ya ocurridos, publicados por los servicios
+// Otros módulos (notificaciones, analytics) reaccionan sin que los servicios los conozcan
+
+use crate::modules_demo::shared::{self, EventBus, Money, OrderId, Outbox, PaymentId, UserId};
+use serde::{Deserialize, Serialize};
+
+/// Lo que pasó, en pasado. Se publica solo si la operación se guardó.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DomainEvent {
+    UserRegistered {
+        user_id: UserId,
+        email: String,
+    },
+    EmailChanged {
+        user_id: UserId,
+        old_email: String,
+        new_email: String,
+    },
+    OrderPlaced {
+        order_id: OrderId,
+        user_id: UserId,
+        total: Money,
+    },
+    OrderConfirmed {
+        order_id: OrderId,
+        user_id: UserId,
+    },
+    OrderShipped {
+        order_id: OrderId,
+        tracking_number: String,
+    },
+    OrderDelivered {
+        order_id: OrderId,
+    },
+    OrderCancelled {
+        order_id: OrderId,
+        reason: String,
+    },
+    OrderRefunded {
+        order_id: OrderId,
+    },
+    PaymentAuthorized {
+        payment_id: PaymentId,
+        order_id: OrderId,
+        amount: Money,
+    },
+    PaymentCaptured {
+        payment_id: PaymentId,
+        order_id: OrderId,
+        amount: Money,
+    },
+    PaymentVoided {
+        payment_id: PaymentId,
+        order_id: OrderId,
+    },
+    PaymentRefunded {
+        payment_id: PaymentId,
+        order_id: OrderId,
+        amount: Money,
+    },
+}
+
+impl DomainEvent {
+    /// Nombre estable, para logs y métricas.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DomainEvent::UserRegistered { .. } => "user.registered",
+            DomainEvent::EmailChanged { .. } => "user.email_changed",
+            DomainEvent::OrderPlaced { .. } => "order.placed",
+            DomainEvent::OrderConfirmed { .. } => "order.confirmed",
+            DomainEvent::OrderShipped { .. } => "order.shipped",
+            DomainEvent::OrderDelivered { .. } => "order.delivered",
+            DomainEvent::OrderCancelled { .. } => "order.cancelled",
+            DomainEvent::OrderRefunded { .. } => "order.refunded",
+            DomainEvent::PaymentAuthorized { .. } => "payment.authorized",
+            DomainEvent::PaymentCaptured { .. } => "payment.captured",
+            DomainEvent::PaymentVoided { .. } => "payment.voided",
+            DomainEvent::PaymentRefunded { .. } => "payment.refunded",
+        }
+    }
+}
+
+/// Un bus para todo el dominio: se crea uno y se pasa a cada servicio
+/// con `with_event_bus`.
+pub type DomainEventBus = EventBus<DomainEvent>;
+
+/// Eventos guardados junto con el cambio; un `OutboxRelay` los publica.
+pub type DomainOutbox = Outbox<DomainEvent>;
+
+/// Bus o outbox, según cómo se construyó el servicio.
+pub(super) type EventSink = shared::EventSink<DomainEvent>;
+
+/*
+QUIÉN PUBLICA QUÉ:
+
+  UserService      create_user → UserRegistered
+                   update_email → EmailChanged
+  OrderService     create_order → OrderPlaced
+                   confirm / ship / deliver / cancel / refund → Order*
+                   expire_reservations → OrderCancelled por cada una
+  PaymentService   authorize / capture / void / refund → Payment*
+
+  (y sus versiones async, con los mismos eventos)
+
+- Se publica DESPUÉS de guardar: un evento nunca describe algo que no
+  quedó persistido
+- Sin outbox, dentro de un UnitOfWork que después hace rollback, el
+  evento ya salió
+- Con `with_outbox`, el servicio encola en lugar de publicar y el outbox
+  participa de su Transactional: rollback → el evento tampoco existe.
+  Un OutboxRelay lo publica en el bus después del commit
+  (los servicios async publican siempre directo)
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::domain::{OrderLine, OrderService, UserService};
+    use std::sync::{Arc, Mutex};
+
+    /// Bus con un suscriptor que guarda todo lo publicado.
+    fn recording_bus() -> (DomainEventBus, Arc<Mutex<Vec<DomainEvent>>>) {
+        let bus = DomainEventBus::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&log);
+        bus.subscribe(move |event| sink.lock().unwrap().push(event.clone()));
+        (bus, log)
+    }
+
+    #[test]
+    fn test_services_share_one_bus() {
+        let (bus, log) = recording_bus();
+        let mut users = UserService::new().with_event_bus(bus.clone());
+        let mut orders = OrderService::new().with_event_bus(bus);
+
+        let alice = users
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        let price: Money = "5.00 USD".parse().unwrap();
+        let product = orders
+            .catalog_mut()
+            .add_product("PEN", "Pen".to_string(), price, 10)
+            .unwrap()
+            .id;
+        let order = orders
+            .create_order(alice.id, vec![OrderLine::new(product, 2)])
+            .unwrap();
+        orders.confirm_order(order.id).unwrap();
+
+        let names: Vec<_> = log.lock().unwrap().iter().map(DomainEvent::name).collect();
+        assert_eq!(
+            names,
+            ["user.registered", "order.placed", "order.confirmed"]
+        );
+    }
+
+    #[test]
+    fn test_failed_operations_publish_nothing() {
+        let (bus, log) = recording_bus();
+        let mut users = UserService::new().with_event_bus(bus.clone());
+        let mut orders = OrderService::new().with_event_bus(bus);
+
+        users
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        log.lock().unwrap().clear();
+
+        assert!(
+            users
+                .create_user("Eve".to_string(), "alice@test.com".to_string())
+                .is_err()
+        );
+        assert!(orders.confirm_order(OrderId(42)).is_err());
+
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_outbox_keeps_events_of_committed_work_only() {
+        use crate::modules_demo::error::AppError;
+        use crate::modules_demo::shared::{OutboxRelay, UnitOfWork};
+
+        let outbox = DomainOutbox::in_memory();
+        let (bus, log) = recording_bus();
+        let mut users = UserService::new().with_outbox(outbox.clone());
+        let mut orders = OrderService::new().with_outbox(outbox.clone());
+        let price: Money = "5.00 USD".parse().unwrap();
+        let product = orders
+            .catalog_mut()
+            .add_product("PEN", "Pen".to_string(), price, 1)
+            .unwrap()
+            .id;
+
+        // Sin stock: el usuario se deshace, y su UserRegistered con él
+        let failed: Result<_, AppError> =
+            UnitOfWork::new((&mut users, &mut orders)).run(|(users, orders)| {
+                let user = users.create_user("Bob".to_string(), "bob@test.com".to_string())?;
+                Ok(orders.create_order(user.id, vec![OrderLine::new(product, 2)])?)
+            });
+        assert!(failed.is_err());
+        assert!(outbox.pending().unwrap().is_empty());
+
+        let placed: Result<_, AppError> =
+            UnitOfWork::new((&mut users, &mut orders)).run(|(users, orders)| {
+                let user = users.create_user("Bob".to_string(), "bob@test.com".to_string())?;
+                Ok(orders.create_order(user.id, vec![OrderLine::new(product, 1)])?)
+            });
+        assert!(placed.is_ok());
+        // Nada publicado todavía: recién cuando pasa el relay
+        assert!(log.lock().unwrap().is_empty());
+
+        OutboxRelay::to_bus(outbox, bus).run_once().unwrap();
+        let names: Vec<_> = log.lock().unwrap().iter().map(DomainEvent::name).collect();
+        assert_eq!(names, ["user.registered", "order.placed"]);
+    }
+}
@@ -1,12 +1,35 @@
 // Módulo domain: organización por dominio/feature
 // Cada submódulo es independiente y auto-contenido
 
+pub mod cart;
+pub mod events;
 pub mod order;
+pub mod order_async;
+pub mod order_event_sourced;
+pub mod order_typestate;
+pub mod payment;
+pub mod payment_async;
+pub mod product;
 pub mod user;
+pub mod user_async;
 
 // Re-exports para API más limpia
-pub use order::{Order, OrderItem, OrderService, OrderStatus};
-pub use user::{User, UserService};
+pub use cart::{Cart, CartError, CartItem, CartRepository, CartService, PriceChange};
+pub use events::{DomainEvent, DomainEventBus, DomainOutbox};
+pub use order::{
+    Order, OrderError, OrderItem, OrderLine, OrderRepository, OrderService, OrderStatus,
+    StatusChange,
+};
+pub use order_async::{AsyncOrderRepository, AsyncOrderService};
+pub use order_event_sourced::{EventSourcedOrderRepository, OrderEvent};
+pub use payment::{
+    FakeGateway, GatewayError, GatewayResponse, Payment, PaymentError, PaymentGateway,
+    PaymentRepository, PaymentService, PaymentStatus, Reconciliation,
+};
+pub use payment_async::{AsyncPaymentRepository, AsyncPaymentService};
+pub use product::{Product, ProductError, ProductRepository, ProductService, StockReservation};
+pub use user::{User, UserError, UserRepository, UserService};
+pub use user_async::{AsyncUserRepository, AsyncUserService};
 
 /*
 VENTAJAS DE mod.rs:
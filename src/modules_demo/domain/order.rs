@@ -1,150 +1,1582 @@
 // Dominio: Order
 // Todo lo relacionado a órdenes en un solo lugar
 
-use std::collections::HashMap;
+use super::events::{DomainEvent, DomainEventBus, DomainOutbox, EventSink};
+use super::order_typestate::{self as typed, OrderState};
+use super::product::{InMemoryProductRepository, ProductError, ProductRepository, ProductService};
+use crate::modules_demo::shared::sqlite::{invalid_column, money_column};
+use crate::modules_demo::shared::validation::{at_least, not_blank, not_empty};
+use crate::modules_demo::shared::{
+    AuditLog, Auditor, Clock, Entity, ErrorCode, ErrorKind, IdGenerator, InMemoryRepository,
+    LogStructuredRepository, Migration, Money, MoneyError, OrderId, Page, ProductId, Query,
+    RepoResult, Repository, RepositoryError, SequentialIdGenerator, SqliteDatabase, SystemClock,
+    Transactional, UserId, Validate, ValidationErrors, Validator,
+};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, OptionalExtension, Params, params};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Order {
-    pub id: u64,
-    pub user_id: u64,
-    pub total: f64,
+    pub id: OrderId,
+    pub user_id: UserId,
+    pub total: Money,
     pub items: Vec<OrderItem>,
     pub status: OrderStatus,
+    pub tracking_number: Option<String>,
+    pub cancellation_reason: Option<String>,
+    pub history: Vec<StatusChange>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderItem {
-    pub product_id: u64,
+    pub product_id: ProductId,
     pub quantity: u32,
-    pub price: f64,
+    /// Precio unitario
+    pub price: Money,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl OrderItem {
+    pub fn subtotal(&self) -> Result<Money, MoneyError> {
+        self.price.checked_mul(self.quantity)
+    }
+}
+
+/// Lo que pide el cliente: producto y cantidad. El precio lo pone el catálogo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderLine {
+    pub product_id: ProductId,
+    pub quantity: u32,
+}
+
+impl OrderLine {
+    pub fn new(product_id: ProductId, quantity: u32) -> Self {
+        Self {
+            product_id,
+            quantity,
+        }
+    }
+}
+
+// Lo que pide el cliente (OrderLine) y lo que se guarda (OrderItem)
+// cumplen las mismas reglas: así los errores de `create_order` hablan
+// de "items[i]" igual que los de la orden.
+
+impl Validate for OrderLine {
+    fn validate(&self, v: &mut Validator) {
+        v.field("quantity", &self.quantity, &[at_least(1)]);
+    }
+}
+
+impl Validate for OrderItem {
+    fn validate(&self, v: &mut Validator) {
+        v.field("quantity", &self.quantity, &[at_least(1)]);
+    }
+}
+
+impl Validate for Order {
+    fn validate(&self, v: &mut Validator) {
+        validate_items(v, &self.items);
+    }
+}
+
+/// Al menos un item, y cada uno válido. También para quien todavía no
+/// tiene una `Order` (líneas del pedido, order_typestate).
+pub(super) fn validate_items<I: Validate + 'static>(v: &mut Validator, items: &[I]) {
+    v.field("items", items, &[not_empty()]).each("items", items);
+}
+
+pub(super) fn validate_tracking_number(tracking_number: &str) -> Result<(), OrderError> {
+    Ok(Validator::check(
+        "tracking_number",
+        tracking_number,
+        &[not_blank()],
+    )?)
+}
+
+pub(super) fn validate_cancellation_reason(reason: &str) -> Result<(), OrderError> {
+    Ok(Validator::check("reason", reason, &[not_blank()])?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderStatus {
     Pending,
     Confirmed,
     Shipped,
     Delivered,
     Cancelled,
+    Refunded,
 }
 
-pub struct OrderRepository {
-    storage: HashMap<u64, Order>,
+/// Registro de una transición aplicada.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub from: OrderStatus,
+    pub to: OrderStatus,
+    pub at: DateTime<Utc>,
 }
 
-impl OrderRepository {
-    pub fn new() -> Self {
-        Self {
-            storage: HashMap::new(),
+// ============================================================
+// STATE MACHINE
+// ============================================================
+
+impl OrderStatus {
+    /// Tabla de transiciones: ÚNICO lugar donde se decide qué es legal.
+    pub fn allowed_transitions(self) -> &'static [OrderStatus] {
+        use OrderStatus::*;
+        match self {
+            Pending => &[Confirmed, Cancelled],
+            Confirmed => &[Shipped, Cancelled],
+            Shipped => &[Delivered],
+            Cancelled => &[Refunded],
+            Delivered | Refunded => &[],
         }
     }
 
-    pub fn save(&mut self, order: Order) -> Result<(), String> {
-        self.storage.insert(order.id, order);
+    pub fn can_transition_to(self, next: OrderStatus) -> bool {
+        self.allowed_transitions().contains(&next)
+    }
+
+    pub fn is_terminal(self) -> bool {
+        self.allowed_transitions().is_empty()
+    }
+}
+
+impl Order {
+    /// Tipo con el que se registra en el `AuditLog`.
+    pub const AUDIT_TYPE: &'static str = "order";
+
+    /// Aplica una transición validándola contra la tabla y la registra.
+    pub fn transition_to(
+        &mut self,
+        next: OrderStatus,
+        at: DateTime<Utc>,
+    ) -> Result<(), OrderError> {
+        if !self.status.can_transition_to(next) {
+            return Err(OrderError::InvalidTransition {
+                id: self.id,
+                from: self.status,
+                to: next,
+            });
+        }
+
+        self.record_transition(next, at);
         Ok(())
     }
 
-    pub fn find_by_id(&self, id: u64) -> Option<&Order> {
-        self.storage.get(&id)
+    // Sin validar: lo usa el typestate, donde el compilador ya garantizó
+    // que la transición es legal. `at` lo pone quien llama (el reloj del
+    // servicio, o el evento al reconstruir).
+    pub(super) fn record_transition(&mut self, next: OrderStatus, at: DateTime<Utc>) {
+        self.history.push(StatusChange {
+            from: self.status,
+            to: next,
+            at,
+        });
+        self.status = next;
+    }
+}
+
+// ============================================================
+// ERRORS
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum OrderError {
+    #[error("Invalid order")]
+    Invalid(#[from] ValidationErrors),
+
+    #[error("Order {id} not found")]
+    NotFound { id: OrderId },
+
+    #[error("Order {id} cannot go from {from:?} to {to:?}")]
+    InvalidTransition {
+        id: OrderId,
+        from: OrderStatus,
+        to: OrderStatus,
+    },
+
+    #[error("Order {id} is {actual:?}, expected {expected:?}")]
+    UnexpectedStatus {
+        id: OrderId,
+        expected: OrderStatus,
+        actual: OrderStatus,
+    },
+
+    #[error("Invalid order amount")]
+    Money(#[from] MoneyError),
+
+    #[error("Catalog operation failed")]
+    Catalog(#[from] ProductError),
+
+    #[error("Order storage failed")]
+    Repository(#[from] RepositoryError),
+}
+
+impl ErrorCode for OrderError {
+    fn code(&self) -> &'static str {
+        match self {
+            OrderError::Invalid(_) => "ORDER_INVALID",
+            OrderError::NotFound { .. } => "ORDER_NOT_FOUND",
+            OrderError::InvalidTransition { .. } => "ORDER_INVALID_TRANSITION",
+            OrderError::UnexpectedStatus { .. } => "ORDER_UNEXPECTED_STATUS",
+            OrderError::Money(e) => e.code(),
+            OrderError::Catalog(e) => e.code(),
+            OrderError::Repository(e) => e.code(),
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            OrderError::Invalid(_) => ErrorKind::Validation,
+            OrderError::NotFound { .. } => ErrorKind::NotFound,
+            OrderError::InvalidTransition { .. } | OrderError::UnexpectedStatus { .. } => {
+                ErrorKind::InvalidState
+            }
+            OrderError::Money(e) => e.kind(),
+            OrderError::Catalog(e) => e.kind(),
+            OrderError::Repository(e) => e.kind(),
+        }
+    }
+}
+
+// ============================================================
+// REPOSITORY
+// ============================================================
+
+impl Entity for Order {
+    type Id = OrderId;
+
+    fn id(&self) -> OrderId {
+        self.id
     }
 
-    pub fn find_by_user(&self, user_id: u64) -> Vec<&Order> {
-        self.storage
-            .values()
-            .filter(|o| o.user_id == user_id)
-            .collect()
+    fn index_keys(&self) -> Vec<(&'static str, String)> {
+        vec![(ORDERS_BY_USER, self.user_id.0.to_string())]
     }
+}
 
-    pub fn count(&self) -> usize {
-        self.storage.len()
+/// Índice multi-valor user → orders (el mismo nombre que en SQLite).
+pub(super) const ORDERS_BY_USER: &str = "orders.user_id";
+
+/// Búsquedas específicas de Order sobre el Repository genérico.
+pub trait OrderRepository: Repository<Order, OrderId> {
+    fn find_by_user(&self, user_id: UserId) -> RepoResult<Vec<Order>> {
+        self.find_indexed(ORDERS_BY_USER, &user_id.0.to_string())
     }
 }
 
-pub struct OrderService {
-    repo: OrderRepository,
+pub type InMemoryOrderRepository = InMemoryRepository<Order>;
+
+impl OrderRepository for InMemoryOrderRepository {}
+
+pub type LogOrderRepository = LogStructuredRepository<Order>;
+
+impl OrderRepository for LogOrderRepository {}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    name: "orders_001_create_tables",
+    sql: "CREATE TABLE orders (
+              id                  INTEGER PRIMARY KEY,
+              user_id             INTEGER NOT NULL REFERENCES users (id),
+              total_minor         INTEGER NOT NULL,
+              currency            TEXT NOT NULL,
+              status              TEXT NOT NULL CHECK (status IN
+                  ('Pending', 'Confirmed', 'Shipped', 'Delivered', 'Cancelled', 'Refunded')),
+              tracking_number     TEXT,
+              cancellation_reason TEXT
+          );
+          CREATE INDEX orders_user_id ON orders (user_id);
+
+          CREATE TABLE order_items (
+              order_id    INTEGER NOT NULL REFERENCES orders (id) ON DELETE CASCADE,
+              position    INTEGER NOT NULL,
+              product_id  INTEGER NOT NULL,
+              quantity    INTEGER NOT NULL CHECK (quantity > 0),
+              price_minor INTEGER NOT NULL,
+              currency    TEXT NOT NULL,
+              PRIMARY KEY (order_id, position)
+          );
+
+          CREATE TABLE order_history (
+              order_id    INTEGER NOT NULL REFERENCES orders (id) ON DELETE CASCADE,
+              position    INTEGER NOT NULL,
+              from_status TEXT NOT NULL,
+              to_status   TEXT NOT NULL,
+              at          TEXT NOT NULL,
+              PRIMARY KEY (order_id, position)
+          );",
+}];
+
+/// Orders en SQLite: la orden, sus items y su historial en tres tablas.
+/// `orders.user_id` es foreign key a `users`: comparte la base con
+/// `SqliteUserRepository`.
+pub struct SqliteOrderRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteOrderRepository {
+    pub fn new(db: SqliteDatabase) -> RepoResult<Self> {
+        db.migrate(MIGRATIONS)?;
+        Ok(Self { db })
+    }
+
+    // Con `upsert = false` un id repetido choca con el PRIMARY KEY.
+    // ON CONFLICT DO UPDATE (no INSERT OR REPLACE): reemplazar borraría la
+    // fila y con ella, en cascada, items e historial; y rompería las FKs
+    // de los pagos que la referencian.
+    fn write(&self, order: &Order, upsert: bool) -> RepoResult<()> {
+        let conflict = if upsert {
+            "ON CONFLICT (id) DO UPDATE SET
+                 user_id = excluded.user_id,
+                 total_minor = excluded.total_minor,
+                 currency = excluded.currency,
+                 status = excluded.status,
+                 tracking_number = excluded.tracking_number,
+                 cancellation_reason = excluded.cancellation_reason"
+        } else {
+            ""
+        };
+
+        self.db.transaction(|tx| {
+            tx.execute(
+                &format!(
+                    "INSERT INTO orders (id, user_id, total_minor, currency, status,
+                                         tracking_number, cancellation_reason)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) {conflict}"
+                ),
+                params![
+                    order.id.0,
+                    order.user_id.0,
+                    order.total.amount_minor(),
+                    order.total.currency().code(),
+                    status_name(order.status),
+                    order.tracking_number,
+                    order.cancellation_reason,
+                ],
+            )?;
+
+            tx.execute("DELETE FROM order_items WHERE order_id = ?1", [order.id.0])?;
+            for (position, item) in order.items.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO order_items
+                         (order_id, position, product_id, quantity, price_minor, currency)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        order.id.0,
+                        position,
+                        item.product_id.0,
+                        item.quantity,
+                        item.price.amount_minor(),
+                        item.price.currency().code(),
+                    ],
+                )?;
+            }
+
+            tx.execute(
+                "DELETE FROM order_history WHERE order_id = ?1",
+                [order.id.0],
+            )?;
+            for (position, change) in order.history.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO order_history (order_id, position, from_status, to_status, at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        order.id.0,
+                        position,
+                        status_name(change.from),
+                        status_name(change.to),
+                        change.at,
+                    ],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn load_where(&self, filter: &str, params: impl Params) -> RepoResult<Vec<Order>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!("SELECT id FROM orders {filter} ORDER BY id"))?;
+            let ids = stmt
+                .query_map(params, |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<u64>>>()?;
+
+            let mut orders = Vec::with_capacity(ids.len());
+            for id in ids {
+                orders.extend(load_order(conn, OrderId(id))?);
+            }
+            Ok(orders)
+        })
+    }
+}
+
+fn load_order(conn: &Connection, id: OrderId) -> rusqlite::Result<Option<Order>> {
+    let order = conn
+        .query_row(
+            "SELECT id, user_id, total_minor, currency, status, tracking_number,
+                    cancellation_reason
+             FROM orders WHERE id = ?1",
+            [id.0],
+            |row| {
+                Ok(Order {
+                    id: OrderId(row.get(0)?),
+                    user_id: UserId(row.get(1)?),
+                    total: money_column(row, 2, 3)?,
+                    items: Vec::new(),
+                    status: status_column(row.get(4)?, 4)?,
+                    tracking_number: row.get(5)?,
+                    cancellation_reason: row.get(6)?,
+                    history: Vec::new(),
+                })
+            },
+        )
+        .optional()?;
+
+    let Some(mut order) = order else {
+        return Ok(None);
+    };
+
+    let mut items = conn.prepare(
+        "SELECT product_id, quantity, price_minor, currency
+         FROM order_items WHERE order_id = ?1 ORDER BY position",
+    )?;
+    order.items = items
+        .query_map([id.0], |row| {
+            Ok(OrderItem {
+                product_id: ProductId(row.get(0)?),
+                quantity: row.get(1)?,
+                price: money_column(row, 2, 3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut history = conn.prepare(
+        "SELECT from_status, to_status, at
+         FROM order_history WHERE order_id = ?1 ORDER BY position",
+    )?;
+    order.history = history
+        .query_map([id.0], |row| {
+            Ok(StatusChange {
+                from: status_column(row.get(0)?, 0)?,
+                to: status_column(row.get(1)?, 1)?,
+                at: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(Some(order))
+}
+
+fn status_name(status: OrderStatus) -> &'static str {
+    match status {
+        OrderStatus::Pending => "Pending",
+        OrderStatus::Confirmed => "Confirmed",
+        OrderStatus::Shipped => "Shipped",
+        OrderStatus::Delivered => "Delivered",
+        OrderStatus::Cancelled => "Cancelled",
+        OrderStatus::Refunded => "Refunded",
+    }
+}
+
+fn status_column(name: String, index: usize) -> rusqlite::Result<OrderStatus> {
+    match name.as_str() {
+        "Pending" => Ok(OrderStatus::Pending),
+        "Confirmed" => Ok(OrderStatus::Confirmed),
+        "Shipped" => Ok(OrderStatus::Shipped),
+        "Delivered" => Ok(OrderStatus::Delivered),
+        "Cancelled" => Ok(OrderStatus::Cancelled),
+        "Refunded" => Ok(OrderStatus::Refunded),
+        _ => Err(invalid_column(
+            index,
+            format!("unknown order status {name}"),
+        )),
+    }
+}
+
+impl Repository<Order, OrderId> for SqliteOrderRepository {
+    fn save(&mut self, order: Order) -> RepoResult<()> {
+        self.write(&order, true)
+    }
+
+    fn insert(&mut self, order: Order) -> RepoResult<()> {
+        self.write(&order, false)
+    }
+
+    fn find_by_id(&self, id: OrderId) -> RepoResult<Option<Order>> {
+        self.db.with_connection(|conn| load_order(conn, id))
+    }
+
+    fn delete(&mut self, id: OrderId) -> RepoResult<Option<Order>> {
+        // items e historial se van con ON DELETE CASCADE
+        self.db.transaction(|tx| {
+            let order = load_order(tx, id)?;
+            tx.execute("DELETE FROM orders WHERE id = ?1", [id.0])?;
+            Ok(order)
+        })
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = Order> + '_>> {
+        Ok(Box::new(self.load_where("", [])?.into_iter()))
+    }
+
+    fn count(&self) -> RepoResult<usize> {
+        self.db.with_connection(|conn| {
+            conn.query_row("SELECT COUNT(*) FROM orders", [], |row| row.get(0))
+        })
+    }
+}
+
+impl Transactional for SqliteOrderRepository {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.db.begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.db.commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.db.rollback()
+    }
+}
+
+impl OrderRepository for SqliteOrderRepository {
+    fn find_by_user(&self, user_id: UserId) -> RepoResult<Vec<Order>> {
+        self.load_where("WHERE user_id = ?1", [user_id.0])
+    }
+}
+
+// ============================================================
+// SERVICE
+// ============================================================
+
+pub struct OrderService<R = InMemoryOrderRepository, P = InMemoryProductRepository> {
+    repo: R,
+    catalog: ProductService<P>,
+    ids: Box<dyn IdGenerator>,
+    clock: Box<dyn Clock>,
+    reservation_ttl: Duration,
+    events: EventSink,
+    audit: Auditor,
 }
 
 impl OrderService {
     pub fn new() -> Self {
-        Self {
-            repo: OrderRepository::new(),
+        Self::with_repository(InMemoryOrderRepository::new())
+            .expect("in-memory repository is always readable")
+    }
+}
+
+impl Default for OrderService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: OrderRepository> OrderService<R> {
+    pub fn with_repository(repo: R) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
+
+        Ok(Self {
+            repo,
+            catalog: ProductService::new(),
+            ids: Box::new(ids),
+            clock: Box::new(SystemClock),
+            reservation_ttl: Self::DEFAULT_RESERVATION_TTL,
+            events: EventSink::default(),
+            audit: Auditor::default(),
+        })
+    }
+}
+
+impl<R: OrderRepository, P: ProductRepository> OrderService<R, P> {
+    /// Cuánto tiempo retiene stock una orden pendiente.
+    pub const DEFAULT_RESERVATION_TTL: Duration = Duration::minutes(15);
+
+    /// Reemplaza el catálogo (y su backend) del que salen precios y stock.
+    pub fn with_catalog<P2: ProductRepository>(
+        self,
+        catalog: ProductService<P2>,
+    ) -> OrderService<R, P2> {
+        OrderService {
+            repo: self.repo,
+            catalog,
+            ids: self.ids,
+            clock: self.clock,
+            reservation_ttl: self.reservation_ttl,
+            events: self.events,
+            audit: self.audit,
         }
     }
 
-    pub fn create_order(&mut self, user_id: u64, items: Vec<OrderItem>) -> Result<Order, String> {
-        if items.is_empty() {
-            return Err("Order must have at least one item".to_string());
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    /// Reloj del vencimiento de reservas (p.ej. `ManualClock` en tests).
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn with_reservation_ttl(mut self, ttl: Duration) -> Self {
+        self.reservation_ttl = ttl;
+        self
+    }
+
+    pub fn with_event_bus(mut self, events: DomainEventBus) -> Self {
+        self.events = self.events.with_bus(events);
+        self
+    }
+
+    /// Encola los eventos en lugar de publicarlos: quedan en la misma
+    /// transacción que el cambio y los publica un `OutboxRelay`.
+    pub fn with_outbox(mut self, outbox: DomainOutbox) -> Self {
+        self.events = self.events.with_outbox(outbox);
+        self
+    }
+
+    pub fn events(&self) -> &DomainEventBus {
+        self.events.bus()
+    }
+
+    /// Registra cada alta y cambio de orden en `log`; en un UnitOfWork, en
+    /// la misma transacción que la orden.
+    pub fn with_audit(mut self, log: AuditLog) -> Self {
+        self.audit = self.audit.with_log(log);
+        self
+    }
+
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.log()
+    }
+
+    /// A nombre de quién quedan los próximos cambios (por defecto "system").
+    pub fn set_actor(&mut self, actor: impl Into<String>) {
+        self.audit.set_actor(actor);
+    }
+
+    pub fn catalog(&self) -> &ProductService<P> {
+        &self.catalog
+    }
+
+    pub fn catalog_mut(&mut self) -> &mut ProductService<P> {
+        &mut self.catalog
+    }
+
+    /// Precios del catálogo + reserva de stock hasta que se confirme,
+    /// cancele o venza la reserva.
+    pub fn create_order(
+        &mut self,
+        user_id: UserId,
+        lines: Vec<OrderLine>,
+    ) -> Result<Order, OrderError> {
+        let mut v = Validator::new();
+        validate_items(&mut v, &lines);
+        v.finish()?;
+
+        let mut items = Vec::with_capacity(lines.len());
+        for line in &lines {
+            items.push(OrderItem {
+                product_id: line.product_id,
+                quantity: line.quantity,
+                price: self.catalog.price_of(line.product_id)?,
+            });
         }
 
-        let total: f64 = items.iter().map(|i| i.price * i.quantity as f64).sum();
+        let id = OrderId(self.ids.next_id()?);
+        let order: Order = typed::Order::new(id, user_id, items)?.into();
+
+        let reserved: Vec<_> = lines.iter().map(|l| (l.product_id, l.quantity)).collect();
+        let expires_at = self.clock.now() + self.reservation_ttl;
+        self.catalog.reserve(id, &reserved, expires_at)?;
+
+        // Sin orden guardada, la reserva no debe quedar colgada
+        if let Err(e) = self.repo.insert(order.clone()) {
+            self.catalog.release(id)?;
+            return Err(e.into());
+        }
 
-        let order = Order {
-            id: (self.repo.count() + 1) as u64,
+        self.audit.created(Order::AUDIT_TYPE, id, &order)?;
+        self.events.emit(DomainEvent::OrderPlaced {
+            order_id: id,
             user_id,
-            total,
-            items,
-            status: OrderStatus::Pending,
-        };
+            total: order.total,
+        })?;
+        Ok(order)
+    }
+
+    pub fn get_order(&self, order_id: OrderId) -> Result<Option<Order>, OrderError> {
+        Ok(self.repo.find_by_id(order_id)?)
+    }
 
+    /// Carga la orden con su estado verificado en el tipo (ver order_typestate).
+    pub fn load<S: OrderState>(&self, order_id: OrderId) -> Result<typed::Order<S>, OrderError> {
+        let order = self
+            .repo
+            .find_by_id(order_id)?
+            .ok_or(OrderError::NotFound { id: order_id })?;
+
+        typed::Order::try_from(order)
+    }
+
+    pub fn store<S: OrderState>(&mut self, order: typed::Order<S>) -> Result<(), OrderError> {
+        let order: Order = order.into();
+        let before = self.repo.find_by_id(order.id)?;
         self.repo.save(order.clone())?;
+
+        match before {
+            Some(before) => self
+                .audit
+                .updated(Order::AUDIT_TYPE, order.id, &before, &order)?,
+            None => self.audit.created(Order::AUDIT_TYPE, order.id, &order)?,
+        }
+        Ok(())
+    }
+
+    /// Confirmar convierte la reserva en salida definitiva de stock.
+    ///
+    /// El stock sale antes de guardar la orden: sin reserva, la orden
+    /// sigue Pending. Si después falla el guardado, el stock ya salió;
+    /// para que ambos se confirmen o deshagan juntos, correrlo en un
+    /// `UnitOfWork`.
+    pub fn confirm_order(&mut self, order_id: OrderId) -> Result<(), OrderError> {
+        let (before, order) = self.prepare(order_id, |order, at| {
+            order.transition_to(OrderStatus::Confirmed, at)
+        })?;
+        self.catalog.commit(order_id)?;
+        self.persist(&before, &order)?;
+
+        self.events.emit(DomainEvent::OrderConfirmed {
+            order_id,
+            user_id: order.user_id,
+        })?;
+        Ok(())
+    }
+
+    pub fn ship_order(
+        &mut self,
+        order_id: OrderId,
+        tracking_number: String,
+    ) -> Result<(), OrderError> {
+        validate_tracking_number(&tracking_number)?;
+
+        self.apply(order_id, |order, at| {
+            order.transition_to(OrderStatus::Shipped, at)?;
+            order.tracking_number = Some(tracking_number.clone());
+            Ok(())
+        })?;
+
+        self.events.emit(DomainEvent::OrderShipped {
+            order_id,
+            tracking_number,
+        })?;
+        Ok(())
+    }
+
+    pub fn deliver_order(&mut self, order_id: OrderId) -> Result<(), OrderError> {
+        self.apply(order_id, |order, at| {
+            order.transition_to(OrderStatus::Delivered, at)
+        })?;
+
+        self.events.emit(DomainEvent::OrderDelivered { order_id })?;
+        Ok(())
+    }
+
+    /// Cancelar devuelve el stock: libera la reserva (Pending) o repone
+    /// lo que ya había salido (Confirmed).
+    ///
+    /// El stock vuelve antes de guardar la orden: si falla, la orden sigue
+    /// en su estado y se puede reintentar. Si después falla el guardado,
+    /// el stock ya volvió; para que ambos se confirmen o deshagan juntos,
+    /// correrlo en un `UnitOfWork`.
+    pub fn cancel_order(&mut self, order_id: OrderId, reason: String) -> Result<(), OrderError> {
+        validate_cancellation_reason(&reason)?;
+
+        let (before, order) = self.prepare(order_id, |order, at| {
+            order.transition_to(OrderStatus::Cancelled, at)?;
+            order.cancellation_reason = Some(reason.clone());
+            Ok(())
+        })?;
+
+        if before.status == OrderStatus::Confirmed {
+            for item in &order.items {
+                self.catalog.restock(item.product_id, item.quantity)?;
+            }
+        }
+        self.catalog.release(order_id)?;
+        self.persist(&before, &order)?;
+
+        self.events
+            .emit(DomainEvent::OrderCancelled { order_id, reason })?;
+        Ok(())
+    }
+
+    /// Cancela las órdenes pendientes cuya reserva venció y devuelve sus ids.
+    ///
+    /// Orden por orden: cancelar y después liberar. Si algo falla, las
+    /// anteriores quedaron completas y la que falló conserva su reserva
+    /// vencida: la próxima pasada la vuelve a encontrar y termina. Dentro
+    /// de un `UnitOfWork`, la pasada entera se confirma o se deshace.
+    pub fn expire_reservations(&mut self, now: DateTime<Utc>) -> Result<Vec<OrderId>, OrderError> {
+        let mut expired = Vec::new();
+
+        for order_id in self.catalog.expired_reservations(now)? {
+            // `now` y no el reloj: el historial dice cuándo venció
+            let cancelled = self.apply(order_id, |order, _| {
+                if order.status != OrderStatus::Pending {
+                    return Ok(());
+                }
+                order.transition_to(OrderStatus::Cancelled, now)?;
+                order.cancellation_reason = Some("reservation expired".to_string());
+                Ok(())
+            })?;
+            self.catalog.release(order_id)?;
+
+            if cancelled.status == OrderStatus::Cancelled {
+                self.events.emit(DomainEvent::OrderCancelled {
+                    order_id,
+                    reason: "reservation expired".to_string(),
+                })?;
+                expired.push(order_id);
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Solo una orden cancelada puede reembolsarse.
+    pub fn refund_order(&mut self, order_id: OrderId) -> Result<(), OrderError> {
+        self.apply(order_id, |order, at| {
+            order.transition_to(OrderStatus::Refunded, at)
+        })?;
+
+        self.events.emit(DomainEvent::OrderRefunded { order_id })?;
+        Ok(())
+    }
+
+    /// Órdenes del usuario, ordenadas por id.
+    pub fn get_user_orders(&self, user_id: UserId) -> Result<Vec<Order>, OrderError> {
+        self.query_user_orders(user_id, &Query::new())
+            .map(|page| page.items)
+    }
+
+    /// Consulta sobre las órdenes del usuario: el índice por usuario
+    /// acota primero, los filtros y el orden corren sobre ese subconjunto.
+    pub fn query_user_orders(
+        &self,
+        user_id: UserId,
+        query: &Query<Order>,
+    ) -> Result<Page<Order>, OrderError> {
+        Ok(query.run(self.repo.find_by_user(user_id)?))
+    }
+
+    // Carga → muta → guarda. Si el comando falla, no se persiste nada.
+    fn apply<F>(&mut self, order_id: OrderId, command: F) -> Result<Order, OrderError>
+    where
+        F: FnOnce(&mut Order, DateTime<Utc>) -> Result<(), OrderError>,
+    {
+        let (before, order) = self.prepare(order_id, command)?;
+        self.persist(&before, &order)?;
         Ok(order)
     }
 
-    pub fn confirm_order(&mut self, order_id: u64) -> Result<(), String> {
-        let order = self
+    // Carga → muta, sin guardar: (antes, después). El comando recibe la
+    // hora del reloj del servicio para el historial.
+    fn prepare<F>(&self, order_id: OrderId, command: F) -> Result<(Order, Order), OrderError>
+    where
+        F: FnOnce(&mut Order, DateTime<Utc>) -> Result<(), OrderError>,
+    {
+        let before = self
             .repo
-            .find_by_id(order_id)
-            .ok_or("Order not found")?
-            .clone();
+            .find_by_id(order_id)?
+            .ok_or(OrderError::NotFound { id: order_id })?;
+
+        let mut order = before.clone();
+        command(&mut order, self.clock.now())?;
+        Ok((before, order))
+    }
 
-        if order.status != OrderStatus::Pending {
-            return Err("Order is not in pending status".to_string());
+    fn persist(&mut self, before: &Order, order: &Order) -> Result<(), OrderError> {
+        if order == before {
+            return Ok(());
         }
 
-        let updated = Order {
-            status: OrderStatus::Confirmed,
-            ..order
-        };
+        self.repo.save(order.clone())?;
+        self.audit
+            .updated(Order::AUDIT_TYPE, order.id, before, order)?;
+        Ok(())
+    }
+}
+
+// La orden, su reserva de stock, sus eventos (si hay outbox) y su
+// auditoría se confirman (o deshacen) juntos. Outbox y auditoría primero:
+// escriben recién en el commit, y si fallan lo demás todavía se deshace
+impl<R, P> Transactional for OrderService<R, P>
+where
+    R: OrderRepository + Transactional,
+    P: ProductRepository + Transactional,
+{
+    fn begin(&mut self) -> RepoResult<()> {
+        (
+            &mut self.events,
+            &mut self.audit,
+            &mut self.repo,
+            &mut self.catalog,
+        )
+            .begin()
+    }
 
-        self.repo.save(updated)
+    fn commit(&mut self) -> RepoResult<()> {
+        (
+            &mut self.events,
+            &mut self.audit,
+            &mut self.repo,
+            &mut self.catalog,
+        )
+            .commit()
     }
 
-    pub fn get_user_orders(&self, user_id: u64) -> Vec<&Order> {
-        self.repo.find_by_user(user_id)
+    fn rollback(&mut self) -> RepoResult<()> {
+        (
+            &mut self.events,
+            &mut self.audit,
+            &mut self.repo,
+            &mut self.catalog,
+        )
+            .rollback()
     }
 }
 
+/*
+CICLO DE VIDA DE UNA ORDEN:
+
+  Pending ──confirm──▶ Confirmed ──ship──▶ Shipped ──deliver──▶ Delivered
+     │                    │
+     └──────cancel────────┴──▶ Cancelled ──refund──▶ Refunded
+
+- La tabla vive en OrderStatus::allowed_transitions (un solo lugar)
+- Order::transition_to es el único que cambia `status`
+- Cada transición queda en `history` con timestamp
+- Transición ilegal → OrderError::InvalidTransition { from, to }
+*/
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use crate::modules_demo::domain::order_event_sourced::EventSourcedOrderRepository;
+    use crate::modules_demo::domain::product::Product;
+    use crate::modules_demo::domain::user::{SqliteUserRepository, User};
+    use crate::modules_demo::shared::testing::{FaultyRepository, backend_tests};
+    use crate::modules_demo::shared::{AuditAction, Currency, ManualClock, SortOrder};
+    use std::sync::Arc;
 
-    #[test]
-    fn test_create_order() {
-        let mut service = OrderService::new();
-        let items = vec![OrderItem {
-            product_id: 1,
-            quantity: 2,
-            price: 15.0,
-        }];
-
-        let order = service.create_order(1, items).unwrap();
-        assert_eq!(order.total, 30.0);
+    /// Crea los usuarios 1 y 2 (orders.user_id es foreign key a users).
+    pub(crate) fn seed_users(db: &SqliteDatabase) {
+        let mut users = SqliteUserRepository::new(db.clone()).unwrap();
+        for id in 1..=2 {
+            users
+                .save(User {
+                    id: UserId(id),
+                    name: format!("user{id}"),
+                    email: format!("user{id}@test.com"),
+                    version: 1,
+                })
+                .unwrap();
+        }
+    }
+
+    /// OrderService sobre SQLite, con los usuarios 1 y 2 ya creados.
+    pub(crate) fn sqlite_orders(db: &SqliteDatabase) -> OrderService<SqliteOrderRepository> {
+        seed_users(db);
+        OrderService::with_repository(SqliteOrderRepository::new(db.clone()).unwrap()).unwrap()
+    }
+
+    fn sqlite_service() -> OrderService<SqliteOrderRepository> {
+        sqlite_orders(&SqliteDatabase::open_in_memory().unwrap())
+    }
+
+    backend_tests! {
+        [
+            test_create_order,
+            test_create_order_total_is_exact,
+            test_create_order_reports_every_invalid_line,
+            test_create_order_mixed_currencies_fails,
+            test_price_comes_from_catalog,
+            test_create_order_reserves_stock,
+            test_confirm_order,
+            test_cancel_returns_stock,
+            test_expired_reservations_cancel_pending_orders,
+            test_confirm_order_twice_fails,
+            test_confirm_without_reservation_fails,
+            test_full_lifecycle_records_history,
+            test_cancel_then_refund,
+            test_invalid_transitions_are_rejected,
+            test_typed_load_and_store,
+            test_ship_requires_tracking_number,
+            test_get_user_orders,
+            test_query_user_orders,
+            test_audit_tracks_order_changes,
+        ]
+        in_memory => OrderService::new(),
+        sqlite => sqlite_service(),
+        event_sourced => OrderService::with_repository(
+            EventSourcedOrderRepository::new().with_snapshot_every(3),
+        ).unwrap(),
+    }
+
+    fn usd(amount: &str) -> Money {
+        format!("{} USD", amount).parse().unwrap()
+    }
+
+    fn add_product<R: OrderRepository, P: ProductRepository>(
+        service: &mut OrderService<R, P>,
+        sku: &str,
+        price: Money,
+        stock: u32,
+    ) -> ProductId {
+        let product = service
+            .catalog_mut()
+            .add_product(sku, sku.to_string(), price, stock)
+            .unwrap();
+        product.id
+    }
+
+    fn pending_order<R: OrderRepository>(service: &mut OrderService<R>) -> Order {
+        let product = add_product(service, "WIDGET", usd("10.00"), 100);
+        service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .unwrap()
+    }
+
+    fn test_create_order<R: OrderRepository>(mut service: OrderService<R>) {
+        let product = add_product(&mut service, "WIDGET", usd("15.00"), 10);
+
+        let order = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 2)])
+            .unwrap();
+        assert_eq!(order.total, usd("30.00"));
+        assert_eq!(order.items[0].price, usd("15.00"));
         assert_eq!(order.status, OrderStatus::Pending);
     }
 
-    #[test]
-    fn test_confirm_order() {
-        let mut service = OrderService::new();
-        let items = vec![OrderItem {
-            product_id: 1,
-            quantity: 1,
-            price: 10.0,
-        }];
-
-        let order = service.create_order(1, items).unwrap();
+    fn test_create_order_reports_every_invalid_line<R: OrderRepository>(
+        mut service: OrderService<R>,
+    ) {
+        let product = add_product(&mut service, "WIDGET", usd("1.00"), 10);
+        let lines = vec![
+            OrderLine::new(product, 2),
+            OrderLine::new(product, 0),
+            OrderLine::new(product, 0),
+        ];
+
+        let err = service.create_order(UserId(1), lines).unwrap_err();
+        let OrderError::Invalid(errors) = &err else {
+            panic!("expected validation errors, got {err:?}");
+        };
+        assert_eq!(errors.paths(), ["items[1].quantity", "items[2].quantity"]);
+        assert_eq!(err.code(), "ORDER_INVALID");
+        assert!(matches!(
+            service.create_order(UserId(1), Vec::new()),
+            Err(OrderError::Invalid(e)) if e.paths() == ["items"]
+        ));
+
+        // Nada reservado
+        let stock = service.catalog().get_product(product).unwrap().unwrap();
+        assert_eq!(stock.available(), 10);
+    }
+
+    fn test_create_order_total_is_exact<R: OrderRepository>(mut service: OrderService<R>) {
+        let a = add_product(&mut service, "A", usd("0.10"), 10);
+        let b = add_product(&mut service, "B", usd("0.20"), 10);
+
+        let lines = vec![OrderLine::new(a, 3), OrderLine::new(b, 1)];
+        let order = service.create_order(UserId(1), lines).unwrap();
+        assert_eq!(order.total, usd("0.50"));
+    }
+
+    fn test_create_order_mixed_currencies_fails<R: OrderRepository>(mut service: OrderService<R>) {
+        let a = add_product(&mut service, "A", usd("1.00"), 10);
+        let eur = Money::from_major(1, Currency::EUR).unwrap();
+        let b = add_product(&mut service, "B", eur, 10);
+
+        let lines = vec![OrderLine::new(a, 1), OrderLine::new(b, 1)];
+        let err = service.create_order(UserId(1), lines).unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::Money(MoneyError::CurrencyMismatch {
+                expected: Currency::USD,
+                found: Currency::EUR
+            })
+        );
+    }
+
+    fn test_price_comes_from_catalog<R: OrderRepository>(mut service: OrderService<R>) {
+        let product = add_product(&mut service, "WIDGET", usd("10.00"), 10);
+
+        let before = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .unwrap();
+        service
+            .catalog_mut()
+            .update_price(product, usd("12.00"))
+            .unwrap();
+        let after = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .unwrap();
+
+        assert_eq!(before.total, usd("10.00"));
+        assert_eq!(after.total, usd("12.00"));
+
+        let err = service
+            .create_order(UserId(1), vec![OrderLine::new(ProductId(99), 1)])
+            .unwrap_err();
+        assert_eq!(err.code(), "PRODUCT_NOT_FOUND");
+    }
+
+    fn test_create_order_reserves_stock<R: OrderRepository>(mut service: OrderService<R>) {
+        let product = add_product(&mut service, "WIDGET", usd("1.00"), 3);
+
+        service
+            .create_order(UserId(1), vec![OrderLine::new(product, 2)])
+            .unwrap();
+        assert_eq!(service.catalog().available(product).unwrap(), 1);
+
+        let err = service
+            .create_order(UserId(2), vec![OrderLine::new(product, 2)])
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::Catalog(ProductError::InsufficientStock {
+                id: product,
+                requested: 2,
+                available: 1
+            })
+        );
+    }
+
+    fn test_confirm_order<R: OrderRepository>(mut service: OrderService<R>) {
+        let product = add_product(&mut service, "WIDGET", usd("10.00"), 5);
+
+        let order = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .unwrap();
         service.confirm_order(order.id).unwrap();
 
-        let confirmed = service.repo.find_by_id(order.id).unwrap();
+        let confirmed = service.repo.find_by_id(order.id).unwrap().unwrap();
         assert_eq!(confirmed.status, OrderStatus::Confirmed);
+
+        let stock = service.catalog().get_product(product).unwrap().unwrap();
+        assert_eq!((stock.stock, stock.available()), (4, 4));
+    }
+
+    fn test_cancel_returns_stock<R: OrderRepository>(mut service: OrderService<R>) {
+        let product = add_product(&mut service, "WIDGET", usd("1.00"), 5);
+        let lines = vec![OrderLine::new(product, 2)];
+
+        let pending = service.create_order(UserId(1), lines.clone()).unwrap();
+        let confirmed = service.create_order(UserId(1), lines).unwrap();
+        service.confirm_order(confirmed.id).unwrap();
+        assert_eq!(service.catalog().available(product).unwrap(), 1);
+
+        service.cancel_order(pending.id, "a".to_string()).unwrap();
+        service.cancel_order(confirmed.id, "b".to_string()).unwrap();
+        assert_eq!(service.catalog().available(product).unwrap(), 5);
+    }
+
+    fn test_expired_reservations_cancel_pending_orders<R: OrderRepository>(
+        service: OrderService<R>,
+    ) {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let mut service = service
+            .with_clock(clock.clone())
+            .with_reservation_ttl(Duration::minutes(10));
+        let product = add_product(&mut service, "WIDGET", usd("1.00"), 5);
+        let lines = vec![OrderLine::new(product, 1)];
+
+        let stale = service.create_order(UserId(1), lines.clone()).unwrap();
+        let confirmed = service.create_order(UserId(1), lines).unwrap();
+        service.confirm_order(confirmed.id).unwrap();
+
+        clock.advance(Duration::minutes(9).num_milliseconds() as u64);
+        assert_eq!(service.expire_reservations(clock.now()).unwrap(), vec![]);
+
+        clock.advance(Duration::minutes(2).num_milliseconds() as u64);
+        assert_eq!(
+            service.expire_reservations(clock.now()).unwrap(),
+            vec![stale.id]
+        );
+
+        let cancelled = service.get_order(stale.id).unwrap().unwrap();
+        assert_eq!(cancelled.status, OrderStatus::Cancelled);
+        assert_eq!(
+            cancelled.cancellation_reason.as_deref(),
+            Some("reservation expired")
+        );
+        assert_eq!(service.catalog().available(product).unwrap(), 4);
+        assert_eq!(cancelled.history.last().unwrap().at, clock.now());
+    }
+
+    impl ProductRepository for FaultyRepository<Product> {}
+
+    #[test]
+    fn test_expiry_handles_each_order_and_stamps_the_given_instant() {
+        let (repo, faults) = FaultyRepository::new();
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let mut service = OrderService::new()
+            .with_catalog(ProductService::with_repository(repo).unwrap())
+            .with_clock(clock.clone())
+            .with_reservation_ttl(Duration::minutes(10));
+        let product = add_product(&mut service, "WIDGET", usd("1.00"), 5);
+        let lines = vec![OrderLine::new(product, 1)];
+        let first = service.create_order(UserId(1), lines.clone()).unwrap();
+        let second = service.create_order(UserId(1), lines).unwrap();
+
+        // El job corre más tarde que el vencimiento: manda `now`, no el reloj
+        let now = clock.now() + Duration::minutes(11);
+        clock.advance(Duration::hours(1).num_milliseconds() as u64);
+
+        faults.fail_writes(true);
+        assert!(service.expire_reservations(now).is_err());
+        // La primera se canceló pero no pudo liberar: sigue vencida
+        assert_eq!(
+            service.catalog().expired_reservations(now).unwrap(),
+            vec![first.id, second.id]
+        );
+        assert_eq!(
+            service.get_order(second.id).unwrap().unwrap().status,
+            OrderStatus::Pending
+        );
+
+        faults.fail_writes(false);
+        assert_eq!(
+            service.expire_reservations(now).unwrap(),
+            vec![first.id, second.id]
+        );
+        assert_eq!(service.catalog().available(product).unwrap(), 5);
+        for id in [first.id, second.id] {
+            let order = service.get_order(id).unwrap().unwrap();
+            assert_eq!(order.status, OrderStatus::Cancelled);
+            assert_eq!(order.history.last().unwrap().at, now);
+        }
+    }
+
+    #[test]
+    fn test_cancel_keeps_the_order_when_stock_cannot_return() {
+        let (repo, faults) = FaultyRepository::new();
+        let mut service =
+            OrderService::new().with_catalog(ProductService::with_repository(repo).unwrap());
+        let product = add_product(&mut service, "WIDGET", usd("1.00"), 5);
+        let lines = vec![OrderLine::new(product, 2)];
+        let pending = service.create_order(UserId(1), lines.clone()).unwrap();
+        let confirmed = service.create_order(UserId(1), lines).unwrap();
+        service.confirm_order(confirmed.id).unwrap();
+
+        faults.fail_writes(true);
+        for id in [pending.id, confirmed.id] {
+            assert!(
+                service
+                    .cancel_order(id, "changed my mind".to_string())
+                    .is_err()
+            );
+        }
+        assert_eq!(
+            service.get_order(pending.id).unwrap().unwrap().status,
+            OrderStatus::Pending
+        );
+        assert_eq!(
+            service.get_order(confirmed.id).unwrap().unwrap().status,
+            OrderStatus::Confirmed
+        );
+
+        // El reintento termina lo que faltaba, sin reponer de más
+        faults.fail_writes(false);
+        for id in [pending.id, confirmed.id] {
+            service
+                .cancel_order(id, "changed my mind".to_string())
+                .unwrap();
+        }
+        let stock = service.catalog().get_product(product).unwrap().unwrap();
+        assert_eq!((stock.stock, stock.available()), (5, 5));
+    }
+
+    fn test_confirm_order_twice_fails<R: OrderRepository>(mut service: OrderService<R>) {
+        let order = pending_order(&mut service);
+        service.confirm_order(order.id).unwrap();
+
+        let err = service.confirm_order(order.id).unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::InvalidTransition {
+                id: order.id,
+                from: OrderStatus::Confirmed,
+                to: OrderStatus::Confirmed
+            }
+        );
+        assert_eq!(err.code(), "ORDER_INVALID_TRANSITION");
+    }
+
+    fn test_confirm_without_reservation_fails<R: OrderRepository>(mut service: OrderService<R>) {
+        let order = pending_order(&mut service);
+        let product = order.items[0].product_id;
+        service.catalog_mut().release(order.id).unwrap();
+
+        let err = service.confirm_order(order.id).unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::Catalog(ProductError::NoReservation { order_id: order.id })
+        );
+        assert_eq!(err.code(), "PRODUCT_NO_RESERVATION");
+
+        let unchanged = service.get_order(order.id).unwrap().unwrap();
+        assert_eq!(unchanged.status, OrderStatus::Pending);
+        assert_eq!(service.catalog().available(product).unwrap(), 100);
+    }
+
+    fn test_full_lifecycle_records_history<R: OrderRepository>(service: OrderService<R>) {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let mut service = service.with_clock(Arc::clone(&clock));
+        let order = pending_order(&mut service);
+
+        let mut times = Vec::new();
+        for step in 0..3 {
+            clock.advance(1_000);
+            times.push(clock.now());
+            match step {
+                0 => service.confirm_order(order.id),
+                1 => service.ship_order(order.id, "TRACK-123".to_string()),
+                _ => service.deliver_order(order.id),
+            }
+            .unwrap();
+        }
+
+        let delivered = service.get_order(order.id).unwrap().unwrap();
+        assert_eq!(delivered.status, OrderStatus::Delivered);
+        assert_eq!(delivered.tracking_number.as_deref(), Some("TRACK-123"));
+
+        let steps: Vec<_> = delivered.history.iter().map(|c| (c.from, c.to)).collect();
+        assert_eq!(
+            steps,
+            vec![
+                (OrderStatus::Pending, OrderStatus::Confirmed),
+                (OrderStatus::Confirmed, OrderStatus::Shipped),
+                (OrderStatus::Shipped, OrderStatus::Delivered),
+            ]
+        );
+        let at: Vec<_> = delivered.history.iter().map(|c| c.at).collect();
+        assert_eq!(at, times);
+    }
+
+    fn test_cancel_then_refund<R: OrderRepository>(mut service: OrderService<R>) {
+        let order = pending_order(&mut service);
+
+        service
+            .cancel_order(order.id, "customer request".to_string())
+            .unwrap();
+        service.refund_order(order.id).unwrap();
+
+        let refunded = service.get_order(order.id).unwrap().unwrap();
+        assert_eq!(refunded.status, OrderStatus::Refunded);
+        assert_eq!(
+            refunded.cancellation_reason.as_deref(),
+            Some("customer request")
+        );
+        assert!(refunded.status.is_terminal());
+    }
+
+    fn test_invalid_transitions_are_rejected<R: OrderRepository>(mut service: OrderService<R>) {
+        let order = pending_order(&mut service);
+
+        // No se puede entregar sin enviar, ni reembolsar sin cancelar
+        assert!(matches!(
+            service.deliver_order(order.id),
+            Err(OrderError::InvalidTransition { .. })
+        ));
+        assert!(matches!(
+            service.refund_order(order.id),
+            Err(OrderError::InvalidTransition { .. })
+        ));
+
+        // Una orden enviada ya no se puede cancelar
+        service.confirm_order(order.id).unwrap();
+        service.ship_order(order.id, "T-1".to_string()).unwrap();
+        let err = service
+            .cancel_order(order.id, "too late".to_string())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::InvalidTransition {
+                id: order.id,
+                from: OrderStatus::Shipped,
+                to: OrderStatus::Cancelled
+            }
+        );
+
+        // El intento fallido no deja rastro
+        let shipped = service.get_order(order.id).unwrap().unwrap();
+        assert_eq!(shipped.status, OrderStatus::Shipped);
+        assert!(shipped.cancellation_reason.is_none());
+        assert_eq!(shipped.history.len(), 2);
+    }
+
+    fn test_typed_load_and_store<R: OrderRepository>(mut service: OrderService<R>) {
+        use typed::{Confirmed, Pending};
+
+        let order = pending_order(&mut service);
+
+        let pending = service.load::<Pending>(order.id).unwrap();
+        service.store(pending.confirm(Utc::now())).unwrap();
+
+        assert!(service.load::<Confirmed>(order.id).is_ok());
+        assert!(matches!(
+            service.load::<Pending>(order.id),
+            Err(OrderError::UnexpectedStatus { .. })
+        ));
+    }
+
+    fn test_ship_requires_tracking_number<R: OrderRepository>(mut service: OrderService<R>) {
+        let order = pending_order(&mut service);
+        service.confirm_order(order.id).unwrap();
+
+        let err = service.ship_order(order.id, " ".to_string()).unwrap_err();
+        assert!(matches!(err, OrderError::Invalid(e) if e.paths() == ["tracking_number"]));
+    }
+
+    fn test_get_user_orders<R: OrderRepository>(mut service: OrderService<R>) {
+        let first = pending_order(&mut service);
+        let product = first.items[0].product_id;
+        let second = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 3)])
+            .unwrap();
+        service
+            .create_order(UserId(2), vec![OrderLine::new(product, 1)])
+            .unwrap();
+
+        // Ordenadas por id, sin importar el backend
+        let ids: Vec<_> = service
+            .get_user_orders(UserId(1))
+            .unwrap()
+            .into_iter()
+            .map(|o| o.id)
+            .collect();
+        assert_eq!(ids, vec![first.id, second.id]);
+        assert!(service.get_user_orders(UserId(9)).unwrap().is_empty());
+    }
+
+    fn test_query_user_orders<R: OrderRepository>(mut service: OrderService<R>) {
+        let first = pending_order(&mut service);
+        let product = first.items[0].product_id;
+        let mut next_order = || {
+            service
+                .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+                .unwrap()
+                .id
+        };
+        let (second, third) = (next_order(), next_order());
+        service.confirm_order(second).unwrap();
+
+        let pending = || {
+            Query::new()
+                .where_eq(|o: &Order| o.status, OrderStatus::Pending)
+                .sort_by(|o| o.id, SortOrder::Desc)
+                .limit(1)
+        };
+        let page = service.query_user_orders(UserId(1), &pending()).unwrap();
+        assert_eq!(page.items[0].id, third);
+        assert_eq!(page.total, 2);
+
+        let rest = service
+            .query_user_orders(UserId(1), &pending().after(page.next.unwrap()))
+            .unwrap();
+        assert_eq!(rest.items[0].id, first.id);
+        assert_eq!(rest.next, None);
+    }
+
+    fn test_audit_tracks_order_changes<R: OrderRepository>(service: OrderService<R>) {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let log = AuditLog::in_memory().with_clock(Arc::clone(&clock));
+        let mut service = service.with_audit(log.clone());
+        let order = pending_order(&mut service);
+
+        clock.advance(1_000);
+        service.set_actor("warehouse");
+        service
+            .ship_order(order.id, "TRACK-1".to_string())
+            .unwrap_err();
+        service.confirm_order(order.id).unwrap();
+        service.ship_order(order.id, "TRACK-1".to_string()).unwrap();
+
+        let history = log.for_entity(Order::AUDIT_TYPE, order.id).unwrap();
+        let actions: Vec<_> = history
+            .iter()
+            .map(|e| (e.action, e.actor.as_str()))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (AuditAction::Created, "system"),
+                (AuditAction::Updated, "warehouse"),
+                (AuditAction::Updated, "warehouse"),
+            ]
+        );
+        let shipped = history[2].change("tracking_number").unwrap();
+        assert_eq!(
+            (shipped.before.clone(), shipped.after.clone()),
+            (Some(serde_json::Value::Null), Some("TRACK-1".into()))
+        );
+
+        let created: Order = log
+            .as_of(Order::AUDIT_TYPE, order.id, history[0].at)
+            .unwrap()
+            .unwrap();
+        assert_eq!(created, order);
+    }
+
+    #[test]
+    fn test_rolled_back_order_leaves_no_audit_entry() {
+        use crate::modules_demo::shared::UnitOfWork;
+
+        let log = AuditLog::in_memory();
+        let mut service = OrderService::new().with_audit(log.clone());
+        let product = add_product(&mut service, "PEN", usd("1.00"), 1);
+
+        let failed: Result<Order, OrderError> = UnitOfWork::new(&mut service).run(|service| {
+            let order = service.create_order(UserId(1), vec![OrderLine::new(product, 1)])?;
+            service.confirm_order(order.id)?;
+            service.confirm_order(order.id)?;
+            Ok(order)
+        });
+
+        assert!(failed.is_err());
+        assert!(log.by_actor("system").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sqlite_foreign_keys() {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        let mut service = sqlite_orders(&db);
+        let product = add_product(&mut service, "WIDGET", usd("1.00"), 5);
+
+        // Usuario inexistente: lo rechaza el esquema y la reserva se libera
+        let err = service
+            .create_order(UserId(99), vec![OrderLine::new(product, 2)])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            OrderError::Repository(RepositoryError::ForeignKeyViolation(_))
+        ));
+        assert_eq!(service.catalog().available(product).unwrap(), 5);
+
+        // Un usuario con órdenes no se puede borrar
+        service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .unwrap();
+        let mut users = SqliteUserRepository::new(db).unwrap();
+        assert!(matches!(
+            users.delete(UserId(1)),
+            Err(RepositoryError::ForeignKeyViolation(_))
+        ));
+        assert!(users.exists(UserId(1)).unwrap());
     }
 }
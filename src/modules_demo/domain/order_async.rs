@@ -0,0 +1,864 @@
+// OrderService async: misma state machine que order.rs, usable desde tokio
+// El catálogo (sync) corre detrás de SpawnBlocking, fuera del runtime
+
+use super::events::{DomainEvent, DomainEventBus};
+use super::order::{
+    self, ORDERS_BY_USER, Order, OrderError, OrderItem, OrderLine, OrderRepository, OrderService,
+    OrderStatus, SqliteOrderRepository,
+};
+use super::order_typestate::{self as typed, OrderState};
+use super::product::{InMemoryProductRepository, ProductRepository, ProductService};
+use crate::modules_demo::shared::async_repository::resume_ids;
+use crate::modules_demo::shared::{
+    AsyncInMemoryRepository, AsyncRepository, Clock, IdGenerator, OrderId, Page, Query, RepoResult,
+    SequentialIdGenerator, SpawnBlocking, SystemClock, UserId, Validator,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+
+// ============================================================
+// REPOSITORY
+// ============================================================
+
+/// Búsquedas específicas de Order sobre el AsyncRepository genérico.
+#[async_trait]
+pub trait AsyncOrderRepository: AsyncRepository<Order, OrderId> {
+    async fn find_by_user(&self, user_id: UserId) -> RepoResult<Vec<Order>> {
+        self.find_indexed(ORDERS_BY_USER, &user_id.0.to_string())
+            .await
+    }
+}
+
+pub type AsyncInMemoryOrderRepository = AsyncInMemoryRepository<Order>;
+
+impl AsyncOrderRepository for AsyncInMemoryOrderRepository {}
+
+#[async_trait]
+impl<R: OrderRepository + Send + 'static> AsyncOrderRepository for SpawnBlocking<R> {
+    async fn find_by_user(&self, user_id: UserId) -> RepoResult<Vec<Order>> {
+        self.run(move |repo| repo.find_by_user(user_id)).await
+    }
+}
+
+pub type BlockingSqliteOrderRepository = SpawnBlocking<SqliteOrderRepository>;
+
+// ============================================================
+// SERVICE
+// ============================================================
+
+pub struct AsyncOrderService<R = AsyncInMemoryOrderRepository, P = InMemoryProductRepository> {
+    repo: R,
+    catalog: SpawnBlocking<ProductService<P>>,
+    ids: Box<dyn IdGenerator>,
+    clock: Box<dyn Clock>,
+    reservation_ttl: Duration,
+    events: DomainEventBus,
+}
+
+impl AsyncOrderService {
+    pub fn new() -> Self {
+        Self {
+            repo: AsyncInMemoryOrderRepository::new(),
+            catalog: SpawnBlocking::new(ProductService::new()),
+            ids: Box::new(SequentialIdGenerator::new()),
+            clock: Box::new(SystemClock),
+            reservation_ttl: <OrderService>::DEFAULT_RESERVATION_TTL,
+            events: DomainEventBus::new(),
+        }
+    }
+}
+
+impl Default for AsyncOrderService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: AsyncOrderRepository> AsyncOrderService<R> {
+    pub async fn with_repository(repo: R) -> RepoResult<Self> {
+        let ids = resume_ids(&repo).await?;
+
+        Ok(Self {
+            repo,
+            catalog: SpawnBlocking::new(ProductService::new()),
+            ids: Box::new(ids),
+            clock: Box::new(SystemClock),
+            reservation_ttl: <OrderService>::DEFAULT_RESERVATION_TTL,
+            events: DomainEventBus::new(),
+        })
+    }
+}
+
+impl<R, P> AsyncOrderService<R, P>
+where
+    R: AsyncOrderRepository,
+    P: ProductRepository + Send + 'static,
+{
+    /// Reemplaza el catálogo (y su backend) del que salen precios y stock.
+    pub fn with_catalog<P2: ProductRepository + Send + 'static>(
+        self,
+        catalog: ProductService<P2>,
+    ) -> AsyncOrderService<R, P2> {
+        AsyncOrderService {
+            repo: self.repo,
+            catalog: SpawnBlocking::new(catalog),
+            ids: self.ids,
+            clock: self.clock,
+            reservation_ttl: self.reservation_ttl,
+            events: self.events,
+        }
+    }
+
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    /// Reloj del vencimiento de reservas (p.ej. `ManualClock` en tests).
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn with_reservation_ttl(mut self, ttl: Duration) -> Self {
+        self.reservation_ttl = ttl;
+        self
+    }
+
+    pub fn with_event_bus(mut self, events: DomainEventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn events(&self) -> &DomainEventBus {
+        &self.events
+    }
+
+    /// El catálogo compartido: `catalog().run(|c| c.add_product(..)).await`.
+    pub fn catalog(&self) -> &SpawnBlocking<ProductService<P>> {
+        &self.catalog
+    }
+
+    /// Precios del catálogo + reserva de stock, como en OrderService.
+    pub async fn create_order(
+        &self,
+        user_id: UserId,
+        lines: Vec<OrderLine>,
+    ) -> Result<Order, OrderError> {
+        let mut v = Validator::new();
+        order::validate_items(&mut v, &lines);
+        v.finish()?;
+
+        let id = OrderId(self.ids.next_id()?);
+        let expires_at = self.clock.now() + self.reservation_ttl;
+
+        // Precios y reserva en una sola llamada al catálogo: ninguna otra
+        // task puede cambiar un precio entre leerlo y reservar
+        let order = self
+            .catalog
+            .run(move |catalog| {
+                let mut items = Vec::with_capacity(lines.len());
+                for line in &lines {
+                    items.push(OrderItem {
+                        product_id: line.product_id,
+                        quantity: line.quantity,
+                        price: catalog.price_of(line.product_id)?,
+                    });
+                }
+
+                let order: Order = typed::Order::new(id, user_id, items)?.into();
+
+                let reserved: Vec<_> = lines.iter().map(|l| (l.product_id, l.quantity)).collect();
+                catalog.reserve(id, &reserved, expires_at)?;
+                Ok::<_, OrderError>(order)
+            })
+            .await?;
+
+        // Sin orden guardada, la reserva no debe quedar colgada
+        if let Err(e) = self.repo.insert(order.clone()).await {
+            self.catalog.run(move |catalog| catalog.release(id)).await?;
+            return Err(e.into());
+        }
+
+        self.events.publish(DomainEvent::OrderPlaced {
+            order_id: id,
+            user_id,
+            total: order.total,
+        });
+        Ok(order)
+    }
+
+    pub async fn get_order(&self, order_id: OrderId) -> Result<Option<Order>, OrderError> {
+        Ok(self.repo.find_by_id(order_id).await?)
+    }
+
+    /// Carga la orden con su estado verificado en el tipo (ver order_typestate).
+    pub async fn load<S: OrderState>(
+        &self,
+        order_id: OrderId,
+    ) -> Result<typed::Order<S>, OrderError> {
+        let order = self
+            .repo
+            .find_by_id(order_id)
+            .await?
+            .ok_or(OrderError::NotFound { id: order_id })?;
+
+        typed::Order::try_from(order)
+    }
+
+    pub async fn store<S: OrderState>(&self, order: typed::Order<S>) -> Result<(), OrderError> {
+        Ok(self.repo.save(order.into()).await?)
+    }
+
+    /// Confirmar convierte la reserva en salida definitiva de stock. Como
+    /// en OrderService, el stock sale antes de guardar la orden: sin
+    /// reserva, la orden sigue Pending.
+    pub async fn confirm_order(&self, order_id: OrderId) -> Result<(), OrderError> {
+        let order = self
+            .prepare(order_id, |order, at| {
+                order.transition_to(OrderStatus::Confirmed, at)
+            })
+            .await?;
+        self.catalog
+            .run(move |catalog| catalog.commit(order_id))
+            .await?;
+        self.repo.save(order.clone()).await?;
+
+        self.events.publish(DomainEvent::OrderConfirmed {
+            order_id,
+            user_id: order.user_id,
+        });
+        Ok(())
+    }
+
+    pub async fn ship_order(
+        &self,
+        order_id: OrderId,
+        tracking_number: String,
+    ) -> Result<(), OrderError> {
+        order::validate_tracking_number(&tracking_number)?;
+
+        self.apply(order_id, |order, at| {
+            order.transition_to(OrderStatus::Shipped, at)?;
+            order.tracking_number = Some(tracking_number.clone());
+            Ok(())
+        })
+        .await?;
+
+        self.events.publish(DomainEvent::OrderShipped {
+            order_id,
+            tracking_number,
+        });
+        Ok(())
+    }
+
+    pub async fn deliver_order(&self, order_id: OrderId) -> Result<(), OrderError> {
+        self.apply(order_id, |order, at| {
+            order.transition_to(OrderStatus::Delivered, at)
+        })
+        .await?;
+
+        self.events
+            .publish(DomainEvent::OrderDelivered { order_id });
+        Ok(())
+    }
+
+    /// Cancelar devuelve el stock. Como en OrderService, el stock vuelve
+    /// antes de guardar la orden: si falla, la orden sigue en su estado.
+    pub async fn cancel_order(&self, order_id: OrderId, reason: String) -> Result<(), OrderError> {
+        order::validate_cancellation_reason(&reason)?;
+
+        let mut previous = OrderStatus::Pending;
+        let order = self
+            .prepare(order_id, |order, at| {
+                previous = order.status;
+                order.transition_to(OrderStatus::Cancelled, at)?;
+                order.cancellation_reason = Some(reason.clone());
+                Ok(())
+            })
+            .await?;
+
+        // Pending: se libera la reserva. Confirmed: el stock vuelve al depósito.
+        let items = order.items.clone();
+        self.catalog
+            .run(move |catalog| {
+                if previous == OrderStatus::Confirmed {
+                    for item in &items {
+                        catalog.restock(item.product_id, item.quantity)?;
+                    }
+                }
+                catalog.release(order_id)
+            })
+            .await?;
+        self.repo.save(order).await?;
+
+        self.events
+            .publish(DomainEvent::OrderCancelled { order_id, reason });
+        Ok(())
+    }
+
+    /// Cancela las órdenes pendientes cuya reserva venció y devuelve sus ids.
+    /// Orden por orden, como `OrderService::expire_reservations`.
+    pub async fn expire_reservations(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<OrderId>, OrderError> {
+        let candidates = self
+            .catalog
+            .run(move |catalog| catalog.expired_reservations(now))
+            .await?;
+
+        let mut expired = Vec::new();
+        for order_id in candidates {
+            let cancelled = self
+                .apply(order_id, move |order, _| {
+                    if order.status != OrderStatus::Pending {
+                        return Ok(());
+                    }
+                    order.transition_to(OrderStatus::Cancelled, now)?;
+                    order.cancellation_reason = Some("reservation expired".to_string());
+                    Ok(())
+                })
+                .await?;
+            self.catalog
+                .run(move |catalog| catalog.release(order_id))
+                .await?;
+
+            if cancelled.status == OrderStatus::Cancelled {
+                self.events.publish(DomainEvent::OrderCancelled {
+                    order_id,
+                    reason: "reservation expired".to_string(),
+                });
+                expired.push(order_id);
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Solo una orden cancelada puede reembolsarse.
+    pub async fn refund_order(&self, order_id: OrderId) -> Result<(), OrderError> {
+        self.apply(order_id, |order, at| {
+            order.transition_to(OrderStatus::Refunded, at)
+        })
+        .await?;
+
+        self.events.publish(DomainEvent::OrderRefunded { order_id });
+        Ok(())
+    }
+
+    pub async fn get_user_orders(&self, user_id: UserId) -> Result<Vec<Order>, OrderError> {
+        self.query_user_orders(user_id, &Query::new())
+            .await
+            .map(|page| page.items)
+    }
+
+    pub async fn query_user_orders(
+        &self,
+        user_id: UserId,
+        query: &Query<Order>,
+    ) -> Result<Page<Order>, OrderError> {
+        Ok(query.run(self.repo.find_by_user(user_id).await?))
+    }
+
+    // Carga → muta → guarda. Entre la carga y el guardado otra task puede
+    // modificar la misma orden: gana la última escritura.
+    async fn apply<F>(&self, order_id: OrderId, command: F) -> Result<Order, OrderError>
+    where
+        F: FnOnce(&mut Order, DateTime<Utc>) -> Result<(), OrderError> + Send,
+    {
+        let order = self.prepare(order_id, command).await?;
+        self.repo.save(order.clone()).await?;
+        Ok(order)
+    }
+
+    // Carga → muta, sin guardar.
+    async fn prepare<F>(&self, order_id: OrderId, command: F) -> Result<Order, OrderError>
+    where
+        F: FnOnce(&mut Order, DateTime<Utc>) -> Result<(), OrderError> + Send,
+    {
+        let mut order = self
+            .repo
+            .find_by_id(order_id)
+            .await?
+            .ok_or(OrderError::NotFound { id: order_id })?;
+
+        command(&mut order, self.clock.now())?;
+        Ok(order)
+    }
+}
+
+/*
+QUÉ CORRE DÓNDE:
+
+  AsyncOrderService::create_order
+     │ catalog.run(...)         → pool bloqueante: precios + reserva
+     │ repo.insert(...).await   → AsyncRepository (tokio RwLock o SpawnBlocking)
+     └ si el insert falla       → catalog.run(release)
+
+- ProductService sigue siendo sync: queda detrás de un Mutex y cada
+  llamada es una unidad (la reserva "todo o nada" no se intercala)
+- apply (cargar → mutar → guardar) NO es atómico entre tasks: dos
+  confirm concurrentes sobre la misma orden pueden pisarse
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::domain::order::tests::seed_users;
+    use crate::modules_demo::domain::product::ProductError;
+    use crate::modules_demo::shared::testing::backend_tests;
+    use crate::modules_demo::shared::{
+        Currency, ErrorCode, ManualClock, Money, MoneyError, ProductId, SqliteDatabase,
+    };
+    use std::sync::Arc;
+
+    async fn in_memory_service() -> AsyncOrderService {
+        AsyncOrderService::new()
+    }
+
+    async fn sqlite_service() -> AsyncOrderService<BlockingSqliteOrderRepository> {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        seed_users(&db);
+        let repo = SqliteOrderRepository::new(db).unwrap();
+        AsyncOrderService::with_repository(SpawnBlocking::new(repo))
+            .await
+            .unwrap()
+    }
+
+    // Espejo de los tests de order.rs, como #[tokio::test]
+    backend_tests! {
+        async [
+            test_create_order,
+            test_create_order_total_is_exact,
+            test_create_order_mixed_currencies_fails,
+            test_price_comes_from_catalog,
+            test_create_order_reserves_stock,
+            test_confirm_order,
+            test_cancel_returns_stock,
+            test_expired_reservations_cancel_pending_orders,
+            test_confirm_order_twice_fails,
+            test_confirm_without_reservation_fails,
+            test_full_lifecycle_records_history,
+            test_cancel_then_refund,
+            test_invalid_transitions_are_rejected,
+            test_typed_load_and_store,
+            test_ship_requires_tracking_number,
+            test_get_user_orders,
+        ]
+        in_memory => in_memory_service(),
+        sqlite => sqlite_service(),
+    }
+
+    fn usd(amount: &str) -> Money {
+        format!("{} USD", amount).parse().unwrap()
+    }
+
+    async fn add_product<R: AsyncOrderRepository>(
+        service: &AsyncOrderService<R>,
+        sku: &'static str,
+        price: Money,
+        stock: u32,
+    ) -> ProductId {
+        let product = service
+            .catalog()
+            .run(move |catalog| catalog.add_product(sku, sku.to_string(), price, stock))
+            .await
+            .unwrap();
+        product.id
+    }
+
+    async fn available<R: AsyncOrderRepository>(
+        service: &AsyncOrderService<R>,
+        product: ProductId,
+    ) -> u32 {
+        service
+            .catalog()
+            .run(move |catalog| catalog.available(product))
+            .await
+            .unwrap()
+    }
+
+    async fn pending_order<R: AsyncOrderRepository>(service: &AsyncOrderService<R>) -> Order {
+        let product = add_product(service, "WIDGET", usd("10.00"), 100).await;
+        service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .await
+            .unwrap()
+    }
+
+    async fn test_create_order<R: AsyncOrderRepository>(service: AsyncOrderService<R>) {
+        let product = add_product(&service, "WIDGET", usd("15.00"), 10).await;
+
+        let order = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 2)])
+            .await
+            .unwrap();
+        assert_eq!(order.total, usd("30.00"));
+        assert_eq!(order.items[0].price, usd("15.00"));
+        assert_eq!(order.status, OrderStatus::Pending);
+    }
+
+    async fn test_create_order_total_is_exact<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let a = add_product(&service, "A", usd("0.10"), 10).await;
+        let b = add_product(&service, "B", usd("0.20"), 10).await;
+
+        let lines = vec![OrderLine::new(a, 3), OrderLine::new(b, 1)];
+        let order = service.create_order(UserId(1), lines).await.unwrap();
+        assert_eq!(order.total, usd("0.50"));
+    }
+
+    async fn test_create_order_mixed_currencies_fails<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let a = add_product(&service, "A", usd("1.00"), 10).await;
+        let eur = Money::from_major(1, Currency::EUR).unwrap();
+        let b = add_product(&service, "B", eur, 10).await;
+
+        let lines = vec![OrderLine::new(a, 1), OrderLine::new(b, 1)];
+        let err = service.create_order(UserId(1), lines).await.unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::Money(MoneyError::CurrencyMismatch {
+                expected: Currency::USD,
+                found: Currency::EUR
+            })
+        );
+    }
+
+    async fn test_price_comes_from_catalog<R: AsyncOrderRepository>(service: AsyncOrderService<R>) {
+        let product = add_product(&service, "WIDGET", usd("10.00"), 10).await;
+
+        let before = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .await
+            .unwrap();
+        service
+            .catalog()
+            .run(move |catalog| catalog.update_price(product, usd("12.00")))
+            .await
+            .unwrap();
+        let after = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .await
+            .unwrap();
+
+        assert_eq!(before.total, usd("10.00"));
+        assert_eq!(after.total, usd("12.00"));
+
+        let err = service
+            .create_order(UserId(1), vec![OrderLine::new(ProductId(99), 1)])
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "PRODUCT_NOT_FOUND");
+    }
+
+    async fn test_create_order_reserves_stock<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let product = add_product(&service, "WIDGET", usd("1.00"), 3).await;
+
+        service
+            .create_order(UserId(1), vec![OrderLine::new(product, 2)])
+            .await
+            .unwrap();
+        assert_eq!(available(&service, product).await, 1);
+
+        let err = service
+            .create_order(UserId(2), vec![OrderLine::new(product, 2)])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::Catalog(ProductError::InsufficientStock {
+                id: product,
+                requested: 2,
+                available: 1
+            })
+        );
+    }
+
+    async fn test_confirm_order<R: AsyncOrderRepository>(service: AsyncOrderService<R>) {
+        let product = add_product(&service, "WIDGET", usd("10.00"), 5).await;
+
+        let order = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+            .await
+            .unwrap();
+        service.confirm_order(order.id).await.unwrap();
+
+        let confirmed = service.repo.find_by_id(order.id).await.unwrap().unwrap();
+        assert_eq!(confirmed.status, OrderStatus::Confirmed);
+
+        let stock = service
+            .catalog()
+            .run(move |catalog| catalog.get_product(product))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((stock.stock, stock.available()), (4, 4));
+    }
+
+    async fn test_cancel_returns_stock<R: AsyncOrderRepository>(service: AsyncOrderService<R>) {
+        let product = add_product(&service, "WIDGET", usd("1.00"), 5).await;
+        let lines = vec![OrderLine::new(product, 2)];
+
+        let pending = service
+            .create_order(UserId(1), lines.clone())
+            .await
+            .unwrap();
+        let confirmed = service.create_order(UserId(1), lines).await.unwrap();
+        service.confirm_order(confirmed.id).await.unwrap();
+        assert_eq!(available(&service, product).await, 1);
+
+        service
+            .cancel_order(pending.id, "a".to_string())
+            .await
+            .unwrap();
+        service
+            .cancel_order(confirmed.id, "b".to_string())
+            .await
+            .unwrap();
+        assert_eq!(available(&service, product).await, 5);
+    }
+
+    async fn test_expired_reservations_cancel_pending_orders<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let service = service
+            .with_clock(clock.clone())
+            .with_reservation_ttl(Duration::minutes(10));
+        let product = add_product(&service, "WIDGET", usd("1.00"), 5).await;
+        let lines = vec![OrderLine::new(product, 1)];
+
+        let stale = service
+            .create_order(UserId(1), lines.clone())
+            .await
+            .unwrap();
+        let confirmed = service.create_order(UserId(1), lines).await.unwrap();
+        service.confirm_order(confirmed.id).await.unwrap();
+
+        clock.advance(Duration::minutes(11).num_milliseconds() as u64);
+        assert_eq!(
+            service.expire_reservations(clock.now()).await.unwrap(),
+            vec![stale.id]
+        );
+
+        let cancelled = service.get_order(stale.id).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, OrderStatus::Cancelled);
+        assert_eq!(
+            cancelled.cancellation_reason.as_deref(),
+            Some("reservation expired")
+        );
+        assert_eq!(available(&service, product).await, 4);
+    }
+
+    async fn test_confirm_order_twice_fails<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let order = pending_order(&service).await;
+        service.confirm_order(order.id).await.unwrap();
+
+        let err = service.confirm_order(order.id).await.unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::InvalidTransition {
+                id: order.id,
+                from: OrderStatus::Confirmed,
+                to: OrderStatus::Confirmed
+            }
+        );
+        assert_eq!(err.code(), "ORDER_INVALID_TRANSITION");
+    }
+
+    async fn test_confirm_without_reservation_fails<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let order = pending_order(&service).await;
+        let product = order.items[0].product_id;
+        service
+            .catalog()
+            .run(move |catalog| catalog.release(order.id))
+            .await
+            .unwrap();
+
+        let err = service.confirm_order(order.id).await.unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::Catalog(ProductError::NoReservation { order_id: order.id })
+        );
+
+        let unchanged = service.get_order(order.id).await.unwrap().unwrap();
+        assert_eq!(unchanged.status, OrderStatus::Pending);
+        assert_eq!(available(&service, product).await, 100);
+    }
+
+    async fn test_full_lifecycle_records_history<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let order = pending_order(&service).await;
+
+        service.confirm_order(order.id).await.unwrap();
+        service
+            .ship_order(order.id, "TRACK-123".to_string())
+            .await
+            .unwrap();
+        service.deliver_order(order.id).await.unwrap();
+
+        let delivered = service.get_order(order.id).await.unwrap().unwrap();
+        assert_eq!(delivered.status, OrderStatus::Delivered);
+        assert_eq!(delivered.tracking_number.as_deref(), Some("TRACK-123"));
+
+        let steps: Vec<_> = delivered.history.iter().map(|c| (c.from, c.to)).collect();
+        assert_eq!(
+            steps,
+            vec![
+                (OrderStatus::Pending, OrderStatus::Confirmed),
+                (OrderStatus::Confirmed, OrderStatus::Shipped),
+                (OrderStatus::Shipped, OrderStatus::Delivered),
+            ]
+        );
+        assert!(delivered.history.windows(2).all(|w| w[0].at <= w[1].at));
+    }
+
+    async fn test_cancel_then_refund<R: AsyncOrderRepository>(service: AsyncOrderService<R>) {
+        let order = pending_order(&service).await;
+
+        service
+            .cancel_order(order.id, "customer request".to_string())
+            .await
+            .unwrap();
+        service.refund_order(order.id).await.unwrap();
+
+        let refunded = service.get_order(order.id).await.unwrap().unwrap();
+        assert_eq!(refunded.status, OrderStatus::Refunded);
+        assert_eq!(
+            refunded.cancellation_reason.as_deref(),
+            Some("customer request")
+        );
+        assert!(refunded.status.is_terminal());
+    }
+
+    async fn test_invalid_transitions_are_rejected<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let order = pending_order(&service).await;
+
+        // No se puede entregar sin enviar, ni reembolsar sin cancelar
+        assert!(matches!(
+            service.deliver_order(order.id).await,
+            Err(OrderError::InvalidTransition { .. })
+        ));
+        assert!(matches!(
+            service.refund_order(order.id).await,
+            Err(OrderError::InvalidTransition { .. })
+        ));
+
+        // Una orden enviada ya no se puede cancelar
+        service.confirm_order(order.id).await.unwrap();
+        service
+            .ship_order(order.id, "T-1".to_string())
+            .await
+            .unwrap();
+        let err = service
+            .cancel_order(order.id, "too late".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::InvalidTransition {
+                id: order.id,
+                from: OrderStatus::Shipped,
+                to: OrderStatus::Cancelled
+            }
+        );
+
+        // El intento fallido no deja rastro
+        let shipped = service.get_order(order.id).await.unwrap().unwrap();
+        assert_eq!(shipped.status, OrderStatus::Shipped);
+        assert!(shipped.cancellation_reason.is_none());
+        assert_eq!(shipped.history.len(), 2);
+    }
+
+    async fn test_typed_load_and_store<R: AsyncOrderRepository>(service: AsyncOrderService<R>) {
+        use typed::{Confirmed, Pending};
+
+        let order = pending_order(&service).await;
+
+        let pending = service.load::<Pending>(order.id).await.unwrap();
+        service.store(pending.confirm(Utc::now())).await.unwrap();
+
+        assert!(service.load::<Confirmed>(order.id).await.is_ok());
+        assert!(matches!(
+            service.load::<Pending>(order.id).await,
+            Err(OrderError::UnexpectedStatus { .. })
+        ));
+    }
+
+    async fn test_ship_requires_tracking_number<R: AsyncOrderRepository>(
+        service: AsyncOrderService<R>,
+    ) {
+        let order = pending_order(&service).await;
+        service.confirm_order(order.id).await.unwrap();
+
+        let err = service
+            .ship_order(order.id, " ".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, OrderError::Invalid(e) if e.paths() == ["tracking_number"]));
+    }
+
+    async fn test_get_user_orders<R: AsyncOrderRepository>(service: AsyncOrderService<R>) {
+        let first = pending_order(&service).await;
+        let product = first.items[0].product_id;
+        let second = service
+            .create_order(UserId(1), vec![OrderLine::new(product, 3)])
+            .await
+            .unwrap();
+        service
+            .create_order(UserId(2), vec![OrderLine::new(product, 1)])
+            .await
+            .unwrap();
+
+        let mut ids: Vec<_> = service
+            .get_user_orders(UserId(1))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|o| o.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![first.id, second.id]);
+        assert!(service.get_user_orders(UserId(9)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_orders_never_oversell() {
+        let service = AsyncOrderService::new();
+        let product = add_product(&service, "WIDGET", usd("1.00"), 5).await;
+        let service = std::sync::Arc::new(service);
+
+        let tasks: Vec<_> = (0..20)
+            .map(|_| {
+                let service = std::sync::Arc::clone(&service);
+                tokio::spawn(async move {
+                    service
+                        .create_order(UserId(1), vec![OrderLine::new(product, 1)])
+                        .await
+                })
+            })
+            .collect();
+
+        let mut placed = 0;
+        for task in tasks {
+            if task.await.unwrap().is_ok() {
+                placed += 1;
+            }
+        }
+        assert_eq!(placed, 5);
+        assert_eq!(available(&service, product).await, 0);
+        assert_eq!(service.get_user_orders(UserId(1)).await.unwrap().len(), 5);
+    }
+}
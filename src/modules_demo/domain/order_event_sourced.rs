@@ -0,0 +1,665 @@
+// Order event-sourced: no se guarda la orden, se guardan los hechos que le pasaron
+// El estado actual es un fold sobre sus eventos; snapshots cada N para no releer todo
+
+use super::order::{ORDERS_BY_USER, Order, OrderItem, OrderRepository, OrderStatus, StatusChange};
+use crate::modules_demo::shared::{
+    Currency, Entity, IdGenerator, InMemoryRepository, Money, OrderId, RepoResult, Repository,
+    RepositoryError, SequentialIdGenerator, Transactional, UserId,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Índice multi-valor: los eventos de una orden (su stream).
+pub const ORDER_STREAM: &str = "order_events.order_id";
+
+/// Cada cuántos eventos de un stream se guarda un snapshot por defecto.
+pub const DEFAULT_SNAPSHOT_EVERY: u64 = 10;
+
+// ============================================================
+// EVENTOS
+// ============================================================
+
+/// Lo que le pasó a una orden. Created fija usuario y moneda; el total
+/// sale de sumar los ItemAdded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OrderEvent {
+    Created {
+        order_id: OrderId,
+        user_id: UserId,
+        currency: Currency,
+    },
+    ItemAdded {
+        item: OrderItem,
+    },
+    Confirmed {
+        at: DateTime<Utc>,
+    },
+    Shipped {
+        tracking_number: String,
+        at: DateTime<Utc>,
+    },
+    Delivered {
+        at: DateTime<Utc>,
+    },
+    Cancelled {
+        reason: String,
+        at: DateTime<Utc>,
+    },
+    Refunded {
+        at: DateTime<Utc>,
+    },
+}
+
+/// Un evento ya guardado: posición global y posición en su stream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Orden global de escritura (lo que recorre una proyección).
+    pub id: u64,
+    pub order_id: OrderId,
+    /// 1, 2, 3... dentro del stream de `order_id`.
+    pub sequence: u64,
+    pub event: OrderEvent,
+}
+
+impl Entity for RecordedEvent {
+    type Id = u64;
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn index_keys(&self) -> Vec<(&'static str, String)> {
+        vec![(ORDER_STREAM, self.order_id.to_string())]
+    }
+}
+
+/// El estado después de aplicar los eventos hasta `sequence`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderSnapshot {
+    pub order_id: OrderId,
+    pub sequence: u64,
+    pub state: Order,
+}
+
+impl Entity for OrderSnapshot {
+    type Id = OrderId;
+
+    fn id(&self) -> OrderId {
+        self.order_id
+    }
+}
+
+// ============================================================
+// FOLD: eventos → estado
+// ============================================================
+
+/// Aplica un evento al estado. Un stream que no respeta las reglas de la
+/// orden (item antes de Created, transición ilegal) está corrupto.
+pub fn apply(state: Option<Order>, event: &OrderEvent) -> RepoResult<Order> {
+    let corrupted = |what: &str| RepositoryError::Corrupted(format!("order stream: {what}"));
+
+    let mut order = match (state, event) {
+        (
+            None,
+            OrderEvent::Created {
+                order_id,
+                user_id,
+                currency,
+            },
+        ) => {
+            return Ok(Order {
+                id: *order_id,
+                user_id: *user_id,
+                total: Money::zero(*currency),
+                items: Vec::new(),
+                status: OrderStatus::Pending,
+                tracking_number: None,
+                cancellation_reason: None,
+                history: Vec::new(),
+            });
+        }
+        (Some(_), OrderEvent::Created { .. }) => return Err(corrupted("created twice")),
+        (None, _) => return Err(corrupted("event before Created")),
+        (Some(order), _) => order,
+    };
+
+    let (next, at) = match event {
+        OrderEvent::Created { .. } => unreachable!("handled above"),
+        OrderEvent::ItemAdded { item } => {
+            order.total = item
+                .subtotal()
+                .and_then(|subtotal| order.total.checked_add(subtotal))
+                .map_err(|e| corrupted(&e.to_string()))?;
+            order.items.push(item.clone());
+            return Ok(order);
+        }
+        OrderEvent::Confirmed { at } => (OrderStatus::Confirmed, at),
+        OrderEvent::Shipped {
+            tracking_number,
+            at,
+        } => {
+            order.tracking_number = Some(tracking_number.clone());
+            (OrderStatus::Shipped, at)
+        }
+        OrderEvent::Delivered { at } => (OrderStatus::Delivered, at),
+        OrderEvent::Cancelled { reason, at } => {
+            order.cancellation_reason = Some(reason.clone());
+            (OrderStatus::Cancelled, at)
+        }
+        OrderEvent::Refunded { at } => (OrderStatus::Refunded, at),
+    };
+
+    if !order.status.can_transition_to(next) {
+        return Err(corrupted(&format!("{:?} → {next:?}", order.status)));
+    }
+    order.record_transition(next, *at);
+    Ok(order)
+}
+
+/// El estado de un stream completo (`None` si está vacío).
+pub fn replay<'a>(events: impl IntoIterator<Item = &'a OrderEvent>) -> RepoResult<Option<Order>> {
+    events
+        .into_iter()
+        .try_fold(None, |state, event| apply(state, event).map(Some))
+}
+
+// ============================================================
+// CAMBIO → EVENTOS
+// ============================================================
+
+/// Los eventos que llevan de `before` a `after`. Los comandos de
+/// OrderService validan y mutan la orden; acá se expresa lo que hicieron
+/// como hechos. Un cambio que ningún evento describe (editar el total,
+/// reescribir el historial) se rechaza.
+pub fn changes(before: Option<&Order>, after: &Order) -> RepoResult<Vec<OrderEvent>> {
+    let mut events = Vec::new();
+    let (items, history) = match before {
+        None => {
+            events.push(OrderEvent::Created {
+                order_id: after.id,
+                user_id: after.user_id,
+                currency: after.total.currency(),
+            });
+            (0, 0)
+        }
+        Some(before) => (before.items.len(), before.history.len()),
+    };
+
+    for item in after.items.iter().skip(items) {
+        events.push(OrderEvent::ItemAdded { item: item.clone() });
+    }
+    for change in after.history.iter().skip(history) {
+        events.push(transition_event(change, after)?);
+    }
+
+    // Lo que se va a guardar tiene que reconstruir exactamente `after`
+    let mut state = before.cloned();
+    for event in &events {
+        state = Some(apply(state, event)?);
+    }
+    if state.as_ref() != Some(after) {
+        return Err(RepositoryError::Storage(format!(
+            "order {} changed in a way no event describes",
+            after.id
+        )));
+    }
+    Ok(events)
+}
+
+fn transition_event(change: &StatusChange, order: &Order) -> RepoResult<OrderEvent> {
+    let at = change.at;
+    Ok(match change.to {
+        OrderStatus::Confirmed => OrderEvent::Confirmed { at },
+        OrderStatus::Shipped => OrderEvent::Shipped {
+            tracking_number: order.tracking_number.clone().unwrap_or_default(),
+            at,
+        },
+        OrderStatus::Delivered => OrderEvent::Delivered { at },
+        OrderStatus::Cancelled => OrderEvent::Cancelled {
+            reason: order.cancellation_reason.clone().unwrap_or_default(),
+            at,
+        },
+        OrderStatus::Refunded => OrderEvent::Refunded { at },
+        OrderStatus::Pending => {
+            return Err(RepositoryError::Storage(format!(
+                "order {} cannot go back to Pending",
+                order.id
+            )));
+        }
+    })
+}
+
+// ============================================================
+// PROYECCIÓN: find_by_user
+// ============================================================
+
+/// Read model user → orders. Se alimenta de los eventos en orden global y
+/// se puede tirar y reconstruir desde cero en cualquier momento.
+#[derive(Debug, Default, PartialEq)]
+pub struct UserOrders {
+    by_user: BTreeMap<UserId, BTreeSet<OrderId>>,
+}
+
+impl UserOrders {
+    pub fn rebuild(events: impl IntoIterator<Item = RecordedEvent>) -> Self {
+        let mut events: Vec<_> = events.into_iter().collect();
+        events.sort_by_key(|e| e.id);
+
+        let mut projection = Self::default();
+        for event in &events {
+            projection.apply(event);
+        }
+        projection
+    }
+
+    pub fn apply(&mut self, recorded: &RecordedEvent) {
+        if let OrderEvent::Created {
+            order_id, user_id, ..
+        } = recorded.event
+        {
+            self.by_user.entry(user_id).or_default().insert(order_id);
+        }
+    }
+
+    pub fn orders_of(&self, user_id: UserId) -> impl Iterator<Item = OrderId> + '_ {
+        self.by_user.get(&user_id).into_iter().flatten().copied()
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = OrderId> + '_ {
+        self.by_user.values().flatten().copied()
+    }
+
+    pub fn contains(&self, order_id: OrderId) -> bool {
+        self.by_user
+            .values()
+            .any(|orders| orders.contains(&order_id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_user.values().map(BTreeSet::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_user.is_empty()
+    }
+}
+
+// ============================================================
+// REPOSITORY
+// ============================================================
+
+/// Implementa OrderRepository: OrderService no sabe si guarda estados o
+/// eventos. `E` guarda los eventos (append-only), `S` los snapshots.
+pub struct EventSourcedOrderRepository<
+    E = InMemoryRepository<RecordedEvent>,
+    S = InMemoryRepository<OrderSnapshot>,
+> {
+    events: E,
+    snapshots: S,
+    by_user: UserOrders,
+    positions: SequentialIdGenerator,
+    snapshot_every: u64,
+}
+
+impl EventSourcedOrderRepository {
+    pub fn new() -> Self {
+        Self::with_stores(InMemoryRepository::new(), InMemoryRepository::new())
+            .expect("in-memory repository is always readable")
+    }
+}
+
+impl Default for EventSourcedOrderRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, S> EventSourcedOrderRepository<E, S>
+where
+    E: Repository<RecordedEvent, u64>,
+    S: Repository<OrderSnapshot, OrderId>,
+{
+    /// Backends ya poblados: la proyección se reconstruye desde los eventos.
+    /// Si no se pueden leer, el error sube (una proyección vacía mentiría).
+    pub fn with_stores(events: E, snapshots: S) -> RepoResult<Self> {
+        let mut repo = Self {
+            by_user: UserOrders::default(),
+            positions: SequentialIdGenerator::new(),
+            events,
+            snapshots,
+            snapshot_every: DEFAULT_SNAPSHOT_EVERY,
+        };
+        repo.rebuild_projection()?;
+        Ok(repo)
+    }
+
+    /// Snapshot cada `every` eventos de un stream (mínimo 1).
+    pub fn with_snapshot_every(mut self, every: u64) -> Self {
+        self.snapshot_every = every.max(1);
+        self
+    }
+
+    /// El stream de la orden, en orden.
+    pub fn events_of(&self, order_id: OrderId) -> RepoResult<Vec<OrderEvent>> {
+        Ok(self
+            .stream(order_id, 0)?
+            .into_iter()
+            .map(|recorded| recorded.event)
+            .collect())
+    }
+
+    /// Tira el read model y lo vuelve a armar desde todos los eventos.
+    pub fn rebuild_projection(&mut self) -> RepoResult<()> {
+        self.by_user = UserOrders::rebuild(self.events.iter()?);
+        self.positions = SequentialIdGenerator::resuming(&self.events)?;
+        Ok(())
+    }
+
+    pub fn projection(&self) -> &UserOrders {
+        &self.by_user
+    }
+
+    /// Estado actual y hasta qué evento llega: último snapshot + lo
+    /// que vino después.
+    fn load(&self, order_id: OrderId) -> RepoResult<Option<(Order, u64)>> {
+        let snapshot = self.snapshots.find_by_id(order_id)?;
+        let after = snapshot.as_ref().map_or(0, |s| s.sequence);
+
+        let mut state = snapshot.map(|s| (s.state, s.sequence));
+        for recorded in self.stream(order_id, after)? {
+            let order = apply(state.map(|(order, _)| order), &recorded.event)?;
+            state = Some((order, recorded.sequence));
+        }
+        Ok(state)
+    }
+
+    /// Eventos de la orden con secuencia mayor a `after`.
+    fn stream(&self, order_id: OrderId, after: u64) -> RepoResult<Vec<RecordedEvent>> {
+        let mut stream: Vec<_> = self
+            .events
+            .find_indexed(ORDER_STREAM, &order_id.to_string())?
+            .into_iter()
+            .filter(|recorded| recorded.sequence > after)
+            .collect();
+        stream.sort_by_key(|recorded| recorded.sequence);
+        Ok(stream)
+    }
+
+    fn load_all(&self, ids: impl Iterator<Item = OrderId>) -> RepoResult<Vec<Order>> {
+        let mut orders = Vec::new();
+        for id in ids {
+            if let Some((order, _)) = self.load(id)? {
+                orders.push(order);
+            }
+        }
+        Ok(orders)
+    }
+}
+
+impl<E, S> Repository<Order, OrderId> for EventSourcedOrderRepository<E, S>
+where
+    E: Repository<RecordedEvent, u64>,
+    S: Repository<OrderSnapshot, OrderId>,
+{
+    /// Agrega los eventos que describen el cambio (ninguno si no cambió).
+    fn save(&mut self, order: Order) -> RepoResult<()> {
+        let loaded = self.load(order.id)?;
+        let sequence = loaded.as_ref().map_or(0, |(_, sequence)| *sequence);
+        let events = changes(loaded.as_ref().map(|(order, _)| order), &order)?;
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let last = sequence + events.len() as u64;
+        for (sequence, event) in (sequence + 1..).zip(events) {
+            let recorded = RecordedEvent {
+                id: self.positions.next_id()?,
+                order_id: order.id,
+                sequence,
+                event,
+            };
+            self.events.insert(recorded.clone())?;
+            self.by_user.apply(&recorded);
+        }
+
+        // Se cruzó un múltiplo de N: el próximo load arranca desde acá
+        if last / self.snapshot_every > sequence / self.snapshot_every {
+            self.snapshots.save(OrderSnapshot {
+                order_id: order.id,
+                sequence: last,
+                state: order,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn find_by_id(&self, id: OrderId) -> RepoResult<Option<Order>> {
+        Ok(self.load(id)?.map(|(order, _)| order))
+    }
+
+    /// Los eventos no se borran: una orden se cancela, no desaparece.
+    fn delete(&mut self, id: OrderId) -> RepoResult<Option<Order>> {
+        Err(RepositoryError::Storage(format!(
+            "order {id} is event-sourced: cancel it instead of deleting"
+        )))
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = Order> + '_>> {
+        Ok(Box::new(self.load_all(self.by_user.all())?.into_iter()))
+    }
+
+    fn exists(&self, id: OrderId) -> RepoResult<bool> {
+        Ok(self.by_user.contains(id))
+    }
+
+    fn count(&self) -> RepoResult<usize> {
+        Ok(self.by_user.len())
+    }
+
+    fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<Order>> {
+        match (index, key.parse()) {
+            (ORDERS_BY_USER, Ok(user_id)) => self.find_by_user(user_id),
+            _ => Ok(self
+                .iter()?
+                .filter(|order| {
+                    order
+                        .index_keys()
+                        .iter()
+                        .any(|(name, value)| *name == index && value == key)
+                })
+                .collect()),
+        }
+    }
+}
+
+impl<E, S> OrderRepository for EventSourcedOrderRepository<E, S>
+where
+    E: Repository<RecordedEvent, u64>,
+    S: Repository<OrderSnapshot, OrderId>,
+{
+    /// Desde la proyección: sin recorrer todas las órdenes.
+    fn find_by_user(&self, user_id: UserId) -> RepoResult<Vec<Order>> {
+        self.load_all(self.by_user.orders_of(user_id))
+    }
+}
+
+// Eventos y snapshots juntos. La proyección vive en memoria: si se
+// deshace, se reconstruye desde lo que quedó
+impl<E, S> Transactional for EventSourcedOrderRepository<E, S>
+where
+    E: Repository<RecordedEvent, u64> + Transactional,
+    S: Repository<OrderSnapshot, OrderId> + Transactional,
+{
+    fn begin(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.snapshots).begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.snapshots).commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.snapshots).rollback()?;
+        self.rebuild_projection()
+    }
+}
+
+/*
+ESTADO GUARDADO vs EVENTOS:
+
+  InMemoryOrderRepository        EventSourcedOrderRepository
+  ┌──────────────────────┐       stream order 7
+  │ order 7: Shipped     │        #1 Created {user 1, USD}
+  │   tracking TRACK-1   │        #2 ItemAdded {WIDGET x2}
+  └──────────────────────┘        #3 Confirmed
+                                  #4 Shipped {TRACK-1}
+        save = pisar               save = agregar lo nuevo
+                                   load = fold(apply, eventos)
+
+SNAPSHOTS (every = 3):
+
+  #1 #2 #3 │snapshot(3)│ #4 #5
+  load(7) = snapshot.state + apply(#4) + apply(#5)
+
+PROYECCIÓN:
+
+  eventos (orden global) ──▶ UserOrders { user 1 → {7, 9} }
+  find_by_user(1) = load(7), load(9)
+  rollback / reabrir    ──▶ rebuild desde cero
+
+- OrderService es el mismo: los comandos mutan la orden y `changes`
+  traduce lo que hicieron a eventos; si algo no se puede expresar como
+  evento, el save falla (la historia es la fuente de verdad)
+- Cada save verifica que replay(antes + eventos) == después
+- Delivered y Refunded también son eventos: sin ellos el ciclo de vida
+  no sería el mismo que el de la versión que guarda estados
+- delete falla: los streams son append-only
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::domain::order_typestate as typed;
+    use crate::modules_demo::shared::ProductId;
+
+    fn usd(amount: &str) -> Money {
+        format!("{} USD", amount).parse().unwrap()
+    }
+
+    fn pending(id: u64, user: u64) -> Order {
+        let item = OrderItem {
+            product_id: ProductId(1),
+            quantity: 2,
+            price: usd("2.50"),
+        };
+        typed::Order::new(OrderId(id), UserId(user), vec![item])
+            .unwrap()
+            .into()
+    }
+
+    fn shipped(repo: &mut EventSourcedOrderRepository, id: u64) -> Order {
+        let mut order = pending(id, 1);
+        repo.save(order.clone()).unwrap();
+        order
+            .transition_to(OrderStatus::Confirmed, Utc::now())
+            .unwrap();
+        repo.save(order.clone()).unwrap();
+        order
+            .transition_to(OrderStatus::Shipped, Utc::now())
+            .unwrap();
+        order.tracking_number = Some("TRACK-1".to_string());
+        repo.save(order.clone()).unwrap();
+        order
+    }
+
+    #[test]
+    fn test_saves_append_events_and_state_is_their_fold() {
+        let mut repo = EventSourcedOrderRepository::new();
+        let order = shipped(&mut repo, 1);
+
+        let events = repo.events_of(order.id).unwrap();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                OrderEvent::Created { .. },
+                OrderEvent::ItemAdded { .. },
+                OrderEvent::Confirmed { .. },
+                OrderEvent::Shipped { .. },
+            ]
+        ));
+
+        let folded = replay(&events).unwrap().unwrap();
+        assert_eq!(folded.total, usd("5.00"));
+        assert_eq!(Some(folded), repo.find_by_id(order.id).unwrap());
+
+        // Guardar lo mismo otra vez no agrega nada
+        repo.save(order.clone()).unwrap();
+        assert_eq!(repo.events_of(order.id).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_load_starts_from_the_latest_snapshot() {
+        let mut repo = EventSourcedOrderRepository::new().with_snapshot_every(3);
+        let order = shipped(&mut repo, 1);
+
+        let snapshot = repo.snapshots.find_by_id(order.id).unwrap().unwrap();
+        assert_eq!(snapshot.sequence, 3);
+
+        // Sin los eventos que cubre el snapshot, load llega igual
+        for recorded in repo.stream(order.id, 0).unwrap() {
+            if recorded.sequence <= snapshot.sequence {
+                repo.events.delete(recorded.id).unwrap();
+            }
+        }
+        assert_eq!(repo.find_by_id(order.id).unwrap(), Some(order));
+    }
+
+    #[test]
+    fn test_projection_is_rebuilt_from_the_events() {
+        let mut repo = EventSourcedOrderRepository::new();
+        for (id, user) in [(1, 1), (2, 2), (3, 1)] {
+            repo.save(pending(id, user)).unwrap();
+        }
+
+        let mut events = InMemoryRepository::new();
+        for recorded in repo.events.find_all().unwrap() {
+            events.save(recorded).unwrap();
+        }
+        let reopened =
+            EventSourcedOrderRepository::with_stores(events, InMemoryRepository::new()).unwrap();
+
+        assert_eq!(reopened.projection(), repo.projection());
+        let ids: Vec<_> = reopened
+            .find_by_user(UserId(1))
+            .unwrap()
+            .into_iter()
+            .map(|order| order.id)
+            .collect();
+        assert_eq!(ids, [OrderId(1), OrderId(3)]);
+    }
+
+    #[test]
+    fn test_changes_no_event_describes_are_rejected() {
+        let mut repo = EventSourcedOrderRepository::new();
+        let mut order = pending(1, 1);
+        repo.save(order.clone()).unwrap();
+
+        order.total = usd("1.00");
+        assert!(matches!(repo.save(order), Err(RepositoryError::Storage(_))));
+        assert!(repo.delete(OrderId(1)).is_err());
+    }
+
+    #[test]
+    fn test_rollback_discards_events_and_projection() {
+        let mut repo = EventSourcedOrderRepository::new();
+
+        repo.begin().unwrap();
+        repo.save(pending(1, 1)).unwrap();
+        assert_eq!(repo.count().unwrap(), 1);
+        repo.rollback().unwrap();
+
+        assert_eq!(repo.count().unwrap(), 0);
+        assert!(repo.events_of(OrderId(1)).unwrap().is_empty());
+    }
+}
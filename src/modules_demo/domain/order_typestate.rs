@@ -0,0 +1,407 @@
+// Typestate: el estado de la orden vive en el TIPO, no en un campo
+// Convive con la state machine runtime de order.rs (misma tabla de transiciones)
+
+use super::order::{self, OrderError, OrderItem, OrderStatus, StatusChange};
+use crate::modules_demo::shared::{Money, OrderId, UserId, Validator};
+use chrono::{DateTime, Utc};
+use std::marker::PhantomData;
+
+// ============================================================
+// ESTADOS (tipos marcadores, tamaño cero)
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pending;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Confirmed;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shipped;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delivered;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Refunded;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Solo los marcadores de este módulo pueden ser estados (trait sellado).
+pub trait OrderState: sealed::Sealed {
+    /// Estado runtime equivalente, usado al convertir desde/hacia storage.
+    const STATUS: OrderStatus;
+}
+
+macro_rules! order_state {
+    ($($state:ident),*) => {
+        $(
+            impl sealed::Sealed for $state {}
+
+            impl OrderState for $state {
+                const STATUS: OrderStatus = OrderStatus::$state;
+            }
+        )*
+    };
+}
+
+order_state!(Pending, Confirmed, Shipped, Delivered, Cancelled, Refunded);
+
+// ============================================================
+// ORDER<S>
+// ============================================================
+
+/// Orden cuyo estado se conoce en compilación.
+///
+/// Envuelve la `order::Order` dinámica, así la conversión en ambos
+/// sentidos no pierde datos (historial, tracking, motivo).
+///
+/// Una transición ilegal no compila:
+///
+/// ```compile_fail
+/// use rust_concepts::modules_demo::domain::order_typestate::{Order, Pending};
+///
+/// fn deliver_without_shipping(order: Order<Pending>) {
+///     order.deliver(chrono::Utc::now()); // ✗ no existe `deliver` para Order<Pending>
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order<S: OrderState> {
+    inner: order::Order,
+    _state: PhantomData<S>,
+}
+
+// Métodos disponibles en TODOS los estados
+impl<S: OrderState> Order<S> {
+    pub fn id(&self) -> OrderId {
+        self.inner.id
+    }
+
+    pub fn user_id(&self) -> UserId {
+        self.inner.user_id
+    }
+
+    pub fn total(&self) -> Money {
+        self.inner.total
+    }
+
+    pub fn items(&self) -> &[OrderItem] {
+        &self.inner.items
+    }
+
+    pub fn history(&self) -> &[StatusChange] {
+        &self.inner.history
+    }
+
+    pub fn status(&self) -> OrderStatus {
+        S::STATUS
+    }
+
+    // Consume self: el valor en el estado anterior deja de existir
+    fn advance<N: OrderState>(mut self, at: DateTime<Utc>) -> Order<N> {
+        self.inner.record_transition(N::STATUS, at);
+        Order {
+            inner: self.inner,
+            _state: PhantomData,
+        }
+    }
+}
+
+impl Order<Pending> {
+    pub fn new(id: OrderId, user_id: UserId, items: Vec<OrderItem>) -> Result<Self, OrderError> {
+        let mut v = Validator::new();
+        order::validate_items(&mut v, &items);
+        v.finish()?;
+
+        // La moneda de la orden la fija el primer item; mezclar es error
+        let currency = items[0].price.currency();
+        let total = items.iter().try_fold(Money::zero(currency), |acc, item| {
+            acc.checked_add(item.subtotal()?)
+        })?;
+
+        Ok(Order {
+            inner: order::Order {
+                id,
+                user_id,
+                total,
+                items,
+                status: OrderStatus::Pending,
+                tracking_number: None,
+                cancellation_reason: None,
+                history: Vec::new(),
+            },
+            _state: PhantomData,
+        })
+    }
+
+    pub fn confirm(self, at: DateTime<Utc>) -> Order<Confirmed> {
+        self.advance(at)
+    }
+
+    pub fn cancel(self, reason: String, at: DateTime<Utc>) -> Result<Order<Cancelled>, OrderError> {
+        cancel(self, reason, at)
+    }
+}
+
+impl Order<Confirmed> {
+    pub fn ship(
+        self,
+        tracking_number: String,
+        at: DateTime<Utc>,
+    ) -> Result<Order<Shipped>, OrderError> {
+        order::validate_tracking_number(&tracking_number)?;
+
+        let mut shipped: Order<Shipped> = self.advance(at);
+        shipped.inner.tracking_number = Some(tracking_number);
+        Ok(shipped)
+    }
+
+    pub fn cancel(self, reason: String, at: DateTime<Utc>) -> Result<Order<Cancelled>, OrderError> {
+        cancel(self, reason, at)
+    }
+}
+
+impl Order<Shipped> {
+    pub fn tracking_number(&self) -> &str {
+        // Invariante: solo se llega a Shipped a través de ship()
+        self.inner.tracking_number.as_deref().unwrap_or_default()
+    }
+
+    pub fn deliver(self, at: DateTime<Utc>) -> Order<Delivered> {
+        self.advance(at)
+    }
+}
+
+impl Order<Cancelled> {
+    pub fn cancellation_reason(&self) -> &str {
+        self.inner
+            .cancellation_reason
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    pub fn refund(self, at: DateTime<Utc>) -> Order<Refunded> {
+        self.advance(at)
+    }
+}
+
+// Compartido por Pending y Confirmed (los únicos estados cancelables)
+fn cancel<S: OrderState>(
+    order: Order<S>,
+    reason: String,
+    at: DateTime<Utc>,
+) -> Result<Order<Cancelled>, OrderError> {
+    order::validate_cancellation_reason(&reason)?;
+
+    let mut cancelled: Order<Cancelled> = order.advance(at);
+    cancelled.inner.cancellation_reason = Some(reason);
+    Ok(cancelled)
+}
+
+// ============================================================
+// CONVERSIONES CON LA ORDER DINÁMICA (storage)
+// ============================================================
+
+impl<S: OrderState> From<Order<S>> for order::Order {
+    fn from(order: Order<S>) -> Self {
+        order.inner
+    }
+}
+
+impl<S: OrderState> TryFrom<order::Order> for Order<S> {
+    type Error = OrderError;
+
+    fn try_from(order: order::Order) -> Result<Self, Self::Error> {
+        if order.status != S::STATUS {
+            return Err(OrderError::UnexpectedStatus {
+                id: order.id,
+                expected: S::STATUS,
+                actual: order.status,
+            });
+        }
+
+        Ok(Order {
+            inner: order,
+            _state: PhantomData,
+        })
+    }
+}
+
+/// Orden cargada de storage cuyo estado aún no se conoce en compilación.
+/// Un `match` recupera el tipo concreto.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnyOrder {
+    Pending(Order<Pending>),
+    Confirmed(Order<Confirmed>),
+    Shipped(Order<Shipped>),
+    Delivered(Order<Delivered>),
+    Cancelled(Order<Cancelled>),
+    Refunded(Order<Refunded>),
+}
+
+impl From<order::Order> for AnyOrder {
+    fn from(order: order::Order) -> Self {
+        fn wrap<S: OrderState>(inner: order::Order) -> Order<S> {
+            Order {
+                inner,
+                _state: PhantomData,
+            }
+        }
+
+        match order.status {
+            OrderStatus::Pending => AnyOrder::Pending(wrap(order)),
+            OrderStatus::Confirmed => AnyOrder::Confirmed(wrap(order)),
+            OrderStatus::Shipped => AnyOrder::Shipped(wrap(order)),
+            OrderStatus::Delivered => AnyOrder::Delivered(wrap(order)),
+            OrderStatus::Cancelled => AnyOrder::Cancelled(wrap(order)),
+            OrderStatus::Refunded => AnyOrder::Refunded(wrap(order)),
+        }
+    }
+}
+
+impl From<AnyOrder> for order::Order {
+    fn from(order: AnyOrder) -> Self {
+        match order {
+            AnyOrder::Pending(o) => o.into(),
+            AnyOrder::Confirmed(o) => o.into(),
+            AnyOrder::Shipped(o) => o.into(),
+            AnyOrder::Delivered(o) => o.into(),
+            AnyOrder::Cancelled(o) => o.into(),
+            AnyOrder::Refunded(o) => o.into(),
+        }
+    }
+}
+
+/*
+RUNTIME vs TYPESTATE:
+
+┌──────────────────────────┬───────────────────────────────────┐
+│ order::Order (runtime)   │ order_typestate::Order<S>         │
+├──────────────────────────┼───────────────────────────────────┤
+│ status: OrderStatus      │ PhantomData<S> (0 bytes)          │
+│ transition_to(next, at)? │ confirm(at) / ship(..) / deliver  │
+│ error en ejecución       │ error de compilación              │
+│ ideal para storage/APIs  │ ideal para lógica interna         │
+└──────────────────────────┴───────────────────────────────────┘
+
+- Ambos comparten la misma tabla: cada método de Order<S> corresponde
+  a una entrada de OrderStatus::allowed_transitions (ver tests)
+- Los métodos consumen `self`: no queda un Order<Pending> "viejo" vivo
+- OrderState está sellado: nadie fuera del módulo inventa estados
+- TryFrom<order::Order> valida el estado al cargar de storage
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::{Currency, ProductId};
+
+    fn at(second: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + second, 0).unwrap()
+    }
+
+    fn items() -> Vec<OrderItem> {
+        vec![OrderItem {
+            product_id: ProductId(1),
+            quantity: 2,
+            price: Money::from_major(5, Currency::USD).unwrap(),
+        }]
+    }
+
+    #[test]
+    fn test_happy_path() {
+        let order = Order::new(OrderId(1), UserId(7), items()).unwrap();
+        assert_eq!(order.total().to_string(), "10.00 USD");
+
+        let delivered = order
+            .confirm(at(1))
+            .ship("TRACK-9".to_string(), at(2))
+            .unwrap()
+            .deliver(at(3));
+
+        assert_eq!(delivered.status(), OrderStatus::Delivered);
+        let times: Vec<_> = delivered.history().iter().map(|c| c.at).collect();
+        assert_eq!(times, [at(1), at(2), at(3)]);
+    }
+
+    #[test]
+    fn test_roundtrip_is_lossless() {
+        let shipped = Order::new(OrderId(1), UserId(7), items())
+            .unwrap()
+            .confirm(at(1))
+            .ship("TRACK-1".to_string(), at(2))
+            .unwrap();
+
+        let stored: order::Order = shipped.clone().into();
+        assert_eq!(stored.status, OrderStatus::Shipped);
+        assert_eq!(stored.tracking_number.as_deref(), Some("TRACK-1"));
+
+        let loaded = Order::<Shipped>::try_from(stored).unwrap();
+        assert_eq!(loaded, shipped);
+        assert_eq!(loaded.tracking_number(), "TRACK-1");
+    }
+
+    #[test]
+    fn test_try_from_wrong_state_fails() {
+        let stored: order::Order = Order::new(OrderId(3), UserId(7), items()).unwrap().into();
+
+        let err = Order::<Shipped>::try_from(stored).unwrap_err();
+        assert_eq!(
+            err,
+            OrderError::UnexpectedStatus {
+                id: OrderId(3),
+                expected: OrderStatus::Shipped,
+                actual: OrderStatus::Pending
+            }
+        );
+    }
+
+    #[test]
+    fn test_any_order_dispatch() {
+        let cancelled = Order::new(OrderId(1), UserId(7), items())
+            .unwrap()
+            .cancel("duplicated".to_string(), at(1))
+            .unwrap();
+        let stored: order::Order = cancelled.into();
+
+        match AnyOrder::from(stored) {
+            AnyOrder::Cancelled(o) => {
+                assert_eq!(o.cancellation_reason(), "duplicated");
+                assert_eq!(o.refund(at(2)).status(), OrderStatus::Refunded);
+            }
+            other => panic!("expected cancelled, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_typestate_agrees_with_runtime_table() {
+        use OrderStatus as S;
+
+        // Cada método de Order<S> debe ser una transición legal en runtime
+        let typed_edges = [
+            (S::Pending, S::Confirmed),
+            (S::Pending, S::Cancelled),
+            (S::Confirmed, S::Shipped),
+            (S::Confirmed, S::Cancelled),
+            (S::Shipped, S::Delivered),
+            (S::Cancelled, S::Refunded),
+        ];
+
+        for (from, to) in typed_edges {
+            assert!(from.can_transition_to(to), "{:?} -> {:?}", from, to);
+        }
+
+        let runtime_edges: usize = [
+            S::Pending,
+            S::Confirmed,
+            S::Shipped,
+            S::Delivered,
+            S::Cancelled,
+            S::Refunded,
+        ]
+        .iter()
+        .map(|s| s.allowed_transitions().len())
+        .sum();
+        assert_eq!(runtime_edges, typed_edges.len());
+    }
+}
@@ -0,0 +1,1282 @@
+// Dominio: Payment
+// Cobros contra un gateway externo (abstraído detrás de un trait)
+
+use super::events::{DomainEvent, DomainEventBus, DomainOutbox, EventSink};
+use super::order::{Order, OrderError, OrderRepository, OrderService, OrderStatus};
+use super::product::ProductRepository;
+use crate::modules_demo::shared::sqlite::{invalid_column, money_column};
+use crate::modules_demo::shared::validation::positive;
+use crate::modules_demo::shared::{
+    Entity, ErrorCode, ErrorKind, IdGenerator, InMemoryRepository, LogStructuredRepository,
+    Migration, Money, MoneyError, OrderId, PaymentId, RepoResult, Repository, RepositoryError,
+    SequentialIdGenerator, SqliteDatabase, Transactional, ValidationErrors, Validator,
+};
+use rusqlite::{OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use thiserror::Error;
+
+// ============================================================
+// MODEL
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaymentStatus {
+    /// Fondos reservados, aún no cobrados
+    Authorized,
+    Captured,
+    PartiallyRefunded,
+    Refunded,
+    /// Autorización liberada sin cobrar
+    Voided,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Payment {
+    pub id: PaymentId,
+    pub order_id: OrderId,
+    /// Monto autorizado (y luego capturado)
+    pub amount: Money,
+    pub refunded: Money,
+    pub status: PaymentStatus,
+    /// Referencia de la autorización en el gateway
+    pub authorization: String,
+}
+
+impl Payment {
+    pub fn captured(&self) -> Money {
+        match self.status {
+            PaymentStatus::Captured
+            | PaymentStatus::PartiallyRefunded
+            | PaymentStatus::Refunded => self.amount,
+            PaymentStatus::Authorized | PaymentStatus::Voided => {
+                Money::zero(self.amount.currency())
+            }
+        }
+    }
+
+    pub fn refundable(&self) -> Result<Money, MoneyError> {
+        self.captured().checked_sub(self.refunded)
+    }
+}
+
+impl Entity for Payment {
+    type Id = PaymentId;
+
+    fn id(&self) -> PaymentId {
+        self.id
+    }
+
+    fn index_keys(&self) -> Vec<(&'static str, String)> {
+        vec![(PAYMENTS_BY_ORDER, self.order_id.0.to_string())]
+    }
+}
+
+/// Índice multi-valor order → payments.
+pub(super) const PAYMENTS_BY_ORDER: &str = "payments.order_id";
+
+// ============================================================
+// GATEWAY
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GatewayError {
+    #[error("declined by gateway: {reason}")]
+    Declined { reason: String },
+
+    #[error("gateway timed out")]
+    Timeout,
+}
+
+/// Procesador de pagos externo. `&self`: un gateway real es un cliente HTTP
+/// compartido; el fake usa mutabilidad interior para su guion.
+pub trait PaymentGateway {
+    /// Reserva `amount` y devuelve la referencia de la autorización.
+    fn authorize(&self, amount: Money) -> Result<String, GatewayError>;
+
+    fn capture(&self, authorization: &str, amount: Money) -> Result<(), GatewayError>;
+
+    fn void(&self, authorization: &str) -> Result<(), GatewayError>;
+
+    fn refund(&self, authorization: &str, amount: Money) -> Result<(), GatewayError>;
+}
+
+/// Respuesta programada para la próxima llamada al `FakeGateway`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GatewayResponse {
+    Approve,
+    Decline(String),
+    Timeout,
+}
+
+/// Gateway en memoria para tests y demos: responde según un guion
+/// (FIFO) y aprueba todo cuando el guion está vacío.
+#[derive(Debug, Default)]
+pub struct FakeGateway {
+    script: Mutex<VecDeque<GatewayResponse>>,
+    next_reference: AtomicU64,
+}
+
+impl FakeGateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn script(&self, response: GatewayResponse) -> &Self {
+        self.script.lock().unwrap().push_back(response);
+        self
+    }
+
+    fn respond(&self) -> Result<(), GatewayError> {
+        match self.script.lock().unwrap().pop_front() {
+            None | Some(GatewayResponse::Approve) => Ok(()),
+            Some(GatewayResponse::Decline(reason)) => Err(GatewayError::Declined { reason }),
+            Some(GatewayResponse::Timeout) => Err(GatewayError::Timeout),
+        }
+    }
+}
+
+impl PaymentGateway for FakeGateway {
+    fn authorize(&self, _amount: Money) -> Result<String, GatewayError> {
+        self.respond()?;
+        let n = self.next_reference.fetch_add(1, Ordering::Relaxed) + 1;
+        Ok(format!("auth_{n}"))
+    }
+
+    fn capture(&self, _authorization: &str, _amount: Money) -> Result<(), GatewayError> {
+        self.respond()
+    }
+
+    fn void(&self, _authorization: &str) -> Result<(), GatewayError> {
+        self.respond()
+    }
+
+    fn refund(&self, _authorization: &str, _amount: Money) -> Result<(), GatewayError> {
+        self.respond()
+    }
+}
+
+// ============================================================
+// ERRORS
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PaymentError {
+    #[error("Invalid payment")]
+    Invalid(#[from] ValidationErrors),
+
+    #[error("Payment {id} not found")]
+    NotFound { id: PaymentId },
+
+    #[error("Order {id} not found")]
+    OrderNotFound { id: OrderId },
+
+    #[error("Order {id} cannot be paid in status {status:?}")]
+    OrderNotPayable { id: OrderId, status: OrderStatus },
+
+    #[error("Payment of {requested} exceeds outstanding {outstanding} for order {order_id}")]
+    ExceedsOrderTotal {
+        order_id: OrderId,
+        requested: Money,
+        outstanding: Money,
+    },
+
+    #[error("Refund of {requested} exceeds refundable {available} on payment {id}")]
+    ExceedsRefundable {
+        id: PaymentId,
+        requested: Money,
+        available: Money,
+    },
+
+    #[error("Cannot {operation} payment {id} in status {status:?}")]
+    InvalidState {
+        id: PaymentId,
+        status: PaymentStatus,
+        operation: &'static str,
+    },
+
+    #[error(transparent)]
+    Gateway(#[from] GatewayError),
+
+    /// El gateway ya actuó, el pago no se pudo guardar y no había cómo
+    /// deshacerlo allá: hay que conciliar a mano.
+    #[error("Payment {id}: {operation} went through at the gateway but was not recorded")]
+    NotRecorded {
+        id: PaymentId,
+        operation: &'static str,
+        source: RepositoryError,
+    },
+
+    #[error("Order lookup failed")]
+    Order(#[from] OrderError),
+
+    #[error(transparent)]
+    Money(#[from] MoneyError),
+
+    #[error("Payment storage failed")]
+    Repository(#[from] RepositoryError),
+}
+
+impl ErrorCode for PaymentError {
+    fn code(&self) -> &'static str {
+        match self {
+            PaymentError::Invalid(_) => "PAYMENT_INVALID",
+            PaymentError::NotFound { .. } => "PAYMENT_NOT_FOUND",
+            PaymentError::OrderNotFound { .. } => "PAYMENT_ORDER_NOT_FOUND",
+            PaymentError::OrderNotPayable { .. } => "PAYMENT_ORDER_NOT_PAYABLE",
+            PaymentError::ExceedsOrderTotal { .. } => "PAYMENT_EXCEEDS_ORDER_TOTAL",
+            PaymentError::ExceedsRefundable { .. } => "PAYMENT_EXCEEDS_REFUNDABLE",
+            PaymentError::InvalidState { .. } => "PAYMENT_INVALID_STATE",
+            PaymentError::Gateway(GatewayError::Declined { .. }) => "PAYMENT_DECLINED",
+            PaymentError::Gateway(GatewayError::Timeout) => "PAYMENT_GATEWAY_TIMEOUT",
+            PaymentError::NotRecorded { .. } => "PAYMENT_NOT_RECORDED",
+            PaymentError::Order(e) => e.code(),
+            PaymentError::Money(e) => e.code(),
+            PaymentError::Repository(e) => e.code(),
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            PaymentError::Invalid(_)
+            | PaymentError::ExceedsOrderTotal { .. }
+            | PaymentError::ExceedsRefundable { .. } => ErrorKind::Validation,
+            PaymentError::NotFound { .. } | PaymentError::OrderNotFound { .. } => {
+                ErrorKind::NotFound
+            }
+            PaymentError::OrderNotPayable { .. }
+            | PaymentError::InvalidState { .. }
+            | PaymentError::Gateway(GatewayError::Declined { .. }) => ErrorKind::InvalidState,
+            PaymentError::Gateway(GatewayError::Timeout) => ErrorKind::Unavailable,
+            PaymentError::NotRecorded { .. } => ErrorKind::Storage,
+            PaymentError::Order(e) => e.kind(),
+            PaymentError::Money(e) => e.kind(),
+            PaymentError::Repository(e) => e.kind(),
+        }
+    }
+}
+
+// ============================================================
+// REPOSITORY
+// ============================================================
+
+pub trait PaymentRepository: Repository<Payment, PaymentId> {
+    fn find_by_order(&self, order_id: OrderId) -> RepoResult<Vec<Payment>> {
+        self.find_indexed(PAYMENTS_BY_ORDER, &order_id.0.to_string())
+    }
+}
+
+pub type InMemoryPaymentRepository = InMemoryRepository<Payment>;
+
+impl PaymentRepository for InMemoryPaymentRepository {}
+
+pub type LogPaymentRepository = LogStructuredRepository<Payment>;
+
+impl PaymentRepository for LogPaymentRepository {}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    name: "payments_001_create_table",
+    sql: "CREATE TABLE payments (
+              id                INTEGER PRIMARY KEY,
+              order_id          INTEGER NOT NULL REFERENCES orders (id),
+              amount_minor      INTEGER NOT NULL CHECK (amount_minor > 0),
+              refunded_minor    INTEGER NOT NULL CHECK (refunded_minor >= 0),
+              currency          TEXT NOT NULL,
+              status            TEXT NOT NULL CHECK (status IN
+                  ('Authorized', 'Captured', 'PartiallyRefunded', 'Refunded', 'Voided')),
+              authorization_ref TEXT NOT NULL
+          );
+          CREATE INDEX payments_order_id ON payments (order_id);",
+}];
+
+/// Payments en SQLite. `payments.order_id` es foreign key a `orders`:
+/// comparte la base con `SqliteOrderRepository`.
+pub struct SqlitePaymentRepository {
+    db: SqliteDatabase,
+}
+
+impl SqlitePaymentRepository {
+    pub fn new(db: SqliteDatabase) -> RepoResult<Self> {
+        db.migrate(MIGRATIONS)?;
+        Ok(Self { db })
+    }
+
+    fn write(&self, payment: &Payment, upsert: bool) -> RepoResult<()> {
+        let conflict = if upsert {
+            "ON CONFLICT (id) DO UPDATE SET
+                 order_id = excluded.order_id,
+                 amount_minor = excluded.amount_minor,
+                 refunded_minor = excluded.refunded_minor,
+                 currency = excluded.currency,
+                 status = excluded.status,
+                 authorization_ref = excluded.authorization_ref"
+        } else {
+            ""
+        };
+
+        self.db.with_connection(|conn| {
+            conn.execute(
+                &format!(
+                    "INSERT INTO payments (id, order_id, amount_minor, refunded_minor, currency,
+                                           status, authorization_ref)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) {conflict}"
+                ),
+                params![
+                    payment.id.0,
+                    payment.order_id.0,
+                    payment.amount.amount_minor(),
+                    payment.refunded.amount_minor(),
+                    payment.amount.currency().code(),
+                    status_name(payment.status),
+                    payment.authorization,
+                ],
+            )
+            .map(drop)
+        })
+    }
+
+    fn load_where(&self, filter: &str, params: impl rusqlite::Params) -> RepoResult<Vec<Payment>> {
+        self.db.with_connection(|conn| {
+            let mut stmt = conn.prepare(&format!("{SELECT_PAYMENT} {filter} ORDER BY id"))?;
+            let payments = stmt.query_map(params, payment_from_row)?;
+            payments.collect()
+        })
+    }
+}
+
+const SELECT_PAYMENT: &str = "SELECT id, order_id, amount_minor, refunded_minor, currency, status,
+                                     authorization_ref
+                              FROM payments";
+
+// `refunded` se guarda en la moneda del monto: un pago nunca mezcla monedas
+fn payment_from_row(row: &Row<'_>) -> rusqlite::Result<Payment> {
+    let amount = money_column(row, 2, 4)?;
+    Ok(Payment {
+        id: PaymentId(row.get(0)?),
+        order_id: OrderId(row.get(1)?),
+        amount,
+        refunded: Money::new(row.get(3)?, amount.currency()),
+        status: status_column(row.get(5)?, 5)?,
+        authorization: row.get(6)?,
+    })
+}
+
+fn status_name(status: PaymentStatus) -> &'static str {
+    match status {
+        PaymentStatus::Authorized => "Authorized",
+        PaymentStatus::Captured => "Captured",
+        PaymentStatus::PartiallyRefunded => "PartiallyRefunded",
+        PaymentStatus::Refunded => "Refunded",
+        PaymentStatus::Voided => "Voided",
+    }
+}
+
+fn status_column(name: String, index: usize) -> rusqlite::Result<PaymentStatus> {
+    match name.as_str() {
+        "Authorized" => Ok(PaymentStatus::Authorized),
+        "Captured" => Ok(PaymentStatus::Captured),
+        "PartiallyRefunded" => Ok(PaymentStatus::PartiallyRefunded),
+        "Refunded" => Ok(PaymentStatus::Refunded),
+        "Voided" => Ok(PaymentStatus::Voided),
+        _ => Err(invalid_column(
+            index,
+            format!("unknown payment status {name}"),
+        )),
+    }
+}
+
+impl Repository<Payment, PaymentId> for SqlitePaymentRepository {
+    fn save(&mut self, payment: Payment) -> RepoResult<()> {
+        self.write(&payment, true)
+    }
+
+    fn insert(&mut self, payment: Payment) -> RepoResult<()> {
+        self.write(&payment, false)
+    }
+
+    fn find_by_id(&self, id: PaymentId) -> RepoResult<Option<Payment>> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                &format!("{SELECT_PAYMENT} WHERE id = ?1"),
+                [id.0],
+                payment_from_row,
+            )
+            .optional()
+        })
+    }
+
+    fn delete(&mut self, id: PaymentId) -> RepoResult<Option<Payment>> {
+        self.db.transaction(|tx| {
+            let payment = tx
+                .query_row(
+                    &format!("{SELECT_PAYMENT} WHERE id = ?1"),
+                    [id.0],
+                    payment_from_row,
+                )
+                .optional()?;
+            tx.execute("DELETE FROM payments WHERE id = ?1", [id.0])?;
+            Ok(payment)
+        })
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = Payment> + '_>> {
+        Ok(Box::new(self.load_where("", [])?.into_iter()))
+    }
+
+    fn count(&self) -> RepoResult<usize> {
+        self.db.with_connection(|conn| {
+            conn.query_row("SELECT COUNT(*) FROM payments", [], |row| row.get(0))
+        })
+    }
+}
+
+impl Transactional for SqlitePaymentRepository {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.db.begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.db.commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.db.rollback()
+    }
+}
+
+impl PaymentRepository for SqlitePaymentRepository {
+    fn find_by_order(&self, order_id: OrderId) -> RepoResult<Vec<Payment>> {
+        self.load_where("WHERE order_id = ?1", [order_id.0])
+    }
+}
+
+// ============================================================
+// RECONCILIATION
+// ============================================================
+
+/// Estado de cobro de una orden: lo que debe vs lo que se cobró.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconciliation {
+    pub order_id: OrderId,
+    pub order_total: Money,
+    /// Autorizado pero todavía no capturado
+    pub pending: Money,
+    pub captured: Money,
+    pub refunded: Money,
+    /// order_total - (captured - refunded)
+    pub outstanding: Money,
+}
+
+impl Reconciliation {
+    /// La orden está cobrada exactamente (ni de menos ni de más).
+    pub fn is_settled(&self) -> bool {
+        self.outstanding.is_zero() && self.pending.is_zero()
+    }
+
+    /// Suma los pagos de `order` (compartido por el servicio sync y el async).
+    pub(super) fn of(order: &Order, payments: &[Payment]) -> Result<Self, PaymentError> {
+        let currency = order.total.currency();
+
+        let pending = Money::sum(
+            currency,
+            payments
+                .iter()
+                .filter(|p| p.status == PaymentStatus::Authorized)
+                .map(|p| p.amount),
+        )?;
+        let captured = Money::sum(currency, payments.iter().map(Payment::captured))?;
+        let refunded = Money::sum(currency, payments.iter().map(|p| p.refunded))?;
+
+        Ok(Reconciliation {
+            order_id: order.id,
+            order_total: order.total,
+            pending,
+            captured,
+            refunded,
+            outstanding: order.total.checked_sub(captured.checked_sub(refunded)?)?,
+        })
+    }
+}
+
+// Monto de authorize y refund, antes de tocar gateway o repositorio
+pub(super) fn validate_amount(amount: &Money) -> Result<(), PaymentError> {
+    Ok(Validator::check("amount", amount, &[positive()])?)
+}
+
+// Reglas de authorize que no dependen de dónde salen orden y pagos
+pub(super) fn check_payable(
+    order: &Order,
+    summary: &Reconciliation,
+    amount: Money,
+) -> Result<(), PaymentError> {
+    if !matches!(order.status, OrderStatus::Pending | OrderStatus::Confirmed) {
+        return Err(PaymentError::OrderNotPayable {
+            id: order.id,
+            status: order.status,
+        });
+    }
+
+    let outstanding = summary.outstanding.checked_sub(summary.pending)?;
+    if amount.checked_sub(outstanding)?.is_positive() {
+        return Err(PaymentError::ExceedsOrderTotal {
+            order_id: order.id,
+            requested: amount,
+            outstanding,
+        });
+    }
+    Ok(())
+}
+
+// Comandos de apply: devuelven false si el estado no admite la operación
+
+pub(super) fn capture_with<G: PaymentGateway + ?Sized>(
+    gateway: &G,
+    payment: &mut Payment,
+) -> Result<bool, PaymentError> {
+    if payment.status != PaymentStatus::Authorized {
+        return Ok(false);
+    }
+    gateway.capture(&payment.authorization, payment.amount)?;
+    payment.status = PaymentStatus::Captured;
+    Ok(true)
+}
+
+pub(super) fn void_with<G: PaymentGateway + ?Sized>(
+    gateway: &G,
+    payment: &mut Payment,
+) -> Result<bool, PaymentError> {
+    if payment.status != PaymentStatus::Authorized {
+        return Ok(false);
+    }
+    gateway.void(&payment.authorization)?;
+    payment.status = PaymentStatus::Voided;
+    Ok(true)
+}
+
+pub(super) fn refund_with<G: PaymentGateway + ?Sized>(
+    gateway: &G,
+    payment: &mut Payment,
+    amount: Money,
+) -> Result<bool, PaymentError> {
+    if !matches!(
+        payment.status,
+        PaymentStatus::Captured | PaymentStatus::PartiallyRefunded
+    ) {
+        return Ok(false);
+    }
+
+    let available = payment.refundable()?;
+    if amount.checked_sub(available)?.is_positive() {
+        return Err(PaymentError::ExceedsRefundable {
+            id: payment.id,
+            requested: amount,
+            available,
+        });
+    }
+
+    gateway.refund(&payment.authorization, amount)?;
+    payment.refunded = payment.refunded.checked_add(amount)?;
+    payment.status = if payment.refundable()?.is_zero() {
+        PaymentStatus::Refunded
+    } else {
+        PaymentStatus::PartiallyRefunded
+    };
+    Ok(true)
+}
+
+// ============================================================
+// SERVICE
+// ============================================================
+
+pub struct PaymentService<R = InMemoryPaymentRepository, G = FakeGateway> {
+    repo: R,
+    gateway: G,
+    ids: Box<dyn IdGenerator>,
+    events: EventSink,
+}
+
+impl PaymentService {
+    pub fn new() -> Self {
+        Self::with_parts(InMemoryPaymentRepository::new(), FakeGateway::new())
+            .expect("in-memory repository is always readable")
+    }
+}
+
+impl Default for PaymentService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: PaymentRepository, G: PaymentGateway> PaymentService<R, G> {
+    pub fn with_parts(repo: R, gateway: G) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
+
+        Ok(Self {
+            repo,
+            gateway,
+            ids: Box::new(ids),
+            events: EventSink::default(),
+        })
+    }
+
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    pub fn with_event_bus(mut self, events: DomainEventBus) -> Self {
+        self.events = self.events.with_bus(events);
+        self
+    }
+
+    /// Encola los eventos en lugar de publicarlos: quedan en la misma
+    /// transacción que el cambio y los publica un `OutboxRelay`.
+    pub fn with_outbox(mut self, outbox: DomainOutbox) -> Self {
+        self.events = self.events.with_outbox(outbox);
+        self
+    }
+
+    pub fn events(&self) -> &DomainEventBus {
+        self.events.bus()
+    }
+
+    pub fn gateway(&self) -> &G {
+        &self.gateway
+    }
+
+    /// Autoriza un pago contra una orden existente. El monto no puede
+    /// superar lo que falta cobrar (admite pagos divididos).
+    pub fn authorize<O: OrderRepository, P: ProductRepository>(
+        &mut self,
+        orders: &OrderService<O, P>,
+        order_id: OrderId,
+        amount: Money,
+    ) -> Result<Payment, PaymentError> {
+        validate_amount(&amount)?;
+
+        let order = orders
+            .get_order(order_id)?
+            .ok_or(PaymentError::OrderNotFound { id: order_id })?;
+
+        let summary = Reconciliation::of(&order, &self.repo.find_by_order(order_id)?)?;
+        check_payable(&order, &summary, amount)?;
+
+        // El id antes que el gateway: si no hay id, no se reserva nada
+        let id = PaymentId(self.ids.next_id()?);
+
+        // Declinado o timeout: no queda registro local
+        let authorization = self.gateway.authorize(amount)?;
+
+        let payment = Payment {
+            id,
+            order_id,
+            amount,
+            refunded: Money::zero(amount.currency()),
+            status: PaymentStatus::Authorized,
+            authorization,
+        };
+
+        let event = DomainEvent::PaymentAuthorized {
+            payment_id: id,
+            order_id,
+            amount,
+        };
+        let recorded = self
+            .repo
+            .insert(payment.clone())
+            .and_then(|()| self.events.emit(event));
+        // Sin registro local la reserva se libera: no queda plata retenida
+        // sin un pago que la respalde
+        if let Err(e) = recorded {
+            return Err(self.compensate(&payment, "authorize", e, Some(release)));
+        }
+        Ok(payment)
+    }
+
+    /// Si el pago no se puede guardar, se reembolsa lo capturado.
+    pub fn capture(&mut self, id: PaymentId) -> Result<Payment, PaymentError> {
+        self.apply(
+            id,
+            "capture",
+            capture_with,
+            Some(refund_captured),
+            |payment| DomainEvent::PaymentCaptured {
+                payment_id: id,
+                order_id: payment.order_id,
+                amount: payment.amount,
+            },
+        )
+    }
+
+    /// Liberar no se deshace: si no se puede guardar, `NotRecorded`.
+    pub fn void(&mut self, id: PaymentId) -> Result<Payment, PaymentError> {
+        self.apply(id, "void", void_with, None, |payment| {
+            DomainEvent::PaymentVoided {
+                payment_id: id,
+                order_id: payment.order_id,
+            }
+        })
+    }
+
+    /// Reembolso total o parcial de un pago capturado. Como `void`, no se
+    /// deshace: si no se puede guardar, `NotRecorded`.
+    pub fn refund(&mut self, id: PaymentId, amount: Money) -> Result<Payment, PaymentError> {
+        validate_amount(&amount)?;
+
+        self.apply(
+            id,
+            "refund",
+            |gateway, payment| refund_with(gateway, payment, amount),
+            None,
+            |payment| DomainEvent::PaymentRefunded {
+                payment_id: id,
+                order_id: payment.order_id,
+                amount,
+            },
+        )
+    }
+
+    pub fn get_payment(&self, id: PaymentId) -> Result<Option<Payment>, PaymentError> {
+        Ok(self.repo.find_by_id(id)?)
+    }
+
+    pub fn get_order_payments(&self, order_id: OrderId) -> Result<Vec<Payment>, PaymentError> {
+        Ok(self.repo.find_by_order(order_id)?)
+    }
+
+    /// Compara el total de la orden (fuente: OrderService) con sus pagos.
+    pub fn reconcile<O: OrderRepository, P: ProductRepository>(
+        &self,
+        orders: &OrderService<O, P>,
+        order_id: OrderId,
+    ) -> Result<Reconciliation, PaymentError> {
+        let order = orders
+            .get_order(order_id)?
+            .ok_or(PaymentError::OrderNotFound { id: order_id })?;
+
+        Reconciliation::of(&order, &self.repo.find_by_order(order_id)?)
+    }
+
+    // Carga → gateway → guarda y emite. `command` devuelve false si el
+    // estado no admite la operación; si el gateway falla, el pago queda
+    // como estaba. Si lo que falla es guardar, `undo` revierte el gateway
+    fn apply<F>(
+        &mut self,
+        id: PaymentId,
+        operation: &'static str,
+        command: F,
+        undo: Option<Undo<G>>,
+        event: impl FnOnce(&Payment) -> DomainEvent,
+    ) -> Result<Payment, PaymentError>
+    where
+        F: FnOnce(&G, &mut Payment) -> Result<bool, PaymentError>,
+    {
+        let mut payment = self
+            .repo
+            .find_by_id(id)?
+            .ok_or(PaymentError::NotFound { id })?;
+
+        if !command(&self.gateway, &mut payment)? {
+            return Err(PaymentError::InvalidState {
+                id,
+                status: payment.status,
+                operation,
+            });
+        }
+
+        let recorded = self
+            .repo
+            .save(payment.clone())
+            .and_then(|()| self.events.emit(event(&payment)));
+        if let Err(e) = recorded {
+            return Err(self.compensate(&payment, operation, e, undo));
+        }
+        Ok(payment)
+    }
+
+    // El gateway ya actuó y el registro local falló. Deshecho en el
+    // gateway: el error de storage, como si nada hubiera pasado. Sin
+    // forma de deshacerlo (o si deshacer falla): NotRecorded
+    fn compensate(
+        &self,
+        payment: &Payment,
+        operation: &'static str,
+        error: RepositoryError,
+        undo: Option<Undo<G>>,
+    ) -> PaymentError {
+        match undo.map(|undo| undo(&self.gateway, payment)) {
+            Some(Ok(())) => PaymentError::Repository(error),
+            Some(Err(_)) | None => PaymentError::NotRecorded {
+                id: payment.id,
+                operation,
+                source: error,
+            },
+        }
+    }
+}
+
+/// Lo que revierte en el gateway una operación que no se pudo guardar.
+type Undo<G> = fn(&G, &Payment) -> Result<(), GatewayError>;
+
+fn release<G: PaymentGateway>(gateway: &G, payment: &Payment) -> Result<(), GatewayError> {
+    gateway.void(&payment.authorization)
+}
+
+fn refund_captured<G: PaymentGateway>(gateway: &G, payment: &Payment) -> Result<(), GatewayError> {
+    gateway.refund(&payment.authorization, payment.amount)
+}
+
+// El outbox primero: escribe recién en el commit, y si falla el pago
+// todavía se deshace
+impl<R: PaymentRepository + Transactional, G> Transactional for PaymentService<R, G> {
+    fn begin(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.repo).begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.repo).commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.repo).rollback()
+    }
+}
+
+/*
+CICLO DE VIDA DE UN PAGO:
+
+  authorize ──▶ Authorized ──capture──▶ Captured ──refund(parcial)──▶ PartiallyRefunded
+                    │                      │                               │
+                    └──void──▶ Voided      └──────refund(total)──────▶ Refunded ◀┘
+
+- El gateway es un trait: FakeGateway en tests, cliente HTTP en producción
+- Declined/Timeout del gateway → error, el estado local NO cambia
+- Gateway OK pero no se puede guardar → se revierte en el gateway
+  (authorize → void, capture → refund). void y refund no tienen vuelta:
+  NotRecorded, para conciliar a mano
+- authorize valida contra la orden real (OrderService), no contra el
+  monto que mande el cliente
+- reconcile: order_total vs captured - refunded → outstanding
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::domain::OrderLine;
+    use crate::modules_demo::domain::order::SqliteOrderRepository;
+    use crate::modules_demo::domain::order::tests::sqlite_orders;
+    use crate::modules_demo::shared::testing::{FaultyRepository, backend_tests};
+    use crate::modules_demo::shared::{UnitOfWork, UserId};
+
+    // Órdenes y pagos en la misma base: payments.order_id → orders.id
+    fn sqlite_services() -> (
+        OrderService<SqliteOrderRepository>,
+        PaymentService<SqlitePaymentRepository>,
+    ) {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        let orders = sqlite_orders(&db);
+        let repo = SqlitePaymentRepository::new(db).unwrap();
+        (
+            orders,
+            PaymentService::with_parts(repo, FakeGateway::new()).unwrap(),
+        )
+    }
+
+    backend_tests! {
+        [
+            test_authorize_capture_settles_order,
+            test_authorize_rejects_amount_over_order_total,
+            test_authorize_unknown_or_cancelled_order,
+            test_declined_and_timeout_leave_no_trace,
+            test_capture_timeout_keeps_authorization,
+            test_void_only_before_capture,
+            test_partial_refunds,
+            test_unit_of_work_rolls_back_order_and_stock,
+            test_unit_of_work_commits_order_and_payment,
+        ]
+        in_memory => (OrderService::new(), PaymentService::new()),
+        sqlite => sqlite_services(),
+    }
+
+    fn usd(amount: &str) -> Money {
+        format!("{} USD", amount).parse().unwrap()
+    }
+
+    fn order_of<O: OrderRepository>(orders: &mut OrderService<O>, total: &str) -> OrderId {
+        let product = orders
+            .catalog_mut()
+            .add_product(&format!("SKU-{total}"), "Item".to_string(), usd(total), 10)
+            .unwrap();
+        orders
+            .create_order(UserId(1), vec![OrderLine::new(product.id, 1)])
+            .unwrap()
+            .id
+    }
+
+    fn test_authorize_capture_settles_order<O: OrderRepository, R: PaymentRepository>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) {
+        let order_id = order_of(&mut orders, "50.00");
+
+        let payment = payments.authorize(&orders, order_id, usd("50.00")).unwrap();
+        assert_eq!(payment.status, PaymentStatus::Authorized);
+        assert!(!payments.reconcile(&orders, order_id).unwrap().is_settled());
+
+        let payment = payments.capture(payment.id).unwrap();
+        assert_eq!(payment.status, PaymentStatus::Captured);
+
+        let summary = payments.reconcile(&orders, order_id).unwrap();
+        assert_eq!(summary.captured, usd("50.00"));
+        assert!(summary.is_settled());
+    }
+
+    fn test_authorize_rejects_amount_over_order_total<O: OrderRepository, R: PaymentRepository>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) {
+        let order_id = order_of(&mut orders, "30.00");
+
+        payments.authorize(&orders, order_id, usd("20.00")).unwrap();
+        let err = payments
+            .authorize(&orders, order_id, usd("20.00"))
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            PaymentError::ExceedsOrderTotal {
+                order_id,
+                requested: usd("20.00"),
+                outstanding: usd("10.00"),
+            }
+        );
+    }
+
+    fn test_authorize_unknown_or_cancelled_order<O: OrderRepository, R: PaymentRepository>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) {
+        let err = payments
+            .authorize(&orders, OrderId(9), usd("1.00"))
+            .unwrap_err();
+        assert_eq!(err, PaymentError::OrderNotFound { id: OrderId(9) });
+
+        let order_id = order_of(&mut orders, "5.00");
+        orders
+            .cancel_order(order_id, "changed mind".to_string())
+            .unwrap();
+
+        let err = payments
+            .authorize(&orders, order_id, usd("5.00"))
+            .unwrap_err();
+        assert_eq!(err.code(), "PAYMENT_ORDER_NOT_PAYABLE");
+    }
+
+    fn test_declined_and_timeout_leave_no_trace<O: OrderRepository, R: PaymentRepository>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) {
+        let order_id = order_of(&mut orders, "10.00");
+
+        payments
+            .gateway()
+            .script(GatewayResponse::Decline("insufficient funds".to_string()))
+            .script(GatewayResponse::Timeout);
+
+        let declined = payments
+            .authorize(&orders, order_id, usd("10.00"))
+            .unwrap_err();
+        assert_eq!(declined.code(), "PAYMENT_DECLINED");
+
+        let timeout = payments
+            .authorize(&orders, order_id, usd("10.00"))
+            .unwrap_err();
+        assert_eq!(timeout, PaymentError::Gateway(GatewayError::Timeout));
+        assert_eq!(timeout.http_status(), 503);
+
+        assert!(payments.get_order_payments(order_id).unwrap().is_empty());
+    }
+
+    fn test_capture_timeout_keeps_authorization<O: OrderRepository, R: PaymentRepository>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) {
+        let order_id = order_of(&mut orders, "10.00");
+        let payment = payments.authorize(&orders, order_id, usd("10.00")).unwrap();
+
+        payments.gateway().script(GatewayResponse::Timeout);
+        assert!(payments.capture(payment.id).is_err());
+
+        let stored = payments.get_payment(payment.id).unwrap().unwrap();
+        assert_eq!(stored.status, PaymentStatus::Authorized);
+
+        // Reintento: el guion está vacío → aprueba
+        assert!(payments.capture(payment.id).is_ok());
+    }
+
+    fn test_void_only_before_capture<O: OrderRepository, R: PaymentRepository>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) {
+        let order_id = order_of(&mut orders, "10.00");
+
+        let first = payments.authorize(&orders, order_id, usd("10.00")).unwrap();
+        assert_eq!(
+            payments.void(first.id).unwrap().status,
+            PaymentStatus::Voided
+        );
+
+        // La autorización liberada vuelve a dejar saldo pendiente
+        let second = payments.authorize(&orders, order_id, usd("10.00")).unwrap();
+        payments.capture(second.id).unwrap();
+
+        assert_eq!(
+            payments.void(second.id).unwrap_err(),
+            PaymentError::InvalidState {
+                id: second.id,
+                status: PaymentStatus::Captured,
+                operation: "void"
+            }
+        );
+    }
+
+    fn test_partial_refunds<O: OrderRepository, R: PaymentRepository>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) {
+        let order_id = order_of(&mut orders, "100.00");
+        let payment = payments
+            .authorize(&orders, order_id, usd("100.00"))
+            .unwrap();
+        payments.capture(payment.id).unwrap();
+
+        let partial = payments.refund(payment.id, usd("30.00")).unwrap();
+        assert_eq!(partial.status, PaymentStatus::PartiallyRefunded);
+
+        let err = payments.refund(payment.id, usd("80.00")).unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::ExceedsRefundable {
+                id: payment.id,
+                requested: usd("80.00"),
+                available: usd("70.00")
+            }
+        );
+
+        let full = payments.refund(payment.id, usd("70.00")).unwrap();
+        assert_eq!(full.status, PaymentStatus::Refunded);
+
+        let summary = payments.reconcile(&orders, order_id).unwrap();
+        assert_eq!(summary.refunded, usd("100.00"));
+        assert_eq!(summary.outstanding, usd("100.00"));
+    }
+
+    // Orden + reserva + pago: o queda todo o no queda nada
+    fn place_and_pay<O, R>(
+        orders: &mut OrderService<O>,
+        payments: &mut PaymentService<R>,
+        lines: Vec<OrderLine>,
+    ) -> Result<Payment, PaymentError>
+    where
+        O: OrderRepository + Transactional,
+        R: PaymentRepository + Transactional,
+    {
+        UnitOfWork::new((orders, payments)).run(|(orders, payments)| {
+            let order = orders.create_order(UserId(1), lines)?;
+            payments.authorize(orders, order.id, order.total)
+        })
+    }
+
+    fn test_unit_of_work_rolls_back_order_and_stock<O, R>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) where
+        O: OrderRepository + Transactional,
+        R: PaymentRepository + Transactional,
+    {
+        let product = orders
+            .catalog_mut()
+            .add_product("SKU-UOW", "Item".to_string(), usd("25.00"), 3)
+            .unwrap()
+            .id;
+        payments
+            .gateway()
+            .script(GatewayResponse::Decline("card expired".to_string()));
+
+        let err = place_and_pay(&mut orders, &mut payments, vec![OrderLine::new(product, 2)])
+            .unwrap_err();
+        assert_eq!(err.code(), "PAYMENT_DECLINED");
+
+        // La orden ya se había guardado y el stock reservado: el rollback lo deshace
+        assert!(orders.get_user_orders(UserId(1)).unwrap().is_empty());
+        assert_eq!(orders.catalog().available(product).unwrap(), 3);
+        assert_eq!(payments.repo.count().unwrap(), 0);
+    }
+
+    fn test_unit_of_work_commits_order_and_payment<O, R>(
+        (mut orders, mut payments): (OrderService<O>, PaymentService<R>),
+    ) where
+        O: OrderRepository + Transactional,
+        R: PaymentRepository + Transactional,
+    {
+        let product = orders
+            .catalog_mut()
+            .add_product("SKU-UOW", "Item".to_string(), usd("25.00"), 3)
+            .unwrap()
+            .id;
+
+        let payment =
+            place_and_pay(&mut orders, &mut payments, vec![OrderLine::new(product, 2)]).unwrap();
+
+        let order = orders.get_order(payment.order_id).unwrap().unwrap();
+        assert_eq!(order.total, usd("50.00"));
+        assert_eq!(orders.catalog().available(product).unwrap(), 1);
+        assert_eq!(
+            payments.get_order_payments(order.id).unwrap(),
+            vec![payment]
+        );
+    }
+
+    #[test]
+    fn test_log_store_backends() {
+        use crate::modules_demo::domain::order::LogOrderRepository;
+        use crate::modules_demo::shared::json_file::tests::TempPath;
+
+        let orders_path = TempPath::new("orders_log");
+        let payments_path = TempPath::new("payments_log");
+
+        let orders_repo = LogOrderRepository::open(&orders_path.0).unwrap();
+        let mut orders = OrderService::with_repository(orders_repo).unwrap();
+        let order_id = order_of(&mut orders, "40.00");
+        orders.confirm_order(order_id).unwrap();
+
+        let payments_repo = LogPaymentRepository::open(&payments_path.0).unwrap();
+        let mut payments = PaymentService::with_parts(payments_repo, FakeGateway::new()).unwrap();
+        let payment = payments.authorize(&orders, order_id, usd("40.00")).unwrap();
+        payments.capture(payment.id).unwrap();
+        payments.refund(payment.id, usd("5.00")).unwrap();
+        drop(payments);
+
+        // Reabrir: el replay reconstruye ambos índices
+        let orders_repo = LogOrderRepository::open(&orders_path.0).unwrap();
+        let orders = OrderService::with_repository(orders_repo).unwrap();
+        let payments_repo = LogPaymentRepository::open(&payments_path.0).unwrap();
+        let payments = PaymentService::with_parts(payments_repo, FakeGateway::new()).unwrap();
+
+        let order = orders.get_order(order_id).unwrap().unwrap();
+        assert_eq!(order.status, OrderStatus::Confirmed);
+        assert_eq!(order.history.len(), 1);
+
+        let stored = payments.get_payment(payment.id).unwrap().unwrap();
+        assert_eq!(stored.status, PaymentStatus::PartiallyRefunded);
+        assert_eq!(stored.refunded, usd("5.00"));
+    }
+
+    #[test]
+    fn test_sqlite_payments_reference_orders() {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        let mut orders = sqlite_orders(&db);
+        let order_id = order_of(&mut orders, "10.00");
+        let mut payments = PaymentService::with_parts(
+            SqlitePaymentRepository::new(db.clone()).unwrap(),
+            FakeGateway::new(),
+        )
+        .unwrap();
+        let payment = payments.authorize(&orders, order_id, usd("10.00")).unwrap();
+
+        // El esquema no deja pagos huérfanos: ni sin orden, ni borrando la orden
+        let mut repo = SqlitePaymentRepository::new(db.clone()).unwrap();
+        let orphan = Payment {
+            id: PaymentId(99),
+            order_id: OrderId(404),
+            ..payment.clone()
+        };
+        assert!(matches!(
+            repo.insert(orphan),
+            Err(RepositoryError::ForeignKeyViolation(_))
+        ));
+
+        let mut order_repo = SqliteOrderRepository::new(db).unwrap();
+        assert!(matches!(
+            order_repo.delete(order_id),
+            Err(RepositoryError::ForeignKeyViolation(_))
+        ));
+        assert_eq!(repo.find_by_order(order_id).unwrap(), vec![payment]);
+    }
+
+    impl PaymentRepository for FaultyRepository<Payment> {}
+
+    /// Aprueba todo y anota cada llamada.
+    #[derive(Default)]
+    struct RecordingGateway {
+        calls: std::sync::Mutex<Vec<&'static str>>,
+    }
+
+    impl RecordingGateway {
+        fn calls(&self) -> Vec<&'static str> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn record(&self, call: &'static str) -> Result<(), GatewayError> {
+            self.calls.lock().unwrap().push(call);
+            Ok(())
+        }
+    }
+
+    impl PaymentGateway for RecordingGateway {
+        fn authorize(&self, _amount: Money) -> Result<String, GatewayError> {
+            self.record("authorize")?;
+            Ok("auth_1".to_string())
+        }
+
+        fn capture(&self, _authorization: &str, _amount: Money) -> Result<(), GatewayError> {
+            self.record("capture")
+        }
+
+        fn void(&self, _authorization: &str) -> Result<(), GatewayError> {
+            self.record("void")
+        }
+
+        fn refund(&self, _authorization: &str, _amount: Money) -> Result<(), GatewayError> {
+            self.record("refund")
+        }
+    }
+
+    #[test]
+    fn test_failed_save_is_undone_at_the_gateway() {
+        let mut orders = OrderService::new();
+        let order_id = order_of(&mut orders, "20.00");
+        let (repo, faults) = FaultyRepository::new();
+        let mut payments = PaymentService::with_parts(repo, RecordingGateway::default()).unwrap();
+
+        faults.fail_writes(true);
+        let err = payments
+            .authorize(&orders, order_id, usd("20.00"))
+            .unwrap_err();
+        assert!(matches!(err, PaymentError::Repository(_)));
+        assert_eq!(payments.gateway().calls(), ["authorize", "void"]);
+        assert!(payments.get_order_payments(order_id).unwrap().is_empty());
+
+        faults.fail_writes(false);
+        let payment = payments.authorize(&orders, order_id, usd("20.00")).unwrap();
+        faults.fail_writes(true);
+        let err = payments.capture(payment.id).unwrap_err();
+        assert!(matches!(err, PaymentError::Repository(_)));
+        assert_eq!(payments.gateway().calls()[3..], ["capture", "refund"]);
+        assert_eq!(payments.get_payment(payment.id).unwrap(), Some(payment));
+    }
+
+    #[test]
+    fn test_failed_save_without_undo_is_reported_as_not_recorded() {
+        let mut orders = OrderService::new();
+        let order_id = order_of(&mut orders, "20.00");
+        let (repo, faults) = FaultyRepository::new();
+        let mut payments = PaymentService::with_parts(repo, RecordingGateway::default()).unwrap();
+        let payment = payments.authorize(&orders, order_id, usd("20.00")).unwrap();
+
+        faults.fail_writes(true);
+        let err = payments.void(payment.id).unwrap_err();
+
+        assert!(matches!(
+            err,
+            PaymentError::NotRecorded { id, operation: "void", .. } if id == payment.id
+        ));
+        assert_eq!(err.code(), "PAYMENT_NOT_RECORDED");
+        assert_eq!(payments.gateway().calls(), ["authorize", "void"]);
+    }
+}
@@ -0,0 +1,536 @@
+// PaymentService async: mismas reglas que payment.rs, usable desde tokio
+// El gateway (sync, bloqueante como un cliente HTTP blocking) va por spawn_blocking
+
+use super::events::{DomainEvent, DomainEventBus};
+use super::order_async::{AsyncOrderRepository, AsyncOrderService};
+use super::payment::{
+    self, FakeGateway, PAYMENTS_BY_ORDER, Payment, PaymentError, PaymentGateway, PaymentRepository,
+    PaymentStatus, Reconciliation, SqlitePaymentRepository,
+};
+use super::product::ProductRepository;
+use crate::modules_demo::shared::async_repository::{blocking, resume_ids};
+use crate::modules_demo::shared::{
+    AsyncInMemoryRepository, AsyncRepository, IdGenerator, Money, OrderId, PaymentId, RepoResult,
+    SequentialIdGenerator, SpawnBlocking,
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// ============================================================
+// REPOSITORY
+// ============================================================
+
+/// Búsquedas específicas de Payment sobre el AsyncRepository genérico.
+#[async_trait]
+pub trait AsyncPaymentRepository: AsyncRepository<Payment, PaymentId> {
+    async fn find_by_order(&self, order_id: OrderId) -> RepoResult<Vec<Payment>> {
+        self.find_indexed(PAYMENTS_BY_ORDER, &order_id.0.to_string())
+            .await
+    }
+}
+
+pub type AsyncInMemoryPaymentRepository = AsyncInMemoryRepository<Payment>;
+
+impl AsyncPaymentRepository for AsyncInMemoryPaymentRepository {}
+
+#[async_trait]
+impl<R: PaymentRepository + Send + 'static> AsyncPaymentRepository for SpawnBlocking<R> {
+    async fn find_by_order(&self, order_id: OrderId) -> RepoResult<Vec<Payment>> {
+        self.run(move |repo| repo.find_by_order(order_id)).await
+    }
+}
+
+pub type BlockingSqlitePaymentRepository = SpawnBlocking<SqlitePaymentRepository>;
+
+// ============================================================
+// SERVICE
+// ============================================================
+
+pub struct AsyncPaymentService<R = AsyncInMemoryPaymentRepository, G = FakeGateway> {
+    repo: R,
+    gateway: Arc<G>,
+    ids: Box<dyn IdGenerator>,
+    events: DomainEventBus,
+}
+
+impl AsyncPaymentService {
+    pub fn new() -> Self {
+        Self {
+            repo: AsyncInMemoryPaymentRepository::new(),
+            gateway: Arc::new(FakeGateway::new()),
+            ids: Box::new(SequentialIdGenerator::new()),
+            events: DomainEventBus::new(),
+        }
+    }
+}
+
+impl Default for AsyncPaymentService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, G> AsyncPaymentService<R, G>
+where
+    R: AsyncPaymentRepository,
+    G: PaymentGateway + Send + Sync + 'static,
+{
+    pub async fn with_parts(repo: R, gateway: G) -> RepoResult<Self> {
+        let ids = resume_ids(&repo).await?;
+
+        Ok(Self {
+            repo,
+            gateway: Arc::new(gateway),
+            ids: Box::new(ids),
+            events: DomainEventBus::new(),
+        })
+    }
+
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    pub fn with_event_bus(mut self, events: DomainEventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn events(&self) -> &DomainEventBus {
+        &self.events
+    }
+
+    pub fn gateway(&self) -> &G {
+        &self.gateway
+    }
+
+    /// Autoriza un pago contra una orden existente (ver PaymentService::authorize).
+    pub async fn authorize<O: AsyncOrderRepository, P: ProductRepository + Send + 'static>(
+        &self,
+        orders: &AsyncOrderService<O, P>,
+        order_id: OrderId,
+        amount: Money,
+    ) -> Result<Payment, PaymentError> {
+        payment::validate_amount(&amount)?;
+
+        let order = orders
+            .get_order(order_id)
+            .await?
+            .ok_or(PaymentError::OrderNotFound { id: order_id })?;
+
+        let summary = Reconciliation::of(&order, &self.repo.find_by_order(order_id).await?)?;
+        payment::check_payable(&order, &summary, amount)?;
+
+        // Declinado o timeout: no queda registro local
+        let gateway = Arc::clone(&self.gateway);
+        let authorization =
+            blocking(move || Ok::<_, PaymentError>(gateway.authorize(amount)?)).await?;
+
+        let payment = Payment {
+            id: PaymentId(self.ids.next_id()?),
+            order_id,
+            amount,
+            refunded: Money::zero(amount.currency()),
+            status: PaymentStatus::Authorized,
+            authorization,
+        };
+
+        self.repo.insert(payment.clone()).await?;
+
+        self.events.publish(DomainEvent::PaymentAuthorized {
+            payment_id: payment.id,
+            order_id,
+            amount,
+        });
+        Ok(payment)
+    }
+
+    pub async fn capture(&self, id: PaymentId) -> Result<Payment, PaymentError> {
+        let payment = self
+            .apply(id, "capture", |gateway, payment| {
+                payment::capture_with(gateway, payment)
+            })
+            .await?;
+
+        self.events.publish(DomainEvent::PaymentCaptured {
+            payment_id: id,
+            order_id: payment.order_id,
+            amount: payment.amount,
+        });
+        Ok(payment)
+    }
+
+    pub async fn void(&self, id: PaymentId) -> Result<Payment, PaymentError> {
+        let payment = self
+            .apply(id, "void", |gateway, payment| {
+                payment::void_with(gateway, payment)
+            })
+            .await?;
+
+        self.events.publish(DomainEvent::PaymentVoided {
+            payment_id: id,
+            order_id: payment.order_id,
+        });
+        Ok(payment)
+    }
+
+    /// Reembolso total o parcial de un pago capturado.
+    pub async fn refund(&self, id: PaymentId, amount: Money) -> Result<Payment, PaymentError> {
+        payment::validate_amount(&amount)?;
+
+        let payment = self
+            .apply(id, "refund", move |gateway, payment| {
+                payment::refund_with(gateway, payment, amount)
+            })
+            .await?;
+
+        self.events.publish(DomainEvent::PaymentRefunded {
+            payment_id: id,
+            order_id: payment.order_id,
+            amount,
+        });
+        Ok(payment)
+    }
+
+    pub async fn get_payment(&self, id: PaymentId) -> Result<Option<Payment>, PaymentError> {
+        Ok(self.repo.find_by_id(id).await?)
+    }
+
+    pub async fn get_order_payments(
+        &self,
+        order_id: OrderId,
+    ) -> Result<Vec<Payment>, PaymentError> {
+        Ok(self.repo.find_by_order(order_id).await?)
+    }
+
+    /// Compara el total de la orden (fuente: AsyncOrderService) con sus pagos.
+    pub async fn reconcile<O: AsyncOrderRepository, P: ProductRepository + Send + 'static>(
+        &self,
+        orders: &AsyncOrderService<O, P>,
+        order_id: OrderId,
+    ) -> Result<Reconciliation, PaymentError> {
+        let order = orders
+            .get_order(order_id)
+            .await?
+            .ok_or(PaymentError::OrderNotFound { id: order_id })?;
+
+        Reconciliation::of(&order, &self.repo.find_by_order(order_id).await?)
+    }
+
+    // Carga → gateway (en el pool bloqueante) → guarda.
+    async fn apply<F>(
+        &self,
+        id: PaymentId,
+        operation: &'static str,
+        command: F,
+    ) -> Result<Payment, PaymentError>
+    where
+        F: FnOnce(&G, &mut Payment) -> Result<bool, PaymentError> + Send + 'static,
+    {
+        let mut payment = self
+            .repo
+            .find_by_id(id)
+            .await?
+            .ok_or(PaymentError::NotFound { id })?;
+
+        let gateway = Arc::clone(&self.gateway);
+        let (applied, payment) = blocking(move || {
+            let applied = command(&gateway, &mut payment)?;
+            Ok::<_, PaymentError>((applied, payment))
+        })
+        .await?;
+
+        if !applied {
+            return Err(PaymentError::InvalidState {
+                id,
+                status: payment.status,
+                operation,
+            });
+        }
+
+        self.repo.save(payment.clone()).await?;
+        Ok(payment)
+    }
+}
+
+/*
+SYNC vs ASYNC:
+
+  PaymentService<R, G>                     AsyncPaymentService<R, G>
+  ────────────────────                     ─────────────────────────
+  gateway: G                               gateway: Arc<G> (cruza a spawn_blocking)
+  authorize(&OrderService, ..)             authorize(&AsyncOrderService, ..).await
+  apply: command(&G, &mut Payment)         apply: el mismo command, en el pool bloqueante
+
+- Las reglas (check_payable, capture_with, refund_with, Reconciliation::of)
+  viven en payment.rs y las usan los dos servicios
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::domain::OrderLine;
+    use crate::modules_demo::domain::order::SqliteOrderRepository;
+    use crate::modules_demo::domain::order::tests::seed_users;
+    use crate::modules_demo::domain::order_async::BlockingSqliteOrderRepository;
+    use crate::modules_demo::domain::payment::{GatewayError, GatewayResponse};
+    use crate::modules_demo::shared::testing::backend_tests;
+    use crate::modules_demo::shared::{ErrorCode, SqliteDatabase, UserId};
+
+    async fn in_memory_services() -> (AsyncOrderService, AsyncPaymentService) {
+        (AsyncOrderService::new(), AsyncPaymentService::new())
+    }
+
+    // Órdenes y pagos en la misma base: payments.order_id → orders.id
+    async fn sqlite_services() -> (
+        AsyncOrderService<BlockingSqliteOrderRepository>,
+        AsyncPaymentService<BlockingSqlitePaymentRepository>,
+    ) {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        seed_users(&db);
+        let orders = SpawnBlocking::new(SqliteOrderRepository::new(db.clone()).unwrap());
+        let payments = SpawnBlocking::new(SqlitePaymentRepository::new(db).unwrap());
+        (
+            AsyncOrderService::with_repository(orders).await.unwrap(),
+            AsyncPaymentService::with_parts(payments, FakeGateway::new())
+                .await
+                .unwrap(),
+        )
+    }
+
+    // Espejo de los tests de payment.rs, como #[tokio::test]
+    backend_tests! {
+        async [
+            test_authorize_capture_settles_order,
+            test_authorize_rejects_amount_over_order_total,
+            test_authorize_unknown_or_cancelled_order,
+            test_declined_and_timeout_leave_no_trace,
+            test_capture_timeout_keeps_authorization,
+            test_void_only_before_capture,
+            test_partial_refunds,
+        ]
+        in_memory => in_memory_services(),
+        sqlite => sqlite_services(),
+    }
+
+    fn usd(amount: &str) -> Money {
+        format!("{} USD", amount).parse().unwrap()
+    }
+
+    async fn order_of<O: AsyncOrderRepository>(
+        orders: &AsyncOrderService<O>,
+        total: &str,
+    ) -> OrderId {
+        let (sku, price) = (format!("SKU-{total}"), usd(total));
+        let product = orders
+            .catalog()
+            .run(move |catalog| catalog.add_product(&sku, "Item".to_string(), price, 10))
+            .await
+            .unwrap();
+        orders
+            .create_order(UserId(1), vec![OrderLine::new(product.id, 1)])
+            .await
+            .unwrap()
+            .id
+    }
+
+    async fn test_authorize_capture_settles_order<
+        O: AsyncOrderRepository,
+        R: AsyncPaymentRepository,
+    >(
+        (orders, payments): (AsyncOrderService<O>, AsyncPaymentService<R>),
+    ) {
+        let order_id = order_of(&orders, "50.00").await;
+
+        let payment = payments
+            .authorize(&orders, order_id, usd("50.00"))
+            .await
+            .unwrap();
+        assert_eq!(payment.status, PaymentStatus::Authorized);
+        assert!(
+            !payments
+                .reconcile(&orders, order_id)
+                .await
+                .unwrap()
+                .is_settled()
+        );
+
+        let payment = payments.capture(payment.id).await.unwrap();
+        assert_eq!(payment.status, PaymentStatus::Captured);
+
+        let summary = payments.reconcile(&orders, order_id).await.unwrap();
+        assert_eq!(summary.captured, usd("50.00"));
+        assert!(summary.is_settled());
+    }
+
+    async fn test_authorize_rejects_amount_over_order_total<
+        O: AsyncOrderRepository,
+        R: AsyncPaymentRepository,
+    >(
+        (orders, payments): (AsyncOrderService<O>, AsyncPaymentService<R>),
+    ) {
+        let order_id = order_of(&orders, "30.00").await;
+
+        payments
+            .authorize(&orders, order_id, usd("20.00"))
+            .await
+            .unwrap();
+        let err = payments
+            .authorize(&orders, order_id, usd("20.00"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            PaymentError::ExceedsOrderTotal {
+                order_id,
+                requested: usd("20.00"),
+                outstanding: usd("10.00"),
+            }
+        );
+    }
+
+    async fn test_authorize_unknown_or_cancelled_order<
+        O: AsyncOrderRepository,
+        R: AsyncPaymentRepository,
+    >(
+        (orders, payments): (AsyncOrderService<O>, AsyncPaymentService<R>),
+    ) {
+        let err = payments
+            .authorize(&orders, OrderId(9), usd("1.00"))
+            .await
+            .unwrap_err();
+        assert_eq!(err, PaymentError::OrderNotFound { id: OrderId(9) });
+
+        let order_id = order_of(&orders, "5.00").await;
+        orders
+            .cancel_order(order_id, "changed mind".to_string())
+            .await
+            .unwrap();
+
+        let err = payments
+            .authorize(&orders, order_id, usd("5.00"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "PAYMENT_ORDER_NOT_PAYABLE");
+    }
+
+    async fn test_declined_and_timeout_leave_no_trace<
+        O: AsyncOrderRepository,
+        R: AsyncPaymentRepository,
+    >(
+        (orders, payments): (AsyncOrderService<O>, AsyncPaymentService<R>),
+    ) {
+        let order_id = order_of(&orders, "10.00").await;
+
+        payments
+            .gateway()
+            .script(GatewayResponse::Decline("insufficient funds".to_string()))
+            .script(GatewayResponse::Timeout);
+
+        let declined = payments
+            .authorize(&orders, order_id, usd("10.00"))
+            .await
+            .unwrap_err();
+        assert_eq!(declined.code(), "PAYMENT_DECLINED");
+
+        let timeout = payments
+            .authorize(&orders, order_id, usd("10.00"))
+            .await
+            .unwrap_err();
+        assert_eq!(timeout, PaymentError::Gateway(GatewayError::Timeout));
+        assert_eq!(timeout.http_status(), 503);
+
+        assert!(
+            payments
+                .get_order_payments(order_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    async fn test_capture_timeout_keeps_authorization<
+        O: AsyncOrderRepository,
+        R: AsyncPaymentRepository,
+    >(
+        (orders, payments): (AsyncOrderService<O>, AsyncPaymentService<R>),
+    ) {
+        let order_id = order_of(&orders, "10.00").await;
+        let payment = payments
+            .authorize(&orders, order_id, usd("10.00"))
+            .await
+            .unwrap();
+
+        payments.gateway().script(GatewayResponse::Timeout);
+        assert!(payments.capture(payment.id).await.is_err());
+
+        let stored = payments.get_payment(payment.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, PaymentStatus::Authorized);
+
+        // Reintento: el guion está vacío → aprueba
+        assert!(payments.capture(payment.id).await.is_ok());
+    }
+
+    async fn test_void_only_before_capture<O: AsyncOrderRepository, R: AsyncPaymentRepository>(
+        (orders, payments): (AsyncOrderService<O>, AsyncPaymentService<R>),
+    ) {
+        let order_id = order_of(&orders, "10.00").await;
+
+        let first = payments
+            .authorize(&orders, order_id, usd("10.00"))
+            .await
+            .unwrap();
+        assert_eq!(
+            payments.void(first.id).await.unwrap().status,
+            PaymentStatus::Voided
+        );
+
+        // La autorización liberada vuelve a dejar saldo pendiente
+        let second = payments
+            .authorize(&orders, order_id, usd("10.00"))
+            .await
+            .unwrap();
+        payments.capture(second.id).await.unwrap();
+
+        assert_eq!(
+            payments.void(second.id).await.unwrap_err(),
+            PaymentError::InvalidState {
+                id: second.id,
+                status: PaymentStatus::Captured,
+                operation: "void"
+            }
+        );
+    }
+
+    async fn test_partial_refunds<O: AsyncOrderRepository, R: AsyncPaymentRepository>(
+        (orders, payments): (AsyncOrderService<O>, AsyncPaymentService<R>),
+    ) {
+        let order_id = order_of(&orders, "100.00").await;
+        let payment = payments
+            .authorize(&orders, order_id, usd("100.00"))
+            .await
+            .unwrap();
+        payments.capture(payment.id).await.unwrap();
+
+        let partial = payments.refund(payment.id, usd("30.00")).await.unwrap();
+        assert_eq!(partial.status, PaymentStatus::PartiallyRefunded);
+
+        let err = payments.refund(payment.id, usd("80.00")).await.unwrap_err();
+        assert_eq!(
+            err,
+            PaymentError::ExceedsRefundable {
+                id: payment.id,
+                requested: usd("80.00"),
+                available: usd("70.00")
+            }
+        );
+
+        let full = payments.refund(payment.id, usd("70.00")).await.unwrap();
+        assert_eq!(full.status, PaymentStatus::Refunded);
+
+        let summary = payments.reconcile(&orders, order_id).await.unwrap();
+        assert_eq!(summary.refunded, usd("100.00"));
+        assert_eq!(summary.outstanding, usd("100.00"));
+    }
+}
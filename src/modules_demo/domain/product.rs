@@ -0,0 +1,526 @@
+// Dominio: Product (catálogo + inventario)
+// El precio de una orden sale de aquí, no de lo que mande el cliente
+
+use crate::modules_demo::shared::validation::{Rule, not_blank, positive};
+use crate::modules_demo::shared::{
+    Entity, ErrorCode, ErrorKind, IdGenerator, InMemoryRepository, Money, OrderId, ProductId,
+    RepoResult, Repository, RepositoryError, SequentialIdGenerator, Transactional, Validate,
+    ValidationErrors, Validator,
+};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+// ============================================================
+// MODEL
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Product {
+    pub id: ProductId,
+    /// Código de catálogo, normalizado a mayúsculas ("LAPTOP-15")
+    pub sku: String,
+    pub name: String,
+    pub price: Money,
+    /// Unidades físicas en depósito (incluye las reservadas)
+    pub stock: u32,
+    pub reservations: Vec<StockReservation>,
+}
+
+/// Unidades apartadas para una orden pendiente.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StockReservation {
+    pub order_id: OrderId,
+    pub quantity: u32,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Product {
+    pub fn reserved(&self) -> u32 {
+        self.reservations.iter().map(|r| r.quantity).sum()
+    }
+
+    /// Lo que todavía se puede vender.
+    pub fn available(&self) -> u32 {
+        self.stock.saturating_sub(self.reserved())
+    }
+
+    pub fn text_rules() -> [Rule<str>; 1] {
+        [not_blank()]
+    }
+
+    pub fn price_rules() -> [Rule<Money>; 1] {
+        [positive()]
+    }
+
+    fn take_reservation(&mut self, order_id: OrderId) -> Option<StockReservation> {
+        let index = self
+            .reservations
+            .iter()
+            .position(|r| r.order_id == order_id)?;
+        Some(self.reservations.remove(index))
+    }
+}
+
+impl Validate for Product {
+    fn validate(&self, v: &mut Validator) {
+        validate_fields(v, &self.sku, &self.name, &self.price);
+    }
+}
+
+// También antes de tener un id (add_product)
+fn validate_fields(v: &mut Validator, sku: &str, name: &str, price: &Money) {
+    v.field("sku", sku, &Product::text_rules())
+        .field("name", name, &Product::text_rules())
+        .field("price", price, &Product::price_rules());
+}
+
+impl Entity for Product {
+    type Id = ProductId;
+
+    fn id(&self) -> ProductId {
+        self.id
+    }
+}
+
+fn normalize_sku(sku: &str) -> String {
+    sku.trim().to_uppercase()
+}
+
+// ============================================================
+// ERRORS
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ProductError {
+    #[error("Invalid product")]
+    Invalid(#[from] ValidationErrors),
+
+    #[error("SKU already exists: {sku}")]
+    SkuAlreadyExists { sku: String },
+
+    #[error("Product {id} not found")]
+    NotFound { id: ProductId },
+
+    #[error("Insufficient stock for product {id}: requested {requested}, available {available}")]
+    InsufficientStock {
+        id: ProductId,
+        requested: u32,
+        available: u32,
+    },
+
+    #[error("Product {id} has active reservations")]
+    StillReserved { id: ProductId },
+
+    #[error("No stock reserved for order {order_id}")]
+    NoReservation { order_id: OrderId },
+
+    #[error("Product storage failed")]
+    Repository(#[from] RepositoryError),
+}
+
+impl ErrorCode for ProductError {
+    fn code(&self) -> &'static str {
+        match self {
+            ProductError::Invalid(_) => "PRODUCT_INVALID",
+            ProductError::SkuAlreadyExists { .. } => "PRODUCT_SKU_EXISTS",
+            ProductError::NotFound { .. } => "PRODUCT_NOT_FOUND",
+            ProductError::InsufficientStock { .. } => "PRODUCT_INSUFFICIENT_STOCK",
+            ProductError::StillReserved { .. } => "PRODUCT_STILL_RESERVED",
+            ProductError::NoReservation { .. } => "PRODUCT_NO_RESERVATION",
+            ProductError::Repository(e) => e.code(),
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ProductError::Invalid(_) => ErrorKind::Validation,
+            ProductError::SkuAlreadyExists { .. } => ErrorKind::Conflict,
+            ProductError::NotFound { .. } => ErrorKind::NotFound,
+            ProductError::InsufficientStock { .. }
+            | ProductError::StillReserved { .. }
+            | ProductError::NoReservation { .. } => ErrorKind::InvalidState,
+            ProductError::Repository(e) => e.kind(),
+        }
+    }
+}
+
+// ============================================================
+// REPOSITORY
+// ============================================================
+
+pub trait ProductRepository: Repository<Product, ProductId> {
+    fn find_by_sku(&self, sku: &str) -> RepoResult<Option<Product>> {
+        let sku = normalize_sku(sku);
+        Ok(self.iter()?.find(|p| p.sku == sku))
+    }
+
+    fn find_reserved_for(&self, order_id: OrderId) -> RepoResult<Vec<Product>> {
+        Ok(self
+            .iter()?
+            .filter(|p| p.reservations.iter().any(|r| r.order_id == order_id))
+            .collect())
+    }
+}
+
+pub type InMemoryProductRepository = InMemoryRepository<Product>;
+
+impl ProductRepository for InMemoryProductRepository {}
+
+// ============================================================
+// SERVICE
+// ============================================================
+
+pub struct ProductService<P = InMemoryProductRepository> {
+    repo: P,
+    ids: Box<dyn IdGenerator>,
+}
+
+impl ProductService {
+    pub fn new() -> Self {
+        Self::with_repository(InMemoryProductRepository::new())
+            .expect("in-memory repository is always readable")
+    }
+}
+
+impl Default for ProductService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: ProductRepository> ProductService<P> {
+    pub fn with_repository(repo: P) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
+
+        Ok(Self {
+            repo,
+            ids: Box::new(ids),
+        })
+    }
+
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    // ----- Catálogo -----
+
+    pub fn add_product(
+        &mut self,
+        sku: &str,
+        name: String,
+        price: Money,
+        stock: u32,
+    ) -> Result<Product, ProductError> {
+        let sku = normalize_sku(sku);
+        let mut v = Validator::new();
+        validate_fields(&mut v, &sku, &name, &price);
+        v.finish()?;
+
+        if self.repo.find_by_sku(&sku)?.is_some() {
+            return Err(ProductError::SkuAlreadyExists { sku });
+        }
+
+        let product = Product {
+            id: ProductId(self.ids.next_id()?),
+            sku,
+            name,
+            price,
+            stock,
+            reservations: Vec::new(),
+        };
+
+        self.repo.insert(product.clone())?;
+        Ok(product)
+    }
+
+    pub fn get_product(&self, id: ProductId) -> Result<Option<Product>, ProductError> {
+        Ok(self.repo.find_by_id(id)?)
+    }
+
+    pub fn find_by_sku(&self, sku: &str) -> Result<Option<Product>, ProductError> {
+        Ok(self.repo.find_by_sku(sku)?)
+    }
+
+    pub fn list_products(&self) -> Result<Vec<Product>, ProductError> {
+        Ok(self.repo.find_all()?)
+    }
+
+    /// Precio vigente de catálogo.
+    pub fn price_of(&self, id: ProductId) -> Result<Money, ProductError> {
+        Ok(self.require(id)?.price)
+    }
+
+    /// Afecta solo a órdenes futuras: las existentes guardan su precio.
+    pub fn update_price(&mut self, id: ProductId, price: Money) -> Result<(), ProductError> {
+        Validator::check("price", &price, &Product::price_rules())?;
+
+        let mut product = self.require(id)?;
+        product.price = price;
+        Ok(self.repo.save(product)?)
+    }
+
+    pub fn remove_product(&mut self, id: ProductId) -> Result<(), ProductError> {
+        if !self.require(id)?.reservations.is_empty() {
+            return Err(ProductError::StillReserved { id });
+        }
+
+        self.repo.delete(id)?;
+        Ok(())
+    }
+
+    // ----- Inventario -----
+
+    pub fn restock(&mut self, id: ProductId, quantity: u32) -> Result<(), ProductError> {
+        let mut product = self.require(id)?;
+        product.stock = product.stock.saturating_add(quantity);
+        Ok(self.repo.save(product)?)
+    }
+
+    pub fn available(&self, id: ProductId) -> Result<u32, ProductError> {
+        Ok(self.require(id)?.available())
+    }
+
+    /// Aparta stock para una orden. Todo o nada: si una línea no alcanza,
+    /// no se reserva ninguna.
+    pub fn reserve(
+        &mut self,
+        order_id: OrderId,
+        lines: &[(ProductId, u32)],
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), ProductError> {
+        // 1. Validar todo antes de tocar nada (líneas repetidas se suman)
+        let mut products: Vec<Product> = Vec::new();
+        for &(id, quantity) in lines {
+            let index = match products.iter().position(|p| p.id == id) {
+                Some(index) => index,
+                None => {
+                    products.push(self.require(id)?);
+                    products.len() - 1
+                }
+            };
+            let product = &mut products[index];
+
+            if quantity > product.available() {
+                return Err(ProductError::InsufficientStock {
+                    id,
+                    requested: quantity,
+                    available: product.available(),
+                });
+            }
+
+            product.reservations.push(StockReservation {
+                order_id,
+                quantity,
+                expires_at,
+            });
+        }
+
+        // 2. Persistir
+        for product in products {
+            self.repo.save(product)?;
+        }
+        Ok(())
+    }
+
+    /// Devuelve al stock disponible lo reservado por la orden (cancelación).
+    pub fn release(&mut self, order_id: OrderId) -> Result<(), ProductError> {
+        for mut product in self.repo.find_reserved_for(order_id)? {
+            while product.take_reservation(order_id).is_some() {}
+            self.repo.save(product)?;
+        }
+        Ok(())
+    }
+
+    /// La orden se confirmó: las unidades reservadas salen del stock.
+    /// Sin reserva (ya liberada o vencida y limpiada) no hay nada que
+    /// confirmar: es un error, no un no-op.
+    pub fn commit(&mut self, order_id: OrderId) -> Result<(), ProductError> {
+        let reserved = self.repo.find_reserved_for(order_id)?;
+        if reserved.is_empty() {
+            return Err(ProductError::NoReservation { order_id });
+        }
+
+        for mut product in reserved {
+            while let Some(reservation) = product.take_reservation(order_id) {
+                product.stock = product.stock.saturating_sub(reservation.quantity);
+            }
+            self.repo.save(product)?;
+        }
+        Ok(())
+    }
+
+    /// Libera las reservas vencidas y devuelve las órdenes afectadas.
+    pub fn release_expired(&mut self, now: DateTime<Utc>) -> Result<Vec<OrderId>, ProductError> {
+        let expired = self.expired_reservations(now)?;
+        for &order_id in &expired {
+            self.release(order_id)?;
+        }
+        Ok(expired)
+    }
+
+    /// Órdenes con alguna reserva vencida en `now`, sin liberarlas (para
+    /// liberar orden por orden, ver `OrderService::expire_reservations`).
+    pub fn expired_reservations(&self, now: DateTime<Utc>) -> Result<Vec<OrderId>, ProductError> {
+        let expired: BTreeSet<OrderId> = self
+            .repo
+            .iter()?
+            .flat_map(|p| p.reservations)
+            .filter(|r| r.expires_at <= now)
+            .map(|r| r.order_id)
+            .collect();
+        Ok(expired.into_iter().collect())
+    }
+
+    fn require(&self, id: ProductId) -> Result<Product, ProductError> {
+        self.repo
+            .find_by_id(id)?
+            .ok_or(ProductError::NotFound { id })
+    }
+}
+
+impl<P: ProductRepository + Transactional> Transactional for ProductService<P> {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.repo.begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.repo.commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.repo.rollback()
+    }
+}
+
+/*
+STOCK Y RESERVAS:
+
+  stock = 10       reservas: [orden 1: 3, orden 2: 2]       available = 5
+
+  create_order ──▶ reserve(order, lines, expires_at)   available ↓
+  confirm      ──▶ commit(order)                       stock ↓, reserva ✗
+  cancel       ──▶ release(order)                      available ↑
+  vencimiento  ──▶ release_expired(now)                available ↑
+
+- Las reservas viven dentro del Product: un solo repositorio, y
+  "available" se calcula sin consultas extra
+- reserve es todo-o-nada: se valida cada línea antes de guardar
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn usd(amount: &str) -> Money {
+        format!("{} USD", amount).parse().unwrap()
+    }
+
+    fn catalog_with_laptop(stock: u32) -> (ProductService, ProductId) {
+        let mut catalog = ProductService::new();
+        let laptop = catalog
+            .add_product("laptop-15", "Laptop".to_string(), usd("999.99"), stock)
+            .unwrap();
+        (catalog, laptop.id)
+    }
+
+    #[test]
+    fn test_add_and_lookup_by_sku() {
+        let (mut catalog, id) = catalog_with_laptop(5);
+
+        let found = catalog.find_by_sku(" Laptop-15 ").unwrap().unwrap();
+        assert_eq!(found.id, id);
+        assert_eq!(found.sku, "LAPTOP-15");
+
+        let err = catalog
+            .add_product("LAPTOP-15", "Other".to_string(), usd("1.00"), 1)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ProductError::SkuAlreadyExists {
+                sku: "LAPTOP-15".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_update_price() {
+        let (mut catalog, id) = catalog_with_laptop(5);
+
+        catalog.update_price(id, usd("899.99")).unwrap();
+        assert_eq!(catalog.price_of(id).unwrap(), usd("899.99"));
+
+        let err = catalog.update_price(id, usd("0.00")).unwrap_err();
+        assert_eq!(err.code(), "PRODUCT_INVALID");
+    }
+
+    #[test]
+    fn test_add_product_reports_every_invalid_field() {
+        let mut catalog = ProductService::new();
+
+        let result = catalog.add_product("  ", " ".to_string(), usd("-1.00"), 5);
+        let Err(ProductError::Invalid(errors)) = result else {
+            panic!("expected validation errors, got {result:?}");
+        };
+        assert_eq!(errors.paths(), ["sku", "name", "price"]);
+        assert!(catalog.list_products().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reserve_is_all_or_nothing() {
+        let (mut catalog, laptop) = catalog_with_laptop(5);
+        let mouse = catalog
+            .add_product("MOUSE", "Mouse".to_string(), usd("29.99"), 1)
+            .unwrap()
+            .id;
+        let expires = Utc::now() + Duration::minutes(15);
+
+        let err = catalog
+            .reserve(OrderId(1), &[(laptop, 2), (mouse, 3)], expires)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ProductError::InsufficientStock {
+                id: mouse,
+                requested: 3,
+                available: 1
+            }
+        );
+        assert_eq!(catalog.available(laptop).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_release_and_commit() {
+        let (mut catalog, id) = catalog_with_laptop(5);
+        let expires = Utc::now() + Duration::minutes(15);
+
+        catalog.reserve(OrderId(1), &[(id, 2)], expires).unwrap();
+        catalog.reserve(OrderId(2), &[(id, 3)], expires).unwrap();
+        assert_eq!(catalog.available(id).unwrap(), 0);
+
+        catalog.release(OrderId(1)).unwrap();
+        assert_eq!(catalog.available(id).unwrap(), 2);
+
+        catalog.commit(OrderId(2)).unwrap();
+        let product = catalog.get_product(id).unwrap().unwrap();
+        assert_eq!((product.stock, product.available()), (2, 2));
+    }
+
+    #[test]
+    fn test_release_expired() {
+        let (mut catalog, id) = catalog_with_laptop(5);
+        let now = Utc::now();
+
+        catalog
+            .reserve(OrderId(1), &[(id, 1)], now - Duration::seconds(1))
+            .unwrap();
+        catalog
+            .reserve(OrderId(2), &[(id, 1)], now + Duration::minutes(5))
+            .unwrap();
+
+        assert_eq!(catalog.release_expired(now).unwrap(), vec![OrderId(1)]);
+        assert_eq!(catalog.available(id).unwrap(), 4);
+    }
+}
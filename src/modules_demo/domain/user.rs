@@ -1,53 +1,295 @@
 // Estrategia: Organización por DOMINIO (Vertical Slicing)
 // Todo lo relacionado a Users está aquí: model, repository, service
 
-use std::collections::HashMap;
+use super::events::{DomainEvent, DomainEventBus, DomainOutbox, EventSink};
+use crate::modules_demo::shared::validation::{Rule, email, max_length, not_blank};
+use crate::modules_demo::shared::{
+    Entity, ErrorCode, ErrorKind, IdGenerator, InMemoryRepository, LogStructuredRepository,
+    Migration, Page, Query, RepoResult, Repository, RepositoryError, SequentialIdGenerator,
+    SqliteDatabase, Transactional, UserId, Validate, ValidationErrors, Validator,
+};
+use rusqlite::{OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 // ============================================================
 // MODEL
 // ============================================================
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct User {
-    pub id: u64,
+    pub id: UserId,
     pub name: String,
     pub email: String,
+    /// 1 al crearse, +1 en cada actualización. La UI la devuelve al
+    /// editar (`update_email_versioned`) para detectar ediciones viejas.
+    /// Archivos anteriores sin el campo: 0.
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Entity for User {
+    type Id = UserId;
+
+    fn id(&self) -> UserId {
+        self.id
+    }
+
+    fn unique_keys(&self) -> Vec<(&'static str, String)> {
+        vec![(EMAIL_UNIQUE, self.email.clone())]
+    }
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn with_version(self, version: u64) -> Self {
+        Self { version, ..self }
+    }
+}
+
+impl User {
+    pub fn name_rules() -> [Rule<str>; 2] {
+        [not_blank(), max_length(100)]
+    }
+
+    pub fn email_rules() -> [Rule<str>; 1] {
+        [email()]
+    }
+}
+
+impl Validate for User {
+    fn validate(&self, v: &mut Validator) {
+        v.field("name", self.name.as_str(), &Self::name_rules())
+            .field("email", self.email.as_str(), &Self::email_rules());
+    }
+}
+
+/// Nombre de la restricción de email único (igual al que reporta SQLite).
+pub(super) const EMAIL_UNIQUE: &str = "users.email";
+
+// ============================================================
+// ERRORS
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum UserError {
+    #[error("Invalid user")]
+    Invalid(#[from] ValidationErrors),
+
+    #[error("Email already exists: {email}")]
+    EmailAlreadyExists { email: String },
+
+    #[error("User {id} not found")]
+    NotFound { id: UserId },
+
+    #[error("User {id} was modified by someone else (expected version {expected}, found {actual})")]
+    VersionConflict {
+        id: UserId,
+        expected: u64,
+        actual: u64,
+    },
+
+    #[error("User storage failed")]
+    Repository(#[from] RepositoryError),
+}
+
+impl ErrorCode for UserError {
+    fn code(&self) -> &'static str {
+        match self {
+            UserError::Invalid(_) => "USER_INVALID",
+            UserError::EmailAlreadyExists { .. } => "USER_EMAIL_EXISTS",
+            UserError::NotFound { .. } => "USER_NOT_FOUND",
+            UserError::VersionConflict { .. } => "USER_VERSION_CONFLICT",
+            UserError::Repository(e) => e.code(),
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            UserError::Invalid(_) => ErrorKind::Validation,
+            UserError::EmailAlreadyExists { .. } | UserError::VersionConflict { .. } => {
+                ErrorKind::Conflict
+            }
+            UserError::NotFound { .. } => ErrorKind::NotFound,
+            UserError::Repository(e) => e.kind(),
+        }
+    }
 }
 
 // ============================================================
 // REPOSITORY
 // ============================================================
 
-pub struct UserRepository {
-    storage: HashMap<u64, User>,
+/// Búsquedas específicas de User sobre el Repository genérico.
+pub trait UserRepository: Repository<User, UserId> {
+    /// O(1) en los backends con índices (ver shared::index).
+    fn find_by_email(&self, email: &str) -> RepoResult<Option<User>> {
+        self.find_unique(EMAIL_UNIQUE, email)
+    }
 }
 
-impl UserRepository {
-    pub fn new() -> Self {
-        Self {
-            storage: HashMap::new(),
+pub type InMemoryUserRepository = InMemoryRepository<User>;
+
+impl UserRepository for InMemoryUserRepository {}
+
+pub type LogUserRepository = LogStructuredRepository<User>;
+
+impl UserRepository for LogUserRepository {}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        name: "users_001_create_table",
+        sql: "CREATE TABLE users (
+                  id    INTEGER PRIMARY KEY,
+                  name  TEXT NOT NULL CHECK (name <> ''),
+                  email TEXT NOT NULL UNIQUE
+              );",
+    },
+    Migration {
+        name: "users_002_add_version",
+        sql: "ALTER TABLE users ADD COLUMN version INTEGER NOT NULL DEFAULT 1;",
+    },
+];
+
+/// Users en SQLite. El email único lo garantiza el esquema, no el servicio.
+pub struct SqliteUserRepository {
+    db: SqliteDatabase,
+}
+
+impl SqliteUserRepository {
+    /// Usa `db` (compartida con otros repositorios) y migra la tabla `users`.
+    pub fn new(db: SqliteDatabase) -> RepoResult<Self> {
+        db.migrate(MIGRATIONS)?;
+        Ok(Self { db })
+    }
+}
+
+fn user_from_row(row: &Row<'_>) -> rusqlite::Result<User> {
+    Ok(User {
+        id: UserId(row.get(0)?),
+        name: row.get(1)?,
+        email: row.get(2)?,
+        version: row.get(3)?,
+    })
+}
+
+impl Repository<User, UserId> for SqliteUserRepository {
+    fn save(&mut self, user: User) -> RepoResult<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO users (id, name, email, version) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (id) DO UPDATE
+                 SET name = excluded.name, email = excluded.email, version = excluded.version",
+                params![user.id.0, user.name, user.email, user.version],
+            )
+            .map(drop)
+        })
+    }
+
+    // Override: el PRIMARY KEY ya rechaza el duplicado, sin consultar antes
+    fn insert(&mut self, user: User) -> RepoResult<()> {
+        self.db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO users (id, name, email, version) VALUES (?1, ?2, ?3, ?4)",
+                params![user.id.0, user.name, user.email, user.version],
+            )
+            .map(drop)
+        })
+    }
+
+    // Override: comparar y escribir en un solo UPDATE, atómico aunque otro
+    // proceso use la misma base
+    fn update_versioned(&mut self, user: User) -> RepoResult<User> {
+        let next = user.clone().with_version(user.version + 1);
+        let updated = self.db.with_connection(|conn| {
+            conn.execute(
+                "UPDATE users SET name = ?1, email = ?2, version = ?3
+                 WHERE id = ?4 AND version = ?5",
+                params![next.name, next.email, next.version, user.id.0, user.version],
+            )
+        })?;
+        if updated == 1 {
+            return Ok(next);
+        }
+
+        // Ninguna fila: el usuario no existe o su versión ya es otra
+        match self.find_by_id(user.id)? {
+            None => Err(RepositoryError::NotFound(format!("{:?}", user.id))),
+            Some(current) => Err(RepositoryError::VersionConflict {
+                id: format!("{:?}", user.id),
+                expected: user.version,
+                actual: current.version,
+            }),
         }
     }
 
-    pub fn save(&mut self, user: User) -> Result<(), String> {
-        self.storage.insert(user.id, user);
-        Ok(())
+    fn find_by_id(&self, id: UserId) -> RepoResult<Option<User>> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT id, name, email, version FROM users WHERE id = ?1",
+                [id.0],
+                user_from_row,
+            )
+            .optional()
+        })
+    }
+
+    fn delete(&mut self, id: UserId) -> RepoResult<Option<User>> {
+        self.db.transaction(|tx| {
+            let user = tx
+                .query_row(
+                    "SELECT id, name, email, version FROM users WHERE id = ?1",
+                    [id.0],
+                    user_from_row,
+                )
+                .optional()?;
+            tx.execute("DELETE FROM users WHERE id = ?1", [id.0])?;
+            Ok(user)
+        })
     }
 
-    pub fn find_by_id(&self, id: u64) -> Option<&User> {
-        self.storage.get(&id)
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = User> + '_>> {
+        let users = self.db.with_connection(|conn| {
+            let mut stmt =
+                conn.prepare("SELECT id, name, email, version FROM users ORDER BY id")?;
+            let rows = stmt.query_map([], user_from_row)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        })?;
+        Ok(Box::new(users.into_iter()))
     }
 
-    pub fn find_by_email(&self, email: &str) -> Option<&User> {
-        self.storage.values().find(|u| u.email == email)
+    fn count(&self) -> RepoResult<usize> {
+        self.db.with_connection(|conn| {
+            conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+        })
+    }
+}
+
+impl Transactional for SqliteUserRepository {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.db.begin()
     }
 
-    pub fn list_all(&self) -> Vec<&User> {
-        self.storage.values().collect()
+    fn commit(&mut self) -> RepoResult<()> {
+        self.db.commit()
     }
 
-    pub fn count(&self) -> usize {
-        self.storage.len()
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.db.rollback()
+    }
+}
+
+impl UserRepository for SqliteUserRepository {
+    fn find_by_email(&self, email: &str) -> RepoResult<Option<User>> {
+        self.db.with_connection(|conn| {
+            conn.query_row(
+                "SELECT id, name, email, version FROM users WHERE email = ?1",
+                [email],
+                user_from_row,
+            )
+            .optional()
+        })
     }
 }
 
@@ -55,70 +297,195 @@ impl UserRepository {
 // SERVICE (Business Logic)
 // ============================================================
 
-pub struct UserService {
-    repo: UserRepository,
+pub struct UserService<R = InMemoryUserRepository> {
+    repo: R,
+    ids: Box<dyn IdGenerator>,
+    events: EventSink,
 }
 
 impl UserService {
     pub fn new() -> Self {
-        Self {
-            repo: UserRepository::new(),
-        }
+        Self::with_repository(InMemoryUserRepository::new())
+            .expect("in-memory repository is always readable")
     }
+}
 
-    pub fn create_user(&mut self, name: String, email: String) -> Result<User, String> {
-        // Validación
-        if name.is_empty() {
-            return Err("Name cannot be empty".to_string());
-        }
+impl Default for UserService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        if !email.contains('@') {
-            return Err("Invalid email format".to_string());
-        }
+impl<R: UserRepository> UserService<R> {
+    pub fn with_repository(repo: R) -> RepoResult<Self> {
+        // Por defecto: secuencial a partir del mayor id ya guardado
+        let ids = SequentialIdGenerator::resuming(&repo)?;
 
-        // Verificar email único
-        if self.repo.find_by_email(&email).is_some() {
-            return Err("Email already exists".to_string());
-        }
+        Ok(Self {
+            repo,
+            ids: Box::new(ids),
+            events: EventSink::default(),
+        })
+    }
+
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    /// Publica en un bus compartido con otros servicios.
+    pub fn with_event_bus(mut self, events: DomainEventBus) -> Self {
+        self.events = self.events.with_bus(events);
+        self
+    }
+
+    /// Encola los eventos en lugar de publicarlos: quedan en la misma
+    /// transacción que el cambio y los publica un `OutboxRelay`.
+    pub fn with_outbox(mut self, outbox: DomainOutbox) -> Self {
+        self.events = self.events.with_outbox(outbox);
+        self
+    }
+
+    /// Para suscribirse a lo que publica este servicio.
+    pub fn events(&self) -> &DomainEventBus {
+        self.events.bus()
+    }
+
+    pub fn create_user(&mut self, name: String, email: String) -> Result<User, UserError> {
+        validate_new_user(&name, &email)?;
 
         let user = User {
-            id: (self.repo.count() + 1) as u64,
+            id: UserId(self.ids.next_id()?),
             name,
             email,
+            version: 1,
         };
 
-        self.repo.save(user.clone())?;
+        // insert (no save): un id repetido es error, nunca sobrescribe.
+        // El email único lo verifica el repositorio (UNIQUE en el esquema):
+        // no hay ventana entre "consultar" y "guardar".
+        self.repo
+            .insert(user.clone())
+            .map_err(|e| write_error(e, &user))?;
+
+        self.events.emit(DomainEvent::UserRegistered {
+            user_id: user.id,
+            email: user.email.clone(),
+        })?;
         Ok(user)
     }
 
-    pub fn get_user(&self, id: u64) -> Option<&User> {
-        self.repo.find_by_id(id)
+    pub fn get_user(&self, id: UserId) -> Result<Option<User>, UserError> {
+        Ok(self.repo.find_by_id(id)?)
     }
 
-    pub fn get_all_users(&self) -> Vec<&User> {
-        self.repo.list_all()
+    /// Todos, ordenados por id.
+    pub fn get_all_users(&self) -> Result<Vec<User>, UserError> {
+        Ok(self.repo.query(&Query::new())?.items)
     }
 
-    pub fn update_email(&mut self, user_id: u64, new_email: String) -> Result<(), String> {
-        if !new_email.contains('@') {
-            return Err("Invalid email format".to_string());
-        }
+    pub fn query_users(&self, query: &Query<User>) -> Result<Page<User>, UserError> {
+        Ok(self.repo.query(query)?)
+    }
+
+    /// Sobre la versión actual, sea cual sea. Para rechazar ediciones
+    /// hechas sobre una copia vieja, `update_email_versioned`.
+    pub fn update_email(&mut self, user_id: UserId, new_email: String) -> Result<(), UserError> {
+        let current = self
+            .repo
+            .find_by_id(user_id)?
+            .ok_or(UserError::NotFound { id: user_id })?;
+
+        self.update_email_versioned(user_id, current.version, new_email)
+            .map(drop)
+    }
+
+    /// Cambia el email solo si el usuario sigue en `expected_version` (la
+    /// que vio quien edita); si no, `UserError::VersionConflict`.
+    /// Devuelve el usuario guardado, con su nueva versión.
+    pub fn update_email_versioned(
+        &mut self,
+        user_id: UserId,
+        expected_version: u64,
+        new_email: String,
+    ) -> Result<User, UserError> {
+        validate_email(&new_email)?;
 
         let user = self
             .repo
-            .find_by_id(user_id)
-            .ok_or("User not found")?
-            .clone();
+            .find_by_id(user_id)?
+            .ok_or(UserError::NotFound { id: user_id })?;
 
-        let updated_user = User {
+        let old_email = user.email.clone();
+        let edited = User {
             email: new_email,
+            version: expected_version,
             ..user
         };
 
-        self.repo.save(updated_user)
+        let saved = self
+            .repo
+            .update_versioned(edited.clone())
+            .map_err(|e| write_error(e, &edited))?;
+
+        self.events.emit(DomainEvent::EmailChanged {
+            user_id,
+            old_email,
+            new_email: saved.email.clone(),
+        })?;
+        Ok(saved)
+    }
+}
+
+// Participa de un UnitOfWork a través de su repositorio (y su outbox,
+// que va primero: escribe recién en el commit)
+impl<R: UserRepository + Transactional> Transactional for UserService<R> {
+    fn begin(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.repo).begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.repo).commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.repo).rollback()
+    }
+}
+
+/// Traduce los conflictos del repositorio al guardar `user` a errores
+/// del dominio: email repetido y versión vieja.
+pub(super) fn write_error(error: RepositoryError, user: &User) -> UserError {
+    match error {
+        RepositoryError::UniqueViolation(constraint) if constraint == EMAIL_UNIQUE => {
+            UserError::EmailAlreadyExists {
+                email: user.email.clone(),
+            }
+        }
+        RepositoryError::VersionConflict {
+            expected, actual, ..
+        } => UserError::VersionConflict {
+            id: user.id,
+            expected,
+            actual,
+        },
+        other => UserError::Repository(other),
     }
 }
 
+/// Las reglas de `User` antes de tener un id: un alta inválida no
+/// consume ids. Compartida con `user_async`.
+pub(super) fn validate_new_user(name: &str, email: &str) -> Result<(), UserError> {
+    let mut v = Validator::new();
+    v.field("name", name, &User::name_rules())
+        .field("email", email, &User::email_rules());
+    Ok(v.finish()?)
+}
+
+pub(super) fn validate_email(email: &str) -> Result<(), UserError> {
+    Ok(Validator::check("email", email, &User::email_rules())?)
+}
+
 // ============================================================
 // VENTAJAS DE ESTE ENFOQUE
 // ============================================================
@@ -164,53 +531,115 @@ DESVENTAJAS:
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::modules_demo::shared::SortOrder;
+    use crate::modules_demo::shared::testing::backend_tests;
 
-    #[test]
-    fn test_create_user_success() {
-        let mut service = UserService::new();
+    fn sqlite_service() -> UserService<SqliteUserRepository> {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        UserService::with_repository(SqliteUserRepository::new(db).unwrap()).unwrap()
+    }
+
+    // Mismos tests, dos backends: tests::in_memory::* y tests::sqlite::*
+    backend_tests! {
+        [
+            test_create_user_success,
+            test_create_user_empty_name,
+            test_create_user_invalid_email,
+            test_create_user_duplicate_email,
+            test_update_email,
+            test_update_email_to_taken_email,
+            test_update_email_user_not_found,
+            test_stale_edit_is_rejected,
+            test_page_through_users,
+        ]
+        in_memory => UserService::new(),
+        sqlite => sqlite_service(),
+    }
+
+    fn test_page_through_users<R: UserRepository>(mut service: UserService<R>) {
+        for (name, domain) in [
+            ("Cy", "b"),
+            ("Al", "a"),
+            ("Bo", "b"),
+            ("Di", "a"),
+            ("Ed", "b"),
+        ] {
+            service
+                .create_user(
+                    name.to_string(),
+                    format!("{}@{domain}.com", name.to_lowercase()),
+                )
+                .unwrap();
+        }
+
+        // Por dominio, y dentro del dominio por nombre; páginas de 2
+        let query = || {
+            Query::new()
+                .sort_by(
+                    |u: &User| u.email.split('@').nth(1).map(str::to_string),
+                    SortOrder::Asc,
+                )
+                .sort_by(|u| u.name.clone(), SortOrder::Asc)
+                .limit(2)
+        };
+        let mut names = Vec::new();
+        let mut page = service.query_users(&query()).unwrap();
+        assert_eq!(page.total, 5);
+        loop {
+            names.extend(page.items.iter().map(|u| u.name.clone()));
+            let Some(cursor) = page.next else { break };
+            page = service.query_users(&query().after(cursor)).unwrap();
+        }
+        assert_eq!(names, ["Al", "Di", "Bo", "Cy", "Ed"]);
+
+        let by_domain = Query::new().filter(|u: &User| u.email.ends_with("@a.com"));
+        assert_eq!(service.query_users(&by_domain).unwrap().total, 2);
+    }
+
+    fn test_create_user_success<R: UserRepository>(mut service: UserService<R>) {
         let user = service
             .create_user("Alice".to_string(), "alice@example.com".to_string())
             .unwrap();
 
         assert_eq!(user.name, "Alice");
         assert_eq!(user.email, "alice@example.com");
-        assert_eq!(user.id, 1);
+        assert_eq!(user.id, UserId(1));
     }
 
-    #[test]
-    fn test_create_user_empty_name() {
-        let mut service = UserService::new();
+    fn test_create_user_empty_name<R: UserRepository>(mut service: UserService<R>) {
         let result = service.create_user("".to_string(), "test@example.com".to_string());
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Name cannot be empty");
+        assert!(matches!(result, Err(UserError::Invalid(e)) if e.paths() == ["name"]));
     }
 
-    #[test]
-    fn test_create_user_invalid_email() {
-        let mut service = UserService::new();
+    fn test_create_user_invalid_email<R: UserRepository>(mut service: UserService<R>) {
         let result = service.create_user("Bob".to_string(), "invalid-email".to_string());
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Invalid email format");
+        let err = result.unwrap_err();
+        assert!(matches!(&err, UserError::Invalid(e) if e.paths() == ["email"]));
+        assert_eq!(err.code(), "USER_INVALID");
+        assert_eq!(err.http_status(), 400);
     }
 
-    #[test]
-    fn test_create_user_duplicate_email() {
-        let mut service = UserService::new();
+    fn test_create_user_duplicate_email<R: UserRepository>(mut service: UserService<R>) {
         service
             .create_user("Alice".to_string(), "alice@test.com".to_string())
             .unwrap();
 
         let result = service.create_user("Bob".to_string(), "alice@test.com".to_string());
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Email already exists");
+        let err = result.unwrap_err();
+        assert_eq!(
+            err,
+            UserError::EmailAlreadyExists {
+                email: "alice@test.com".to_string()
+            }
+        );
+        assert_eq!(err.http_status(), 409);
+        assert_eq!(service.get_all_users().unwrap().len(), 1);
     }
 
-    #[test]
-    fn test_update_email() {
-        let mut service = UserService::new();
+    fn test_update_email<R: UserRepository>(mut service: UserService<R>) {
         let user = service
             .create_user("Charlie".to_string(), "charlie@old.com".to_string())
             .unwrap();
@@ -219,9 +648,161 @@ mod tests {
             .update_email(user.id, "charlie@new.com".to_string())
             .unwrap();
 
-        let updated = service.get_user(user.id).unwrap();
+        let updated = service.get_user(user.id).unwrap().unwrap();
         assert_eq!(updated.email, "charlie@new.com");
     }
 
+    fn test_stale_edit_is_rejected<R: UserRepository>(mut service: UserService<R>) {
+        let user = service
+            .create_user("Dana".to_string(), "dana@test.com".to_string())
+            .unwrap();
+        assert_eq!(user.version, 1);
+
+        // Dos pestañas abiertas sobre la versión 1: la primera en guardar gana
+        let saved = service
+            .update_email_versioned(user.id, 1, "dana@first.com".to_string())
+            .unwrap();
+        assert_eq!(saved.version, 2);
+
+        let stale = service.update_email_versioned(user.id, 1, "dana@second.com".to_string());
+        assert_eq!(
+            stale,
+            Err(UserError::VersionConflict {
+                id: user.id,
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(stale.unwrap_err().http_status(), 409);
+        assert_eq!(service.get_user(user.id).unwrap().unwrap(), saved);
+    }
+
+    fn test_update_email_to_taken_email<R: UserRepository>(mut service: UserService<R>) {
+        service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        let bob = service
+            .create_user("Bob".to_string(), "bob@test.com".to_string())
+            .unwrap();
+
+        let result = service.update_email(bob.id, "alice@test.com".to_string());
+        assert_eq!(
+            result,
+            Err(UserError::EmailAlreadyExists {
+                email: "alice@test.com".to_string()
+            })
+        );
+
+        let unchanged = service.get_user(bob.id).unwrap().unwrap();
+        assert_eq!(unchanged.email, "bob@test.com");
+    }
+
+    fn test_update_email_user_not_found<R: UserRepository>(mut service: UserService<R>) {
+        let result = service.update_email(UserId(42), "ghost@test.com".to_string());
+
+        assert_eq!(result, Err(UserError::NotFound { id: UserId(42) }));
+    }
+
+    #[test]
+    fn test_ids_come_from_injected_generator() {
+        use crate::modules_demo::shared::RandomIdGenerator;
+
+        let mut a = UserService::new().with_id_generator(RandomIdGenerator::seeded(7));
+        let mut b = UserService::new().with_id_generator(RandomIdGenerator::seeded(7));
+
+        let ua = a
+            .create_user("A".to_string(), "a@test.com".to_string())
+            .unwrap();
+        let ub = b
+            .create_user("A".to_string(), "a@test.com".to_string())
+            .unwrap();
+
+        assert_eq!(ua.id, ub.id); // misma semilla → mismo id
+        assert_ne!(ua.id, UserId(1));
+    }
+
+    #[test]
+    fn test_id_continues_after_existing_users() {
+        let mut repo = InMemoryUserRepository::new();
+        repo.save(User {
+            id: UserId(41),
+            name: "Old".to_string(),
+            email: "old@test.com".to_string(),
+            version: 1,
+        })
+        .unwrap();
+
+        let mut service = UserService::with_repository(repo).unwrap();
+        let user = service
+            .create_user("New".to_string(), "new@test.com".to_string())
+            .unwrap();
+
+        assert_eq!(user.id, UserId(42));
+    }
+
+    #[test]
+    fn test_log_store_survives_restart() {
+        use crate::modules_demo::shared::json_file::tests::TempPath;
+
+        let path = TempPath::new("domain_users_log");
+        let mut service =
+            UserService::with_repository(LogUserRepository::open(&path.0).unwrap()).unwrap();
+        let alice = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        service
+            .update_email(alice.id, "alice@new.com".to_string())
+            .unwrap();
+        drop(service);
+
+        let service =
+            UserService::with_repository(LogUserRepository::open(&path.0).unwrap()).unwrap();
+        let reloaded = service.get_user(alice.id).unwrap().unwrap();
+        assert_eq!(reloaded.email, "alice@new.com");
+    }
+
+    #[test]
+    fn test_sqlite_file_survives_restart() {
+        use crate::modules_demo::shared::json_file::tests::TempPath;
+
+        let path = TempPath::new("domain_users_sqlite");
+        let db = SqliteDatabase::open(&path.0).unwrap();
+        let mut service =
+            UserService::with_repository(SqliteUserRepository::new(db).unwrap()).unwrap();
+        let alice = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        drop(service);
+
+        // Reabrir: la migración ya corrió y el id sigue desde el último
+        let db = SqliteDatabase::open(&path.0).unwrap();
+        let mut service =
+            UserService::with_repository(SqliteUserRepository::new(db).unwrap()).unwrap();
+        assert_eq!(service.get_user(alice.id).unwrap().unwrap().name, "Alice");
+
+        let bob = service
+            .create_user("Bob".to_string(), "bob@test.com".to_string())
+            .unwrap();
+        assert_eq!(bob.id, UserId(2));
+    }
+
+    #[test]
+    fn test_random_ids_fit_in_sqlite() {
+        use crate::modules_demo::shared::RandomIdGenerator;
+
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        let mut service = UserService::with_repository(SqliteUserRepository::new(db).unwrap())
+            .unwrap()
+            .with_id_generator(RandomIdGenerator::seeded(1));
+
+        for i in 0..20 {
+            let user = service
+                .create_user(format!("User {i}"), format!("user{i}@test.com"))
+                .unwrap();
+            assert_eq!(service.get_user(user.id).unwrap(), Some(user));
+        }
+        assert_eq!(service.get_all_users().unwrap().len(), 20);
+    }
+
     // Ventaja: Todos los tests de User están aquí, aislados de otros dominios
 }
@@ -0,0 +1,448 @@
+// UserService async: mismas reglas que user.rs, usable desde tasks de tokio
+// `&self` en todos los métodos: se comparte con Arc, sin Mutex alrededor
+
+use super::events::{DomainEvent, DomainEventBus};
+use super::user::{self, SqliteUserRepository, User, UserError, UserRepository};
+use crate::modules_demo::shared::async_repository::resume_ids;
+use crate::modules_demo::shared::{
+    AsyncInMemoryRepository, AsyncRepository, IdGenerator, Page, Query, RepoResult,
+    SequentialIdGenerator, SpawnBlocking, UserId,
+};
+use async_trait::async_trait;
+
+// ============================================================
+// REPOSITORY
+// ============================================================
+
+/// Búsquedas específicas de User sobre el AsyncRepository genérico.
+#[async_trait]
+pub trait AsyncUserRepository: AsyncRepository<User, UserId> {
+    async fn find_by_email(&self, email: &str) -> RepoResult<Option<User>> {
+        self.find_unique(user::EMAIL_UNIQUE, email).await
+    }
+}
+
+pub type AsyncInMemoryUserRepository = AsyncInMemoryRepository<User>;
+
+impl AsyncUserRepository for AsyncInMemoryUserRepository {}
+
+// Cualquier UserRepository sync (JSON, log, SQLite) vía spawn_blocking,
+// conservando su find_by_email (en SQLite, una consulta indexada)
+#[async_trait]
+impl<R: UserRepository + Send + 'static> AsyncUserRepository for SpawnBlocking<R> {
+    async fn find_by_email(&self, email: &str) -> RepoResult<Option<User>> {
+        let email = email.to_string();
+        self.run(move |repo| repo.find_by_email(&email)).await
+    }
+}
+
+pub type BlockingSqliteUserRepository = SpawnBlocking<SqliteUserRepository>;
+
+// ============================================================
+// SERVICE
+// ============================================================
+
+pub struct AsyncUserService<R = AsyncInMemoryUserRepository> {
+    repo: R,
+    ids: Box<dyn IdGenerator>,
+    events: DomainEventBus,
+}
+
+impl AsyncUserService {
+    pub fn new() -> Self {
+        Self {
+            repo: AsyncInMemoryUserRepository::new(),
+            ids: Box::new(SequentialIdGenerator::new()),
+            events: DomainEventBus::new(),
+        }
+    }
+}
+
+impl Default for AsyncUserService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: AsyncUserRepository> AsyncUserService<R> {
+    /// Async porque retomar los ids requiere leer el repositorio.
+    pub async fn with_repository(repo: R) -> RepoResult<Self> {
+        let ids = resume_ids(&repo).await?;
+
+        Ok(Self {
+            repo,
+            ids: Box::new(ids),
+            events: DomainEventBus::new(),
+        })
+    }
+
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    pub fn with_event_bus(mut self, events: DomainEventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn events(&self) -> &DomainEventBus {
+        &self.events
+    }
+
+    pub async fn create_user(&self, name: String, email: String) -> Result<User, UserError> {
+        user::validate_new_user(&name, &email)?;
+
+        let user = User {
+            id: UserId(self.ids.next_id()?),
+            name,
+            email,
+            version: 1,
+        };
+
+        // Igual que en sync: el repositorio rechaza el email repetido de
+        // forma atómica, así dos tasks con el mismo email no pasan las dos
+        self.repo
+            .insert(user.clone())
+            .await
+            .map_err(|e| user::write_error(e, &user))?;
+
+        self.events.publish(DomainEvent::UserRegistered {
+            user_id: user.id,
+            email: user.email.clone(),
+        });
+        Ok(user)
+    }
+
+    pub async fn get_user(&self, id: UserId) -> Result<Option<User>, UserError> {
+        Ok(self.repo.find_by_id(id).await?)
+    }
+
+    pub async fn get_all_users(&self) -> Result<Vec<User>, UserError> {
+        Ok(self.repo.query(&Query::new()).await?.items)
+    }
+
+    pub async fn query_users(&self, query: &Query<User>) -> Result<Page<User>, UserError> {
+        Ok(self.repo.query(query).await?)
+    }
+
+    /// Sobre la versión leída: si otra task escribe entre la lectura y el
+    /// guardado, falla con `VersionConflict` en lugar de pisarla.
+    pub async fn update_email(&self, user_id: UserId, new_email: String) -> Result<(), UserError> {
+        let current = self
+            .repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or(UserError::NotFound { id: user_id })?;
+
+        self.update_email_versioned(user_id, current.version, new_email)
+            .await
+            .map(drop)
+    }
+
+    pub async fn update_email_versioned(
+        &self,
+        user_id: UserId,
+        expected_version: u64,
+        new_email: String,
+    ) -> Result<User, UserError> {
+        user::validate_email(&new_email)?;
+
+        let user = self
+            .repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or(UserError::NotFound { id: user_id })?;
+
+        let old_email = user.email.clone();
+        let edited = User {
+            email: new_email,
+            version: expected_version,
+            ..user
+        };
+
+        // Comparar y guardar en una sola llamada: sin .await en el medio
+        let saved = self
+            .repo
+            .update_versioned(edited.clone())
+            .await
+            .map_err(|e| user::write_error(e, &edited))?;
+
+        self.events.publish(DomainEvent::EmailChanged {
+            user_id,
+            old_email,
+            new_email: saved.email.clone(),
+        });
+        Ok(saved)
+    }
+}
+
+/*
+SYNC vs ASYNC:
+
+  UserService<R: UserRepository>           AsyncUserService<R: AsyncUserRepository>
+  ──────────────────────────────           ────────────────────────────────────────
+  &mut self para crear/actualizar          &self → Arc<AsyncUserService> entre tasks
+  with_repository(repo)                    with_repository(repo).await
+  InMemoryUserRepository                   AsyncInMemoryUserRepository (tokio RwLock)
+  SqliteUserRepository                     SpawnBlocking<SqliteUserRepository>
+
+- Las reglas de negocio son las mismas y devuelven los mismos UserError
+- Los ids salen de un IdGenerator (Send + Sync): dos tasks nunca
+  reciben el mismo
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::testing::backend_tests;
+    use crate::modules_demo::shared::{ErrorCode, SortOrder, SqliteDatabase};
+    use std::sync::Arc;
+
+    async fn in_memory_service() -> AsyncUserService {
+        AsyncUserService::new()
+    }
+
+    async fn sqlite_service() -> AsyncUserService<BlockingSqliteUserRepository> {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        let repo = SqliteUserRepository::new(db).unwrap();
+        AsyncUserService::with_repository(SpawnBlocking::new(repo))
+            .await
+            .unwrap()
+    }
+
+    // Espejo de los tests de user.rs, como #[tokio::test]
+    backend_tests! {
+        async [
+            test_create_user_success,
+            test_create_user_empty_name,
+            test_create_user_invalid_email,
+            test_create_user_duplicate_email,
+            test_update_email,
+            test_update_email_to_taken_email,
+            test_update_email_user_not_found,
+            test_concurrent_signups_same_email,
+            test_concurrent_edits_one_wins,
+            test_page_through_users,
+        ]
+        in_memory => in_memory_service(),
+        sqlite => sqlite_service(),
+    }
+
+    async fn test_create_user_success<R: AsyncUserRepository>(service: AsyncUserService<R>) {
+        let user = service
+            .create_user("Alice".to_string(), "alice@example.com".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(user.name, "Alice");
+        assert_eq!(user.email, "alice@example.com");
+        assert_eq!(user.id, UserId(1));
+    }
+
+    async fn test_create_user_empty_name<R: AsyncUserRepository>(service: AsyncUserService<R>) {
+        let result = service
+            .create_user("".to_string(), "test@example.com".to_string())
+            .await;
+
+        assert!(matches!(result, Err(UserError::Invalid(e)) if e.paths() == ["name"]));
+    }
+
+    async fn test_create_user_invalid_email<R: AsyncUserRepository>(service: AsyncUserService<R>) {
+        let result = service
+            .create_user("Bob".to_string(), "invalid-email".to_string())
+            .await;
+
+        let err = result.unwrap_err();
+        assert!(matches!(&err, UserError::Invalid(e) if e.paths() == ["email"]));
+        assert_eq!(err.code(), "USER_INVALID");
+        assert_eq!(err.http_status(), 400);
+    }
+
+    async fn test_create_user_duplicate_email<R: AsyncUserRepository>(
+        service: AsyncUserService<R>,
+    ) {
+        service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .await
+            .unwrap();
+
+        let result = service
+            .create_user("Bob".to_string(), "alice@test.com".to_string())
+            .await;
+
+        let err = result.unwrap_err();
+        assert_eq!(
+            err,
+            UserError::EmailAlreadyExists {
+                email: "alice@test.com".to_string()
+            }
+        );
+        assert_eq!(err.http_status(), 409);
+        assert_eq!(service.get_all_users().await.unwrap().len(), 1);
+    }
+
+    async fn test_update_email<R: AsyncUserRepository>(service: AsyncUserService<R>) {
+        let user = service
+            .create_user("Charlie".to_string(), "charlie@old.com".to_string())
+            .await
+            .unwrap();
+
+        service
+            .update_email(user.id, "charlie@new.com".to_string())
+            .await
+            .unwrap();
+
+        let updated = service.get_user(user.id).await.unwrap().unwrap();
+        assert_eq!(updated.email, "charlie@new.com");
+    }
+
+    async fn test_update_email_to_taken_email<R: AsyncUserRepository>(
+        service: AsyncUserService<R>,
+    ) {
+        service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .await
+            .unwrap();
+        let bob = service
+            .create_user("Bob".to_string(), "bob@test.com".to_string())
+            .await
+            .unwrap();
+
+        let result = service
+            .update_email(bob.id, "alice@test.com".to_string())
+            .await;
+        assert_eq!(
+            result,
+            Err(UserError::EmailAlreadyExists {
+                email: "alice@test.com".to_string()
+            })
+        );
+
+        let unchanged = service.get_user(bob.id).await.unwrap().unwrap();
+        assert_eq!(unchanged.email, "bob@test.com");
+    }
+
+    async fn test_update_email_user_not_found<R: AsyncUserRepository>(
+        service: AsyncUserService<R>,
+    ) {
+        let result = service
+            .update_email(UserId(42), "ghost@test.com".to_string())
+            .await;
+
+        assert_eq!(result, Err(UserError::NotFound { id: UserId(42) }));
+    }
+
+    async fn test_concurrent_signups_same_email<R: AsyncUserRepository + 'static>(
+        service: AsyncUserService<R>,
+    ) {
+        let service = Arc::new(service);
+
+        let tasks: Vec<_> = (0..8)
+            .map(|n| {
+                let service = Arc::clone(&service);
+                tokio::spawn(async move {
+                    service
+                        .create_user(format!("User {n}"), "same@test.com".to_string())
+                        .await
+                })
+            })
+            .collect();
+
+        let mut created = 0;
+        for task in tasks {
+            match task.await.unwrap() {
+                Ok(_) => created += 1,
+                Err(e) => assert!(matches!(e, UserError::EmailAlreadyExists { .. })),
+            }
+        }
+        assert_eq!(created, 1);
+        assert_eq!(service.get_all_users().await.unwrap().len(), 1);
+    }
+
+    async fn test_concurrent_edits_one_wins<R: AsyncUserRepository + 'static>(
+        service: AsyncUserService<R>,
+    ) {
+        let service = Arc::new(service);
+        let user = service
+            .create_user("Eve".to_string(), "eve@test.com".to_string())
+            .await
+            .unwrap();
+
+        // Todas editan la versión que leyeron: solo una puede guardarla
+        let tasks: Vec<_> = (0..8)
+            .map(|n| {
+                let service = Arc::clone(&service);
+                tokio::spawn(async move {
+                    service
+                        .update_email_versioned(user.id, user.version, format!("eve{n}@test.com"))
+                        .await
+                })
+            })
+            .collect();
+
+        let mut saved = Vec::new();
+        for task in tasks {
+            match task.await.unwrap() {
+                Ok(user) => saved.push(user),
+                Err(e) => assert!(matches!(e, UserError::VersionConflict { actual: 2, .. })),
+            }
+        }
+        assert_eq!(saved.len(), 1);
+        assert_eq!(
+            service.get_user(user.id).await.unwrap(),
+            Some(saved[0].clone())
+        );
+    }
+
+    async fn test_page_through_users<R: AsyncUserRepository>(service: AsyncUserService<R>) {
+        for name in ["Cy", "Al", "Bo"] {
+            service
+                .create_user(name.to_string(), format!("{name}@test.com"))
+                .await
+                .unwrap();
+        }
+
+        let by_name = || {
+            Query::new()
+                .sort_by(|u: &User| u.name.clone(), SortOrder::Asc)
+                .limit(2)
+        };
+        let first = service.query_users(&by_name()).await.unwrap();
+        let second = service
+            .query_users(&by_name().after(first.next.unwrap()))
+            .await
+            .unwrap();
+
+        let names: Vec<_> = first
+            .items
+            .iter()
+            .chain(&second.items)
+            .map(|u| u.name.as_str())
+            .collect();
+        assert_eq!(names, ["Al", "Bo", "Cy"]);
+        assert_eq!(second.total, 3);
+        assert!(second.next.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_id_continues_after_existing_users() {
+        let repo = AsyncInMemoryUserRepository::new();
+        repo.save(User {
+            id: UserId(41),
+            name: "Old".to_string(),
+            email: "old@test.com".to_string(),
+            version: 1,
+        })
+        .await
+        .unwrap();
+
+        let service = AsyncUserService::with_repository(repo).await.unwrap();
+        let user = service
+            .create_user("New".to_string(), "new@test.com".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(user.id, UserId(42));
+    }
+}
@@ -0,0 +1,129 @@
+// Error de aplicación: compone los errores de cada dominio
+// Los dominios no se conocen entre sí; solo la capa superior los junta
+
+use super::domain::{OrderError, PaymentError, UserError};
+use super::shared::{ErrorCode, ErrorKind, RepositoryError};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("user operation failed")]
+    User(#[from] UserError),
+
+    #[error("order operation failed")]
+    Order(#[from] OrderError),
+
+    #[error("payment operation failed")]
+    Payment(#[from] PaymentError),
+
+    /// Falló el begin/commit/rollback de un UnitOfWork, no un dominio
+    #[error("transaction failed")]
+    Transaction(#[from] RepositoryError),
+}
+
+impl ErrorCode for AppError {
+    // Delegación: el código estable es siempre el del error de dominio
+    fn code(&self) -> &'static str {
+        match self {
+            AppError::User(e) => e.code(),
+            AppError::Order(e) => e.code(),
+            AppError::Payment(e) => e.code(),
+            AppError::Transaction(e) => e.code(),
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            AppError::User(e) => e.kind(),
+            AppError::Order(e) => e.kind(),
+            AppError::Payment(e) => e.kind(),
+            AppError::Transaction(e) => e.kind(),
+        }
+    }
+}
+
+/*
+CADENA DE ERRORES (source()):
+
+AppError::User                     "user operation failed"
+  └── UserError::Repository        "User storage failed"
+        └── RepositoryError        "storage failure: disk full"
+
+- #[from] genera From<UserError> → `?` convierte automáticamente
+- #[from] también marca el campo como #[source]
+- Display de cada nivel describe SOLO ese nivel (no repite la causa)
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::domain::{OrderLine, OrderService, UserService};
+    use crate::modules_demo::shared::{UnitOfWork, UserId};
+    use std::error::Error;
+
+    fn register(service: &mut UserService, email: &str) -> Result<UserId, AppError> {
+        let user = service.create_user("Alice".to_string(), email.to_string())?;
+        Ok(user.id)
+    }
+
+    #[test]
+    fn test_question_mark_converts_domain_error() {
+        let mut service = UserService::new();
+        register(&mut service, "alice@test.com").unwrap();
+
+        let err = register(&mut service, "alice@test.com").unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::User(UserError::EmailAlreadyExists { .. })
+        ));
+        assert_eq!(err.code(), "USER_EMAIL_EXISTS");
+        assert_eq!(err.http_status(), 409);
+        assert_eq!(err.exit_code(), 73);
+    }
+
+    #[test]
+    fn test_source_chain() {
+        let err = AppError::from(UserError::from(RepositoryError::Storage(
+            "disk full".to_string(),
+        )));
+
+        let mut chain = vec![err.to_string()];
+        let mut current = err.source();
+        while let Some(cause) = current {
+            chain.push(cause.to_string());
+            current = cause.source();
+        }
+
+        assert_eq!(
+            chain,
+            vec![
+                "user operation failed",
+                "User storage failed",
+                "storage failure: disk full"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unit_of_work_across_domains() {
+        let mut users = UserService::new();
+        let mut orders = OrderService::new();
+        let price = "5.00 USD".parse().unwrap();
+        let product = orders
+            .catalog_mut()
+            .add_product("BOOK", "Book".to_string(), price, 1)
+            .unwrap()
+            .id;
+
+        // Alta de usuario + primera orden: sin stock suficiente no queda ni el usuario
+        let result: Result<_, AppError> =
+            UnitOfWork::new((&mut users, &mut orders)).run(|(users, orders)| {
+                let user = users.create_user("Bob".to_string(), "bob@test.com".to_string())?;
+                Ok(orders.create_order(user.id, vec![OrderLine::new(product, 2)])?)
+            });
+
+        assert_eq!(result.unwrap_err().code(), "PRODUCT_INSUFFICIENT_STOCK");
+        assert!(users.get_all_users().unwrap().is_empty());
+        assert_eq!(orders.catalog().available(product).unwrap(), 1);
+    }
+}
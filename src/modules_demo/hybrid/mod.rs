@@ -3,7 +3,10 @@
 pub mod user;
 
 // Re-exports
-pub use user::{User, UserService};
+pub use user::{
+    JsonFileUserRepository, SharedUserService, User, UserError, UserEvent, UserEventBus,
+    UserService,
+};
 
 /*
 ESTRATEGIA HÍBRIDA:
@@ -13,9 +16,12 @@ hybrid/
 ├── mod.rs           ← Punto de entrada
 └── user/
     ├── mod.rs       ← Re-exports del dominio
+    ├── error.rs     ← Errores tipados
+    ├── events.rs    ← Eventos publicados por el service
     ├── model.rs     ← Estructuras de datos
     ├── repository.rs ← Persistencia
-    └── service.rs   ← Lógica de negocio
+    ├── service.rs   ← Lógica de negocio
+    └── shared_service.rs ← Misma lógica, Send + Sync + Clone
 
 Uso desde fuera:
 ```rust
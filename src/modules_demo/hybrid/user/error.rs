@@ -0,0 +1,82 @@
+// Error: Errores tipados del dominio User
+// Separado del service para que model/repository puedan usarlo sin ciclos
+
+use crate::modules_demo::shared::{
+    ErrorCode, ErrorKind, RepositoryError, UserId, ValidationErrors,
+};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum UserError {
+    /// Todos los campos que no cumplen las reglas de `User`.
+    #[error("Invalid user")]
+    Invalid(#[from] ValidationErrors),
+
+    #[error("Email already exists: {email}")]
+    EmailAlreadyExists { email: String },
+
+    #[error("Email {email} already in use by user {owner_id}")]
+    EmailInUse { email: String, owner_id: UserId },
+
+    #[error("User {id} not found")]
+    NotFound { id: UserId },
+
+    /// Se editó una copia vieja: otro la guardó después de leerla.
+    #[error("User {id} changed since version {expected} (now {actual})")]
+    VersionConflict {
+        id: UserId,
+        expected: u64,
+        actual: u64,
+    },
+
+    /// Restaurar solo tiene sentido sobre un usuario borrado.
+    #[error("User {id} is not deleted")]
+    NotDeleted { id: UserId },
+
+    #[error("User storage failed")]
+    Repository(#[from] RepositoryError),
+}
+
+impl ErrorCode for UserError {
+    fn code(&self) -> &'static str {
+        match self {
+            UserError::Invalid(_) => "USER_INVALID",
+            UserError::EmailAlreadyExists { .. } => "USER_EMAIL_EXISTS",
+            UserError::EmailInUse { .. } => "USER_EMAIL_IN_USE",
+            UserError::NotFound { .. } => "USER_NOT_FOUND",
+            UserError::VersionConflict { .. } => "USER_VERSION_CONFLICT",
+            UserError::NotDeleted { .. } => "USER_NOT_DELETED",
+            UserError::Repository(e) => e.code(),
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            UserError::Invalid(_) => ErrorKind::Validation,
+            UserError::EmailAlreadyExists { .. }
+            | UserError::EmailInUse { .. }
+            | UserError::VersionConflict { .. } => ErrorKind::Conflict,
+            UserError::NotFound { .. } => ErrorKind::NotFound,
+            UserError::NotDeleted { .. } => ErrorKind::InvalidState,
+            UserError::Repository(e) => e.kind(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_repository_error_is_source() {
+        let err = UserError::from(RepositoryError::Storage("disk full".to_string()));
+
+        assert_eq!(err.code(), "REPOSITORY_STORAGE");
+        assert_eq!(err.exit_code(), 74);
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            "storage failure: disk full"
+        );
+    }
+}
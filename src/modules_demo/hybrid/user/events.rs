@@ -0,0 +1,110 @@
+// Events: lo que el service publica cuando una operación se guardó
+// Notificaciones, analytics, etc. se suscriben sin que el service los conozca
+
+use crate::modules_demo::shared::{EventBus, Outbox, UserId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UserEvent {
+    Registered {
+        user_id: UserId,
+        email: String,
+    },
+    EmailChanged {
+        user_id: UserId,
+        old_email: String,
+        new_email: String,
+    },
+    /// Soft delete: todavía se puede restaurar.
+    Deleted {
+        user_id: UserId,
+    },
+    Restored {
+        user_id: UserId,
+    },
+    /// Borrado definitivo por retención.
+    Purged {
+        user_id: UserId,
+    },
+}
+
+pub type UserEventBus = EventBus<UserEvent>;
+
+/// Eventos guardados junto con el cambio; un `OutboxRelay` los publica.
+pub type UserOutbox = Outbox<UserEvent>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::hybrid::user::{SharedUserService, UserService};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_service_publishes_after_each_change() {
+        let bus = UserEventBus::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        bus.subscribe(move |event| sink.lock().unwrap().push(event.clone()));
+
+        let mut service = UserService::new().with_event_bus(bus);
+        let alice = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        service
+            .update_email(alice.id, "alice@new.com".to_string())
+            .unwrap();
+        service.delete_user(alice.id).unwrap();
+        // Fallidas: no publican
+        assert!(service.delete_user(alice.id).is_err());
+        assert!(
+            service
+                .create_user("".to_string(), "x@test.com".to_string())
+                .is_err()
+        );
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                UserEvent::Registered {
+                    user_id: alice.id,
+                    email: "alice@test.com".to_string()
+                },
+                UserEvent::EmailChanged {
+                    user_id: alice.id,
+                    old_email: "alice@test.com".to_string(),
+                    new_email: "alice@new.com".to_string()
+                },
+                UserEvent::Deleted { user_id: alice.id },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_async_subscriber_decoupled_from_shared_service() {
+        let service = SharedUserService::new();
+        let (welcome, mut sent) = mpsc::unbounded_channel();
+
+        // "Notificaciones": manda un email de bienvenida, lento, en su task
+        service.events().subscribe_async(move |event| {
+            let welcome = welcome.clone();
+            async move {
+                if let UserEvent::Registered { email, .. } = event {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    welcome.send(email).unwrap();
+                }
+            }
+        });
+
+        service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        service
+            .create_user("Bob".to_string(), "bob@test.com".to_string())
+            .unwrap();
+
+        assert_eq!(sent.recv().await.unwrap(), "alice@test.com");
+        assert_eq!(sent.recv().await.unwrap(), "bob@test.com");
+    }
+}
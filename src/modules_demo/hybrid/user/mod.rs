@@ -1,23 +1,34 @@
 // Módulo user: Enfoque híbrido
 // Separa responsabilidades técnicas dentro del dominio
 
+pub mod error;
+pub mod events;
 pub mod model;
 pub mod repository;
 pub mod service;
+pub mod shared_service;
 
 // Re-exports: API pública limpia
+pub use error::UserError;
+pub use events::{UserEvent, UserEventBus, UserOutbox};
 pub use model::User;
+pub use repository::JsonFileUserRepository;
 pub use service::UserService;
+pub use shared_service::SharedUserService;
 
-// repository::UserRepository no se exporta (implementación interna)
+// repository::UserRepository no se exporta (implementación interna);
+// sí el backend concreto, que se elige al construir el service
 
 /*
 VENTAJAS DEL ENFOQUE HÍBRIDO:
 
 1. SEPARACIÓN DE RESPONSABILIDADES
    - model.rs: Solo datos y validaciones básicas
+   - error.rs: Errores tipados del dominio
+   - events.rs: Lo que el service publica (UserEvent)
    - repository.rs: Solo persistencia
    - service.rs: Solo lógica de negocio
+   - shared_service.rs: La misma lógica, compartible entre threads
 
 2. TESTABILIDAD
    - Cada capa se puede testear independientemente
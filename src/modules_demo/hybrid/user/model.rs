@@ -1,25 +1,61 @@
 // Model: Solo la estructura de datos
 // Separado para reutilización fácil
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::modules_demo::shared::UserId;
+use crate::modules_demo::shared::validation::{
+    Rule, Validate, Validator, email, max_length, not_blank,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct User {
-    pub id: u64,
+    pub id: UserId,
     pub name: String,
     pub email: String,
+    /// Cuántas veces se guardó: la UI la manda de vuelta al editar para
+    /// que una edición sobre datos viejos falle en lugar de pisar.
+    #[serde(default)]
+    pub version: u64,
+    /// Soft delete: oculto pero recuperable hasta que lo purgue la
+    /// retención. `None`: activo.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl User {
-    pub fn new(id: u64, name: String, email: String) -> Self {
-        Self { id, name, email }
+    /// Tipo con el que se registra en el `AuditLog`.
+    pub const AUDIT_TYPE: &'static str = "user";
+
+    /// Un usuario recién creado: versión 1.
+    pub fn new(id: UserId, name: String, email: String) -> Self {
+        Self {
+            id,
+            name,
+            email,
+            version: 1,
+            deleted_at: None,
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    // Reglas en el modelo: el servicio las corre antes de tener un id
+    pub fn name_rules() -> [Rule<str>; 2] {
+        [not_blank(), max_length(100)]
     }
 
-    // Métodos de validación en el modelo
-    pub fn is_valid_email(email: &str) -> bool {
-        email.contains('@') && email.contains('.')
+    pub fn email_rules() -> [Rule<str>; 1] {
+        [email()]
     }
+}
 
-    pub fn is_valid_name(name: &str) -> bool {
-        !name.is_empty() && name.len() <= 100
+impl Validate for User {
+    fn validate(&self, v: &mut Validator) {
+        v.field("name", self.name.as_str(), &Self::name_rules())
+            .field("email", self.email.as_str(), &Self::email_rules());
     }
 }
 
@@ -27,15 +63,21 @@ impl User {
 mod tests {
     use super::*;
 
+    fn user(name: &str, email: &str) -> User {
+        User::new(UserId::new(1), name.to_string(), email.to_string())
+    }
+
     #[test]
-    fn test_valid_email() {
-        assert!(User::is_valid_email("test@example.com"));
-        assert!(!User::is_valid_email("invalid"));
+    fn test_valid_user() {
+        assert_eq!(user("Alice", "test@example.com").validated(), Ok(()));
     }
 
     #[test]
-    fn test_valid_name() {
-        assert!(User::is_valid_name("Alice"));
-        assert!(!User::is_valid_name(""));
+    fn test_invalid_user_reports_every_field() {
+        let errors = user(&"x".repeat(101), "invalid").validated().unwrap_err();
+
+        assert_eq!(errors.paths(), ["name", "email"]);
+        assert_eq!(errors.errors()[0].code, "LENGTH");
+        assert_eq!(errors.errors()[1].code, "EMAIL");
     }
 }
@@ -2,42 +2,174 @@
 // Solo se encarga de guardar/recuperar datos
 
 use super::model::User;
-use std::collections::HashMap;
+use crate::modules_demo::shared::{
+    Entity, InMemoryRepository, JsonFileRepository, RepoResult, Repository, Transactional, UserId,
+};
+use std::path::PathBuf;
 
-pub struct UserRepository {
-    storage: HashMap<u64, User>,
-}
+impl Entity for User {
+    type Id = UserId;
 
-impl UserRepository {
-    pub fn new() -> Self {
-        Self {
-            storage: HashMap::new(),
+    fn id(&self) -> UserId {
+        self.id
+    }
+
+    // El email de un borrado sigue reservado: restaurar nunca choca
+    fn unique_keys(&self) -> Vec<(&'static str, String)> {
+        vec![(EMAIL_UNIQUE, self.email.clone())]
+    }
+
+    fn index_keys(&self) -> Vec<(&'static str, String)> {
+        match self.deleted_at {
+            Some(_) => vec![(DELETED_INDEX, DELETED.to_string())],
+            None => Vec::new(),
         }
     }
 
-    pub fn save(&mut self, user: User) -> Result<(), String> {
-        self.storage.insert(user.id, user);
-        Ok(())
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn with_version(self, version: u64) -> Self {
+        Self { version, ..self }
+    }
+}
+
+/// Índice único de email: lo mantiene el repositorio, no el service.
+pub(super) const EMAIL_UNIQUE: &str = "users.email";
+
+/// Índice de los borrados (soft delete): solo ellos tienen clave.
+const DELETED_INDEX: &str = "users.deleted";
+const DELETED: &str = "deleted";
+
+/// Contrato de persistencia de User: CRUD genérico + búsqueda por email.
+/// Cualquier backend (SQL, NoSQL, archivo) que lo implemente sirve al service.
+///
+/// Las lecturas de `Repository` (`find_by_id`, `find_all`, `query`,
+/// `count`) no ven a los borrados; para verlos, los `_including_deleted`.
+pub trait UserRepository: Repository<User, UserId> {
+    fn find_by_id_including_deleted(&self, id: UserId) -> RepoResult<Option<User>>;
+
+    fn find_all_including_deleted(&self) -> RepoResult<Vec<User>>;
+
+    /// Incluye a los borrados: su email sigue reservado.
+    fn find_by_email(&self, email: &str) -> RepoResult<Option<User>> {
+        self.find_unique(EMAIL_UNIQUE, email)
+    }
+
+    /// Los que tienen `deleted_at` (para restaurar o purgar).
+    fn find_deleted(&self) -> RepoResult<Vec<User>> {
+        self.find_indexed(DELETED_INDEX, DELETED)
+    }
+}
+
+// ============================================================
+// SOFT DELETE
+// ============================================================
+
+/// Envuelve un backend genérico y esconde a los borrados de sus
+/// lecturas. Ids, versiones e índices siguen viéndolos: un id o un
+/// email de un borrado no se reutilizan, y restaurarlo es un
+/// `update_versioned` más.
+#[derive(Default)]
+pub struct SoftDeleteUserRepository<R> {
+    inner: R,
+}
+
+impl<R: Repository<User, UserId>> SoftDeleteUserRepository<R> {
+    pub fn wrap(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Repository<User, UserId>> Repository<User, UserId> for SoftDeleteUserRepository<R> {
+    fn save(&mut self, user: User) -> RepoResult<()> {
+        self.inner.save(user)
+    }
+
+    fn find_by_id(&self, id: UserId) -> RepoResult<Option<User>> {
+        Ok(self.inner.find_by_id(id)?.filter(|user| !user.is_deleted()))
+    }
+
+    /// Borrado físico (la purga).
+    fn delete(&mut self, id: UserId) -> RepoResult<Option<User>> {
+        self.inner.delete(id)
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = User> + '_>> {
+        Ok(Box::new(
+            self.inner.iter()?.filter(|user| !user.is_deleted()),
+        ))
     }
 
-    pub fn find_by_id(&self, id: u64) -> Option<&User> {
-        self.storage.get(&id)
+    // Sin filtrar: ven la fila guardada, esté borrada o no
+    fn insert(&mut self, user: User) -> RepoResult<()> {
+        self.inner.insert(user)
     }
 
-    pub fn find_by_email(&self, email: &str) -> Option<&User> {
-        self.storage.values().find(|u| u.email == email)
+    fn update(&mut self, user: User) -> RepoResult<()> {
+        self.inner.update(user)
     }
 
-    pub fn list_all(&self) -> Vec<&User> {
-        self.storage.values().collect()
+    fn update_versioned(&mut self, user: User) -> RepoResult<User> {
+        self.inner.update_versioned(user)
     }
 
-    pub fn delete(&mut self, id: u64) -> Option<User> {
-        self.storage.remove(&id)
+    fn exists(&self, id: UserId) -> RepoResult<bool> {
+        self.inner.exists(id)
     }
 
-    pub fn count(&self) -> usize {
-        self.storage.len()
+    fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<User>> {
+        self.inner.find_unique(index, key)
+    }
+
+    fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<User>> {
+        self.inner.find_indexed(index, key)
+    }
+}
+
+impl<R: Repository<User, UserId>> UserRepository for SoftDeleteUserRepository<R> {
+    fn find_by_id_including_deleted(&self, id: UserId) -> RepoResult<Option<User>> {
+        self.inner.find_by_id(id)
+    }
+
+    fn find_all_including_deleted(&self) -> RepoResult<Vec<User>> {
+        self.inner.find_all()
+    }
+}
+
+impl<R: Transactional> Transactional for SoftDeleteUserRepository<R> {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.inner.begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.inner.commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.inner.rollback()
+    }
+}
+
+// ============================================================
+// BACKENDS
+// ============================================================
+
+pub type InMemoryUserRepository = SoftDeleteUserRepository<InMemoryRepository<User>>;
+
+impl InMemoryUserRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Backend persistente: users en un archivo JSON (ver shared::json_file).
+pub type JsonFileUserRepository = SoftDeleteUserRepository<JsonFileRepository<User>>;
+
+impl JsonFileUserRepository {
+    pub fn open(path: impl Into<PathBuf>) -> RepoResult<Self> {
+        JsonFileRepository::open(path).map(Self::wrap)
     }
 }
 
@@ -47,35 +179,68 @@ mod tests {
 
     #[test]
     fn test_save_and_find() {
-        let mut repo = UserRepository::new();
-        let user = User::new(1, "Alice".to_string(), "alice@test.com".to_string());
+        let mut repo = InMemoryUserRepository::new();
+        let user = User::new(UserId(1), "Alice".to_string(), "alice@test.com".to_string());
 
         repo.save(user.clone()).unwrap();
-        let found = repo.find_by_id(1).unwrap();
+        let found = repo.find_by_id(UserId(1)).unwrap().unwrap();
 
-        assert_eq!(found, &user);
+        assert_eq!(found, user);
     }
 
     #[test]
     fn test_find_by_email() {
-        let mut repo = UserRepository::new();
-        let user = User::new(1, "Bob".to_string(), "bob@test.com".to_string());
+        let mut repo = InMemoryUserRepository::new();
+        let user = User::new(UserId(1), "Bob".to_string(), "bob@test.com".to_string());
 
         repo.save(user.clone()).unwrap();
-        let found = repo.find_by_email("bob@test.com").unwrap();
+        let found = repo.find_by_email("bob@test.com").unwrap().unwrap();
 
         assert_eq!(found.name, "Bob");
     }
 
     #[test]
     fn test_delete() {
-        let mut repo = UserRepository::new();
-        let user = User::new(1, "Charlie".to_string(), "charlie@test.com".to_string());
+        let mut repo = InMemoryUserRepository::new();
+        let user = User::new(
+            UserId(1),
+            "Charlie".to_string(),
+            "charlie@test.com".to_string(),
+        );
 
         repo.save(user).unwrap();
-        let deleted = repo.delete(1).unwrap();
+        let deleted = repo.delete(UserId(1)).unwrap().unwrap();
 
         assert_eq!(deleted.name, "Charlie");
-        assert!(repo.find_by_id(1).is_none());
+        assert!(repo.find_by_id(UserId(1)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_soft_deleted_are_indexed_and_hidden_by_default() {
+        let mut repo = InMemoryUserRepository::new();
+        let mut user = User::new(UserId(1), "Dana".to_string(), "dana@test.com".to_string());
+        let other = User::new(UserId(2), "Eve".to_string(), "eve@test.com".to_string());
+        repo.save(user.clone()).unwrap();
+        repo.save(other.clone()).unwrap();
+        assert!(repo.find_deleted().unwrap().is_empty());
+
+        user.deleted_at = Some(chrono::Utc::now());
+        repo.save(user.clone()).unwrap();
+
+        assert_eq!(repo.find_deleted().unwrap(), vec![user.clone()]);
+        assert_eq!(repo.find_by_id(UserId(1)).unwrap(), None);
+        assert_eq!(repo.find_all().unwrap(), vec![other.clone()]);
+        assert_eq!(repo.count().unwrap(), 1);
+
+        assert_eq!(
+            repo.find_by_id_including_deleted(UserId(1)).unwrap(),
+            Some(user.clone())
+        );
+        let mut all = repo.find_all_including_deleted().unwrap();
+        all.sort_by_key(|u| u.id);
+        assert_eq!(all, vec![user.clone(), other]);
+        // El id y el email siguen tomados
+        assert!(repo.exists(UserId(1)).unwrap());
+        assert_eq!(repo.find_by_email("dana@test.com").unwrap(), Some(user));
     }
 }
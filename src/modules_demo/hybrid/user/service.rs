@@ -1,87 +1,403 @@
 // Service: Lógica de negocio
 // Orquesta modelo y repositorio
 
+use super::error::UserError;
+use super::events::{UserEvent, UserEventBus, UserOutbox};
 use super::model::User;
-use super::repository::UserRepository;
+use super::repository::{EMAIL_UNIQUE, InMemoryUserRepository, UserRepository};
+use crate::modules_demo::shared::{
+    AuditLog, Auditor, Clock, EventSink, IdGenerator, Page, Query, RepoResult, RepositoryError,
+    SequentialIdGenerator, SystemClock, Transactional, UserId, Validator,
+};
+use chrono::{DateTime, Duration, Utc};
 
-pub struct UserService {
-    repo: UserRepository,
-    next_id: u64,
+/// Cuánto se conserva un usuario borrado antes de que la purga lo
+/// elimine del todo.
+pub const DEFAULT_RETENTION: Duration = Duration::days(30);
+
+pub struct UserService<R = InMemoryUserRepository> {
+    repo: R,
+    ids: Box<dyn IdGenerator>,
+    events: EventSink<UserEvent>,
+    audit: Auditor,
+    clock: Box<dyn Clock>,
+    retention: Duration,
 }
 
 impl UserService {
     pub fn new() -> Self {
-        Self {
-            repo: UserRepository::new(),
-            next_id: 1,
-        }
+        Self::with_repository(InMemoryUserRepository::new())
+            .expect("in-memory repository is always readable")
     }
+}
 
-    pub fn create_user(&mut self, name: String, email: String) -> Result<User, String> {
-        // Validar usando métodos del modelo
-        if !User::is_valid_name(&name) {
-            return Err("Invalid name".to_string());
-        }
+impl Default for UserService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        if !User::is_valid_email(&email) {
-            return Err("Invalid email format".to_string());
-        }
+impl<R: UserRepository> UserService<R> {
+    /// Inyecta cualquier backend que implemente UserRepository.
+    pub fn with_repository(repo: R) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
 
-        // Verificar email único (lógica de negocio)
-        if self.repo.find_by_email(&email).is_some() {
-            return Err("Email already exists".to_string());
-        }
+        Ok(Self {
+            repo,
+            ids: Box::new(ids),
+            events: EventSink::default(),
+            audit: Auditor::default(),
+            clock: Box::new(SystemClock),
+            retention: DEFAULT_RETENTION,
+        })
+    }
+
+    /// Reemplaza la estrategia de ids (random, snowflake, ...).
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
 
-        let user = User::new(self.next_id, name, email);
-        self.next_id += 1;
+    /// Reloj de `deleted_at` y de la purga (p.ej. `ManualClock` en tests).
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
 
-        self.repo.save(user.clone())?;
+    /// Cuánto tiempo un borrado sigue siendo restaurable.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Publica en un bus compartido (otro módulo ya tiene un clon).
+    pub fn with_event_bus(mut self, events: UserEventBus) -> Self {
+        self.events = self.events.with_bus(events);
+        self
+    }
+
+    /// Encola los eventos en `outbox` en lugar de publicarlos: dentro de
+    /// un `UnitOfWork` se confirman (o deshacen) junto con el cambio. Un
+    /// `OutboxRelay` los publica después.
+    pub fn with_outbox(mut self, outbox: UserOutbox) -> Self {
+        self.events = self.events.with_outbox(outbox);
+        self
+    }
+
+    pub fn events(&self) -> &UserEventBus {
+        self.events.bus()
+    }
+
+    /// Registra cada alta, cambio y baja en `log` (ver `set_actor`). El
+    /// cambio se guarda antes que su entrada: para que se confirmen o
+    /// deshagan juntos, correr la escritura en un `UnitOfWork`.
+    pub fn with_audit(mut self, log: AuditLog) -> Self {
+        self.audit = self.audit.with_log(log);
+        self
+    }
+
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.log()
+    }
+
+    /// A nombre de quién quedan los próximos cambios (por defecto "system").
+    pub fn set_actor(&mut self, actor: impl Into<String>) {
+        self.audit.set_actor(actor);
+    }
+
+    pub fn create_user(&mut self, name: String, email: String) -> Result<User, UserError> {
+        let (name, email) = validate_new_user(name, email)?;
+        let user = register(&mut self.repo, self.ids.as_ref(), name, email)?;
+
+        self.audit.created(User::AUDIT_TYPE, user.id, &user)?;
+        self.events.emit(registered(&user))?;
         Ok(user)
     }
 
-    pub fn get_user(&self, id: u64) -> Option<&User> {
-        self.repo.find_by_id(id)
+    /// Los borrados no aparecen (ver `get_user_including_deleted`).
+    pub fn get_user(&self, id: UserId) -> Result<Option<User>, UserError> {
+        Ok(self.repo.find_by_id(id)?)
     }
 
-    pub fn update_email(&mut self, user_id: u64, new_email: String) -> Result<(), String> {
-        if !User::is_valid_email(&new_email) {
-            return Err("Invalid email format".to_string());
-        }
+    pub fn get_user_including_deleted(&self, id: UserId) -> Result<Option<User>, UserError> {
+        Ok(self.repo.find_by_id_including_deleted(id)?)
+    }
 
-        // Verificar que no existe otro usuario con ese email
-        if let Some(existing) = self.repo.find_by_email(&new_email) {
-            if existing.id != user_id {
-                return Err("Email already in use".to_string());
-            }
-        }
+    pub fn update_email(&mut self, user_id: UserId, new_email: String) -> Result<(), UserError> {
+        let new_email = validate_email(new_email)?;
+        let (before, after, event) = change_email(&mut self.repo, user_id, None, new_email)?;
 
-        let user = self
-            .repo
-            .find_by_id(user_id)
-            .ok_or("User not found")?
-            .clone();
+        self.audit
+            .updated(User::AUDIT_TYPE, user_id, &before, &after)?;
+        self.events.emit(event)?;
+        Ok(())
+    }
+
+    /// Solo si el usuario sigue en `expected_version` (la que vio quien
+    /// edita). Devuelve el usuario guardado, con su versión nueva.
+    pub fn update_email_versioned(
+        &mut self,
+        user_id: UserId,
+        expected_version: u64,
+        new_email: String,
+    ) -> Result<User, UserError> {
+        let new_email = validate_email(new_email)?;
+        let (before, after, event) =
+            change_email(&mut self.repo, user_id, Some(expected_version), new_email)?;
 
-        let updated = User::new(user.id, user.name, new_email);
-        self.repo.save(updated)
+        self.audit
+            .updated(User::AUDIT_TYPE, user_id, &before, &after)?;
+        self.events.emit(event)?;
+        Ok(after)
     }
 
-    pub fn delete_user(&mut self, id: u64) -> Result<(), String> {
-        self.repo.delete(id).ok_or("User not found".to_string())?;
+    /// Soft delete: deja de verse pero se puede restaurar hasta que venza
+    /// la retención.
+    pub fn delete_user(&mut self, id: UserId) -> Result<(), UserError> {
+        let (before, after) = soft_delete(&mut self.repo, id, self.clock.now())?;
+
+        self.audit.updated(User::AUDIT_TYPE, id, &before, &after)?;
+        self.events.emit(UserEvent::Deleted { user_id: id })?;
         Ok(())
     }
 
-    pub fn list_all_users(&self) -> Vec<&User> {
-        self.repo.list_all()
+    pub fn restore_user(&mut self, id: UserId) -> Result<User, UserError> {
+        let (before, after) = restore(&mut self.repo, id)?;
+
+        self.audit.updated(User::AUDIT_TYPE, id, &before, &after)?;
+        self.events.emit(UserEvent::Restored { user_id: id })?;
+        Ok(after)
+    }
+
+    /// Job de retención: borra del todo a los que llevan más de
+    /// `retention` borrados. Devuelve sus ids.
+    pub fn purge_expired(&mut self) -> Result<Vec<UserId>, UserError> {
+        let purged = purge(&mut self.repo, cutoff(self.clock.as_ref(), self.retention))?;
+
+        for user in &purged {
+            self.audit.deleted(User::AUDIT_TYPE, user.id, user)?;
+            self.events.emit(UserEvent::Purged { user_id: user.id })?;
+        }
+        Ok(purged.into_iter().map(|user| user.id).collect())
+    }
+
+    /// Activos, ordenados por id (para paginar, `query_users`).
+    pub fn list_all_users(&self) -> Result<Vec<User>, UserError> {
+        self.query_users(&Query::new()).map(|page| page.items)
+    }
+
+    /// Sobre los activos: los borrados no cuentan ni para filtrar ni para
+    /// paginar.
+    pub fn query_users(&self, query: &Query<User>) -> Result<Page<User>, UserError> {
+        Ok(self.repo.query(query)?)
+    }
+
+    /// Incluye los borrados (soporte: buscar qué restaurar).
+    pub fn query_users_including_deleted(
+        &self,
+        query: &Query<User>,
+    ) -> Result<Page<User>, UserError> {
+        Ok(query.run(self.repo.find_all_including_deleted()?))
+    }
+
+    /// Activos.
+    pub fn user_count(&self) -> Result<usize, UserError> {
+        Ok(self.repo.count()?)
+    }
+}
+
+// El usuario, su auditoría y sus eventos (con outbox) se confirman (o
+// deshacen) juntos. Outbox y auditoría primero: escriben recién en el
+// commit, y si fallan el usuario todavía se puede deshacer
+impl<R: UserRepository + Transactional> Transactional for UserService<R> {
+    fn begin(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.audit, &mut self.repo).begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.audit, &mut self.repo).commit()
     }
 
-    pub fn user_count(&self) -> usize {
-        self.repo.count()
+    fn rollback(&mut self) -> RepoResult<()> {
+        (&mut self.events, &mut self.audit, &mut self.repo).rollback()
     }
 }
 
+// ============================================================
+// REGLAS (compartidas con SharedUserService)
+// ============================================================
+
+// Validaciones que no tocan el repositorio: el servicio compartido las
+// corre antes de tomar ningún lock.
+
+pub(super) fn validate_new_user(
+    name: String,
+    email: String,
+) -> Result<(String, String), UserError> {
+    let mut v = Validator::new();
+    v.field("name", name.as_str(), &User::name_rules()).field(
+        "email",
+        email.as_str(),
+        &User::email_rules(),
+    );
+    v.finish()?;
+    Ok((name, email))
+}
+
+pub(super) fn validate_email(email: String) -> Result<String, UserError> {
+    Validator::check("email", email.as_str(), &User::email_rules())?;
+    Ok(email)
+}
+
+// Consultar el email y guardar deben ocurrir sin que nadie escriba en el
+// medio: quien llama garantiza acceso exclusivo al repositorio (&mut).
+
+pub(super) fn register<R: UserRepository>(
+    repo: &mut R,
+    ids: &dyn IdGenerator,
+    name: String,
+    email: String,
+) -> Result<User, UserError> {
+    let user = User::new(UserId(ids.next_id()?), name, email);
+
+    // insert: si el generador repite un id, falla en vez de pisar. El
+    // email repetido lo detecta el índice único del repositorio
+    match repo.insert(user.clone()) {
+        Ok(()) => Ok(user),
+        Err(RepositoryError::UniqueViolation(index)) if index == EMAIL_UNIQUE => {
+            Err(UserError::EmailAlreadyExists { email: user.email })
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub(super) fn registered(user: &User) -> UserEvent {
+    UserEvent::Registered {
+        user_id: user.id,
+        email: user.email.clone(),
+    }
+}
+
+/// `expected_version`: la que vio quien edita (`None`: la actual).
+/// Devuelve el usuario antes y después (para auditar) y el evento a
+/// publicar; lo publica quien llama, ya fuera de cualquier lock.
+pub(super) fn change_email<R: UserRepository>(
+    repo: &mut R,
+    user_id: UserId,
+    expected_version: Option<u64>,
+    new_email: String,
+) -> Result<(User, User, UserEvent), UserError> {
+    // Primero el usuario: a quien no existe (o está borrado) no se le
+    // dice de quién es un email
+    let user = repo
+        .find_by_id(user_id)?
+        .ok_or(UserError::NotFound { id: user_id })?;
+
+    // Verificar que no existe otro usuario con ese email
+    if let Some(existing) = repo.find_by_email(&new_email)?
+        && existing.id != user_id
+    {
+        return Err(UserError::EmailInUse {
+            email: new_email,
+            owner_id: existing.id,
+        });
+    }
+
+    let edited = User {
+        email: new_email.clone(),
+        version: expected_version.unwrap_or(user.version),
+        ..user.clone()
+    };
+    let saved = repo.update_versioned(edited).map_err(|e| match e {
+        RepositoryError::VersionConflict {
+            expected, actual, ..
+        } => UserError::VersionConflict {
+            id: user_id,
+            expected,
+            actual,
+        },
+        other => other.into(),
+    })?;
+
+    let event = UserEvent::EmailChanged {
+        user_id,
+        old_email: user.email.clone(),
+        new_email,
+    };
+    Ok((user, saved, event))
+}
+
+/// Antes y después de marcarlo borrado en `at`.
+pub(super) fn soft_delete<R: UserRepository>(
+    repo: &mut R,
+    user_id: UserId,
+    at: DateTime<Utc>,
+) -> Result<(User, User), UserError> {
+    let user = repo
+        .find_by_id(user_id)?
+        .ok_or(UserError::NotFound { id: user_id })?;
+
+    let deleted = repo.update_versioned(User {
+        deleted_at: Some(at),
+        ..user.clone()
+    })?;
+    Ok((user, deleted))
+}
+
+pub(super) fn restore<R: UserRepository>(
+    repo: &mut R,
+    user_id: UserId,
+) -> Result<(User, User), UserError> {
+    let user = repo
+        .find_by_id_including_deleted(user_id)?
+        .ok_or(UserError::NotFound { id: user_id })?;
+    if !user.is_deleted() {
+        return Err(UserError::NotDeleted { id: user_id });
+    }
+
+    let restored = repo.update_versioned(User {
+        deleted_at: None,
+        ..user.clone()
+    })?;
+    Ok((user, restored))
+}
+
+/// Borra del todo a los borrados en `cutoff` o antes. Devuelve lo que
+/// borró, por id.
+pub(super) fn purge<R: UserRepository>(
+    repo: &mut R,
+    cutoff: DateTime<Utc>,
+) -> Result<Vec<User>, UserError> {
+    let mut expired: Vec<_> = repo
+        .find_deleted()?
+        .into_iter()
+        .filter(|user| user.deleted_at.is_some_and(|at| at <= cutoff))
+        .collect();
+    expired.sort_by_key(|user| user.id);
+
+    for user in &expired {
+        repo.delete(user.id)?;
+    }
+    Ok(expired)
+}
+
+/// Hasta cuándo un borrado ya venció.
+pub(super) fn cutoff(clock: &dyn Clock, retention: Duration) -> DateTime<Utc> {
+    clock
+        .now()
+        .checked_sub_signed(retention)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::modules_demo::shared::testing::FaultyRepository;
+    use crate::modules_demo::shared::{
+        AuditAction, AuditEntry, ManualClock, SortOrder, UnitOfWork,
+    };
+    use serde_json::Value;
+    use std::sync::Arc;
 
     #[test]
     fn test_create_user_success() {
@@ -90,7 +406,7 @@ mod tests {
             .create_user("Alice".to_string(), "alice@example.com".to_string())
             .unwrap();
 
-        assert_eq!(user.id, 1);
+        assert_eq!(user.id, UserId(1));
         assert_eq!(user.name, "Alice");
     }
 
@@ -102,7 +418,24 @@ mod tests {
             .unwrap();
 
         let result = service.create_user("Bob".to_string(), "alice@test.com".to_string());
-        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            UserError::EmailAlreadyExists {
+                email: "alice@test.com".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_create_user_reports_every_invalid_field() {
+        let mut service = UserService::new();
+
+        let result = service.create_user(" ".to_string(), "alice@test".to_string());
+        let Err(UserError::Invalid(errors)) = result else {
+            panic!("expected validation errors, got {result:?}");
+        };
+        assert_eq!(errors.paths(), ["name", "email"]);
+        assert_eq!(service.user_count().unwrap(), 0);
     }
 
     #[test]
@@ -116,10 +449,90 @@ mod tests {
             .update_email(user.id, "charlie@new.com".to_string())
             .unwrap();
 
-        let updated = service.get_user(user.id).unwrap();
+        let updated = service.get_user(user.id).unwrap().unwrap();
         assert_eq!(updated.email, "charlie@new.com");
     }
 
+    #[test]
+    fn test_update_email_versioned_rejects_stale_version() {
+        let mut service = UserService::new();
+        let user = service
+            .create_user("Erin".to_string(), "erin@test.com".to_string())
+            .unwrap();
+
+        let saved = service
+            .update_email_versioned(user.id, user.version, "erin@new.com".to_string())
+            .unwrap();
+        assert_eq!(saved.version, user.version + 1);
+
+        // Segunda edición sobre la versión que ya no existe
+        let stale =
+            service.update_email_versioned(user.id, user.version, "erin@old.com".to_string());
+        assert_eq!(
+            stale,
+            Err(UserError::VersionConflict {
+                id: user.id,
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(service.get_user(user.id).unwrap(), Some(saved));
+    }
+
+    #[test]
+    fn test_audit_answers_who_changed_the_email_and_when() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let log = AuditLog::in_memory().with_clock(Arc::clone(&clock));
+        let mut service = UserService::new()
+            .with_audit(log.clone())
+            .with_clock(Arc::clone(&clock));
+
+        let user = service
+            .create_user("Grace".to_string(), "grace@old.com".to_string())
+            .unwrap();
+        let registered_at = log.for_entity(User::AUDIT_TYPE, user.id).unwrap()[0].at;
+
+        clock.advance(60_000);
+        service.set_actor("support:kim");
+        service
+            .update_email(user.id, "grace@new.com".to_string())
+            .unwrap();
+
+        let history = log.for_entity(User::AUDIT_TYPE, user.id).unwrap();
+        let change = &history[1];
+        assert_eq!(
+            (change.action, change.actor.as_str()),
+            (AuditAction::Updated, "support:kim")
+        );
+        assert_eq!(change.at, registered_at + chrono::Duration::minutes(1));
+        assert_eq!(
+            change.change("email").unwrap().before,
+            Some("grace@old.com".into())
+        );
+        assert_eq!(log.by_actor("support:kim").unwrap(), vec![change.clone()]);
+
+        // El valor pisado sigue recuperable
+        let then: User = log
+            .as_of(User::AUDIT_TYPE, user.id, registered_at)
+            .unwrap()
+            .unwrap();
+        assert_eq!(then, user);
+
+        // Soft delete: un cambio de deleted_at; la purga es el Deleted
+        service.delete_user(user.id).unwrap();
+        clock.advance(DEFAULT_RETENTION.num_milliseconds() as u64);
+        service.purge_expired().unwrap();
+
+        let history = log.for_entity(User::AUDIT_TYPE, user.id).unwrap();
+        assert_eq!(history[2].action, AuditAction::Updated);
+        assert_eq!(
+            history[2].change("deleted_at").unwrap().before,
+            Some(Value::Null)
+        );
+        assert_eq!(history[3].action, AuditAction::Deleted);
+        assert_eq!(history[3].change("email").unwrap().after, None);
+    }
+
     #[test]
     fn test_delete_user() {
         let mut service = UserService::new();
@@ -128,6 +541,261 @@ mod tests {
             .unwrap();
 
         service.delete_user(user.id).unwrap();
-        assert!(service.get_user(user.id).is_none());
+        assert!(service.get_user(user.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_failed_audit_rolls_back_the_write_in_a_unit_of_work() {
+        let (store, faults) = FaultyRepository::<AuditEntry>::new();
+        let log = AuditLog::with_store(store).unwrap();
+        let mut service = UserService::new().with_audit(log.clone());
+        let user = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+
+        faults.fail_writes(true);
+        let created = UnitOfWork::new(&mut service)
+            .run(|service| service.create_user("Bob".to_string(), "bob@test.com".to_string()));
+        let updated = UnitOfWork::new(&mut service)
+            .run(|service| service.update_email(user.id, "new@test.com".to_string()));
+
+        assert!(matches!(created, Err(UserError::Repository(_))));
+        assert!(matches!(updated, Err(UserError::Repository(_))));
+        assert_eq!(log.for_entity(User::AUDIT_TYPE, user.id).unwrap().len(), 1);
+        assert_eq!(service.list_all_users().unwrap(), vec![user]);
+    }
+
+    #[test]
+    fn test_deleted_user_is_hidden_until_restored() {
+        let mut service = UserService::new();
+        let kept = service
+            .create_user("Kept".to_string(), "kept@test.com".to_string())
+            .unwrap();
+        let user = service
+            .create_user("Oops".to_string(), "oops@test.com".to_string())
+            .unwrap();
+
+        service.delete_user(user.id).unwrap();
+        assert_eq!(service.list_all_users().unwrap(), vec![kept.clone()]);
+        assert_eq!(service.user_count().unwrap(), 1);
+        assert_eq!(
+            service.update_email(user.id, "new@test.com".to_string()),
+            Err(UserError::NotFound { id: user.id })
+        );
+        // Ni siquiera con un email tomado: no revela de quién es
+        assert_eq!(
+            service.update_email(user.id, "kept@test.com".to_string()),
+            Err(UserError::NotFound { id: user.id })
+        );
+        assert_eq!(
+            service.delete_user(user.id),
+            Err(UserError::NotFound { id: user.id })
+        );
+
+        let all = service
+            .query_users_including_deleted(&Query::new())
+            .unwrap();
+        assert_eq!(all.items.len(), 2);
+        assert!(
+            service
+                .get_user_including_deleted(user.id)
+                .unwrap()
+                .unwrap()
+                .is_deleted()
+        );
+        // El email queda reservado mientras se pueda restaurar
+        assert!(matches!(
+            service.create_user("Other".to_string(), "oops@test.com".to_string()),
+            Err(UserError::EmailAlreadyExists { .. })
+        ));
+
+        let restored = service.restore_user(user.id).unwrap();
+        assert_eq!(restored.deleted_at, None);
+        assert_eq!(service.get_user(user.id).unwrap(), Some(restored));
+        assert_eq!(
+            service.restore_user(user.id),
+            Err(UserError::NotDeleted { id: user.id })
+        );
+    }
+
+    #[test]
+    fn test_purge_removes_only_expired_deletions() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let mut service = UserService::new()
+            .with_clock(Arc::clone(&clock))
+            .with_retention(Duration::days(7));
+        let old = service
+            .create_user("Old".to_string(), "old@test.com".to_string())
+            .unwrap();
+        let recent = service
+            .create_user("Recent".to_string(), "recent@test.com".to_string())
+            .unwrap();
+
+        service.delete_user(old.id).unwrap();
+        clock.advance(Duration::days(5).num_milliseconds() as u64);
+        service.delete_user(recent.id).unwrap();
+        clock.advance(Duration::days(2).num_milliseconds() as u64);
+
+        assert_eq!(service.purge_expired().unwrap(), vec![old.id]);
+        assert_eq!(service.get_user_including_deleted(old.id).unwrap(), None);
+        assert!(service.restore_user(recent.id).is_ok());
+        assert!(service.purge_expired().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_missing_user() {
+        let mut service = UserService::new();
+
+        assert_eq!(
+            service.delete_user(UserId(99)),
+            Err(UserError::NotFound { id: UserId(99) })
+        );
+    }
+
+    #[test]
+    fn test_ids_are_not_reused_after_delete() {
+        let mut service = UserService::new();
+        service
+            .create_user("A".to_string(), "a@test.com".to_string())
+            .unwrap();
+        let b = service
+            .create_user("B".to_string(), "b@test.com".to_string())
+            .unwrap();
+
+        service.delete_user(b.id).unwrap();
+        let c = service
+            .create_user("C".to_string(), "c@test.com".to_string())
+            .unwrap();
+
+        assert_eq!(c.id, UserId(3));
+    }
+
+    #[test]
+    fn test_colliding_generator_is_rejected() {
+        use crate::modules_demo::shared::RepositoryError;
+
+        // Generador roto que siempre devuelve el mismo id
+        struct Constant;
+        impl IdGenerator for Constant {
+            fn next_id(&self) -> RepoResult<u64> {
+                Ok(7)
+            }
+        }
+
+        let mut service = UserService::new().with_id_generator(Constant);
+        service
+            .create_user("A".to_string(), "a@test.com".to_string())
+            .unwrap();
+
+        let err = service
+            .create_user("B".to_string(), "b@test.com".to_string())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            UserError::Repository(RepositoryError::AlreadyExists(_))
+        ));
+    }
+
+    // Mismo escenario contra cualquier backend: el service no cambia
+    fn exercise<R: UserRepository>(mut service: UserService<R>) {
+        let alice = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        let bob = service
+            .create_user("Bob".to_string(), "bob@test.com".to_string())
+            .unwrap();
+
+        assert_eq!(
+            service.update_email(bob.id, "alice@test.com".to_string()),
+            Err(UserError::EmailInUse {
+                email: "alice@test.com".to_string(),
+                owner_id: alice.id
+            })
+        );
+
+        let newest_first = Query::new()
+            .sort_by(|u: &User| u.id, SortOrder::Desc)
+            .limit(1);
+        let page = service.query_users(&newest_first).unwrap();
+        assert_eq!(page.items, vec![bob.clone()]);
+        assert_eq!(page.total, 2);
+        assert_eq!(service.list_all_users().unwrap(), vec![alice.clone(), bob]);
+
+        service.delete_user(alice.id).unwrap();
+        assert_eq!(service.user_count().unwrap(), 1);
+        assert_eq!(service.list_all_users().unwrap()[0].email, "bob@test.com");
+    }
+
+    #[test]
+    fn test_in_memory_backend() {
+        exercise(UserService::new());
+    }
+
+    #[test]
+    fn test_json_file_backend() {
+        use super::super::repository::JsonFileUserRepository;
+        use crate::modules_demo::shared::json_file::tests::TempPath;
+
+        let path = TempPath::new("hybrid_users");
+        let repo = JsonFileUserRepository::open(&path.0).unwrap();
+        exercise(UserService::with_repository(repo).unwrap());
+
+        // Al reabrir, los datos siguen ahí y los ids no se reutilizan
+        let repo = JsonFileUserRepository::open(&path.0).unwrap();
+        let mut service = UserService::with_repository(repo).unwrap();
+        assert_eq!(service.user_count().unwrap(), 1);
+
+        let carol = service
+            .create_user("Carol".to_string(), "carol@test.com".to_string())
+            .unwrap();
+        assert_eq!(carol.id, UserId(3));
+    }
+
+    #[test]
+    fn test_outbox_commits_with_the_json_file_write_in_a_unit_of_work() {
+        use super::super::events::UserOutbox;
+        use super::super::repository::JsonFileUserRepository;
+        use crate::modules_demo::shared::json_file::tests::TempPath;
+
+        let (users, messages) = (
+            TempPath::new("outbox_users"),
+            TempPath::new("outbox_events"),
+        );
+        let outbox = UserOutbox::open(&messages.0).unwrap();
+        let repo = JsonFileUserRepository::open(&users.0).unwrap();
+        let mut service = UserService::with_repository(repo)
+            .unwrap()
+            .with_outbox(outbox.clone());
+        let published = Arc::new(std::sync::Mutex::new(0));
+        let seen = Arc::clone(&published);
+        service
+            .events()
+            .subscribe(move |_| *seen.lock().unwrap() += 1);
+
+        let alice = UnitOfWork::new(&mut service)
+            .run(|service| service.create_user("Alice".to_string(), "alice@test.com".to_string()))
+            .unwrap();
+        let failed: Result<(), UserError> = UnitOfWork::new(&mut service).run(|service| {
+            service.create_user("Bob".to_string(), "bob@test.com".to_string())?;
+            Err(UserError::NotFound { id: UserId(99) })
+        });
+        assert!(failed.is_err());
+
+        // Encolado, no publicado: eso lo hace el relay
+        assert_eq!(*published.lock().unwrap(), 0);
+
+        // Al reabrir los dos archivos: el alta confirmada y su evento, y
+        // nada de la deshecha
+        let repo = JsonFileUserRepository::open(&users.0).unwrap();
+        let reopened = UserService::with_repository(repo).unwrap();
+        assert_eq!(reopened.list_all_users().unwrap(), vec![alice.clone()]);
+        let pending: Vec<_> = UserOutbox::open(&messages.0)
+            .unwrap()
+            .pending()
+            .unwrap()
+            .into_iter()
+            .map(|message| message.event)
+            .collect();
+        assert_eq!(pending, vec![registered(&alice)]);
     }
 }
@@ -0,0 +1,721 @@
+// SharedUserService: UserService compartible entre threads (Send + Sync + Clone)
+// Mismas reglas que service.rs; el repositorio vive detrás de un RwLock
+
+use super::error::UserError;
+use super::events::{UserEvent, UserEventBus};
+use super::model::User;
+use super::repository::{InMemoryUserRepository, UserRepository};
+use super::service::{
+    DEFAULT_RETENTION, change_email, cutoff, purge, register, registered, restore, soft_delete,
+    validate_email, validate_new_user,
+};
+use crate::modules_demo::shared::{
+    AuditLog, Auditor, Clock, IdGenerator, Page, Query, RepoResult, RepositoryError,
+    SequentialIdGenerator, SystemClock, Transactional, UnitOfWork, UserId,
+};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::task::JoinHandle;
+
+/// Handle barato de clonar: todos los clones ven los mismos usuarios.
+///
+/// - Lecturas (`get_user`, `list_all_users`, ...) toman el lock de lectura
+///   y no se bloquean entre sí.
+/// - Escrituras toman el lock de escritura para consultar, guardar y
+///   auditar (en una unidad de trabajo); las validaciones corren antes,
+///   sin lock.
+/// - Los ids salen de un `IdGenerator` atómico, fuera del lock del repo.
+/// - Los eventos se publican con el write lock tomado: un suscriptor
+///   síncrono no puede llamar al servicio (se bloquearía); para eso está
+///   `subscribe_async`.
+pub struct SharedUserService<R = InMemoryUserRepository> {
+    repo: Arc<RwLock<R>>,
+    ids: Arc<dyn IdGenerator>,
+    events: UserEventBus,
+    audit: Auditor,
+    clock: Arc<dyn Clock>,
+    retention: chrono::Duration,
+}
+
+// Manual: derive(Clone) exigiría R: Clone, y solo se clonan los Arc
+impl<R> Clone for SharedUserService<R> {
+    fn clone(&self) -> Self {
+        Self {
+            repo: Arc::clone(&self.repo),
+            ids: Arc::clone(&self.ids),
+            events: self.events.clone(),
+            audit: self.audit.clone(),
+            clock: Arc::clone(&self.clock),
+            retention: self.retention,
+        }
+    }
+}
+
+impl SharedUserService {
+    pub fn new() -> Self {
+        Self::with_repository(InMemoryUserRepository::new())
+            .expect("in-memory repository is always readable")
+    }
+}
+
+impl Default for SharedUserService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: UserRepository + Transactional + Send + Sync> SharedUserService<R> {
+    pub fn with_repository(repo: R) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
+
+        Ok(Self {
+            repo: Arc::new(RwLock::new(repo)),
+            ids: Arc::new(ids),
+            events: UserEventBus::new(),
+            audit: Auditor::default(),
+            clock: Arc::new(SystemClock),
+            retention: DEFAULT_RETENTION,
+        })
+    }
+
+    /// Reemplaza la estrategia de ids. Llamar antes de clonar: los clones
+    /// ya creados conservan el generador anterior.
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Arc::new(ids);
+        self
+    }
+
+    /// Como `with_id_generator`: llamar antes de clonar.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Como `UserService::with_retention`; llamar antes de clonar.
+    pub fn with_retention(mut self, retention: chrono::Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Como `with_id_generator`: llamar antes de clonar.
+    pub fn with_event_bus(mut self, events: UserEventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn events(&self) -> &UserEventBus {
+        &self.events
+    }
+
+    /// Como `with_id_generator`: llamar antes de clonar.
+    pub fn with_audit(mut self, log: AuditLog) -> Self {
+        self.audit = self.audit.with_log(log);
+        self
+    }
+
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit.log()
+    }
+
+    /// Un clon que audita a nombre de `actor`: cada request usa el suyo
+    /// sin afectar a los demás threads.
+    pub fn as_actor(&self, actor: impl Into<String>) -> Self {
+        let mut service = self.clone();
+        service.audit.set_actor(actor);
+        service
+    }
+
+    pub fn create_user(&self, name: String, email: String) -> Result<User, UserError> {
+        let (name, email) = validate_new_user(name, email)?;
+
+        // insert bajo el write lock: el índice único del repositorio ve
+        // siempre el último email guardado, sin carreras entre threads
+        self.transact(|repo, audit| {
+            let user = register(repo, self.ids.as_ref(), name, email)?;
+            audit.created(User::AUDIT_TYPE, user.id, &user)?;
+            let event = registered(&user);
+            Ok((user, vec![event]))
+        })
+    }
+
+    pub fn get_user(&self, id: UserId) -> Result<Option<User>, UserError> {
+        Ok(self.read()?.find_by_id(id)?)
+    }
+
+    pub fn get_user_including_deleted(&self, id: UserId) -> Result<Option<User>, UserError> {
+        Ok(self.read()?.find_by_id_including_deleted(id)?)
+    }
+
+    pub fn update_email(&self, user_id: UserId, new_email: String) -> Result<(), UserError> {
+        let new_email = validate_email(new_email)?;
+
+        self.transact(|repo, audit| {
+            let (before, after, event) = change_email(repo, user_id, None, new_email)?;
+            audit.updated(User::AUDIT_TYPE, user_id, &before, &after)?;
+            Ok(((), vec![event]))
+        })
+    }
+
+    /// Como `UserService::update_email_versioned`. Lectura y escritura
+    /// bajo el mismo write lock: entre threads gana uno solo.
+    pub fn update_email_versioned(
+        &self,
+        user_id: UserId,
+        expected_version: u64,
+        new_email: String,
+    ) -> Result<User, UserError> {
+        let new_email = validate_email(new_email)?;
+
+        self.transact(|repo, audit| {
+            let (before, after, event) =
+                change_email(repo, user_id, Some(expected_version), new_email)?;
+            audit.updated(User::AUDIT_TYPE, user_id, &before, &after)?;
+            Ok((after, vec![event]))
+        })
+    }
+
+    /// Soft delete, como `UserService::delete_user`.
+    pub fn delete_user(&self, id: UserId) -> Result<(), UserError> {
+        self.transact(|repo, audit| {
+            let (before, after) = soft_delete(repo, id, self.clock.now())?;
+            audit.updated(User::AUDIT_TYPE, id, &before, &after)?;
+            Ok(((), vec![UserEvent::Deleted { user_id: id }]))
+        })
+    }
+
+    pub fn restore_user(&self, id: UserId) -> Result<User, UserError> {
+        self.transact(|repo, audit| {
+            let (before, after) = restore(repo, id)?;
+            audit.updated(User::AUDIT_TYPE, id, &before, &after)?;
+            Ok((after, vec![UserEvent::Restored { user_id: id }]))
+        })
+    }
+
+    /// Una pasada del job de retención (ver `spawn_purge_job`).
+    pub fn purge_expired(&self) -> Result<Vec<UserId>, UserError> {
+        let cutoff = cutoff(self.clock.as_ref(), self.retention);
+        self.transact(|repo, audit| {
+            let mut ids = Vec::new();
+            let mut events = Vec::new();
+            for user in purge(repo, cutoff)? {
+                audit.deleted(User::AUDIT_TYPE, user.id, &user)?;
+                ids.push(user.id);
+                events.push(UserEvent::Purged { user_id: user.id });
+            }
+            Ok((ids, events))
+        })
+    }
+
+    pub fn list_all_users(&self) -> Result<Vec<User>, UserError> {
+        self.query_users(&Query::new()).map(|page| page.items)
+    }
+
+    pub fn query_users(&self, query: &Query<User>) -> Result<Page<User>, UserError> {
+        Ok(self.read()?.query(query)?)
+    }
+
+    pub fn query_users_including_deleted(
+        &self,
+        query: &Query<User>,
+    ) -> Result<Page<User>, UserError> {
+        Ok(query.run(self.read()?.find_all_including_deleted()?))
+    }
+
+    pub fn user_count(&self) -> Result<usize, UserError> {
+        Ok(self.read()?.count()?)
+    }
+
+    // Un lock envenenado = un thread entró en pánico a mitad de una
+    // escritura: el estado es dudoso, se reporta en vez de seguir
+    fn read(&self) -> Result<RwLockReadGuard<'_, R>, UserError> {
+        self.repo.read().map_err(|_| poisoned())
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, R>, UserError> {
+        self.repo.write().map_err(|_| poisoned())
+    }
+
+    // Escritura y auditoría bajo el mismo write lock y en una unidad de
+    // trabajo: las entradas quedan en el orden de los cambios (as_of
+    // depende de eso) y si auditar falla el cambio se deshace. La
+    // auditoría primero, como en UserService. Los eventos se publican
+    // después del commit pero todavía con el lock: los suscriptores los
+    // ven en el mismo orden que los cambios
+    fn transact<T>(
+        &self,
+        work: impl FnOnce(&mut R, &Auditor) -> Result<(T, Vec<UserEvent>), UserError>,
+    ) -> Result<T, UserError> {
+        let mut repo = self.write()?;
+        // Clon propio: otros threads auditan con sus handles, fuera de
+        // esta transacción
+        let mut audit = self.audit.clone();
+        let (value, events) = UnitOfWork::new((&mut audit, &mut *repo))
+            .run(|(audit, repo)| work(&mut **repo, audit))?;
+
+        for event in events {
+            self.events.publish(event);
+        }
+        Ok(value)
+    }
+}
+
+impl<R: UserRepository + Transactional + Send + Sync + 'static> SharedUserService<R> {
+    /// Corre `purge_expired` cada `every` en una task de tokio, hasta que
+    /// se aborte el handle. Cada pasada va en `spawn_blocking`: el backend
+    /// puede hacer I/O síncrono bajo el write lock.
+    pub fn spawn_purge_job(&self, every: std::time::Duration) -> JoinHandle<()> {
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            loop {
+                ticker.tick().await;
+                let service = service.clone();
+                // Un error de storage se reintenta en la próxima pasada
+                let pass = tokio::task::spawn_blocking(move || service.purge_expired());
+                if pass.await.is_err() {
+                    return; // un suscriptor entró en pánico
+                }
+            }
+        })
+    }
+}
+
+fn poisoned() -> UserError {
+    UserError::Repository(RepositoryError::Storage(
+        "user repository lock poisoned".to_string(),
+    ))
+}
+
+/*
+UserService vs SharedUserService:
+
+  UserService<R>                      SharedUserService<R>
+  ──────────────                      ────────────────────
+  &mut self para escribir             &self siempre
+  un dueño                            clone() → otro handle, mismo estado
+  repo: R                             repo: Arc<RwLock<R>>
+  ids: Box<dyn IdGenerator>           ids: Arc<dyn IdGenerator>
+  events: UserEventBus                events: UserEventBus (compartido)
+
+  create_user(name, email)
+     │ validate_new_user   ← sin lock
+     ▼
+  ┌─ write lock ────────────────────┐
+  │ begin (audit, repo)             │
+  │ ids.next_id()                   │  atómico respecto de
+  │ repo.insert                     │  cualquier otra escritura
+  │  └ índice users.email: ¿libre?  │
+  │ audit.created                   │  entradas en el orden de
+  │ commit (o rollback si falló)    │  los cambios
+  │ events.publish(Registered)      │  eventos también en orden:
+  └─────────────────────────────────┘  un suscriptor síncrono no
+                                       puede llamar al servicio
+                                       (subscribe_async sí)
+
+  get_user / list_all_users / user_count → read lock (N lectores a la vez)
+
+SOFT DELETE Y RETENCIÓN:
+
+  delete_user   deleted_at = now        oculto de get/list/count
+  restore_user  deleted_at = None       vuelve a verse
+  purge job     deleted_at <= now - retention → repo.delete (definitivo)
+
+  el email de un borrado sigue reservado hasta la purga
+
+- Las reglas viven en service.rs (register, change_email, validate_*):
+  los dos servicios no pueden divergir
+- std::sync::RwLock, no tokio: ninguna operación espera I/O async
+  sosteniendo el lock (ver sync_send.rs para el patrón Arc<RwLock<T>>)
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::testing::FaultyRepository;
+    use crate::modules_demo::shared::{AuditEntry, ManualClock, RepoResult, Repository};
+    use std::collections::HashSet;
+    use std::sync::Barrier;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn assert_shareable<T: Send + Sync + Clone + 'static>() {}
+
+    #[test]
+    fn test_handle_is_send_sync_and_clones_share_state() {
+        assert_shareable::<SharedUserService>();
+
+        let service = SharedUserService::new();
+        let other = service.clone();
+        let alice = other
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+
+        assert_eq!(service.get_user(alice.id).unwrap(), Some(alice));
+    }
+
+    #[test]
+    fn test_as_actor_audits_each_handle_under_its_own_name() {
+        let log = AuditLog::in_memory();
+        let service = SharedUserService::new().with_audit(log.clone());
+
+        let user = service
+            .as_actor("signup")
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        let admin = service.as_actor("admin");
+        thread::spawn(move || admin.update_email(user.id, "alice@new.com".to_string()))
+            .join()
+            .unwrap()
+            .unwrap();
+        service.delete_user(user.id).unwrap();
+
+        let actors: Vec<_> = log
+            .for_entity(User::AUDIT_TYPE, user.id)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.actor)
+            .collect();
+        assert_eq!(actors, ["signup", "admin", "system"]);
+    }
+
+    #[test]
+    fn test_failed_audit_rolls_back_the_write() {
+        let (store, faults) = FaultyRepository::<AuditEntry>::new();
+        let log = AuditLog::with_store(store).unwrap();
+        let service = SharedUserService::new().with_audit(log.clone());
+        let user = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+
+        faults.fail_writes(true);
+        let created = service.create_user("Bob".to_string(), "bob@test.com".to_string());
+        let updated = service.update_email(user.id, "new@test.com".to_string());
+        let deleted = service.delete_user(user.id);
+
+        assert!(matches!(created, Err(UserError::Repository(_))));
+        assert!(matches!(updated, Err(UserError::Repository(_))));
+        assert!(matches!(deleted, Err(UserError::Repository(_))));
+        assert_eq!(log.for_entity(User::AUDIT_TYPE, user.id).unwrap().len(), 1);
+        assert_eq!(service.list_all_users().unwrap(), vec![user]);
+    }
+
+    #[test]
+    fn test_concurrent_updates_are_audited_in_the_order_they_were_saved() {
+        const WRITERS: usize = 8;
+        let log = AuditLog::in_memory();
+        let service = SharedUserService::new().with_audit(log.clone());
+        let user = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+
+        let barrier = Arc::new(Barrier::new(WRITERS));
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|i| {
+                let (service, barrier) = (service.clone(), Arc::clone(&barrier));
+                thread::spawn(move || {
+                    barrier.wait();
+                    service
+                        .update_email(user.id, format!("alice{i}@test.com"))
+                        .unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        // Cada entrada parte del estado que dejó la anterior: el replay
+        // termina en lo que quedó guardado
+        let entries = log.for_entity(User::AUDIT_TYPE, user.id).unwrap();
+        let versions: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| entry.change("version")?.after.clone())
+            .collect();
+        let expected: Vec<_> = (1..=WRITERS as u64 + 1)
+            .map(serde_json::Value::from)
+            .collect();
+        assert_eq!(versions, expected);
+        let replayed: User = log
+            .as_of(User::AUDIT_TYPE, user.id, chrono::Utc::now())
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(replayed), service.get_user(user.id).unwrap());
+    }
+
+    #[test]
+    fn test_concurrent_updates_publish_in_the_order_they_were_saved() {
+        const WRITERS: usize = 8;
+        let service = SharedUserService::new();
+        let user = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        service.events().subscribe(move |event| {
+            if let UserEvent::EmailChanged {
+                old_email,
+                new_email,
+                ..
+            } = event
+            {
+                sink.lock()
+                    .unwrap()
+                    .push((old_email.clone(), new_email.clone()));
+            }
+        });
+
+        let barrier = Arc::new(Barrier::new(WRITERS));
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|i| {
+                let (service, barrier) = (service.clone(), Arc::clone(&barrier));
+                thread::spawn(move || {
+                    barrier.wait();
+                    service
+                        .update_email(user.id, format!("alice{i}@test.com"))
+                        .unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        // Cada evento parte del email que dejó el anterior
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), WRITERS);
+        let mut current = "alice@test.com".to_string();
+        for (old_email, new_email) in seen.iter() {
+            assert_eq!(old_email, &current);
+            current = new_email.clone();
+        }
+        assert_eq!(service.get_user(user.id).unwrap().unwrap().email, current);
+    }
+
+    #[tokio::test]
+    async fn test_purge_job_hard_deletes_after_retention() {
+        let clock = Arc::new(ManualClock::new(1_700_000_000_000));
+        let service = SharedUserService::new()
+            .with_clock(Arc::clone(&clock))
+            .with_retention(chrono::Duration::hours(1));
+        let user = service
+            .create_user("Gone".to_string(), "gone@test.com".to_string())
+            .unwrap();
+        service.delete_user(user.id).unwrap();
+
+        let job = service.spawn_purge_job(Duration::from_millis(5));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(
+            service
+                .get_user_including_deleted(user.id)
+                .unwrap()
+                .is_some()
+        );
+
+        clock.advance(60 * 60 * 1_000);
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while service
+            .get_user_including_deleted(user.id)
+            .unwrap()
+            .is_some()
+        {
+            assert!(Instant::now() < deadline, "purge job never ran");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        job.abort();
+    }
+
+    #[test]
+    fn test_same_rules_as_user_service() {
+        let service = SharedUserService::new();
+        let alice = service
+            .create_user("Alice".to_string(), "alice@test.com".to_string())
+            .unwrap();
+        let bob = service
+            .create_user("Bob".to_string(), "bob@test.com".to_string())
+            .unwrap();
+
+        assert_eq!(
+            service.create_user("Eve".to_string(), "alice@test.com".to_string()),
+            Err(UserError::EmailAlreadyExists {
+                email: "alice@test.com".to_string()
+            })
+        );
+        assert_eq!(
+            service.update_email(bob.id, "alice@test.com".to_string()),
+            Err(UserError::EmailInUse {
+                email: "alice@test.com".to_string(),
+                owner_id: alice.id
+            })
+        );
+        assert!(matches!(
+            service.create_user("".to_string(), "x@test.com".to_string()),
+            Err(UserError::Invalid(e)) if e.paths() == ["name"]
+        ));
+
+        service.delete_user(alice.id).unwrap();
+        assert_eq!(
+            service.delete_user(alice.id),
+            Err(UserError::NotFound { id: alice.id })
+        );
+        assert_eq!(service.user_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_signups_same_email_only_one_wins() {
+        const THREADS: usize = 16;
+
+        let service = SharedUserService::new();
+        let start = Arc::new(Barrier::new(THREADS));
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|n| {
+                let (service, start) = (service.clone(), Arc::clone(&start));
+                thread::spawn(move || {
+                    start.wait(); // todos arrancan juntos
+                    service.create_user(format!("User {n}"), "same@test.com".to_string())
+                })
+            })
+            .collect();
+
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        assert!(results.iter().filter_map(|r| r.as_ref().err()).all(|e| {
+            *e == UserError::EmailAlreadyExists {
+                email: "same@test.com".to_string(),
+            }
+        }));
+        assert_eq!(service.user_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_stress_emails_stay_unique() {
+        const THREADS: usize = 8;
+        const OPS: usize = 300;
+        const EMAILS: usize = 40;
+
+        let service = SharedUserService::new();
+        let created = Arc::new(AtomicUsize::new(0));
+        let start = Arc::new(Barrier::new(THREADS));
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let (service, created, start) =
+                    (service.clone(), Arc::clone(&created), Arc::clone(&start));
+                thread::spawn(move || {
+                    start.wait();
+                    for op in 0..OPS {
+                        // Todos los threads compiten por el mismo pool de emails
+                        let email = format!("user{}@test.com", (t * 7 + op * 13) % EMAILS);
+                        match op % 3 {
+                            0 | 1 => {
+                                if service.create_user(format!("T{t}"), email).is_ok() {
+                                    created.fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                            _ => {
+                                // Mover un usuario existente a un email del pool
+                                let target = UserId((op % EMAILS) as u64 + 1);
+                                let _ = service.update_email(target, email);
+                            }
+                        }
+                        let _ = service.list_all_users().unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let users = service.list_all_users().unwrap();
+        let emails: HashSet<_> = users.iter().map(|u| u.email.as_str()).collect();
+        let ids: HashSet<_> = users.iter().map(|u| u.id).collect();
+
+        assert_eq!(emails.len(), users.len(), "email duplicado");
+        assert_eq!(ids.len(), users.len(), "id duplicado");
+        assert_eq!(users.len(), created.load(Ordering::Relaxed));
+        assert!(users.len() <= EMAILS);
+    }
+
+    /// Repositorio que registra cuántos lectores hay adentro a la vez.
+    #[derive(Default)]
+    struct OverlapProbe {
+        inner: InMemoryUserRepository,
+        inside: AtomicUsize,
+        max_inside: AtomicUsize,
+    }
+
+    impl Repository<User, UserId> for OverlapProbe {
+        fn save(&mut self, user: User) -> RepoResult<()> {
+            self.inner.save(user)
+        }
+
+        // Espera (con tope) a que entre un segundo lector: si las lecturas
+        // se serializaran, nunca habría dos adentro
+        fn find_by_id(&self, id: UserId) -> RepoResult<Option<User>> {
+            let now = self.inside.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_inside.fetch_max(now, Ordering::SeqCst);
+
+            let deadline = Instant::now() + Duration::from_secs(2);
+            while self.max_inside.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+                thread::yield_now();
+            }
+
+            self.inside.fetch_sub(1, Ordering::SeqCst);
+            self.inner.find_by_id(id)
+        }
+
+        fn delete(&mut self, id: UserId) -> RepoResult<Option<User>> {
+            self.inner.delete(id)
+        }
+
+        fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = User> + '_>> {
+            self.inner.iter()
+        }
+    }
+
+    impl UserRepository for OverlapProbe {
+        fn find_by_id_including_deleted(&self, id: UserId) -> RepoResult<Option<User>> {
+            self.inner.find_by_id_including_deleted(id)
+        }
+
+        fn find_all_including_deleted(&self) -> RepoResult<Vec<User>> {
+            self.inner.find_all_including_deleted()
+        }
+    }
+
+    impl Transactional for OverlapProbe {
+        fn begin(&mut self) -> RepoResult<()> {
+            self.inner.begin()
+        }
+
+        fn commit(&mut self) -> RepoResult<()> {
+            self.inner.commit()
+        }
+
+        fn rollback(&mut self) -> RepoResult<()> {
+            self.inner.rollback()
+        }
+    }
+
+    #[test]
+    fn test_reads_do_not_block_each_other() {
+        let service = SharedUserService::with_repository(OverlapProbe::default()).unwrap();
+
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let service = service.clone();
+                thread::spawn(move || service.get_user(UserId(1)).unwrap())
+            })
+            .collect();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let probe = service.read().unwrap();
+        assert_eq!(probe.max_inside.load(Ordering::SeqCst), 2);
+    }
+}
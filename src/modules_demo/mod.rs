@@ -2,8 +2,10 @@
 // Demuestra diferentes estrategias de organización
 
 pub mod domain;
+pub mod error;
 pub mod hybrid;
 pub mod monolithic;
+pub mod shared;
 
 /*
 RESUMEN DE ESTRATEGIAS:
@@ -24,9 +26,11 @@ RESUMEN DE ESTRATEGIAS:
 │                  DOMAIN (Por Feature/Vertical)                   │
 ├─────────────────────────────────────────────────────────────────┤
 │ domain/                                                          │
+│   ├── cart.rs    → Cart + CartRepo + CartService                │
 │   ├── user.rs    → User + UserRepo + UserService                │
 │   ├── order.rs   → Order + OrderRepo + OrderService             │
-│   └── payment.rs → Payment + PaymentRepo + PaymentService       │
+│   ├── payment.rs → Payment + PaymentRepo + PaymentService       │
+│   └── product.rs → Product + ProductRepo + ProductService       │
 │                                                                  │
 │ ✓ Alta cohesión, bajo acoplamiento                              │
 │ ✓ Ideal para microservicios/DDD                                 │
@@ -2,7 +2,13 @@
 // ✗ Anti-patrón para código de producción
 // ✓ OK para scripts pequeños, demos, prototipos
 
-use std::collections::HashMap;
+use super::shared::validation::{self, at_least, max_length, not_blank, not_empty, positive};
+use super::shared::{
+    Entity, ErrorCode, ErrorKind, IdGenerator, InMemoryRepository, Money, MoneyError, OrderId,
+    PaymentId, ProductId, Query, RepoResult, Repository, RepositoryError, SequentialIdGenerator,
+    UserId, Validate, ValidationErrors, Validator,
+};
+use thiserror::Error;
 
 // ============================================================
 // MODELS
@@ -10,31 +16,41 @@ use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct User {
-    pub id: u64,
+    pub id: UserId,
     pub name: String,
     pub email: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct Order {
-    pub id: u64,
-    pub user_id: u64,
-    pub total: f64,
+    pub id: OrderId,
+    pub user_id: UserId,
+    pub total: Money,
     pub items: Vec<OrderItem>,
 }
 
 #[derive(Debug, Clone)]
 pub struct OrderItem {
-    pub product_id: u64,
+    pub product_id: ProductId,
     pub quantity: u32,
-    pub price: f64,
+    pub price: Money,
+}
+
+impl Validate for OrderItem {
+    fn validate(&self, v: &mut Validator) {
+        v.field("quantity", &self.quantity, &[at_least(1)]).field(
+            "price",
+            &self.price,
+            &[positive()],
+        );
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Payment {
-    pub id: u64,
-    pub order_id: u64,
-    pub amount: f64,
+    pub id: PaymentId,
+    pub order_id: OrderId,
+    pub amount: Money,
     pub status: PaymentStatus,
 }
 
@@ -46,179 +62,279 @@ pub enum PaymentStatus {
 }
 
 // ============================================================
-// REPOSITORIES (Data Access)
+// ERRORS
 // ============================================================
 
-pub struct UserRepository {
-    storage: HashMap<u64, User>,
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum UserError {
+    #[error("Invalid user")]
+    Invalid(#[from] ValidationErrors),
+
+    #[error("User storage failed")]
+    Repository(#[from] RepositoryError),
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum OrderError {
+    #[error("Invalid order")]
+    Invalid(#[from] ValidationErrors),
+
+    #[error("Invalid order amount")]
+    Money(#[from] MoneyError),
+
+    #[error("Order storage failed")]
+    Repository(#[from] RepositoryError),
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PaymentError {
+    #[error("Invalid payment for order {order_id}")]
+    Invalid {
+        order_id: OrderId,
+        #[source]
+        errors: ValidationErrors,
+    },
+
+    #[error("Payment storage failed")]
+    Repository(#[from] RepositoryError),
 }
 
-impl UserRepository {
-    pub fn new() -> Self {
-        Self {
-            storage: HashMap::new(),
+impl ErrorCode for UserError {
+    fn code(&self) -> &'static str {
+        match self {
+            UserError::Invalid(_) => "USER_INVALID",
+            UserError::Repository(e) => e.code(),
         }
     }
 
-    pub fn save(&mut self, user: User) -> Result<(), String> {
-        self.storage.insert(user.id, user);
-        Ok(())
+    fn kind(&self) -> ErrorKind {
+        match self {
+            UserError::Repository(e) => e.kind(),
+            _ => ErrorKind::Validation,
+        }
     }
+}
 
-    pub fn find_by_id(&self, id: u64) -> Option<&User> {
-        self.storage.get(&id)
+impl ErrorCode for OrderError {
+    fn code(&self) -> &'static str {
+        match self {
+            OrderError::Invalid(_) => "ORDER_INVALID",
+            OrderError::Money(e) => e.code(),
+            OrderError::Repository(e) => e.code(),
+        }
     }
 
-    pub fn find_all(&self) -> Vec<&User> {
-        self.storage.values().collect()
+    fn kind(&self) -> ErrorKind {
+        match self {
+            OrderError::Invalid(_) => ErrorKind::Validation,
+            OrderError::Money(e) => e.kind(),
+            OrderError::Repository(e) => e.kind(),
+        }
     }
 }
 
-pub struct OrderRepository {
-    storage: HashMap<u64, Order>,
-}
-
-impl OrderRepository {
-    pub fn new() -> Self {
-        Self {
-            storage: HashMap::new(),
+impl ErrorCode for PaymentError {
+    fn code(&self) -> &'static str {
+        match self {
+            PaymentError::Invalid { .. } => "PAYMENT_INVALID",
+            PaymentError::Repository(e) => e.code(),
         }
     }
 
-    pub fn save(&mut self, order: Order) -> Result<(), String> {
-        self.storage.insert(order.id, order);
-        Ok(())
+    fn kind(&self) -> ErrorKind {
+        match self {
+            PaymentError::Invalid { .. } => ErrorKind::Validation,
+            PaymentError::Repository(e) => e.kind(),
+        }
     }
+}
 
-    pub fn find_by_id(&self, id: u64) -> Option<&Order> {
-        self.storage.get(&id)
-    }
+// ============================================================
+// REPOSITORIES (Data Access)
+// ============================================================
+
+impl Entity for User {
+    type Id = UserId;
 
-    pub fn find_by_user_id(&self, user_id: u64) -> Vec<&Order> {
-        self.storage
-            .values()
-            .filter(|o| o.user_id == user_id)
-            .collect()
+    fn id(&self) -> UserId {
+        self.id
     }
 }
 
-pub struct PaymentRepository {
-    storage: HashMap<u64, Payment>,
-}
+impl Entity for Order {
+    type Id = OrderId;
 
-impl PaymentRepository {
-    pub fn new() -> Self {
-        Self {
-            storage: HashMap::new(),
-        }
+    fn id(&self) -> OrderId {
+        self.id
     }
+}
 
-    pub fn save(&mut self, payment: Payment) -> Result<(), String> {
-        self.storage.insert(payment.id, payment);
-        Ok(())
-    }
+impl Entity for Payment {
+    type Id = PaymentId;
 
-    pub fn find_by_order_id(&self, order_id: u64) -> Option<&Payment> {
-        self.storage.values().find(|p| p.order_id == order_id)
+    fn id(&self) -> PaymentId {
+        self.id
     }
 }
 
+pub type UserRepository = InMemoryRepository<User>;
+pub type OrderRepository = InMemoryRepository<Order>;
+pub type PaymentRepository = InMemoryRepository<Payment>;
+
 // ============================================================
 // SERVICES (Business Logic)
 // ============================================================
 
 pub struct UserService {
     repo: UserRepository,
+    ids: Box<dyn IdGenerator>,
 }
 
 impl UserService {
-    pub fn new(repo: UserRepository) -> Self {
-        Self { repo }
+    pub fn new(repo: UserRepository) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
+        Ok(Self {
+            repo,
+            ids: Box::new(ids),
+        })
     }
 
-    pub fn create_user(&mut self, name: String, email: String) -> Result<User, String> {
-        if name.is_empty() {
-            return Err("Name cannot be empty".to_string());
-        }
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
 
-        if !email.contains('@') {
-            return Err("Invalid email format".to_string());
-        }
+    pub fn create_user(&mut self, name: String, email: String) -> Result<User, UserError> {
+        // Problema: las reglas viven en el servicio, no en el modelo
+        let mut v = Validator::new();
+        v.field("name", name.as_str(), &[not_blank(), max_length(100)])
+            .field("email", email.as_str(), &[validation::email()]);
+        v.finish()?;
 
         let user = User {
-            id: self.repo.storage.len() as u64 + 1,
+            id: UserId(self.ids.next_id()?),
             name,
             email,
         };
 
-        self.repo.save(user.clone())?;
+        self.repo.insert(user.clone())?;
         Ok(user)
     }
 
-    pub fn get_user(&self, id: u64) -> Option<&User> {
-        self.repo.find_by_id(id)
+    pub fn get_user(&self, id: UserId) -> Result<Option<User>, UserError> {
+        Ok(self.repo.find_by_id(id)?)
     }
 }
 
 pub struct OrderService {
     repo: OrderRepository,
+    ids: Box<dyn IdGenerator>,
 }
 
 impl OrderService {
-    pub fn new(repo: OrderRepository) -> Self {
-        Self { repo }
+    pub fn new(repo: OrderRepository) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
+        Ok(Self {
+            repo,
+            ids: Box::new(ids),
+        })
+    }
+
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
     }
 
-    pub fn create_order(&mut self, user_id: u64, items: Vec<OrderItem>) -> Result<Order, String> {
-        if items.is_empty() {
-            return Err("Order must have at least one item".to_string());
+    pub fn create_order(
+        &mut self,
+        user_id: UserId,
+        items: Vec<OrderItem>,
+    ) -> Result<Order, OrderError> {
+        let mut v = Validator::new();
+        v.field("items", items.as_slice(), &[not_empty()])
+            .each("items", &items);
+        // Entre ítems (ninguna regla de un solo campo lo ve): todos en la
+        // moneda del primero, si no el total no se puede sumar
+        if let Some(first) = items.first() {
+            let currency = first.price.currency();
+            for (i, item) in items.iter().enumerate().skip(1) {
+                if item.price.currency() != currency {
+                    v.fail(
+                        &format!("items[{i}].price"),
+                        "CURRENCY_MISMATCH",
+                        format!("must be in {currency}, like items[0]"),
+                    );
+                }
+            }
         }
+        v.finish()?;
 
-        let total: f64 = items.iter().map(|i| i.price * i.quantity as f64).sum();
+        let currency = items[0].price.currency();
+        let total = items.iter().try_fold(Money::zero(currency), |acc, i| {
+            acc.checked_add(i.price.checked_mul(i.quantity)?)
+        })?;
 
         let order = Order {
-            id: self.repo.storage.len() as u64 + 1,
+            id: OrderId(self.ids.next_id()?),
             user_id,
             total,
             items,
         };
 
-        self.repo.save(order.clone())?;
+        self.repo.insert(order.clone())?;
         Ok(order)
     }
 
-    pub fn get_user_orders(&self, user_id: u64) -> Vec<&Order> {
-        self.repo.find_by_user_id(user_id)
+    pub fn get_user_orders(&self, user_id: UserId) -> Result<Vec<Order>, OrderError> {
+        let query = Query::new().where_eq(|o: &Order| o.user_id, user_id);
+        Ok(self.repo.query(&query)?.items)
     }
 }
 
 pub struct PaymentService {
     repo: PaymentRepository,
+    ids: Box<dyn IdGenerator>,
 }
 
 impl PaymentService {
-    pub fn new(repo: PaymentRepository) -> Self {
-        Self { repo }
+    pub fn new(repo: PaymentRepository) -> RepoResult<Self> {
+        let ids = SequentialIdGenerator::resuming(&repo)?;
+        Ok(Self {
+            repo,
+            ids: Box::new(ids),
+        })
     }
 
-    pub fn process_payment(&mut self, order_id: u64, amount: f64) -> Result<Payment, String> {
-        if amount <= 0.0 {
-            return Err("Amount must be positive".to_string());
-        }
+    pub fn with_id_generator(mut self, ids: impl IdGenerator + 'static) -> Self {
+        self.ids = Box::new(ids);
+        self
+    }
+
+    pub fn process_payment(
+        &mut self,
+        order_id: OrderId,
+        amount: Money,
+    ) -> Result<Payment, PaymentError> {
+        Validator::check("amount", &amount, &[positive()])
+            .map_err(|errors| PaymentError::Invalid { order_id, errors })?;
 
         let payment = Payment {
-            id: self.repo.storage.len() as u64 + 1,
+            id: PaymentId(self.ids.next_id()?),
             order_id,
             amount,
             status: PaymentStatus::Completed,
         };
 
-        self.repo.save(payment.clone())?;
+        self.repo.insert(payment.clone())?;
         Ok(payment)
     }
 
-    pub fn get_payment_for_order(&self, order_id: u64) -> Option<&Payment> {
-        self.repo.find_by_order_id(order_id)
+    pub fn get_payment_for_order(
+        &self,
+        order_id: OrderId,
+    ) -> Result<Option<Payment>, PaymentError> {
+        let mut payments = self.repo.iter()?;
+        Ok(payments.find(|p| p.order_id == order_id))
     }
 }
 
@@ -271,9 +387,13 @@ SOLUCIÓN:
 mod tests {
     use super::*;
 
+    fn usd(amount: &str) -> Money {
+        format!("{} USD", amount).parse().unwrap()
+    }
+
     #[test]
     fn test_create_user() {
-        let mut service = UserService::new(UserRepository::new());
+        let mut service = UserService::new(UserRepository::new()).unwrap();
         let user = service
             .create_user("John".to_string(), "john@test.com".to_string())
             .unwrap();
@@ -282,14 +402,71 @@ mod tests {
 
     #[test]
     fn test_create_order() {
-        let mut service = OrderService::new(OrderRepository::new());
+        let mut service = OrderService::new(OrderRepository::new()).unwrap();
         let items = vec![OrderItem {
-            product_id: 1,
+            product_id: ProductId(1),
             quantity: 2,
-            price: 10.0,
+            price: usd("10.00"),
         }];
-        let order = service.create_order(1, items).unwrap();
-        assert_eq!(order.total, 20.0);
+        let order = service.create_order(UserId(1), items).unwrap();
+        assert_eq!(order.total, usd("20.00"));
+    }
+
+    #[test]
+    fn test_process_payment_rejects_non_positive_amount() {
+        let mut service = PaymentService::new(PaymentRepository::new()).unwrap();
+        let err = service
+            .process_payment(OrderId(1), usd("0.00"))
+            .unwrap_err();
+
+        assert!(matches!(
+            &err,
+            PaymentError::Invalid { order_id: OrderId(1), errors } if errors.paths() == ["amount"]
+        ));
+        assert_eq!(err.code(), "PAYMENT_INVALID");
+    }
+
+    #[test]
+    fn test_create_order_reports_every_invalid_item() {
+        let mut service = OrderService::new(OrderRepository::new()).unwrap();
+        let item = |quantity| OrderItem {
+            product_id: ProductId(1),
+            quantity,
+            price: usd("10.00"),
+        };
+
+        let err = service
+            .create_order(UserId(1), vec![item(0), item(1), item(0)])
+            .unwrap_err();
+        let OrderError::Invalid(errors) = err else {
+            panic!("expected validation errors, got {err:?}");
+        };
+        assert_eq!(errors.paths(), ["items[0].quantity", "items[2].quantity"]);
+    }
+
+    #[test]
+    fn test_create_order_rejects_free_items_and_mixed_currencies() {
+        let mut service = OrderService::new(OrderRepository::new()).unwrap();
+        let item = |price: &str| OrderItem {
+            product_id: ProductId(1),
+            quantity: 1,
+            price: price.parse().unwrap(),
+        };
+
+        let err = service
+            .create_order(
+                UserId(1),
+                vec![item("0.00 USD"), item("5.00 EUR"), item("5.00 USD")],
+            )
+            .unwrap_err();
+        let OrderError::Invalid(errors) = err else {
+            panic!("expected validation errors, got {err:?}");
+        };
+        assert_eq!(errors.paths(), ["items[0].price", "items[1].price"]);
+        assert_eq!(
+            errors.for_path("items[1].price").next().unwrap().code,
+            "CURRENCY_MISMATCH"
+        );
     }
 
     // Problema: Tests de diferentes dominios mezclados en el mismo módulo
@@ -0,0 +1,457 @@
+// Repository async: el mismo contrato que Repository, pero con `async fn` y `&self`
+// Los backends bloqueantes (archivo, SQLite) se adaptan con spawn_blocking
+
+use super::id_gen::SequentialIdGenerator;
+use super::index::SecondaryIndexes;
+use super::query::{Page, Query};
+use super::repository::{Entity, RepoResult, Repository, RepositoryError, next_version};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+// ============================================================
+// TRAIT
+// ============================================================
+
+/// CRUD async sobre entidades identificadas por `Id`.
+///
+/// `&self` en lugar de `&mut self`: el repositorio se comparte entre
+/// tasks (detrás de un `Arc`) y cada backend sincroniza por dentro.
+#[async_trait]
+pub trait AsyncRepository<T, Id>: Send + Sync
+where
+    T: Entity<Id = Id> + Send + 'static,
+    Id: Copy + Debug + Send + Sync + 'static,
+{
+    /// Inserta o reemplaza (upsert).
+    async fn save(&self, entity: T) -> RepoResult<()>;
+
+    async fn find_by_id(&self, id: Id) -> RepoResult<Option<T>>;
+
+    /// Elimina y devuelve la entidad, `None` si no existía.
+    async fn delete(&self, id: Id) -> RepoResult<Option<T>>;
+
+    async fn find_all(&self) -> RepoResult<Vec<T>>;
+
+    /// Inserta solo si el id no existe todavía.
+    ///
+    /// Sin default (a diferencia del trait sync): "consultar y después
+    /// guardar" con un `.await` en el medio deja pasar a otra task.
+    async fn insert(&self, entity: T) -> RepoResult<()>;
+
+    /// Reemplaza solo si el id ya existe. Sin default, por lo mismo.
+    async fn update(&self, entity: T) -> RepoResult<()>;
+
+    /// Igual que `Repository::update_versioned`. Sin default: comparar la
+    /// versión y guardar tienen que ocurrir sin un `.await` en el medio.
+    async fn update_versioned(&self, entity: T) -> RepoResult<T>;
+
+    async fn exists(&self, id: Id) -> RepoResult<bool> {
+        Ok(self.find_by_id(id).await?.is_some())
+    }
+
+    async fn count(&self) -> RepoResult<usize> {
+        Ok(self.find_all().await?.len())
+    }
+
+    /// Igual que `Repository::find_unique`: por defecto recorre todo.
+    async fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        Ok(self
+            .find_all()
+            .await?
+            .into_iter()
+            .find(|e| e.unique_keys().iter().any(|(i, k)| *i == index && k == key)))
+    }
+
+    async fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        Ok(self
+            .find_all()
+            .await?
+            .into_iter()
+            .filter(|e| e.index_keys().iter().any(|(i, k)| *i == index && k == key))
+            .collect())
+    }
+
+    /// Igual que `Repository::query`, sobre `find_all`.
+    async fn query(&self, query: &Query<T>) -> RepoResult<Page<T>>
+    where
+        T: Sync,
+        Id: Ord,
+    {
+        Ok(query.run(self.find_all().await?))
+    }
+
+    async fn count_where(&self, query: &Query<T>) -> RepoResult<usize>
+    where
+        T: Sync,
+        Id: Ord,
+    {
+        Ok(query.count(self.find_all().await?))
+    }
+}
+
+/// `SequentialIdGenerator::resuming` para repositorios async: un error
+/// de lectura sube, no se arranca de nuevo en 1.
+pub async fn resume_ids<T, R>(repo: &R) -> RepoResult<SequentialIdGenerator>
+where
+    T: Entity + Send + 'static,
+    T::Id: Into<u64> + Send + Sync + 'static,
+    R: AsyncRepository<T, T::Id>,
+{
+    SequentialIdGenerator::after(repo.find_all().await?.iter().map(|e| e.id()))
+}
+
+// ============================================================
+// IMPLEMENTACIÓN EN MEMORIA (nativa async)
+// ============================================================
+
+/// HashMap detrás de un `tokio::sync::RwLock`: lecturas concurrentes,
+/// escrituras exclusivas, sin bloquear el thread del runtime.
+pub struct AsyncInMemoryRepository<T: Entity> {
+    storage: RwLock<State<T>>,
+}
+
+// Entidades e índices bajo el mismo lock: nunca se ven desincronizados
+struct State<T: Entity> {
+    entities: HashMap<T::Id, T>,
+    indexes: SecondaryIndexes<T>,
+}
+
+impl<T: Entity> State<T> {
+    fn put(&mut self, entity: T) -> RepoResult<()> {
+        self.indexes.insert(&entity)?;
+        self.entities.insert(entity.id(), entity);
+        Ok(())
+    }
+}
+
+impl<T: Entity> AsyncInMemoryRepository<T> {
+    pub fn new() -> Self {
+        Self {
+            storage: RwLock::new(State {
+                entities: HashMap::new(),
+                indexes: SecondaryIndexes::new(),
+            }),
+        }
+    }
+}
+
+impl<T: Entity> Default for AsyncInMemoryRepository<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl<T> AsyncRepository<T, T::Id> for AsyncInMemoryRepository<T>
+where
+    T: Entity + Send + Sync + 'static,
+    T::Id: Send + Sync + 'static,
+{
+    async fn save(&self, entity: T) -> RepoResult<()> {
+        self.storage.write().await.put(entity)
+    }
+
+    async fn find_by_id(&self, id: T::Id) -> RepoResult<Option<T>> {
+        Ok(self.storage.read().await.entities.get(&id).cloned())
+    }
+
+    async fn delete(&self, id: T::Id) -> RepoResult<Option<T>> {
+        let mut storage = self.storage.write().await;
+        storage.indexes.remove(id);
+        Ok(storage.entities.remove(&id))
+    }
+
+    async fn find_all(&self) -> RepoResult<Vec<T>> {
+        Ok(self
+            .storage
+            .read()
+            .await
+            .entities
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    // Chequeo y escritura bajo el mismo write lock: atómico
+    async fn insert(&self, entity: T) -> RepoResult<()> {
+        let mut storage = self.storage.write().await;
+        if storage.entities.contains_key(&entity.id()) {
+            return Err(RepositoryError::AlreadyExists(format!("{:?}", entity.id())));
+        }
+        storage.put(entity)
+    }
+
+    async fn update(&self, entity: T) -> RepoResult<()> {
+        let mut storage = self.storage.write().await;
+        if !storage.entities.contains_key(&entity.id()) {
+            return Err(RepositoryError::NotFound(format!("{:?}", entity.id())));
+        }
+        storage.put(entity)
+    }
+
+    async fn update_versioned(&self, entity: T) -> RepoResult<T> {
+        let mut storage = self.storage.write().await;
+        let next = next_version(storage.entities.get(&entity.id()), &entity)?;
+        storage.put(next.clone())?;
+        Ok(next)
+    }
+
+    async fn exists(&self, id: T::Id) -> RepoResult<bool> {
+        Ok(self.storage.read().await.entities.contains_key(&id))
+    }
+
+    async fn count(&self) -> RepoResult<usize> {
+        Ok(self.storage.read().await.entities.len())
+    }
+
+    async fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        let storage = self.storage.read().await;
+        Ok(storage
+            .indexes
+            .unique_id(index, key)
+            .and_then(|id| storage.entities.get(&id).cloned()))
+    }
+
+    async fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        let storage = self.storage.read().await;
+        Ok(storage
+            .indexes
+            .ids(index, key)
+            .iter()
+            .filter_map(|id| storage.entities.get(id).cloned())
+            .collect())
+    }
+}
+
+// ============================================================
+// ADAPTADOR PARA BACKENDS BLOQUEANTES
+// ============================================================
+
+/// Envuelve algo síncrono (un Repository, un servicio) para usarlo desde
+/// async: cada llamada corre en el pool de `spawn_blocking`, así el I/O
+/// de disco no frena al resto de las tasks.
+///
+/// Clonar es barato; todos los clones comparten el mismo valor.
+pub struct SpawnBlocking<R> {
+    inner: Arc<Mutex<R>>,
+}
+
+impl<R> Clone for SpawnBlocking<R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<R: Send + 'static> SpawnBlocking<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Corre `f` con acceso exclusivo al valor, fuera del runtime async.
+    pub async fn run<T, E, F>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut R) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<RepositoryError> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        blocking(move || {
+            let mut guard = inner
+                .lock()
+                .map_err(|_| RepositoryError::Storage("blocking backend poisoned".to_string()))?;
+            f(&mut guard)
+        })
+        .await
+    }
+}
+
+/// `spawn_blocking` con el error del task (panic, runtime cerrándose)
+/// traducido a `RepositoryError::Storage`.
+pub async fn blocking<T, E, F>(f: F) -> Result<T, E>
+where
+    F: FnOnce() -> Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: From<RepositoryError> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) => Err(RepositoryError::Storage(format!("blocking task failed: {e}")).into()),
+    }
+}
+
+#[async_trait]
+impl<T, R> AsyncRepository<T, T::Id> for SpawnBlocking<R>
+where
+    T: Entity + Send + 'static,
+    T::Id: Send + Sync + 'static,
+    R: Repository<T, T::Id> + Send + 'static,
+{
+    async fn save(&self, entity: T) -> RepoResult<()> {
+        self.run(move |repo| repo.save(entity)).await
+    }
+
+    async fn find_by_id(&self, id: T::Id) -> RepoResult<Option<T>> {
+        self.run(move |repo| repo.find_by_id(id)).await
+    }
+
+    async fn delete(&self, id: T::Id) -> RepoResult<Option<T>> {
+        self.run(move |repo| repo.delete(id)).await
+    }
+
+    async fn find_all(&self) -> RepoResult<Vec<T>> {
+        self.run(|repo| repo.find_all()).await
+    }
+
+    // Bajo el Mutex, el insert del backend (chequeo + escritura) es atómico
+    async fn insert(&self, entity: T) -> RepoResult<()> {
+        self.run(move |repo| repo.insert(entity)).await
+    }
+
+    async fn update(&self, entity: T) -> RepoResult<()> {
+        self.run(move |repo| repo.update(entity)).await
+    }
+
+    async fn update_versioned(&self, entity: T) -> RepoResult<T> {
+        self.run(move |repo| repo.update_versioned(entity)).await
+    }
+
+    async fn exists(&self, id: T::Id) -> RepoResult<bool> {
+        self.run(move |repo| repo.exists(id)).await
+    }
+
+    async fn count(&self) -> RepoResult<usize> {
+        self.run(|repo| repo.count()).await
+    }
+
+    async fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        let (index, key) = (index.to_string(), key.to_string());
+        self.run(move |repo| repo.find_unique(&index, &key)).await
+    }
+
+    async fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        let (index, key) = (index.to_string(), key.to_string());
+        self.run(move |repo| repo.find_indexed(&index, &key)).await
+    }
+}
+
+/*
+SYNC vs ASYNC:
+
+  Repository (sync)                AsyncRepository
+  ─────────────────                ─────────────────────────
+  &mut self para escribir          &self siempre (Arc-friendly)
+  insert/update con default        insert/update/update_versioned
+                                   obligatorios
+  iter() → Box<dyn Iterator>       find_all() → Vec (un iterador
+                                   prestado no cruza un .await)
+
+  AsyncInMemoryRepository          tokio RwLock, nunca bloquea el runtime
+  SpawnBlocking<R: Repository>     Mutex + spawn_blocking: JSON, log,
+                                   SQLite... cualquier backend sync
+
+        task async ──await──▶ SpawnBlocking::run
+                                   │ spawn_blocking
+                                   ▼
+                         pool de threads bloqueantes
+                                   │ lock()
+                                   ▼
+                               R: Repository (I/O)
+
+- Un Mutex std (no tokio) alcanza: el lock se toma DENTRO del thread
+  bloqueante, nunca se sostiene a través de un .await
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::InMemoryRepository;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Item {
+        id: u64,
+        name: String,
+    }
+
+    impl Entity for Item {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.id
+        }
+    }
+
+    fn item(id: u64, name: &str) -> Item {
+        Item {
+            id,
+            name: name.to_string(),
+        }
+    }
+
+    async fn exercise<R: AsyncRepository<Item, u64>>(repo: R) {
+        repo.insert(item(1, "a")).await.unwrap();
+        repo.save(item(2, "b")).await.unwrap();
+        assert_eq!(
+            repo.insert(item(1, "again")).await,
+            Err(RepositoryError::AlreadyExists("1".to_string()))
+        );
+        assert_eq!(
+            repo.update(item(9, "x")).await,
+            Err(RepositoryError::NotFound("9".to_string()))
+        );
+
+        repo.update(item(2, "b2")).await.unwrap();
+        assert_eq!(repo.find_by_id(2).await.unwrap(), Some(item(2, "b2")));
+        assert_eq!(repo.count().await.unwrap(), 2);
+
+        assert_eq!(repo.delete(1).await.unwrap(), Some(item(1, "a")));
+        assert!(!repo.exists(1).await.unwrap());
+        assert_eq!(repo.find_all().await.unwrap(), vec![item(2, "b2")]);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_crud() {
+        exercise(AsyncInMemoryRepository::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_spawn_blocking_adapter_crud() {
+        exercise(SpawnBlocking::new(InMemoryRepository::<Item>::new())).await;
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_inserts_same_id_only_one_wins() {
+        let repo = Arc::new(AsyncInMemoryRepository::new());
+
+        let tasks: Vec<_> = (0..16)
+            .map(|n| {
+                let repo = Arc::clone(&repo);
+                tokio::spawn(async move { repo.insert(item(1, &format!("task {n}"))).await })
+            })
+            .collect();
+
+        let mut winners = 0;
+        for task in tasks {
+            if task.await.unwrap().is_ok() {
+                winners += 1;
+            }
+        }
+        assert_eq!(winners, 1);
+        assert_eq!(repo.count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resume_ids_after_existing() {
+        let repo = AsyncInMemoryRepository::new();
+        repo.save(item(41, "old")).await.unwrap();
+
+        use crate::modules_demo::shared::IdGenerator;
+        assert_eq!(resume_ids(&repo).await.unwrap().next_id(), Ok(42));
+    }
+}
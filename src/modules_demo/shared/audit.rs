@@ -0,0 +1,548 @@
+// Auditoría: quién cambió qué y cuándo, con el antes/después de cada campo
+// Consultable por entidad y por actor; reconstruye una entidad a una fecha
+
+use super::id_gen::{Clock, IdGenerator, SequentialIdGenerator, SystemClock};
+use super::json_file::JsonFileRepository;
+use super::repository::{Entity, InMemoryRepository, RepoResult, RepositoryError};
+use super::shared_repository::{SharedRepository, SharedStore};
+use super::unit_of_work::Transactional;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Índice multi-valor por entidad ("tipo:id").
+pub const AUDIT_BY_ENTITY: &str = "audit.entity";
+
+/// Índice multi-valor por actor.
+pub const AUDIT_BY_ACTOR: &str = "audit.actor";
+
+/// Actor por defecto: cambios que no inició nadie en particular (jobs,
+/// migraciones, tests).
+pub const SYSTEM_ACTOR: &str = "system";
+
+// ============================================================
+// ENTRADA
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Un campo de primer nivel que cambió. `None`: el campo no existía
+/// (antes de crear, después de borrar).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub action: AuditAction,
+    pub actor: String,
+    pub at: DateTime<Utc>,
+    pub changes: Vec<FieldChange>,
+}
+
+impl AuditEntry {
+    /// El cambio de `field`, si esta entrada lo tocó.
+    pub fn change(&self, field: &str) -> Option<&FieldChange> {
+        self.changes.iter().find(|c| c.field == field)
+    }
+}
+
+impl Entity for AuditEntry {
+    type Id = u64;
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn index_keys(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                AUDIT_BY_ENTITY,
+                entity_key(&self.entity_type, &self.entity_id),
+            ),
+            (AUDIT_BY_ACTOR, self.actor.clone()),
+        ]
+    }
+}
+
+fn entity_key(entity_type: &str, entity_id: &str) -> String {
+    format!("{entity_type}:{entity_id}")
+}
+
+// ============================================================
+// LOG
+// ============================================================
+
+/// Registro append-only. Clonar es barato: varios servicios escriben en
+/// el mismo log (y participan de la misma transacción).
+#[derive(Clone)]
+pub struct AuditLog {
+    entries: SharedRepository<AuditEntry>,
+    ids: Arc<SequentialIdGenerator>,
+    clock: Arc<dyn Clock>,
+}
+
+impl AuditLog {
+    pub fn in_memory() -> Self {
+        Self::with_store(InMemoryRepository::new())
+            .expect("in-memory repository is always readable")
+    }
+
+    /// Persistente (JSON): sobrevive reinicios.
+    pub fn open(path: impl Into<PathBuf>) -> RepoResult<Self> {
+        Self::with_store(JsonFileRepository::open(path)?)
+    }
+
+    pub fn with_store(store: impl SharedStore<AuditEntry> + 'static) -> RepoResult<Self> {
+        // Continuar la numeración: el id ordena las entradas de un mismo
+        // milisegundo
+        let ids = SequentialIdGenerator::resuming(&store)?;
+
+        Ok(Self {
+            entries: SharedRepository::new(store),
+            ids: Arc::new(ids),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Reemplaza el reloj (p.ej. `ManualClock` en tests).
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn created<T: Serialize>(
+        &self,
+        actor: &str,
+        entity_type: &str,
+        entity_id: impl Display,
+        entity: &T,
+    ) -> RepoResult<AuditEntry> {
+        let changes = diff(None, Some(&fields(entity)?));
+        self.append(AuditAction::Created, actor, entity_type, entity_id, changes)
+    }
+
+    /// Solo los campos que cambiaron.
+    pub fn updated<T: Serialize>(
+        &self,
+        actor: &str,
+        entity_type: &str,
+        entity_id: impl Display,
+        before: &T,
+        after: &T,
+    ) -> RepoResult<AuditEntry> {
+        let changes = diff(Some(&fields(before)?), Some(&fields(after)?));
+        self.append(AuditAction::Updated, actor, entity_type, entity_id, changes)
+    }
+
+    /// Guarda el último estado completo: lo borrado sigue siendo visible.
+    pub fn deleted<T: Serialize>(
+        &self,
+        actor: &str,
+        entity_type: &str,
+        entity_id: impl Display,
+        entity: &T,
+    ) -> RepoResult<AuditEntry> {
+        let changes = diff(Some(&fields(entity)?), None);
+        self.append(AuditAction::Deleted, actor, entity_type, entity_id, changes)
+    }
+
+    /// Historia de una entidad, de la más vieja a la más nueva.
+    pub fn for_entity(
+        &self,
+        entity_type: &str,
+        entity_id: impl Display,
+    ) -> RepoResult<Vec<AuditEntry>> {
+        let key = entity_key(entity_type, &entity_id.to_string());
+        self.find(AUDIT_BY_ENTITY, &key)
+    }
+
+    /// Todo lo que hizo `actor`, en orden.
+    pub fn by_actor(&self, actor: &str) -> RepoResult<Vec<AuditEntry>> {
+        self.find(AUDIT_BY_ACTOR, actor)
+    }
+
+    /// La entidad tal como estaba en `at`: `None` si todavía no existía o
+    /// ya estaba borrada.
+    pub fn as_of<T: DeserializeOwned>(
+        &self,
+        entity_type: &str,
+        entity_id: impl Display,
+        at: DateTime<Utc>,
+    ) -> RepoResult<Option<T>> {
+        let mut state: Option<Map<String, Value>> = None;
+
+        for entry in self.for_entity(entity_type, entity_id)? {
+            if entry.at > at {
+                break;
+            }
+            match entry.action {
+                AuditAction::Deleted => state = None,
+                AuditAction::Created | AuditAction::Updated => {
+                    let fields = state.get_or_insert_with(Map::new);
+                    for change in entry.changes {
+                        match change.after {
+                            Some(value) => fields.insert(change.field, value),
+                            None => fields.remove(&change.field),
+                        };
+                    }
+                }
+            }
+        }
+
+        state
+            .map(|fields| serde_json::from_value(Value::Object(fields)))
+            .transpose()
+            .map_err(|e| RepositoryError::Corrupted(format!("audit replay: {e}")))
+    }
+
+    fn append(
+        &self,
+        action: AuditAction,
+        actor: &str,
+        entity_type: &str,
+        entity_id: impl Display,
+        changes: Vec<FieldChange>,
+    ) -> RepoResult<AuditEntry> {
+        let entry = AuditEntry {
+            id: self.ids.next_id()?,
+            entity_type: entity_type.to_string(),
+            entity_id: entity_id.to_string(),
+            action,
+            actor: actor.to_string(),
+            at: self.clock.now(),
+            changes,
+        };
+        self.entries.with(|store| store.insert(entry.clone()))?;
+        Ok(entry)
+    }
+
+    fn find(&self, index: &str, key: &str) -> RepoResult<Vec<AuditEntry>> {
+        let mut entries = self.entries.with(|store| store.find_indexed(index, key))?;
+        entries.sort_by_key(|e| e.id);
+        Ok(entries)
+    }
+}
+
+// Auditar dentro de la transacción del cambio: si se deshace, la entrada
+// también (solo esa: las de otros servicios que comparten el log quedan)
+impl Transactional for AuditLog {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.entries.begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.entries.commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.entries.rollback()
+    }
+}
+
+// Campos de primer nivel: suficiente para "el email pasó de a a b" sin
+// depender del tipo concreto
+fn fields<T: Serialize>(entity: &T) -> RepoResult<Map<String, Value>> {
+    match serde_json::to_value(entity) {
+        Ok(Value::Object(fields)) => Ok(fields),
+        Ok(other) => Err(RepositoryError::Storage(format!(
+            "only structs can be audited, got {other}"
+        ))),
+        Err(e) => Err(RepositoryError::Storage(format!("audit serialize: {e}"))),
+    }
+}
+
+fn diff(
+    before: Option<&Map<String, Value>>,
+    after: Option<&Map<String, Value>>,
+) -> Vec<FieldChange> {
+    let empty = Map::new();
+    let (before, after) = (before.unwrap_or(&empty), after.unwrap_or(&empty));
+
+    let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter(|name| before.get(*name) != after.get(*name))
+        .map(|name| FieldChange {
+            field: name.clone(),
+            before: before.get(name).cloned(),
+            after: after.get(name).cloned(),
+        })
+        .collect()
+}
+
+// ============================================================
+// AUDITOR (lo que guarda un servicio)
+// ============================================================
+
+/// Log opcional + actor actual. Sin log, registrar no hace nada: auditar
+/// es opt-in (`with_audit` en los servicios).
+#[derive(Clone)]
+pub struct Auditor {
+    log: Option<AuditLog>,
+    actor: String,
+}
+
+impl Default for Auditor {
+    fn default() -> Self {
+        Self {
+            log: None,
+            actor: SYSTEM_ACTOR.to_string(),
+        }
+    }
+}
+
+impl Auditor {
+    pub fn with_log(mut self, log: AuditLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    pub fn log(&self) -> Option<&AuditLog> {
+        self.log.as_ref()
+    }
+
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    pub fn set_actor(&mut self, actor: impl Into<String>) {
+        self.actor = actor.into();
+    }
+
+    pub fn created<T: Serialize>(
+        &self,
+        entity_type: &str,
+        entity_id: impl Display,
+        entity: &T,
+    ) -> RepoResult<()> {
+        match &self.log {
+            Some(log) => log
+                .created(&self.actor, entity_type, entity_id, entity)
+                .map(drop),
+            None => Ok(()),
+        }
+    }
+
+    pub fn updated<T: Serialize>(
+        &self,
+        entity_type: &str,
+        entity_id: impl Display,
+        before: &T,
+        after: &T,
+    ) -> RepoResult<()> {
+        match &self.log {
+            Some(log) => log
+                .updated(&self.actor, entity_type, entity_id, before, after)
+                .map(drop),
+            None => Ok(()),
+        }
+    }
+
+    pub fn deleted<T: Serialize>(
+        &self,
+        entity_type: &str,
+        entity_id: impl Display,
+        entity: &T,
+    ) -> RepoResult<()> {
+        match &self.log {
+            Some(log) => log
+                .deleted(&self.actor, entity_type, entity_id, entity)
+                .map(drop),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Transactional for Auditor {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.log.as_mut().map_or(Ok(()), Transactional::begin)
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.log.as_mut().map_or(Ok(()), Transactional::commit)
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.log.as_mut().map_or(Ok(()), Transactional::rollback)
+    }
+}
+
+/*
+UNA ENTRADA POR CAMBIO:
+
+  update_email(1, "b@x.com")   actor = "admin"
+
+  AuditEntry #7
+    entity  user:1        action Updated     at 2024-05-01T10:00:00Z
+    changes email    "a@x.com" → "b@x.com"
+            version  1         → 2
+
+RECONSTRUIR (as_of):
+
+  #3 Created {id, name, email: a, version: 1}   10:00
+  #7 Updated {email: a→b, version: 1→2}         11:00
+  #9 Deleted                                    12:00
+
+  as_of(10:30) = replay #3          → email a
+  as_of(11:30) = replay #3, #7      → email b
+  as_of(12:30) = ... #9             → None
+
+- Diffs de primer nivel (serde_json::Value): sirve para cualquier
+  entidad serializable sin código por tipo
+- Deleted guarda el último estado completo: se ve qué se borró
+- El timestamp sale de un Clock inyectable; el id desempata entradas del
+  mismo milisegundo
+- Comparte backend y transacción vía SharedRepository: en un UnitOfWork
+  el cambio y su entrada se confirman (o deshacen) juntos
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::ManualClock;
+    use crate::modules_demo::shared::json_file::tests::TempPath;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        title: String,
+        pages: u32,
+    }
+
+    fn doc(title: &str, pages: u32) -> Doc {
+        Doc {
+            title: title.to_string(),
+            pages,
+        }
+    }
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    #[test]
+    fn test_update_records_only_changed_fields() {
+        let log = AuditLog::in_memory();
+        log.created("ana", "doc", 1, &doc("a", 1)).unwrap();
+        let entry = log
+            .updated("bob", "doc", 1, &doc("a", 1), &doc("a", 2))
+            .unwrap();
+
+        assert_eq!(entry.action, AuditAction::Updated);
+        assert_eq!(
+            entry.changes,
+            vec![FieldChange {
+                field: "pages".to_string(),
+                before: Some(1.into()),
+                after: Some(2.into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_queries_by_entity_and_actor() {
+        let log = AuditLog::in_memory();
+        log.created("ana", "doc", 1, &doc("a", 1)).unwrap();
+        log.created("bob", "doc", 2, &doc("b", 1)).unwrap();
+        log.deleted("ana", "doc", 2, &doc("b", 1)).unwrap();
+
+        let history = log.for_entity("doc", 2).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].action, AuditAction::Deleted);
+        assert_eq!(history[1].change("title").unwrap().after, None);
+
+        let by_ana: Vec<_> = log
+            .by_actor("ana")
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.entity_id, e.action))
+            .collect();
+        assert_eq!(
+            by_ana,
+            vec![
+                ("1".to_string(), AuditAction::Created),
+                ("2".to_string(), AuditAction::Deleted),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_as_of_replays_history_up_to_the_instant() {
+        let clock = Arc::new(ManualClock::new(1_000));
+        let log = AuditLog::in_memory().with_clock(Arc::clone(&clock));
+
+        log.created("ana", "doc", 1, &doc("draft", 1)).unwrap();
+        clock.advance(1_000);
+        log.updated("ana", "doc", 1, &doc("draft", 1), &doc("final", 3))
+            .unwrap();
+        clock.advance(1_000);
+        log.deleted("ana", "doc", 1, &doc("final", 3)).unwrap();
+
+        let as_of = |millis| log.as_of::<Doc>("doc", 1, at(millis)).unwrap();
+        assert_eq!(as_of(999), None);
+        assert_eq!(as_of(1_500), Some(doc("draft", 1)));
+        assert_eq!(as_of(2_000), Some(doc("final", 3)));
+        assert_eq!(as_of(3_000), None);
+    }
+
+    #[test]
+    fn test_rolled_back_entries_are_discarded() {
+        let mut log = AuditLog::in_memory();
+
+        log.begin().unwrap();
+        log.created("ana", "doc", 1, &doc("a", 1)).unwrap();
+        log.rollback().unwrap();
+
+        assert!(log.for_entity("doc", 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rollback_keeps_entries_from_services_outside_the_transaction() {
+        let path = TempPath::new("audit-shared");
+        let mut inside = AuditLog::open(&path.0).unwrap();
+        let outside = inside.clone();
+
+        inside.begin().unwrap();
+        inside.created("ana", "doc", 1, &doc("a", 1)).unwrap();
+        outside.created("bob", "doc", 2, &doc("b", 1)).unwrap();
+        inside.rollback().unwrap();
+
+        assert!(outside.for_entity("doc", 1).unwrap().is_empty());
+        assert_eq!(outside.for_entity("doc", 2).unwrap().len(), 1);
+        let reopened = AuditLog::open(&path.0).unwrap();
+        assert_eq!(reopened.by_actor("bob").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_open_keeps_entries_and_numbering() {
+        let path = TempPath::new("audit");
+
+        AuditLog::open(&path.0)
+            .unwrap()
+            .created("ana", "doc", 1, &doc("a", 1))
+            .unwrap();
+        let reopened = AuditLog::open(&path.0).unwrap();
+        let entry = reopened.deleted("ana", "doc", 1, &doc("a", 1)).unwrap();
+
+        assert_eq!(entry.id, 2);
+        assert_eq!(reopened.for_entity("doc", 1).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,126 @@
+// Errores: categorías y códigos estables compartidos por todos los dominios
+// Cada dominio define su propio enum; aquí solo está el "idioma común"
+
+use super::money::MoneyError;
+use super::repository::RepositoryError;
+
+// ============================================================
+// CATEGORÍAS
+// ============================================================
+
+/// Categoría de un error de dominio, independiente del dominio concreto.
+/// Es lo que se traduce a status HTTP o exit code de CLI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Validation,
+    NotFound,
+    Conflict,
+    InvalidState,
+    Storage,
+    /// Un servicio externo (gateway, API) no respondió
+    Unavailable,
+}
+
+impl ErrorKind {
+    pub fn http_status(self) -> u16 {
+        match self {
+            ErrorKind::Validation => 400,
+            ErrorKind::NotFound => 404,
+            ErrorKind::Conflict => 409,
+            ErrorKind::InvalidState => 422,
+            ErrorKind::Storage => 500,
+            ErrorKind::Unavailable => 503,
+        }
+    }
+
+    /// Exit codes estilo sysexits.h
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Validation => 65,   // EX_DATAERR
+            ErrorKind::NotFound => 66,     // EX_NOINPUT
+            ErrorKind::Conflict => 73,     // EX_CANTCREAT
+            ErrorKind::InvalidState => 70, // EX_SOFTWARE
+            ErrorKind::Storage => 74,      // EX_IOERR
+            ErrorKind::Unavailable => 69,  // EX_UNAVAILABLE
+        }
+    }
+}
+
+// ============================================================
+// TRAIT
+// ============================================================
+
+/// Todo error de dominio expone un código estable (no cambia aunque
+/// cambie el mensaje) y su categoría.
+pub trait ErrorCode {
+    fn code(&self) -> &'static str;
+
+    fn kind(&self) -> ErrorKind;
+
+    fn http_status(&self) -> u16 {
+        self.kind().http_status()
+    }
+
+    fn exit_code(&self) -> i32 {
+        self.kind().exit_code()
+    }
+}
+
+impl ErrorCode for RepositoryError {
+    fn code(&self) -> &'static str {
+        match self {
+            RepositoryError::NotFound(_) => "REPOSITORY_NOT_FOUND",
+            RepositoryError::AlreadyExists(_) => "REPOSITORY_ALREADY_EXISTS",
+            RepositoryError::UniqueViolation(_) => "REPOSITORY_UNIQUE_VIOLATION",
+            RepositoryError::ForeignKeyViolation(_) => "REPOSITORY_FOREIGN_KEY_VIOLATION",
+            RepositoryError::VersionConflict { .. } => "REPOSITORY_VERSION_CONFLICT",
+            RepositoryError::Storage(_) => "REPOSITORY_STORAGE",
+            RepositoryError::Corrupted(_) => "REPOSITORY_CORRUPTED",
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            RepositoryError::NotFound(_) => ErrorKind::NotFound,
+            RepositoryError::AlreadyExists(_)
+            | RepositoryError::UniqueViolation(_)
+            | RepositoryError::ForeignKeyViolation(_)
+            | RepositoryError::VersionConflict { .. } => ErrorKind::Conflict,
+            RepositoryError::Storage(_) | RepositoryError::Corrupted(_) => ErrorKind::Storage,
+        }
+    }
+}
+
+impl ErrorCode for MoneyError {
+    fn code(&self) -> &'static str {
+        match self {
+            MoneyError::CurrencyMismatch { .. } => "MONEY_CURRENCY_MISMATCH",
+            MoneyError::Overflow => "MONEY_OVERFLOW",
+            MoneyError::UnknownCurrency(_) => "MONEY_UNKNOWN_CURRENCY",
+            MoneyError::Parse(_) => "MONEY_PARSE",
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Validation
+    }
+}
+
+/*
+¿POR QUÉ CÓDIGOS ESTABLES?
+
+- El mensaje (Display) es para humanos y puede cambiar
+- El código ("USER_NOT_FOUND") es un contrato con los clientes
+- ErrorKind agrupa códigos para mapear a HTTP / exit codes:
+
+  ┌──────────────┬──────┬──────┐
+  │ ErrorKind    │ HTTP │ Exit │
+  ├──────────────┼──────┼──────┤
+  │ Validation   │ 400  │  65  │
+  │ NotFound     │ 404  │  66  │
+  │ Conflict     │ 409  │  73  │
+  │ InvalidState │ 422  │  70  │
+  │ Storage      │ 500  │  74  │
+  │ Unavailable  │ 503  │  69  │
+  └──────────────┴──────┴──────┘
+*/
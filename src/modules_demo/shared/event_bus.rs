@@ -0,0 +1,312 @@
+// EventBus: publicar/suscribir en el mismo proceso, sin que el que publica conozca a nadie
+// Suscriptores sync (closures, como el EventEmitter de clousures.rs) y async (tasks de tokio)
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use tokio::sync::mpsc;
+
+type Handler<E> = Arc<Mutex<Box<dyn FnMut(&E) + Send>>>;
+
+/// Identifica una suscripción para poder darla de baja.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+enum Subscriber<E> {
+    /// Corre dentro de `publish`, en el thread del que publica
+    Sync(Handler<E>),
+    /// Recibe una copia por canal; la procesa su propia task
+    Async(mpsc::UnboundedSender<E>),
+}
+
+struct Inner<E> {
+    next_id: AtomicU64,
+    subscribers: Mutex<Vec<(SubscriptionId, Subscriber<E>)>>,
+    /// Un publish a la vez entrega eventos (orden igual para todos)
+    dispatch: Mutex<()>,
+    /// Thread que está entregando; si publica de nuevo, se encola
+    dispatcher: Mutex<Option<ThreadId>>,
+    /// Publicados desde un handler, esperando a que termine el actual
+    pending: Mutex<VecDeque<E>>,
+}
+
+/// Bus de eventos de tipo `E`. Clonar es barato: todos los clones
+/// comparten los mismos suscriptores (un servicio publica, otro módulo
+/// se suscribe con su propio clon).
+pub struct EventBus<E> {
+    inner: Arc<Inner<E>>,
+}
+
+// Manual: derive(Clone) exigiría E: Clone sobre el tipo, y solo se clona el Arc
+impl<E> Clone for EventBus<E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<E: Clone + Send + 'static> Default for EventBus<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Clone + Send + 'static> EventBus<E> {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                next_id: AtomicU64::new(1),
+                subscribers: Mutex::new(Vec::new()),
+                dispatch: Mutex::new(()),
+                dispatcher: Mutex::new(None),
+                pending: Mutex::new(VecDeque::new()),
+            }),
+        }
+    }
+
+    /// Suscriptor síncrono: corre dentro de `publish`, en orden de
+    /// suscripción. Debe ser rápido; lo lento va en `subscribe_async`.
+    pub fn subscribe(&self, handler: impl FnMut(&E) + Send + 'static) -> SubscriptionId {
+        self.add(Subscriber::Sync(Arc::new(Mutex::new(Box::new(handler)))))
+    }
+
+    /// Suscriptor async: una task de tokio recibe los eventos en orden de
+    /// publicación y espera a `handler` antes de pasar al siguiente.
+    /// `publish` no lo espera.
+    ///
+    /// Hay que llamarlo dentro de un runtime de tokio.
+    pub fn subscribe_async<F, Fut>(&self, mut handler: F) -> SubscriptionId
+    where
+        F: FnMut(E) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            // Termina cuando se cierra el canal (unsubscribe o bus descartado)
+            while let Some(event) = receiver.recv().await {
+                handler(event).await;
+            }
+        });
+        self.add(Subscriber::Async(sender))
+    }
+
+    /// Da de baja una suscripción. `false` si no existía.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.subscribers();
+        let before = subscribers.len();
+        subscribers.retain(|(sid, _)| *sid != id);
+        subscribers.len() != before
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers().len()
+    }
+
+    /// Entrega `event` a todos los suscriptores.
+    ///
+    /// Si un handler publica, ese evento se entrega cuando termina el
+    /// actual (después de que todos vieron el actual), no en el medio.
+    pub fn publish(&self, event: E) {
+        let me = thread::current().id();
+        if *lock(&self.inner.dispatcher) == Some(me) {
+            lock(&self.inner.pending).push_back(event);
+            return;
+        }
+
+        let _turn = lock(&self.inner.dispatch);
+        let _dispatching = Dispatching::start(&self.inner, me);
+
+        let mut next = Some(event);
+        while let Some(event) = next {
+            self.deliver(&event);
+            next = lock(&self.inner.pending).pop_front();
+        }
+    }
+
+    fn deliver(&self, event: &E) {
+        // Se copia la lista y se suelta el lock antes de llamar a nadie:
+        // un handler puede suscribir o darse de baja
+        let handlers: Vec<Handler<E>> = {
+            let mut subscribers = self.subscribers();
+            subscribers.retain(|(_, subscriber)| match subscriber {
+                Subscriber::Async(sender) => sender.send(event.clone()).is_ok(),
+                Subscriber::Sync(_) => true,
+            });
+            subscribers
+                .iter()
+                .filter_map(|(_, subscriber)| match subscriber {
+                    Subscriber::Sync(handler) => Some(Arc::clone(handler)),
+                    Subscriber::Async(_) => None,
+                })
+                .collect()
+        };
+
+        for handler in handlers {
+            let mut handler = lock(&handler);
+            handler(event);
+        }
+    }
+
+    fn add(&self, subscriber: Subscriber<E>) -> SubscriptionId {
+        let id = SubscriptionId(self.inner.next_id.fetch_add(1, Ordering::Relaxed));
+        self.subscribers().push((id, subscriber));
+        id
+    }
+
+    fn subscribers(&self) -> MutexGuard<'_, Vec<(SubscriptionId, Subscriber<E>)>> {
+        lock(&self.inner.subscribers)
+    }
+}
+
+// Un pánico en un handler no deja el bus inutilizable: ningún lock del bus
+// queda con datos a medio modificar
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Marca al thread actual como el que entrega; al salir (aunque un
+/// handler entre en pánico) lo desmarca y descarta lo encolado.
+struct Dispatching<'a, E> {
+    inner: &'a Inner<E>,
+}
+
+impl<'a, E> Dispatching<'a, E> {
+    fn start(inner: &'a Inner<E>, thread: ThreadId) -> Self {
+        *lock(&inner.dispatcher) = Some(thread);
+        Self { inner }
+    }
+}
+
+impl<E> Drop for Dispatching<'_, E> {
+    fn drop(&mut self) {
+        *lock(&self.inner.dispatcher) = None;
+        lock(&self.inner.pending).clear();
+    }
+}
+
+/*
+PUBLICAR Y SUSCRIBIR:
+
+  UserService ──publish(UserRegistered)──▶ EventBus
+                                             │
+               ┌─────────────────────────────┼──────────────────────────┐
+               ▼ sync (mismo thread)         ▼ async (canal)            ▼
+         |e| métricas += 1          mpsc ──▶ task: enviar email   mpsc ──▶ task: analytics
+         (antes de volver)                   (después, en orden)
+
+- El servicio solo conoce el bus, no a los suscriptores: agregar
+  notificaciones o analytics no toca UserService
+- sync: FnMut(&E) + Send (el EventEmitter de clousures.rs, pero
+  compartible entre threads)
+- async: un canal por suscriptor → cada uno procesa a su ritmo y en
+  orden; publish nunca espera I/O
+- Publicar desde un handler no reentra: se encola y sale al terminar la
+  entrega actual, así todos ven los eventos en el mismo orden
+- Sin persistencia: un evento publicado mientras nadie escucha se pierde
+  (para eso, un outbox)
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sync_subscribers_run_in_order() {
+        let bus = EventBus::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        for name in ["first", "second"] {
+            let log = Arc::clone(&log);
+            bus.subscribe(move |event: &u32| log.lock().unwrap().push(format!("{name}:{event}")));
+        }
+        bus.publish(1);
+        bus.publish(2);
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["first:1", "second:1", "first:2", "second:2"]
+        );
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let bus = EventBus::new();
+        let count = Arc::new(AtomicU64::new(0));
+
+        let counter = Arc::clone(&count);
+        let id = bus.subscribe(move |_: &()| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        bus.publish(());
+
+        assert!(bus.unsubscribe(id));
+        assert!(!bus.unsubscribe(id));
+        bus.publish(());
+
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+        assert_eq!(bus.subscriber_count(), 0);
+    }
+
+    #[test]
+    fn test_handler_can_publish_without_deadlock() {
+        let bus = EventBus::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let (inner_bus, log) = (bus.clone(), Arc::clone(&seen));
+        bus.subscribe(move |n: &u32| {
+            log.lock().unwrap().push(*n);
+            if *n > 0 {
+                inner_bus.publish(n - 1);
+            }
+        });
+        bus.publish(2);
+
+        assert_eq!(*seen.lock().unwrap(), [2, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_async_subscriber_receives_in_order() {
+        let bus = EventBus::new();
+        let (done, mut received) = mpsc::unbounded_channel();
+
+        bus.subscribe_async(move |n: u32| {
+            let done = done.clone();
+            async move {
+                // Un handler lento no reordena ni bloquea a quien publica
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                done.send(n).unwrap();
+            }
+        });
+        for n in 0..5 {
+            bus.publish(n);
+        }
+
+        let mut got = Vec::new();
+        while got.len() < 5 {
+            got.push(received.recv().await.unwrap());
+        }
+        assert_eq!(got, [0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_stops_async_task() {
+        let bus = EventBus::new();
+        let (done, mut received) = mpsc::unbounded_channel();
+
+        let id = bus.subscribe_async(move |n: u32| {
+            let done = done.clone();
+            async move { done.send(n).unwrap() }
+        });
+        bus.publish(1);
+        bus.unsubscribe(id);
+        bus.publish(2);
+
+        assert_eq!(received.recv().await, Some(1));
+        // La task terminó y soltó su copia de `done`: el canal se cierra
+        assert_eq!(received.recv().await, None);
+    }
+}
@@ -0,0 +1,595 @@
+// Generación de IDs: estrategia inyectable en lugar de `count() + 1`
+// `count() + 1` reutiliza ids tras un delete: [1, 2, 3] - 2 → count=2 → nuevo id 3 ✗
+
+use super::repository::{Entity, RepoResult, Repository, RepositoryError};
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// ============================================================
+// TRAIT
+// ============================================================
+
+/// Fuente de identificadores únicos.
+///
+/// `&self` (no `&mut self`): cada implementación usa mutabilidad interior,
+/// así un mismo generador puede compartirse entre servicios e hilos.
+pub trait IdGenerator<T = u64>: Send + Sync {
+    /// Falla solo si el generador ya no puede dar ids válidos (Snowflake
+    /// pasado su rango de tiempo): el alta se rechaza en lugar de romper
+    /// el proceso.
+    fn next_id(&self) -> RepoResult<T>;
+}
+
+// ============================================================
+// SEQUENTIAL: 1, 2, 3, ... (determinista, ideal para tests)
+// ============================================================
+
+pub struct SequentialIdGenerator {
+    next: AtomicU64,
+}
+
+impl SequentialIdGenerator {
+    pub fn new() -> Self {
+        Self::starting_at(1)
+    }
+
+    pub fn starting_at(first: u64) -> Self {
+        Self {
+            next: AtomicU64::new(first),
+        }
+    }
+
+    /// Continúa después del mayor id existente (repositorio pre-cargado).
+    /// Si el mayor ya es `u64::MAX`, no queda ninguno después: error en
+    /// lugar de volver a 0.
+    pub fn after<I: Into<u64>>(existing: impl IntoIterator<Item = I>) -> RepoResult<Self> {
+        let max = existing.into_iter().map(Into::into).max().unwrap_or(0);
+        let first = max
+            .checked_add(1)
+            .ok_or_else(|| RepositoryError::Storage(format!("no ids left after {max}")))?;
+        Ok(Self::starting_at(first))
+    }
+
+    /// `after` con los ids de un repositorio. Si no se puede leer, el
+    /// error sube: arrancar en 1 haría chocar cada alta con un id viejo.
+    pub fn resuming<T, R>(repo: &R) -> RepoResult<Self>
+    where
+        T: Entity,
+        T::Id: Into<u64>,
+        R: Repository<T, T::Id>,
+    {
+        Self::after(repo.iter()?.map(|e| e.id()))
+    }
+}
+
+impl Default for SequentialIdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for SequentialIdGenerator {
+    fn next_id(&self) -> RepoResult<u64> {
+        Ok(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// ============================================================
+// RANDOM: id aleatorio (no revela cuántas entidades hay)
+// ============================================================
+
+/// Ids en `1..=i64::MAX`: SQLite guarda INTEGER con signo, y un u64
+/// con el bit alto prendido no entra.
+pub struct RandomIdGenerator {
+    rng: Mutex<StdRng>,
+}
+
+impl RandomIdGenerator {
+    pub fn new() -> Self {
+        Self {
+            rng: Mutex::new(StdRng::from_os_rng()),
+        }
+    }
+
+    /// Misma semilla → misma secuencia (tests reproducibles).
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Default for RandomIdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for RandomIdGenerator {
+    fn next_id(&self) -> RepoResult<u64> {
+        // 0 queda reservado como "sin id"
+        Ok(self.rng.lock().unwrap().random_range(1..=i64::MAX as u64))
+    }
+}
+
+// ============================================================
+// CLOCK (inyectable para generadores basados en tiempo)
+// ============================================================
+
+pub trait Clock: Send + Sync {
+    /// Milisegundos desde UNIX epoch.
+    fn now_millis(&self) -> u64;
+
+    /// La misma hora como fecha (timestamps de auditoría, soft delete).
+    /// Un reloj fuera del rango de chrono satura en lugar de fallar.
+    fn now(&self) -> DateTime<Utc> {
+        i64::try_from(self.now_millis())
+            .ok()
+            .and_then(DateTime::from_timestamp_millis)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// Reloj manual para tests: solo avanza cuando se le pide.
+pub struct ManualClock {
+    millis: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn new(millis: u64) -> Self {
+        Self {
+            millis: AtomicU64::new(millis),
+        }
+    }
+
+    pub fn advance(&self, millis: u64) {
+        self.millis.fetch_add(millis, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.millis.load(Ordering::Relaxed)
+    }
+}
+
+/// Un reloj compartido: quien lo avanza (un test) y quien lo lee (un
+/// servicio) ven la misma hora.
+impl<C: Clock + ?Sized> Clock for std::sync::Arc<C> {
+    fn now_millis(&self) -> u64 {
+        (**self).now_millis()
+    }
+}
+
+// ============================================================
+// SNOWFLAKE: ordenado por tiempo, sin coordinación entre nodos
+// ============================================================
+
+/// Nodo de un `SnowflakeIdGenerator`: 10 bits, `0..=NodeId::MAX`.
+/// Validado al construirlo, así el generador no puede recibir uno que
+/// pise los bits del timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeId(u16);
+
+impl NodeId {
+    pub const MAX: u16 = (1 << SnowflakeIdGenerator::NODE_BITS) - 1;
+
+    /// `None` si no entra en 10 bits.
+    pub const fn new(id: u16) -> Option<Self> {
+        if id <= Self::MAX {
+            Some(Self(id))
+        } else {
+            None
+        }
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+/// | 41 bits: ms desde `epoch` | 10 bits: nodo | 12 bits: secuencia |
+///
+/// Los 41 bits alcanzan ~69 años desde `epoch`; pasado ese límite
+/// `next_id` devuelve error en lugar de pisar el bit de signo o los nodos.
+pub struct SnowflakeIdGenerator {
+    node_id: u64,
+    epoch_millis: u64,
+    clock: Box<dyn Clock>,
+    // (último ms usado, secuencia dentro de ese ms)
+    state: Mutex<(u64, u64)>,
+}
+
+impl SnowflakeIdGenerator {
+    const NODE_BITS: u32 = 10;
+    const SEQUENCE_BITS: u32 = 12;
+    const MAX_NODE: u64 = (1 << Self::NODE_BITS) - 1;
+    const MAX_SEQUENCE: u64 = (1 << Self::SEQUENCE_BITS) - 1;
+    const MILLIS_BITS: u32 = 41;
+    const MAX_MILLIS: u64 = (1 << Self::MILLIS_BITS) - 1;
+
+    /// Epoch propio (2024-01-01) para que los 41 bits duren ~69 años.
+    pub const DEFAULT_EPOCH_MILLIS: u64 = 1_704_067_200_000;
+
+    pub fn new(node_id: NodeId) -> Self {
+        Self::with_clock(node_id, Self::DEFAULT_EPOCH_MILLIS, Box::new(SystemClock))
+    }
+
+    pub fn with_clock(node_id: NodeId, epoch_millis: u64, clock: Box<dyn Clock>) -> Self {
+        Self {
+            node_id: u64::from(node_id.get()),
+            epoch_millis,
+            clock,
+            state: Mutex::new((0, 0)),
+        }
+    }
+
+    /// Descompone un id en (ms desde epoch, nodo, secuencia).
+    pub fn decompose(id: u64) -> (u64, u64, u64) {
+        let sequence = id & Self::MAX_SEQUENCE;
+        let node = (id >> Self::SEQUENCE_BITS) & Self::MAX_NODE;
+        let millis = id >> (Self::SEQUENCE_BITS + Self::NODE_BITS);
+        (millis, node, sequence)
+    }
+}
+
+impl IdGenerator for SnowflakeIdGenerator {
+    fn next_id(&self) -> RepoResult<u64> {
+        let now = self.clock.now_millis().saturating_sub(self.epoch_millis);
+        let mut state = self.state.lock().unwrap();
+        let (last, sequence) = *state;
+
+        // Si el reloj retrocede, seguimos desde el último ms usado (monótono)
+        let (millis, sequence) = if now > last {
+            (now, 0)
+        } else if sequence < Self::MAX_SEQUENCE {
+            (last, sequence + 1)
+        } else {
+            // Secuencia agotada en este ms: "tomamos prestado" el siguiente
+            (last + 1, 0)
+        };
+
+        if millis > Self::MAX_MILLIS {
+            return Err(RepositoryError::Storage(format!(
+                "snowflake timestamp {millis} does not fit in {} bits (epoch too old)",
+                Self::MILLIS_BITS
+            )));
+        }
+
+        *state = (millis, sequence);
+        Ok((millis << (Self::NODE_BITS + Self::SEQUENCE_BITS))
+            | (self.node_id << Self::SEQUENCE_BITS)
+            | sequence)
+    }
+}
+
+// ============================================================
+// UUID (128 bits, formato RFC 9562)
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Uuid(u128);
+
+impl Uuid {
+    pub fn from_u128(value: u128) -> Self {
+        Self(value)
+    }
+
+    pub fn as_u128(self) -> u128 {
+        self.0
+    }
+
+    pub fn version(self) -> u8 {
+        ((self.0 >> 76) & 0xF) as u8
+    }
+
+    // Fija los bits de versión (4) y variante (10xx)
+    fn with_version(bits: u128, version: u8) -> Self {
+        let bits = bits & !(0xF << 76) | (u128::from(version) << 76);
+        let bits = bits & !(0b11 << 62) | (0b10 << 62);
+        Self(bits)
+    }
+}
+
+impl fmt::Display for Uuid {
+    // xxxxxxxx-xxxx-Vxxx-xxxx-xxxxxxxxxxxx
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            (v >> 96) as u32,
+            (v >> 80) as u16,
+            (v >> 64) as u16,
+            (v >> 48) as u16,
+            v & 0xFFFF_FFFF_FFFF
+        )
+    }
+}
+
+/// UUIDv4: 122 bits aleatorios.
+pub struct UuidV4Generator {
+    rng: Mutex<StdRng>,
+}
+
+impl UuidV4Generator {
+    pub fn new() -> Self {
+        Self {
+            rng: Mutex::new(StdRng::from_os_rng()),
+        }
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl Default for UuidV4Generator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator<Uuid> for UuidV4Generator {
+    fn next_id(&self) -> RepoResult<Uuid> {
+        let bits: u128 = self.rng.lock().unwrap().random();
+        Ok(Uuid::with_version(bits, 4))
+    }
+}
+
+/// UUIDv7: 48 bits de timestamp (ms) + contador de 12 bits + aleatorio
+/// → ordenable por creación, también dentro de un mismo ms.
+///
+/// Contador en `rand_a` (RFC 9562 §6.2, método 1): arranca en 0 en cada
+/// ms nuevo y sube con cada id del mismo ms.
+pub struct UuidV7Generator {
+    clock: Box<dyn Clock>,
+    // (último ms usado, contador dentro de ese ms, rng)
+    state: Mutex<(u64, u16, StdRng)>,
+}
+
+impl UuidV7Generator {
+    const MAX_COUNTER: u16 = (1 << 12) - 1;
+
+    pub fn new() -> Self {
+        Self::with_clock(Box::new(SystemClock), StdRng::from_os_rng())
+    }
+
+    pub fn with_clock(clock: Box<dyn Clock>, rng: StdRng) -> Self {
+        Self {
+            clock,
+            state: Mutex::new((0, 0, rng)),
+        }
+    }
+}
+
+impl Default for UuidV7Generator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator<Uuid> for UuidV7Generator {
+    fn next_id(&self) -> RepoResult<Uuid> {
+        let now = self.clock.now_millis() & 0xFFFF_FFFF_FFFF;
+        let mut state = self.state.lock().unwrap();
+        let (last, counter, rng) = &mut *state;
+
+        // Igual que Snowflake: reloj que retrocede o contador agotado →
+        // se sigue desde el último ms usado (monótono)
+        let (millis, next) = if now > *last {
+            (now, 0)
+        } else if *counter < Self::MAX_COUNTER {
+            (*last, *counter + 1)
+        } else {
+            (*last + 1, 0)
+        };
+        (*last, *counter) = (millis, next);
+
+        let random: u128 = rng.random::<u128>() & ((1 << 62) - 1);
+        let bits = (u128::from(millis) << 80) | (u128::from(next) << 64) | random;
+        Ok(Uuid::with_version(bits, 7))
+    }
+}
+
+/*
+ESTRATEGIAS:
+
+┌────────────┬──────────┬───────────┬──────────────┬─────────────────┐
+│ Estrategia │ Tamaño   │ Ordenable │ Determinista │ Uso típico      │
+├────────────┼──────────┼───────────┼──────────────┼─────────────────┤
+│ Sequential │ u64      │ ✓         │ ✓            │ tests, 1 nodo   │
+│ Random     │ u64      │ ✗         │ con semilla  │ ids no adivinab.│
+│ Snowflake  │ u64      │ ✓ (tiempo)│ con Clock    │ multi-nodo      │
+│ UUIDv4     │ 128 bits │ ✗         │ con semilla  │ ids globales    │
+│ UUIDv7     │ 128 bits │ ✓ (tiempo)│ con Clock    │ ids globales    │
+└────────────┴──────────┴───────────┴──────────────┴─────────────────┘
+
+Los servicios reciben `Box<dyn IdGenerator>` → el id nunca depende
+de cuántas entidades hay en el repositorio.
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::testing::FaultyRepository;
+    use std::collections::HashSet;
+
+    fn node(id: u16) -> NodeId {
+        NodeId::new(id).unwrap()
+    }
+
+    #[test]
+    fn test_sequential() {
+        let ids = SequentialIdGenerator::starting_at(10);
+        assert_eq!(
+            (
+                ids.next_id().unwrap(),
+                ids.next_id().unwrap(),
+                ids.next_id().unwrap()
+            ),
+            (10, 11, 12)
+        );
+
+        let resumed = SequentialIdGenerator::after([3u64, 9, 4]).unwrap();
+        assert_eq!(resumed.next_id().unwrap(), 10);
+    }
+
+    #[test]
+    fn test_sequential_after_max_id_is_an_error() {
+        let result = SequentialIdGenerator::after([u64::MAX]);
+
+        assert!(matches!(result, Err(RepositoryError::Storage(_))));
+    }
+
+    #[test]
+    fn test_resuming_propagates_read_errors() {
+        #[derive(Clone)]
+        struct Item(u64);
+        impl Entity for Item {
+            type Id = u64;
+            fn id(&self) -> u64 {
+                self.0
+            }
+        }
+
+        let (mut repo, faults) = FaultyRepository::new();
+        repo.save(Item(7)).unwrap();
+        assert_eq!(
+            SequentialIdGenerator::resuming(&repo)
+                .unwrap()
+                .next_id()
+                .unwrap(),
+            8
+        );
+
+        faults.fail_reads(true);
+        assert!(SequentialIdGenerator::resuming(&repo).is_err());
+    }
+
+    #[test]
+    fn test_random_is_reproducible_with_seed() {
+        let a = RandomIdGenerator::seeded(42);
+        let b = RandomIdGenerator::seeded(42);
+
+        let first: Vec<u64> = (0..5).map(|_| a.next_id().unwrap()).collect();
+        let second: Vec<u64> = (0..5).map(|_| b.next_id().unwrap()).collect();
+        assert_eq!(first, second);
+        assert!(first.iter().all(|&id| (1..=i64::MAX as u64).contains(&id)));
+    }
+
+    #[test]
+    fn test_snowflake_is_monotonic_and_unique() {
+        let epoch = SnowflakeIdGenerator::DEFAULT_EPOCH_MILLIS;
+        let clock = ManualClock::new(epoch + 1_000);
+        let ids = SnowflakeIdGenerator::with_clock(node(3), epoch, Box::new(clock));
+
+        // Más ids que secuencias por ms: fuerza el "préstamo" del ms siguiente
+        let generated: Vec<u64> = (0..5_000).map(|_| ids.next_id().unwrap()).collect();
+
+        assert!(generated.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(SnowflakeIdGenerator::decompose(generated[0]), (1_000, 3, 0));
+        assert_eq!(
+            SnowflakeIdGenerator::decompose(generated[4_096]),
+            (1_001, 3, 0)
+        );
+    }
+
+    #[test]
+    fn test_snowflake_survives_clock_going_backwards() {
+        let epoch = 0;
+        let clock = std::sync::Arc::new(ManualClock::new(500));
+
+        struct Shared(std::sync::Arc<ManualClock>);
+        impl Clock for Shared {
+            fn now_millis(&self) -> u64 {
+                self.0.now_millis()
+            }
+        }
+
+        let ids = SnowflakeIdGenerator::with_clock(node(1), epoch, Box::new(Shared(clock.clone())));
+        let before = ids.next_id().unwrap();
+        clock.millis.store(100, Ordering::Relaxed); // el reloj retrocede
+        let after = ids.next_id().unwrap();
+
+        assert!(after > before);
+    }
+
+    #[test]
+    fn test_snowflake_rejects_timestamps_beyond_41_bits() {
+        let clock = std::sync::Arc::new(ManualClock::new((1 << 41) - 1));
+        let ids = SnowflakeIdGenerator::with_clock(node(1), 0, Box::new(clock.clone()));
+        let last = ids.next_id().unwrap();
+
+        clock.advance(1);
+        assert!(matches!(ids.next_id(), Err(RepositoryError::Storage(_))));
+        // El error no avanza el estado: sigue fallando en vez de reciclar
+        assert!(ids.next_id().is_err());
+        assert_eq!(SnowflakeIdGenerator::decompose(last).0, (1 << 41) - 1);
+    }
+
+    #[test]
+    fn test_node_id_must_fit_in_10_bits() {
+        assert_eq!(NodeId::new(1023).map(NodeId::get), Some(1023));
+        assert_eq!(NodeId::new(1024), None);
+    }
+
+    #[test]
+    fn test_uuid_versions_and_format() {
+        let v4 = UuidV4Generator::seeded(1).next_id().unwrap();
+        assert_eq!(v4.version(), 4);
+
+        let s = v4.to_string();
+        assert_eq!(s.len(), 36);
+        assert_eq!(&s[14..15], "4");
+        assert!(matches!(&s[19..20], "8" | "9" | "a" | "b"));
+
+        let gen_v7 = UuidV7Generator::with_clock(
+            Box::new(ManualClock::new(1_700_000_000_000)),
+            StdRng::seed_from_u64(1),
+        );
+        let v7 = gen_v7.next_id().unwrap();
+        assert_eq!(v7.version(), 7);
+        assert_eq!(v7.as_u128() >> 80, 1_700_000_000_000);
+    }
+
+    #[test]
+    fn test_uuid_v7_is_monotonic_within_a_millisecond() {
+        let ids = UuidV7Generator::with_clock(
+            Box::new(ManualClock::new(1_700_000_000_000)),
+            StdRng::seed_from_u64(1),
+        );
+
+        // Más ids que valores del contador: fuerza el ms siguiente
+        let generated: Vec<Uuid> = (0..5_000).map(|_| ids.next_id().unwrap()).collect();
+
+        assert!(generated.windows(2).all(|w| w[0] < w[1]));
+        assert!(generated.iter().all(|id| id.version() == 7));
+        assert_eq!(generated[4_096].as_u128() >> 80, 1_700_000_000_001);
+    }
+
+    #[test]
+    fn test_uuid_v4_uniqueness() {
+        let ids = UuidV4Generator::new();
+        let set: HashSet<Uuid> = (0..1_000).map(|_| ids.next_id().unwrap()).collect();
+        assert_eq!(set.len(), 1_000);
+    }
+}
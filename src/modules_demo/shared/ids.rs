@@ -0,0 +1,136 @@
+// IDs tipados (newtype pattern): un OrderId no se puede pasar donde va un UserId
+// Mismo layout que u64 (zero-cost), pero el compilador distingue cada entidad
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid {kind}: {input:?}")]
+pub struct ParseIdError {
+    pub kind: &'static str,
+    pub input: String,
+}
+
+macro_rules! define_id {
+    ($($(#[$meta:meta])* $name:ident),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            #[derive(
+                Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+            )]
+            #[serde(transparent)] // JSON: 42, no {"0": 42}
+            pub struct $name(pub u64);
+
+            impl $name {
+                pub const fn new(value: u64) -> Self {
+                    Self(value)
+                }
+
+                pub const fn value(self) -> u64 {
+                    self.0
+                }
+            }
+
+            impl From<u64> for $name {
+                fn from(value: u64) -> Self {
+                    Self(value)
+                }
+            }
+
+            impl From<$name> for u64 {
+                fn from(id: $name) -> Self {
+                    id.0
+                }
+            }
+
+            impl fmt::Display for $name {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "{}", self.0)
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = ParseIdError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    s.trim().parse().map(Self).map_err(|_| ParseIdError {
+                        kind: stringify!($name),
+                        input: s.to_string(),
+                    })
+                }
+            }
+        )*
+    };
+}
+
+define_id!(
+    /// Identificador de User
+    ///
+    /// ```compile_fail
+    /// use rust_concepts::modules_demo::shared::{OrderId, UserId};
+    ///
+    /// fn find_user(id: UserId) {}
+    /// find_user(OrderId(1)); // ✗ expected `UserId`, found `OrderId`
+    /// ```
+    UserId,
+    /// Identificador de Order
+    OrderId,
+    /// Identificador de Payment
+    PaymentId,
+    /// Identificador de Product
+    ProductId,
+    /// Identificador de Cart
+    CartId,
+);
+
+/*
+NEWTYPE IDs:
+
+    // Antes: todo es u64, el compilador no distingue
+    fn create_order(user_id: u64, ...)
+    create_order(order.id, ...)        // ✓ compila... y es un bug
+
+    // Después: cada entidad tiene su tipo
+    fn create_order(user_id: UserId, ...)
+    create_order(order.id, ...)        // ✗ expected UserId, found OrderId
+
+- Zero-cost: size_of::<UserId>() == size_of::<u64>()
+- Display/FromStr: "42" ↔ UserId(42) (URLs, CLI)
+- serde(transparent): se serializa como el número, sin envoltorio
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_and_parse() {
+        let id: UserId = "42".parse().unwrap();
+
+        assert_eq!(id, UserId(42));
+        assert_eq!(id.to_string(), "42");
+        assert_eq!(
+            "abc".parse::<OrderId>(),
+            Err(ParseIdError {
+                kind: "OrderId",
+                input: "abc".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_serde_is_transparent() {
+        let json = serde_json::to_string(&PaymentId(7)).unwrap();
+        assert_eq!(json, "7");
+
+        let back: PaymentId = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, PaymentId(7));
+    }
+
+    #[test]
+    fn test_zero_cost() {
+        assert_eq!(size_of::<ProductId>(), size_of::<u64>());
+    }
+}
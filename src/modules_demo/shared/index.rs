@@ -0,0 +1,262 @@
+// Índices secundarios en memoria: valor → id, sin recorrer todas las entidades
+// Los declara la entidad (unique_keys / index_keys); el backend solo los mantiene
+
+use super::repository::{Entity, RepoResult, RepositoryError};
+use std::collections::{HashMap, HashSet};
+
+/// Índices de un repositorio, mantenidos en cada save/delete.
+///
+/// - únicos (`Entity::unique_keys`): valor → un id; un segundo id con el
+///   mismo valor es `RepositoryError::UniqueViolation`
+/// - multi-valor (`Entity::index_keys`): valor → conjunto de ids
+///
+/// Guarda además las claves vigentes de cada id, así quitar o reemplazar
+/// una entidad no necesita la versión anterior (el log store no la tiene
+/// en memoria).
+pub struct SecondaryIndexes<T: Entity> {
+    unique: HashMap<&'static str, HashMap<String, T::Id>>,
+    multi: HashMap<&'static str, HashMap<String, HashSet<T::Id>>>,
+    keys_of: HashMap<T::Id, IndexedKeys>,
+}
+
+#[derive(Clone)]
+struct IndexedKeys {
+    unique: Vec<(&'static str, String)>,
+    multi: Vec<(&'static str, String)>,
+}
+
+// Manual: derive(Clone) exigiría T: Clone sobre el tipo, no sobre los ids
+impl<T: Entity> Clone for SecondaryIndexes<T> {
+    fn clone(&self) -> Self {
+        Self {
+            unique: self.unique.clone(),
+            multi: self.multi.clone(),
+            keys_of: self.keys_of.clone(),
+        }
+    }
+}
+
+impl<T: Entity> Default for SecondaryIndexes<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Entity> SecondaryIndexes<T> {
+    pub fn new() -> Self {
+        Self {
+            unique: HashMap::new(),
+            multi: HashMap::new(),
+            keys_of: HashMap::new(),
+        }
+    }
+
+    /// Indexa entidades ya persistidas (al abrir un archivo, al reproducir
+    /// un log). Un valor único repetido significa datos inconsistentes.
+    pub fn build<'a>(entities: impl IntoIterator<Item = &'a T>) -> RepoResult<Self>
+    where
+        T: 'a,
+    {
+        let mut indexes = Self::new();
+        for entity in entities {
+            indexes
+                .insert(entity)
+                .map_err(|e| RepositoryError::Corrupted(e.to_string()))?;
+        }
+        Ok(indexes)
+    }
+
+    /// Verifica sin modificar nada: otro id ya tiene alguno de los valores
+    /// únicos de `entity` → `UniqueViolation`. El propio id no cuenta, así
+    /// un update no choca consigo mismo.
+    pub fn check(&self, entity: &T) -> RepoResult<()> {
+        let id = entity.id();
+        for (index, key) in entity.unique_keys() {
+            match self.unique_id(index, &key) {
+                Some(owner) if owner != id => {
+                    return Err(RepositoryError::UniqueViolation(index.to_string()));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Indexa `entity`, reemplazando las claves de su versión anterior.
+    pub fn insert(&mut self, entity: &T) -> RepoResult<()> {
+        self.check(entity)?;
+
+        let id = entity.id();
+        self.remove(id);
+
+        let keys = IndexedKeys {
+            unique: entity.unique_keys(),
+            multi: entity.index_keys(),
+        };
+        for (index, key) in &keys.unique {
+            self.unique
+                .entry(index)
+                .or_default()
+                .insert(key.clone(), id);
+        }
+        for (index, key) in &keys.multi {
+            self.multi
+                .entry(index)
+                .or_default()
+                .entry(key.clone())
+                .or_default()
+                .insert(id);
+        }
+        self.keys_of.insert(id, keys);
+        Ok(())
+    }
+
+    /// Quita todas las claves de `id` (no hace nada si no estaba).
+    pub fn remove(&mut self, id: T::Id) {
+        let Some(keys) = self.keys_of.remove(&id) else {
+            return;
+        };
+
+        for (index, key) in keys.unique {
+            if let Some(values) = self.unique.get_mut(index) {
+                values.remove(&key);
+            }
+        }
+        for (index, key) in keys.multi {
+            if let Some(values) = self.multi.get_mut(index)
+                && let Some(ids) = values.get_mut(&key)
+            {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    values.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Dueño de `key` en un índice único.
+    pub fn unique_id(&self, index: &str, key: &str) -> Option<T::Id> {
+        self.unique.get(index)?.get(key).copied()
+    }
+
+    /// Ids con `key` en un índice multi-valor (orden no especificado).
+    pub fn ids(&self, index: &str, key: &str) -> Vec<T::Id> {
+        self.multi
+            .get(index)
+            .and_then(|values| values.get(key))
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/*
+CÓMO SE MANTIENEN:
+
+  save(user 7, email "a@x")          unique["users.email"]
+     │ check: ¿"a@x" tiene otro id?    "a@x" → 7
+     │ remove(7): saca "old@x"          ─────────
+     └ insert: "a@x" → 7             keys_of
+                                        7 → [users.email = "a@x"]
+  delete(7)
+     └ remove(7): keys_of[7] dice qué entradas borrar
+
+  save(order 3, user 1)              multi["orders.user_id"]
+                                        "1" → {3, 5, 9}
+
+- find_unique / find_indexed: O(1) en vez de recorrer storage.values()
+- La unicidad la detecta el índice (check), no el servicio
+- SQLite no usa esto: tiene UNIQUE e INDEX en el esquema
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct Account {
+        id: u64,
+        email: String,
+        team: u32,
+    }
+
+    impl Entity for Account {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.id
+        }
+
+        fn unique_keys(&self) -> Vec<(&'static str, String)> {
+            vec![("accounts.email", self.email.clone())]
+        }
+
+        fn index_keys(&self) -> Vec<(&'static str, String)> {
+            vec![("accounts.team", self.team.to_string())]
+        }
+    }
+
+    fn account(id: u64, email: &str, team: u32) -> Account {
+        Account {
+            id,
+            email: email.to_string(),
+            team,
+        }
+    }
+
+    fn sorted(mut ids: Vec<u64>) -> Vec<u64> {
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_update_moves_keys() {
+        let mut indexes = SecondaryIndexes::new();
+        indexes.insert(&account(1, "a@x", 10)).unwrap();
+        indexes.insert(&account(2, "b@x", 10)).unwrap();
+
+        indexes.insert(&account(1, "new@x", 20)).unwrap();
+
+        assert_eq!(indexes.unique_id("accounts.email", "a@x"), None);
+        assert_eq!(indexes.unique_id("accounts.email", "new@x"), Some(1));
+        assert_eq!(indexes.ids("accounts.team", "10"), vec![2]);
+        assert_eq!(indexes.ids("accounts.team", "20"), vec![1]);
+    }
+
+    #[test]
+    fn test_unique_violation_changes_nothing() {
+        let mut indexes = SecondaryIndexes::new();
+        indexes.insert(&account(1, "a@x", 10)).unwrap();
+
+        assert_eq!(
+            indexes.insert(&account(2, "a@x", 10)),
+            Err(RepositoryError::UniqueViolation(
+                "accounts.email".to_string()
+            ))
+        );
+        assert_eq!(indexes.unique_id("accounts.email", "a@x"), Some(1));
+        assert_eq!(indexes.ids("accounts.team", "10"), vec![1]);
+    }
+
+    #[test]
+    fn test_remove_frees_keys() {
+        let mut indexes = SecondaryIndexes::new();
+        indexes.insert(&account(1, "a@x", 10)).unwrap();
+        indexes.insert(&account(2, "b@x", 10)).unwrap();
+
+        indexes.remove(1);
+
+        assert_eq!(indexes.unique_id("accounts.email", "a@x"), None);
+        assert_eq!(sorted(indexes.ids("accounts.team", "10")), vec![2]);
+        indexes.insert(&account(3, "a@x", 10)).unwrap();
+    }
+
+    #[test]
+    fn test_build_rejects_duplicates_as_corruption() {
+        let accounts = [account(1, "a@x", 1), account(2, "a@x", 1)];
+
+        assert!(matches!(
+            SecondaryIndexes::build(&accounts),
+            Err(RepositoryError::Corrupted(_))
+        ));
+    }
+}
@@ -0,0 +1,381 @@
+// Repository en archivo JSON: mismo contrato que InMemoryRepository, pero persistente
+// Lecturas desde memoria, cada escritura reescribe el archivo de forma atómica
+
+use super::checksum::fnv1a64;
+use super::index::SecondaryIndexes;
+use super::repository::{Entity, RepoResult, Repository, RepositoryError};
+use super::unit_of_work::Transactional;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{ErrorKind as IoErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+const FORMAT_VERSION: u32 = 1;
+
+/// Contenido del archivo: los datos más un checksum de esos datos.
+#[derive(Serialize, Deserialize)]
+struct Snapshot<T> {
+    version: u32,
+    checksum: String,
+    entities: Vec<T>,
+}
+
+pub struct JsonFileRepository<T: Entity> {
+    path: PathBuf,
+    storage: HashMap<T::Id, T>,
+    indexes: SecondaryIndexes<T>,
+    /// Estado al hacer `begin`: mientras exista, las escrituras quedan en
+    /// memoria y el archivo se reescribe una sola vez en `commit`.
+    backup: Option<Backup<T>>,
+}
+
+type Backup<T> = (HashMap<<T as Entity>::Id, T>, SecondaryIndexes<T>);
+
+impl<T> JsonFileRepository<T>
+where
+    T: Entity + Serialize + DeserializeOwned,
+    T::Id: Ord,
+{
+    /// Abre (o prepara) el archivo y carga su contenido en memoria.
+    /// Un archivo inexistente es un repositorio vacío; uno ilegible es
+    /// `RepositoryError::Corrupted`.
+    pub fn open(path: impl Into<PathBuf>) -> RepoResult<Self> {
+        let path = path.into();
+        let storage = match fs::read(&path) {
+            Ok(bytes) => Self::decode(&path, &bytes)?,
+            Err(e) if e.kind() == IoErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(storage_error(&path, e)),
+        };
+
+        let indexes = SecondaryIndexes::build(storage.values())?;
+
+        Ok(Self {
+            path,
+            storage,
+            indexes,
+            backup: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn decode(path: &Path, bytes: &[u8]) -> RepoResult<HashMap<T::Id, T>> {
+        let corrupted =
+            |reason: String| RepositoryError::Corrupted(format!("{}: {}", path.display(), reason));
+
+        let snapshot: Snapshot<T> =
+            serde_json::from_slice(bytes).map_err(|e| corrupted(e.to_string()))?;
+
+        if snapshot.version != FORMAT_VERSION {
+            return Err(corrupted(format!(
+                "unsupported format version {}",
+                snapshot.version
+            )));
+        }
+
+        let expected = checksum(&snapshot.entities)?;
+        if snapshot.checksum != expected {
+            return Err(corrupted(format!(
+                "checksum mismatch (stored {}, computed {})",
+                snapshot.checksum, expected
+            )));
+        }
+
+        let mut storage = HashMap::with_capacity(snapshot.entities.len());
+        for entity in snapshot.entities {
+            let id = entity.id();
+            if storage.insert(id, entity).is_some() {
+                return Err(corrupted(format!("duplicate id {:?}", id)));
+            }
+        }
+        Ok(storage)
+    }
+
+    // Escribe a `<archivo>.tmp`, fsync y rename: quien lea el archivo ve
+    // la versión anterior completa o la nueva completa, nunca una mitad.
+    fn persist(&self, storage: &HashMap<T::Id, T>) -> RepoResult<()> {
+        let mut entities: Vec<&T> = storage.values().collect();
+        entities.sort_by_key(|e| e.id());
+
+        let snapshot = Snapshot {
+            version: FORMAT_VERSION,
+            checksum: checksum(&entities)?,
+            entities,
+        };
+        let bytes = serde_json::to_vec_pretty(&snapshot)
+            .map_err(|e| RepositoryError::Storage(e.to_string()))?;
+
+        let tmp = self.path.with_extension("json.tmp");
+        let write = || -> std::io::Result<()> {
+            let mut file = File::create(&tmp)?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+            fs::rename(&tmp, &self.path)
+        };
+
+        write().map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            storage_error(&self.path, e)
+        })
+    }
+
+    // Primero se escribe el disco y recién después la memoria: si el
+    // archivo falla, el repositorio queda como estaba. Dentro de una
+    // transacción el disco espera al commit.
+    fn write(&mut self, storage: HashMap<T::Id, T>) -> RepoResult<()> {
+        if self.backup.is_none() {
+            self.persist(&storage)?;
+        }
+        self.storage = storage;
+        Ok(())
+    }
+}
+
+fn checksum<T: Serialize>(entities: &[T]) -> RepoResult<String> {
+    let bytes =
+        serde_json::to_vec(entities).map_err(|e| RepositoryError::Storage(e.to_string()))?;
+    Ok(format!("{:016x}", fnv1a64(&bytes)))
+}
+
+fn storage_error(path: &Path, e: std::io::Error) -> RepositoryError {
+    RepositoryError::Storage(format!("{}: {}", path.display(), e))
+}
+
+impl<T> Repository<T, T::Id> for JsonFileRepository<T>
+where
+    T: Entity + Serialize + DeserializeOwned,
+    T::Id: Ord,
+{
+    fn save(&mut self, entity: T) -> RepoResult<()> {
+        // El índice se toca recién con el archivo escrito
+        self.indexes.check(&entity)?;
+        let mut next = self.storage.clone();
+        next.insert(entity.id(), entity.clone());
+        self.write(next)?;
+        self.indexes.insert(&entity)
+    }
+
+    fn find_by_id(&self, id: T::Id) -> RepoResult<Option<T>> {
+        Ok(self.storage.get(&id).cloned())
+    }
+
+    fn delete(&mut self, id: T::Id) -> RepoResult<Option<T>> {
+        let mut next = self.storage.clone();
+        let removed = next.remove(&id);
+        if removed.is_some() {
+            self.write(next)?;
+            self.indexes.remove(id);
+        }
+        Ok(removed)
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = T> + '_>> {
+        Ok(Box::new(self.storage.values().cloned()))
+    }
+
+    fn exists(&self, id: T::Id) -> RepoResult<bool> {
+        Ok(self.storage.contains_key(&id))
+    }
+
+    fn count(&self) -> RepoResult<usize> {
+        Ok(self.storage.len())
+    }
+
+    fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        Ok(self
+            .indexes
+            .unique_id(index, key)
+            .and_then(|id| self.storage.get(&id).cloned()))
+    }
+
+    fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        Ok(self
+            .indexes
+            .ids(index, key)
+            .iter()
+            .filter_map(|id| self.storage.get(id).cloned())
+            .collect())
+    }
+}
+
+impl<T> Transactional for JsonFileRepository<T>
+where
+    T: Entity + Serialize + DeserializeOwned,
+    T::Id: Ord,
+{
+    fn begin(&mut self) -> RepoResult<()> {
+        if self.backup.is_some() {
+            return Err(RepositoryError::Storage(
+                "transaction already in progress".to_string(),
+            ));
+        }
+        self.backup = Some((self.storage.clone(), self.indexes.clone()));
+        Ok(())
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        let (storage, indexes) = self
+            .backup
+            .take()
+            .ok_or_else(|| RepositoryError::Storage("no transaction in progress".to_string()))?;
+
+        // Si el archivo no se pudo escribir, la memoria vuelve a coincidir
+        // con el disco: la transacción no ocurrió
+        if let Err(e) = self.persist(&self.storage) {
+            self.storage = storage;
+            self.indexes = indexes;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        let (storage, indexes) = self
+            .backup
+            .take()
+            .ok_or_else(|| RepositoryError::Storage("no transaction in progress".to_string()))?;
+        self.storage = storage;
+        self.indexes = indexes;
+        Ok(())
+    }
+}
+
+/*
+FORMATO Y GARANTÍAS:
+
+  users.json
+  {
+    "version": 1,
+    "checksum": "9f1c...",        ← FNV-1a de `entities` serializado
+    "entities": [ {...}, {...} ]  ← ordenadas por id (diffs estables)
+  }
+
+- open(): archivo ausente → vacío; JSON inválido, versión desconocida,
+  checksum distinto, ids o claves únicas duplicadas → RepositoryError::Corrupted
+- Índices secundarios (index.rs) reconstruidos en open(), solo en memoria
+- Escritura atómica: users.json.tmp + fsync + rename
+- Si la escritura falla, la memoria NO cambia (disco primero)
+- Cada save reescribe todo el archivo: O(n), pensado para datasets chicos
+- Transactional: begin guarda una copia en memoria, las escrituras no
+  tocan el disco, commit reescribe el archivo una vez (rollback: la copia)
+*/
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// Ruta única en el directorio temporal; se borra al salir del scope.
+    pub(crate) struct TempPath(pub PathBuf);
+
+    impl TempPath {
+        pub(crate) fn new(name: &str) -> Self {
+            static COUNTER: AtomicU64 = AtomicU64::new(0);
+            let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let file = format!("modules_demo_{}_{}_{}.json", std::process::id(), n, name);
+            Self(std::env::temp_dir().join(file))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+            let _ = fs::remove_file(self.0.with_extension("json.tmp"));
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        id: u64,
+        text: String,
+    }
+
+    impl Entity for Note {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.id
+        }
+    }
+
+    fn note(id: u64, text: &str) -> Note {
+        Note {
+            id,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_persists_across_reopen() {
+        let path = TempPath::new("reopen");
+
+        let mut repo = JsonFileRepository::open(&path.0).unwrap();
+        repo.save(note(1, "a")).unwrap();
+        repo.save(note(2, "b")).unwrap();
+        repo.delete(1).unwrap();
+        drop(repo);
+
+        let reopened = JsonFileRepository::<Note>::open(&path.0).unwrap();
+        assert_eq!(reopened.find_all().unwrap(), vec![note(2, "b")]);
+        assert!(!path.0.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn test_missing_file_is_empty() {
+        let path = TempPath::new("missing");
+
+        let repo = JsonFileRepository::<Note>::open(&path.0).unwrap();
+        assert_eq!(repo.count().unwrap(), 0);
+        assert!(!path.0.exists());
+    }
+
+    #[test]
+    fn test_detects_corruption() {
+        let path = TempPath::new("corrupt");
+        let mut repo = JsonFileRepository::open(&path.0).unwrap();
+        repo.save(note(1, "original")).unwrap();
+
+        // Alguien edita el archivo a mano: el JSON es válido, el checksum no
+        let edited = fs::read_to_string(&path.0)
+            .unwrap()
+            .replace("original", "tampered");
+        fs::write(&path.0, edited).unwrap();
+
+        let err = JsonFileRepository::<Note>::open(&path.0).err().unwrap();
+        assert!(matches!(&err, RepositoryError::Corrupted(msg) if msg.contains("checksum")));
+
+        // Escritura truncada: ni siquiera es JSON
+        fs::write(&path.0, "{\"version\": 1, \"entit").unwrap();
+        let err = JsonFileRepository::<Note>::open(&path.0).err().unwrap();
+        assert!(matches!(err, RepositoryError::Corrupted(_)));
+    }
+
+    #[test]
+    fn test_transaction_writes_file_only_on_commit() {
+        let path = TempPath::new("transaction");
+        let reopen = || {
+            JsonFileRepository::<Note>::open(&path.0)
+                .unwrap()
+                .find_all()
+        };
+        let mut repo = JsonFileRepository::open(&path.0).unwrap();
+        repo.save(note(1, "a")).unwrap();
+
+        repo.begin().unwrap();
+        repo.save(note(2, "b")).unwrap();
+        assert_eq!(repo.count().unwrap(), 2);
+        assert_eq!(reopen().unwrap(), vec![note(1, "a")]);
+        repo.rollback().unwrap();
+        assert_eq!(repo.find_all().unwrap(), vec![note(1, "a")]);
+
+        repo.begin().unwrap();
+        repo.delete(1).unwrap();
+        repo.save(note(3, "c")).unwrap();
+        repo.commit().unwrap();
+        assert_eq!(reopen().unwrap(), vec![note(3, "c")]);
+    }
+}
@@ -0,0 +1,532 @@
+// Storage log-structured (estilo Bitcask): solo se agrega al final del archivo
+// save/delete = un registro nuevo; el índice en memoria apunta al último de cada id
+
+use super::checksum::fnv1a64;
+use super::index::SecondaryIndexes;
+use super::repository::{Entity, RepoResult, Repository, RepositoryError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// | len: u32 LE | checksum: u64 LE | payload: `len` bytes (JSON) |
+const HEADER_LEN: u64 = 4 + 8;
+
+#[derive(Serialize, Deserialize)]
+enum Record<T, Id> {
+    Save(T),
+    Delete(Id),
+}
+
+/// Posición del último registro `Save` vigente de una entidad.
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    offset: u64,
+    len: u32,
+}
+
+/// Métricas del log, útiles para decidir cuándo compactar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogStats {
+    /// Entidades vivas
+    pub live: usize,
+    /// Registros en el archivo (incluye versiones viejas y deletes)
+    pub records: usize,
+    pub file_bytes: u64,
+}
+
+pub struct LogStructuredRepository<T: Entity> {
+    path: PathBuf,
+    writer: File,
+    // Lecturas con &self: el handle de lectura necesita seek
+    reader: Mutex<File>,
+    index: HashMap<T::Id, Location>,
+    // Índices secundarios: se reconstruyen en el replay igual que `index`
+    indexes: SecondaryIndexes<T>,
+    records: usize,
+    len: u64,
+    /// Test: el próximo append escribe solo estos bytes y falla
+    #[cfg(test)]
+    tear_next_append: Option<usize>,
+}
+
+impl<T> LogStructuredRepository<T>
+where
+    T: Entity + Serialize + DeserializeOwned,
+    T::Id: Serialize + DeserializeOwned,
+{
+    /// Abre el log y reconstruye el índice reproduciéndolo desde el inicio.
+    ///
+    /// Una escritura cortada al final (crash a mitad de un append) se
+    /// trunca; un registro inválido seguido de registros válidos es
+    /// `RepositoryError::Corrupted`.
+    pub fn open(path: impl Into<PathBuf>) -> RepoResult<Self> {
+        let path = path.into();
+        let writer = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(&path)
+            .map_err(|e| storage_error(&path, e))?;
+        let reader = File::open(&path).map_err(|e| storage_error(&path, e))?;
+
+        let mut repo = Self {
+            path,
+            writer,
+            reader: Mutex::new(reader),
+            index: HashMap::new(),
+            indexes: SecondaryIndexes::new(),
+            records: 0,
+            len: 0,
+            #[cfg(test)]
+            tear_next_append: None,
+        };
+        repo.replay()?;
+        Ok(repo)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn stats(&self) -> LogStats {
+        LogStats {
+            live: self.index.len(),
+            records: self.records,
+            file_bytes: self.len,
+        }
+    }
+
+    /// Reescribe el log con un único `Save` por entidad viva: descarta
+    /// versiones viejas y deletes. Atómico (archivo nuevo + rename).
+    pub fn compact(&mut self) -> RepoResult<()> {
+        let tmp = self.path.with_extension("compact");
+        let live: Vec<T> = self.iter()?.collect();
+
+        let write = || -> std::io::Result<()> {
+            let mut file = File::create(&tmp)?;
+            for entity in live {
+                file.write_all(&encode(&Record::<T, T::Id>::Save(entity))?)?;
+            }
+            file.sync_all()?;
+            fs::rename(&tmp, &self.path)
+        };
+        write().map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            storage_error(&self.path, e)
+        })?;
+
+        // Los handles viejos apuntan al archivo reemplazado
+        *self = Self::open(self.path.clone())?;
+        Ok(())
+    }
+
+    fn replay(&mut self) -> RepoResult<()> {
+        let mut bytes = Vec::new();
+        self.reader
+            .get_mut()
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .map_err(|e| storage_error(&self.path, e))?;
+
+        let mut offset = 0usize;
+        while offset < bytes.len() {
+            match decode_frame(&bytes[offset..]) {
+                Frame::Complete { len, payload } => {
+                    let record: Record<T, T::Id> = serde_json::from_slice(payload)
+                        .map_err(|e| self.corrupted(offset, &e.to_string()))?;
+                    self.apply(record, offset as u64, len)
+                        .map_err(|e| self.corrupted(offset, &e.to_string()))?;
+                    offset += HEADER_LEN as usize + len as usize;
+                }
+                Frame::Torn => break,
+                Frame::BadChecksum { len } => {
+                    let end = offset + HEADER_LEN as usize + len as usize;
+                    if end < bytes.len() {
+                        return Err(self.corrupted(offset, "checksum mismatch"));
+                    }
+                    break; // último registro a medio escribir
+                }
+            }
+        }
+
+        if offset < bytes.len() {
+            self.writer
+                .set_len(offset as u64)
+                .and_then(|_| self.writer.sync_all())
+                .map_err(|e| storage_error(&self.path, e))?;
+        }
+        self.len = offset as u64;
+        Ok(())
+    }
+
+    fn apply(&mut self, record: Record<T, T::Id>, offset: u64, len: u32) -> RepoResult<()> {
+        self.records += 1;
+        match record {
+            Record::Save(entity) => {
+                self.indexes.insert(&entity)?;
+                self.index.insert(entity.id(), Location { offset, len });
+            }
+            Record::Delete(id) => {
+                self.indexes.remove(id);
+                self.index.remove(&id);
+            }
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, record: &Record<T, T::Id>) -> RepoResult<Location> {
+        let frame = encode(record).map_err(|e| storage_error(&self.path, e))?;
+        let offset = self.len;
+
+        if let Err(e) = self.write_frame(&frame) {
+            // Un registro a medias quedaría entre `len` y el final: el
+            // próximo append iría detrás y su offset no sería el que se
+            // indexa. Se recorta a lo último completo
+            self.writer
+                .set_len(self.len)
+                .map_err(|e| storage_error(&self.path, e))?;
+            return Err(storage_error(&self.path, e));
+        }
+
+        self.len += frame.len() as u64;
+        self.records += 1;
+        Ok(Location {
+            offset,
+            len: (frame.len() as u64 - HEADER_LEN) as u32,
+        })
+    }
+
+    fn write_frame(&mut self, frame: &[u8]) -> std::io::Result<()> {
+        #[cfg(test)]
+        if let Some(written) = self.tear_next_append.take() {
+            self.writer.write_all(&frame[..written])?;
+            return Err(std::io::Error::other("disk full"));
+        }
+
+        self.writer.write_all(frame)?;
+        self.writer.sync_data()
+    }
+
+    fn read(&self, location: Location) -> RepoResult<T> {
+        let mut frame = vec![0; HEADER_LEN as usize + location.len as usize];
+        {
+            let mut reader = self.reader.lock().unwrap();
+            reader
+                .seek(SeekFrom::Start(location.offset))
+                .and_then(|_| reader.read_exact(&mut frame))
+                .map_err(|e| storage_error(&self.path, e))?;
+        }
+
+        let offset = location.offset as usize;
+        let Frame::Complete { payload, .. } = decode_frame(&frame) else {
+            return Err(self.corrupted(offset, "checksum mismatch"));
+        };
+        match serde_json::from_slice::<Record<T, T::Id>>(payload) {
+            Ok(Record::Save(entity)) => Ok(entity),
+            Ok(Record::Delete(_)) => Err(self.corrupted(offset, "index points to a delete")),
+            Err(e) => Err(self.corrupted(offset, &e.to_string())),
+        }
+    }
+
+    fn corrupted(&self, offset: usize, reason: &str) -> RepositoryError {
+        RepositoryError::Corrupted(format!(
+            "{} at offset {}: {}",
+            self.path.display(),
+            offset,
+            reason
+        ))
+    }
+}
+
+enum Frame<'a> {
+    Complete {
+        len: u32,
+        payload: &'a [u8],
+    },
+    /// Faltan bytes: el header o el payload quedaron cortados
+    Torn,
+    BadChecksum {
+        len: u32,
+    },
+}
+
+fn decode_frame(bytes: &[u8]) -> Frame<'_> {
+    if bytes.len() < HEADER_LEN as usize {
+        return Frame::Torn;
+    }
+
+    let len = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+    let checksum = u64::from_le_bytes(bytes[4..12].try_into().unwrap());
+    let Some(payload) = bytes[HEADER_LEN as usize..].get(..len as usize) else {
+        return Frame::Torn;
+    };
+
+    if fnv1a64(payload) != checksum {
+        return Frame::BadChecksum { len };
+    }
+    Frame::Complete { len, payload }
+}
+
+fn encode<R: Serialize>(record: &R) -> std::io::Result<Vec<u8>> {
+    let payload = serde_json::to_vec(record)?;
+    let len = u32::try_from(payload.len())
+        .map_err(|_| std::io::Error::other("record larger than 4 GiB"))?;
+
+    let mut frame = Vec::with_capacity(HEADER_LEN as usize + payload.len());
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(&fnv1a64(&payload).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+fn storage_error(path: &Path, e: std::io::Error) -> RepositoryError {
+    RepositoryError::Storage(format!("{}: {}", path.display(), e))
+}
+
+impl<T> Repository<T, T::Id> for LogStructuredRepository<T>
+where
+    T: Entity + Serialize + DeserializeOwned,
+    T::Id: Serialize + DeserializeOwned,
+{
+    fn save(&mut self, entity: T) -> RepoResult<()> {
+        self.indexes.check(&entity)?;
+        let location = self.append(&Record::Save(entity.clone()))?;
+        self.indexes.insert(&entity)?;
+        self.index.insert(entity.id(), location);
+        Ok(())
+    }
+
+    fn find_by_id(&self, id: T::Id) -> RepoResult<Option<T>> {
+        self.index.get(&id).map(|&loc| self.read(loc)).transpose()
+    }
+
+    fn delete(&mut self, id: T::Id) -> RepoResult<Option<T>> {
+        let Some(existing) = self.find_by_id(id)? else {
+            return Ok(None);
+        };
+
+        self.append(&Record::<T, T::Id>::Delete(id))?;
+        self.indexes.remove(id);
+        self.index.remove(&id);
+        Ok(Some(existing))
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = T> + '_>> {
+        let entities = self
+            .index
+            .values()
+            .map(|&loc| self.read(loc))
+            .collect::<RepoResult<Vec<_>>>()?;
+        Ok(Box::new(entities.into_iter()))
+    }
+
+    fn exists(&self, id: T::Id) -> RepoResult<bool> {
+        Ok(self.index.contains_key(&id))
+    }
+
+    fn count(&self) -> RepoResult<usize> {
+        Ok(self.index.len())
+    }
+
+    fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        self.indexes
+            .unique_id(index, key)
+            .map_or(Ok(None), |id| self.find_by_id(id))
+    }
+
+    fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        let mut found = Vec::new();
+        for id in self.indexes.ids(index, key) {
+            found.extend(self.find_by_id(id)?);
+        }
+        Ok(found)
+    }
+}
+
+/*
+LOG-STRUCTURED STORAGE:
+
+  archivo (solo append)                         índice (memoria)
+  ┌─────────────────────────────────────┐       ┌──────────────────┐
+  │ Save(user 1 v1)                     │◀─┐    │ user 1 → off 120 │
+  │ Save(user 2)                        │  │ ┌──│ user 2 → off  60 │
+  │ Save(user 1 v2)                     │◀─┼─┘  └──────────────────┘
+  │ Delete(user 3)                      │  │
+  │ Save(user 1 v3) ← off 120           │──┘ (v1, v2 ya son basura)
+  └─────────────────────────────────────┘
+
+- Cada registro: | len | checksum | payload JSON |
+- open(): replay completo → índice; escritura cortada al final → truncate
+- Registro inválido en el medio → Corrupted (no es un crash, es daño)
+- compact(): un Save por entidad viva, archivo nuevo + rename
+- Lecturas: seek + read del registro apuntado (el valor no vive en memoria)
+- Índices secundarios (index.rs): solo las claves viven en memoria;
+  find_unique / find_indexed leen del disco únicamente lo que encuentran
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::json_file::tests::TempPath;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        id: u64,
+        text: String,
+    }
+
+    impl Entity for Note {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.id
+        }
+
+        fn index_keys(&self) -> Vec<(&'static str, String)> {
+            vec![("notes.text", self.text.clone())]
+        }
+    }
+
+    fn note(id: u64, text: &str) -> Note {
+        Note {
+            id,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_replay_rebuilds_index() {
+        let path = TempPath::new("log_replay");
+
+        let mut log = LogStructuredRepository::open(&path.0).unwrap();
+        log.save(note(1, "v1")).unwrap();
+        log.save(note(2, "b")).unwrap();
+        log.save(note(1, "v2")).unwrap();
+        log.delete(2).unwrap();
+        drop(log);
+
+        let log = LogStructuredRepository::<Note>::open(&path.0).unwrap();
+        assert_eq!(log.find_all().unwrap(), vec![note(1, "v2")]);
+        assert_eq!(log.stats().records, 4);
+    }
+
+    #[test]
+    fn test_replay_rebuilds_secondary_indexes() {
+        let path = TempPath::new("log_indexes");
+
+        let mut log = LogStructuredRepository::open(&path.0).unwrap();
+        log.save(note(1, "a")).unwrap();
+        log.save(note(2, "a")).unwrap();
+        log.save(note(1, "b")).unwrap();
+        log.save(note(3, "a")).unwrap();
+        log.delete(3).unwrap();
+        drop(log);
+
+        let log = LogStructuredRepository::<Note>::open(&path.0).unwrap();
+        assert_eq!(
+            log.find_indexed("notes.text", "a").unwrap(),
+            vec![note(2, "a")]
+        );
+        assert_eq!(
+            log.find_indexed("notes.text", "b").unwrap(),
+            vec![note(1, "b")]
+        );
+    }
+
+    #[test]
+    fn test_torn_tail_is_truncated() {
+        let path = TempPath::new("log_torn");
+
+        let mut log = LogStructuredRepository::open(&path.0).unwrap();
+        log.save(note(1, "kept")).unwrap();
+        log.save(note(2, "torn")).unwrap();
+        let full = log.stats().file_bytes;
+        drop(log);
+
+        // Crash a mitad del último append: faltan los últimos bytes
+        let file = OpenOptions::new().write(true).open(&path.0).unwrap();
+        file.set_len(full - 5).unwrap();
+        drop(file);
+
+        let mut log = LogStructuredRepository::<Note>::open(&path.0).unwrap();
+        assert_eq!(log.find_all().unwrap(), vec![note(1, "kept")]);
+
+        // El archivo quedó truncado en el último registro válido
+        log.save(note(3, "after")).unwrap();
+        drop(log);
+        let log = LogStructuredRepository::<Note>::open(&path.0).unwrap();
+        assert_eq!(log.count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_failed_append_is_truncated_away() {
+        let path = TempPath::new("log_failed_append");
+
+        let mut log = LogStructuredRepository::open(&path.0).unwrap();
+        log.save(note(1, "kept")).unwrap();
+        let before = log.stats();
+
+        log.tear_next_append = Some(7);
+        assert!(matches!(
+            log.save(note(2, "torn")),
+            Err(RepositoryError::Storage(_))
+        ));
+        assert_eq!(log.stats(), before);
+        assert_eq!(fs::metadata(&path.0).unwrap().len(), before.file_bytes);
+
+        // El siguiente append queda donde el índice cree que está
+        log.save(note(3, "after")).unwrap();
+        assert_eq!(log.find_by_id(3).unwrap(), Some(note(3, "after")));
+        drop(log);
+        let log = LogStructuredRepository::<Note>::open(&path.0).unwrap();
+        assert_eq!(log.count().unwrap(), 2);
+        assert_eq!(log.find_by_id(3).unwrap(), Some(note(3, "after")));
+    }
+
+    #[test]
+    fn test_corruption_in_the_middle_is_an_error() {
+        let path = TempPath::new("log_corrupt");
+
+        let mut log = LogStructuredRepository::open(&path.0).unwrap();
+        log.save(note(1, "first")).unwrap();
+        log.save(note(2, "second")).unwrap();
+        drop(log);
+
+        let mut bytes = fs::read(&path.0).unwrap();
+        bytes[HEADER_LEN as usize + 2] ^= 0xFF; // un byte del primer payload
+        fs::write(&path.0, bytes).unwrap();
+
+        let err = LogStructuredRepository::<Note>::open(&path.0)
+            .err()
+            .unwrap();
+        assert!(matches!(err, RepositoryError::Corrupted(msg) if msg.contains("offset 0")));
+    }
+
+    #[test]
+    fn test_compaction_keeps_only_live_records() {
+        let path = TempPath::new("log_compact");
+
+        let mut log = LogStructuredRepository::open(&path.0).unwrap();
+        for version in 0..10 {
+            log.save(note(1, &format!("v{version}"))).unwrap();
+        }
+        log.save(note(2, "gone")).unwrap();
+        log.delete(2).unwrap();
+        let before = log.stats();
+
+        log.compact().unwrap();
+        let after = log.stats();
+
+        assert_eq!(after.records, 1);
+        assert!(after.file_bytes < before.file_bytes);
+        assert_eq!(log.find_by_id(1).unwrap(), Some(note(1, "v9")));
+
+        drop(log);
+        let log = LogStructuredRepository::<Note>::open(&path.0).unwrap();
+        assert_eq!(log.find_all().unwrap(), vec![note(1, "v9")]);
+    }
+}
@@ -0,0 +1,74 @@
+// Módulo shared: código común a todas las estrategias de organización
+// Evita que monolithic, domain y hybrid dupliquen la misma infraestructura
+
+pub mod async_repository;
+pub mod audit;
+pub mod checksum;
+pub mod error;
+pub mod event_bus;
+pub mod id_gen;
+pub mod ids;
+pub mod index;
+pub mod json_file;
+pub mod log_store;
+pub mod money;
+pub mod outbox;
+pub mod query;
+pub mod repository;
+pub mod shared_repository;
+pub mod sqlite;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod unit_of_work;
+pub mod validation;
+
+// Re-exports
+pub use async_repository::{AsyncInMemoryRepository, AsyncRepository, SpawnBlocking};
+pub use audit::{AuditAction, AuditEntry, AuditLog, Auditor, FieldChange};
+pub use error::{ErrorCode, ErrorKind};
+pub use event_bus::{EventBus, SubscriptionId};
+pub use id_gen::{
+    Clock, IdGenerator, ManualClock, NodeId, RandomIdGenerator, SequentialIdGenerator,
+    SnowflakeIdGenerator, SystemClock, Uuid, UuidV4Generator, UuidV7Generator,
+};
+pub use ids::{CartId, OrderId, ParseIdError, PaymentId, ProductId, UserId};
+pub use index::SecondaryIndexes;
+pub use json_file::JsonFileRepository;
+pub use log_store::{LogStats, LogStructuredRepository};
+pub use money::{Currency, Money, MoneyError, RoundingMode};
+pub use outbox::{DeliveryStatus, EventSink, Outbox, OutboxMessage, OutboxRelay, RelayReport};
+pub use query::{Cursor, Page, Query, SortOrder};
+pub use repository::{Entity, InMemoryRepository, RepoResult, Repository, RepositoryError};
+pub use shared_repository::{SharedRepository, SharedStore};
+pub use sqlite::{Migration, SqliteDatabase};
+pub use unit_of_work::{Transactional, UnitOfWork};
+pub use validation::{FieldError, Validate, ValidationErrors, Validator};
+
+/*
+¿POR QUÉ UN MÓDULO SHARED?
+
+Cada estrategia (monolithic, domain, hybrid) tenía su propio
+HashMap<u64, _> con save/find_by_id/count copiado a mano.
+
+shared/ contiene solo abstracciones técnicas sin lógica de negocio:
+- repository.rs → trait Repository<T, Id> + InMemoryRepository
+- error.rs      → ErrorKind + ErrorCode (códigos estables)
+- audit.rs      → AuditLog (quién cambió qué y cuándo, diffs por campo, as_of)
+- event_bus.rs  → EventBus (publicar/suscribir, sync y async)
+- ids.rs        → UserId, OrderId, PaymentId, ProductId, CartId (newtypes)
+- id_gen.rs     → IdGenerator (sequential, random, snowflake, UUID)
+- index.rs      → SecondaryIndexes (índices únicos y multi-valor en memoria)
+- money.rs      → Money (enteros + moneda, sin f64)
+- outbox.rs     → Outbox + OutboxRelay (eventos en la transacción, entrega al menos una vez)
+- shared_repository.rs → SharedRepository (un backend, varios dueños, un solo begin/commit)
+- query.rs      → Query (filtros, orden, offset/cursor) → Page
+- json_file.rs  → JsonFileRepository (persistencia en archivo JSON)
+- log_store.rs  → LogStructuredRepository (log append-only + compactación)
+- checksum.rs   → fnv1a64 (detección de datos corruptos)
+- sqlite.rs     → SqliteDatabase (conexión compartida + migraciones)
+- unit_of_work.rs → Transactional + UnitOfWork (commit/rollback conjunto)
+- async_repository.rs → AsyncRepository + SpawnBlocking (backends sync en async)
+- validation.rs → Validate + Validator (reglas reutilizables, todos los errores con su ruta)
+
+Los dominios dependen de shared/, nunca al revés.
+*/
@@ -0,0 +1,414 @@
+// Money: importes como enteros en unidades menores (centavos) + moneda
+// Nunca f64: 999.99 * 0.9 = 899.991 no es un precio válido
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+// ============================================================
+// CURRENCY
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Currency {
+    USD,
+    EUR,
+    GBP,
+    ARS,
+    JPY,
+}
+
+impl Currency {
+    /// Cantidad de decimales de la moneda (ISO 4217).
+    pub fn minor_units(self) -> u32 {
+        match self {
+            Currency::JPY => 0,
+            _ => 2,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Currency::USD => "USD",
+            Currency::EUR => "EUR",
+            Currency::GBP => "GBP",
+            Currency::ARS => "ARS",
+            Currency::JPY => "JPY",
+        }
+    }
+
+    fn scale(self) -> i64 {
+        10_i64.pow(self.minor_units())
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "USD" => Ok(Currency::USD),
+            "EUR" => Ok(Currency::EUR),
+            "GBP" => Ok(Currency::GBP),
+            "ARS" => Ok(Currency::ARS),
+            "JPY" => Ok(Currency::JPY),
+            other => Err(MoneyError::UnknownCurrency(other.to_string())),
+        }
+    }
+}
+
+// ============================================================
+// ERRORS
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MoneyError {
+    #[error("currency mismatch: expected {expected}, found {found}")]
+    CurrencyMismatch { expected: Currency, found: Currency },
+
+    #[error("arithmetic overflow")]
+    Overflow,
+
+    #[error("unknown currency: {0}")]
+    UnknownCurrency(String),
+
+    #[error("invalid money format: {0:?}")]
+    Parse(String),
+}
+
+// ============================================================
+// ROUNDING
+// ============================================================
+
+/// Cómo redondear cuando una operación (porcentaje) no cae en un centavo exacto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// 0.5 se aleja del cero (el "redondeo escolar")
+    HalfUp,
+    /// 0.5 va al par más cercano (redondeo bancario)
+    HalfEven,
+    /// Trunca hacia el cero
+    Down,
+    /// Se aleja del cero si hay resto
+    Up,
+}
+
+impl RoundingMode {
+    // Divide con redondeo; `denominator` siempre > 0
+    fn divide(self, numerator: i128, denominator: i128) -> i128 {
+        let quotient = numerator / denominator;
+        let remainder = (numerator % denominator).abs();
+        let step = numerator.signum();
+
+        let round_away = match self {
+            RoundingMode::Down => false,
+            RoundingMode::Up => remainder != 0,
+            RoundingMode::HalfUp => remainder * 2 >= denominator,
+            RoundingMode::HalfEven => {
+                remainder * 2 > denominator || (remainder * 2 == denominator && quotient % 2 != 0)
+            }
+        };
+
+        if round_away {
+            quotient + step
+        } else {
+            quotient
+        }
+    }
+}
+
+// ============================================================
+// MONEY
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    amount_minor: i64,
+    currency: Currency,
+}
+
+impl Money {
+    /// `amount_minor` en unidades menores: `Money::new(1999, USD)` = 19.99 USD
+    pub fn new(amount_minor: i64, currency: Currency) -> Self {
+        Self {
+            amount_minor,
+            currency,
+        }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Unidades enteras: `Money::from_major(20, USD)` = 20.00 USD
+    pub fn from_major(units: i64, currency: Currency) -> Result<Self, MoneyError> {
+        let minor = units
+            .checked_mul(currency.scale())
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(minor, currency))
+    }
+
+    pub fn amount_minor(&self) -> i64 {
+        self.amount_minor
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount_minor == 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.amount_minor > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount_minor < 0
+    }
+
+    // Aritmética checked: mezclar monedas u overflow → error, nunca pánico
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        let amount = self
+            .amount_minor
+            .checked_add(other.amount_minor)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency))
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        let amount = self
+            .amount_minor
+            .checked_sub(other.amount_minor)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency))
+    }
+
+    /// Precio unitario × cantidad.
+    pub fn checked_mul(self, quantity: u32) -> Result<Money, MoneyError> {
+        let amount = self
+            .amount_minor
+            .checked_mul(i64::from(quantity))
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency))
+    }
+
+    /// Porcentaje en basis points (1 bp = 0.01%): `percent(1050, ..)` = 10.50%
+    pub fn percent(self, basis_points: i64, mode: RoundingMode) -> Result<Money, MoneyError> {
+        let scaled = i128::from(self.amount_minor) * i128::from(basis_points);
+        let amount = mode.divide(scaled, 10_000);
+        let amount = i64::try_from(amount).map_err(|_| MoneyError::Overflow)?;
+        Ok(Money::new(amount, self.currency))
+    }
+
+    /// Suma todos los importes; deben compartir `currency`.
+    pub fn sum<I>(currency: Currency, amounts: I) -> Result<Money, MoneyError>
+    where
+        I: IntoIterator<Item = Money>,
+    {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(currency), |acc, m| acc.checked_add(m))
+    }
+
+    fn ensure_same_currency(self, other: Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch {
+                expected: self.currency,
+                found: other.currency,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Money {
+    // "1234.50 USD", "-0.05 EUR", "100 JPY"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.currency.minor_units() as usize;
+        let sign = if self.amount_minor < 0 { "-" } else { "" };
+        let abs = self.amount_minor.unsigned_abs();
+
+        if digits == 0 {
+            return write!(f, "{}{} {}", sign, abs, self.currency);
+        }
+
+        let scale = self.currency.scale() as u64;
+        write!(
+            f,
+            "{}{}.{:0width$} {}",
+            sign,
+            abs / scale,
+            abs % scale,
+            self.currency,
+            width = digits
+        )
+    }
+}
+
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    // Formato inverso a Display: "<importe> <MONEDA>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_err = || MoneyError::Parse(s.to_string());
+
+        let (amount, code) = s.trim().split_once(' ').ok_or_else(parse_err)?;
+        let currency: Currency = code.trim().parse()?;
+        let digits = currency.minor_units() as usize;
+
+        let (negative, amount) = match amount.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, amount),
+        };
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+
+        let all_digits = |p: &str| p.chars().all(|c| c.is_ascii_digit());
+        if whole.is_empty() || !all_digits(whole) || !all_digits(fraction) {
+            return Err(parse_err());
+        }
+        // Más decimales que la moneda admite sería perder precisión en silencio
+        if fraction.len() > digits || (amount.contains('.') && fraction.is_empty()) {
+            return Err(parse_err());
+        }
+
+        let whole: i64 = whole.parse().map_err(|_| parse_err())?;
+        let fraction: i64 = format!("{:0<width$}", fraction, width = digits)
+            .parse()
+            .unwrap_or(0);
+
+        let minor = whole
+            .checked_mul(currency.scale())
+            .and_then(|w| w.checked_add(fraction))
+            .ok_or(MoneyError::Overflow)?;
+
+        Ok(Money::new(if negative { -minor } else { minor }, currency))
+    }
+}
+
+/*
+¿POR QUÉ NO f64?
+
+    0.1 + 0.2            = 0.30000000000000004
+    999.99 * 0.9         = 899.991            ← no existe ese precio
+    sum de 1000 × 0.01   ≠ 10.00
+
+Money:
+- amount_minor: i64 → 999.99 USD se guarda como 99999
+- currency: cada importe sabe en qué moneda está
+- checked_add/sub/mul → Result (overflow y mezcla de monedas son errores)
+- percent(bps, RoundingMode) → el redondeo es EXPLÍCITO
+
+┌────────────┬─────────┬─────────┬──────┬──────┐
+│ valor      │ HalfUp  │HalfEven │ Down │  Up  │
+├────────────┼─────────┼─────────┼──────┼──────┤
+│  2.5 ¢     │   3     │   2     │  2   │  3   │
+│  3.5 ¢     │   4     │   4     │  3   │  4   │
+│ -2.5 ¢     │  -3     │  -2     │ -2   │ -3   │
+└────────────┴─────────┴─────────┴──────┴──────┘
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(s: &str) -> Money {
+        format!("{} USD", s).parse().unwrap()
+    }
+
+    #[test]
+    fn test_arithmetic_is_exact() {
+        let total = Money::sum(Currency::USD, vec![usd("0.10"), usd("0.20")]).unwrap();
+        assert_eq!(total, usd("0.30"));
+
+        assert_eq!(usd("19.99").checked_mul(3).unwrap(), usd("59.97"));
+        assert_eq!(usd("5.00").checked_sub(usd("7.25")).unwrap(), usd("-2.25"));
+    }
+
+    #[test]
+    fn test_mixing_currencies_is_refused() {
+        let eur = Money::from_major(1, Currency::EUR).unwrap();
+
+        assert_eq!(
+            usd("1.00").checked_add(eur),
+            Err(MoneyError::CurrencyMismatch {
+                expected: Currency::USD,
+                found: Currency::EUR
+            })
+        );
+    }
+
+    #[test]
+    fn test_overflow_is_an_error() {
+        let max = Money::new(i64::MAX, Currency::USD);
+
+        assert_eq!(max.checked_add(usd("0.01")), Err(MoneyError::Overflow));
+        assert_eq!(max.checked_mul(2), Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn test_percent_rounding_modes() {
+        // 10% de 0.25 = 2.5 centavos
+        let m = usd("0.25");
+        assert_eq!(m.percent(1000, RoundingMode::HalfUp).unwrap(), usd("0.03"));
+        assert_eq!(
+            m.percent(1000, RoundingMode::HalfEven).unwrap(),
+            usd("0.02")
+        );
+        assert_eq!(m.percent(1000, RoundingMode::Down).unwrap(), usd("0.02"));
+        assert_eq!(m.percent(1000, RoundingMode::Up).unwrap(), usd("0.03"));
+
+        let neg = usd("-0.25");
+        assert_eq!(
+            neg.percent(1000, RoundingMode::HalfUp).unwrap(),
+            usd("-0.03")
+        );
+
+        // El caso de estructuras_ids: 90% de 999.99
+        let discounted = usd("999.99").percent(9000, RoundingMode::HalfEven).unwrap();
+        assert_eq!(discounted, usd("899.99"));
+    }
+
+    #[test]
+    fn test_display_and_parse_roundtrip() {
+        for s in ["0.00 USD", "1234.50 EUR", "-0.05 GBP", "100 JPY"] {
+            let m: Money = s.parse().unwrap();
+            assert_eq!(m.to_string(), s);
+        }
+
+        assert_eq!(usd("7").amount_minor(), 700);
+        assert_eq!(usd("7.5").amount_minor(), 750);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            "1.234 USD".parse::<Money>(),
+            Err(MoneyError::Parse(_))
+        ));
+        assert!(matches!(
+            "1.5 JPY".parse::<Money>(),
+            Err(MoneyError::Parse(_))
+        ));
+        assert!(matches!(
+            "abc USD".parse::<Money>(),
+            Err(MoneyError::Parse(_))
+        ));
+        assert!(matches!("10".parse::<Money>(), Err(MoneyError::Parse(_))));
+        assert_eq!(
+            "1.00 XYZ".parse::<Money>(),
+            Err(MoneyError::UnknownCurrency("XYZ".to_string()))
+        );
+    }
+}
@@ -0,0 +1,662 @@
+// Outbox: los eventos se guardan junto con el cambio que los produjo
+// Un relay los entrega después (al menos una vez), con reintentos y dead letters
+
+use super::event_bus::EventBus;
+use super::id_gen::{IdGenerator, SequentialIdGenerator};
+use super::json_file::JsonFileRepository;
+use super::repository::{Entity, InMemoryRepository, RepoResult, RepositoryError};
+use super::shared_repository::{SharedRepository, SharedStore};
+use super::unit_of_work::Transactional;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Índice multi-valor por estado: el relay solo lee los pendientes.
+pub const OUTBOX_BY_STATUS: &str = "outbox.status";
+
+/// Intentos por defecto antes de mandar un mensaje a dead letter.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+// ============================================================
+// MENSAJE
+// ============================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// Falló `max_attempts` veces: el relay no lo vuelve a intentar
+    /// hasta que alguien lo reencole a mano.
+    DeadLetter,
+}
+
+impl DeliveryStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::DeadLetter => "dead_letter",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxMessage<E> {
+    /// Creciente: el relay entrega en orden de id.
+    pub id: u64,
+    pub event: E,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+impl<E: Clone> Entity for OutboxMessage<E> {
+    type Id = u64;
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn index_keys(&self) -> Vec<(&'static str, String)> {
+        vec![(OUTBOX_BY_STATUS, self.status.as_str().to_string())]
+    }
+}
+
+// ============================================================
+// OUTBOX
+// ============================================================
+
+/// Cola persistente de eventos. Clonar es barato: los servicios que
+/// escriben y el relay que entrega comparten el mismo estado.
+pub struct Outbox<E: Clone> {
+    messages: SharedRepository<OutboxMessage<E>>,
+    ids: Arc<SequentialIdGenerator>,
+}
+
+impl<E: Clone> Clone for Outbox<E> {
+    fn clone(&self) -> Self {
+        Self {
+            messages: self.messages.clone(),
+            ids: Arc::clone(&self.ids),
+        }
+    }
+}
+
+impl<E: Clone + Send + 'static> Outbox<E> {
+    /// Se pierde al terminar el proceso; sirve para tests y para
+    /// desacoplar la entrega dentro de un mismo proceso.
+    pub fn in_memory() -> Self {
+        Self::with_store(InMemoryRepository::new())
+            .expect("in-memory repository is always readable")
+    }
+
+    /// Cualquier backend que pueda participar de un UnitOfWork.
+    pub fn with_store(store: impl SharedStore<OutboxMessage<E>> + 'static) -> RepoResult<Self> {
+        // Continuar la numeración: el orden de entrega es el orden de id
+        let ids = SequentialIdGenerator::resuming(&store)?;
+
+        Ok(Self {
+            messages: SharedRepository::new(store),
+            ids: Arc::new(ids),
+        })
+    }
+
+    /// Guarda `event` como pendiente. Dentro de un UnitOfWork queda en la
+    /// misma transacción que el cambio que lo produjo.
+    pub fn enqueue(&self, event: E) -> RepoResult<u64> {
+        // El id se toma bajo el lock del store: los mensajes quedan
+        // guardados en el mismo orden que sus ids
+        self.messages.with(|store| {
+            let id = self.ids.next_id()?;
+            store.insert(OutboxMessage {
+                id,
+                event,
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                last_error: None,
+            })?;
+            Ok(id)
+        })
+    }
+
+    pub fn find(&self, id: u64) -> RepoResult<Option<OutboxMessage<E>>> {
+        self.messages.with(|store| store.find_by_id(id))
+    }
+
+    /// Pendientes de entrega, en orden de id.
+    pub fn pending(&self) -> RepoResult<Vec<OutboxMessage<E>>> {
+        self.with_status(DeliveryStatus::Pending)
+    }
+
+    pub fn dead_letters(&self) -> RepoResult<Vec<OutboxMessage<E>>> {
+        self.with_status(DeliveryStatus::DeadLetter)
+    }
+
+    /// Devuelve un dead letter a la cola, con los intentos en cero
+    /// (p.ej. después de corregir al suscriptor que fallaba).
+    pub fn requeue(&self, id: u64) -> RepoResult<()> {
+        self.messages.with(|store| {
+            let mut message = store
+                .find_by_id(id)?
+                .filter(|m| m.status == DeliveryStatus::DeadLetter)
+                .ok_or_else(|| RepositoryError::NotFound(format!("dead letter {id}")))?;
+
+            message.status = DeliveryStatus::Pending;
+            message.attempts = 0;
+            store.save(message)
+        })
+    }
+
+    /// Borra los ya entregados. Devuelve cuántos.
+    pub fn purge_delivered(&self) -> RepoResult<usize> {
+        self.messages.with(|store| {
+            let delivered =
+                store.find_indexed(OUTBOX_BY_STATUS, DeliveryStatus::Delivered.as_str())?;
+            for message in &delivered {
+                store.delete(message.id)?;
+            }
+            Ok(delivered.len())
+        })
+    }
+
+    fn with_status(&self, status: DeliveryStatus) -> RepoResult<Vec<OutboxMessage<E>>> {
+        let messages = self
+            .messages
+            .with(|store| store.find_indexed(OUTBOX_BY_STATUS, status.as_str()))?;
+        Ok(sorted(messages))
+    }
+
+    /// Pendientes para el relay, o nada si hay una transacción abierta:
+    /// lo que se encola fuera de ella mientras tanto tiene ids mayores que
+    /// los suyos, y entregarlo antes rompería el orden de id.
+    fn committed_pending(&self) -> RepoResult<Vec<OutboxMessage<E>>> {
+        let pending = self.messages.with_committed(|store| {
+            store.find_indexed(OUTBOX_BY_STATUS, DeliveryStatus::Pending.as_str())
+        })?;
+        Ok(pending.map(sorted).unwrap_or_default())
+    }
+
+    /// Registra el resultado de un intento. Un mensaje que ya no está
+    /// pendiente (otro relay, un requeue) no se toca.
+    fn record(
+        &self,
+        id: u64,
+        outcome: Result<(), String>,
+        max_attempts: u32,
+    ) -> RepoResult<Outcome> {
+        self.messages.with(|store| {
+            let Some(mut message) = store
+                .find_by_id(id)?
+                .filter(|m| m.status == DeliveryStatus::Pending)
+            else {
+                return Ok(Outcome::Skipped);
+            };
+
+            message.attempts += 1;
+            let result = match outcome {
+                Ok(()) => {
+                    message.status = DeliveryStatus::Delivered;
+                    message.last_error = None;
+                    Outcome::Delivered
+                }
+                Err(error) => {
+                    message.last_error = Some(error);
+                    if message.attempts >= max_attempts {
+                        message.status = DeliveryStatus::DeadLetter;
+                        Outcome::DeadLettered
+                    } else {
+                        Outcome::Failed
+                    }
+                }
+            };
+            store.save(message)?;
+            Ok(result)
+        })
+    }
+}
+
+impl<E> Outbox<E>
+where
+    E: Clone + Send + Serialize + DeserializeOwned + 'static,
+{
+    /// Persistente: lo que quedó pendiente al caerse el proceso se entrega
+    /// al reabrir.
+    pub fn open(path: impl Into<PathBuf>) -> RepoResult<Self> {
+        Self::with_store(JsonFileRepository::open(path)?)
+    }
+}
+
+fn sorted<E>(mut messages: Vec<OutboxMessage<E>>) -> Vec<OutboxMessage<E>> {
+    messages.sort_by_key(|m| m.id);
+    messages
+}
+
+// Los servicios que escriben en el outbox lo incluyen en su propio
+// Transactional; varios servicios → un solo begin/commit real
+impl<E: Clone + Send + 'static> Transactional for Outbox<E> {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.messages.begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.messages.commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.messages.rollback()
+    }
+}
+
+// ============================================================
+// SINK (lo que guarda un servicio)
+// ============================================================
+
+/// A dónde va lo que emite un servicio: directo al bus o, si tiene
+/// outbox, a la misma transacción que el cambio.
+pub struct EventSink<E: Clone> {
+    bus: EventBus<E>,
+    outbox: Option<Outbox<E>>,
+}
+
+// Manual: derive exigiría E: Clone + Default sobre el tipo
+impl<E: Clone> Clone for EventSink<E> {
+    fn clone(&self) -> Self {
+        Self {
+            bus: self.bus.clone(),
+            outbox: self.outbox.clone(),
+        }
+    }
+}
+
+impl<E: Clone + Send + 'static> Default for EventSink<E> {
+    fn default() -> Self {
+        Self {
+            bus: EventBus::new(),
+            outbox: None,
+        }
+    }
+}
+
+impl<E: Clone + Send + 'static> EventSink<E> {
+    pub fn with_bus(mut self, bus: EventBus<E>) -> Self {
+        self.bus = bus;
+        self
+    }
+
+    pub fn with_outbox(mut self, outbox: Outbox<E>) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
+    pub fn bus(&self) -> &EventBus<E> {
+        &self.bus
+    }
+
+    /// Con outbox puede fallar (es una escritura): el servicio devuelve el
+    /// error y, dentro de un UnitOfWork, se deshace también el cambio.
+    pub fn emit(&self, event: E) -> RepoResult<()> {
+        match &self.outbox {
+            Some(outbox) => outbox.enqueue(event).map(drop),
+            None => {
+                self.bus.publish(event);
+                Ok(())
+            }
+        }
+    }
+}
+
+// Sin outbox no hay nada que confirmar
+impl<E: Clone + Send + 'static> Transactional for EventSink<E> {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.outbox.as_mut().map_or(Ok(()), Transactional::begin)
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.outbox.as_mut().map_or(Ok(()), Transactional::commit)
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.outbox.as_mut().map_or(Ok(()), Transactional::rollback)
+    }
+}
+
+// ============================================================
+// RELAY
+// ============================================================
+
+type Deliver<E> = Box<dyn FnMut(&E) -> Result<(), String> + Send>;
+
+enum Outcome {
+    Delivered,
+    Failed,
+    DeadLettered,
+    Skipped,
+}
+
+/// Resultado de una pasada del relay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelayReport {
+    pub delivered: usize,
+    /// Fallaron pero se reintentan en la próxima pasada.
+    pub failed: usize,
+    pub dead_lettered: usize,
+}
+
+/// Lee los pendientes del outbox y los entrega con `deliver`.
+///
+/// Marca un mensaje como entregado recién después de que `deliver`
+/// devuelve `Ok`: si el proceso muere en el medio, se entrega de nuevo
+/// (al menos una vez). Los suscriptores deben tolerar duplicados.
+///
+/// Un `deliver` que entra en pánico manda ese mensaje directo a dead
+/// letter (reintentarlo volvería a romper) y el relay sigue con el resto.
+pub struct OutboxRelay<E: Clone> {
+    outbox: Outbox<E>,
+    deliver: Deliver<E>,
+    max_attempts: u32,
+}
+
+impl<E: Clone + Send + 'static> OutboxRelay<E> {
+    pub fn new(
+        outbox: Outbox<E>,
+        deliver: impl FnMut(&E) -> Result<(), String> + Send + 'static,
+    ) -> Self {
+        Self {
+            outbox,
+            deliver: Box::new(deliver),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    /// Entrega a los suscriptores de un bus. `publish` no falla, así que
+    /// nada termina en dead letter.
+    pub fn to_bus(outbox: Outbox<E>, bus: EventBus<E>) -> Self {
+        Self::new(outbox, move |event: &E| {
+            bus.publish(event.clone());
+            Ok(())
+        })
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Una pasada: intenta cada pendiente una vez, en orden de id. Un
+    /// mensaje que falla no frena a los siguientes.
+    pub fn run_once(&mut self) -> RepoResult<RelayReport> {
+        let mut report = RelayReport::default();
+
+        // Sin el lock mientras se entrega: un suscriptor puede llamar a
+        // un servicio que escribe en este mismo outbox
+        for message in self.outbox.committed_pending()? {
+            let deliver = AssertUnwindSafe(|| (self.deliver)(&message.event));
+            let (outcome, max_attempts) = match panic::catch_unwind(deliver) {
+                Ok(outcome) => (outcome, self.max_attempts),
+                // Un solo intento: el pánico es un bug, no un error pasajero
+                Err(panic) => (Err(panic_message(panic.as_ref())), 1),
+            };
+            match self.outbox.record(message.id, outcome, max_attempts)? {
+                Outcome::Delivered => report.delivered += 1,
+                Outcome::Failed => report.failed += 1,
+                Outcome::DeadLettered => report.dead_lettered += 1,
+                Outcome::Skipped => {}
+            }
+        }
+        Ok(report)
+    }
+
+    /// Corre `run_once` cada `every` en una task de tokio, hasta que se
+    /// aborte el handle. Cada pasada va en `spawn_blocking`: el outbox
+    /// en archivo hace I/O síncrono.
+    ///
+    /// La task termina con el primer error de storage: el handle lo
+    /// devuelve y quien la supervisa decide si relanzarla.
+    pub fn spawn(mut self, every: Duration) -> JoinHandle<RepoResult<()>> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            loop {
+                ticker.tick().await;
+                let pass = tokio::task::spawn_blocking(move || {
+                    let report = self.run_once();
+                    (self, report)
+                });
+                // Los pánicos de `deliver` ya se atraparon en run_once:
+                // este es del propio relay o del storage
+                let (relay, report) = pass
+                    .await
+                    .map_err(|e| RepositoryError::Storage(format!("outbox relay failed: {e}")))?;
+                report?;
+                self = relay;
+            }
+        })
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause");
+    format!("subscriber panicked: {message}")
+}
+
+/*
+DE LA ESCRITURA A LOS SUSCRIPTORES:
+
+  UnitOfWork::run ─────────────────────────────────────┐
+  │ orders.confirm_order(id)                           │ commit: los dos
+  │   ├ repo.save(order)                               │ rollback: ninguno
+  │   └ outbox.enqueue(OrderConfirmed) → Pending #7    │
+  └────────────────────────────────────────────────────┘
+                        │
+  OutboxRelay (cada N ms, en otra task)
+                        │ pending() en orden de id
+                        ▼
+                 deliver(&event) ──Ok──▶ Delivered
+                        │
+                       Err ─▶ attempts += 1 ─▶ Pending (próxima pasada)
+                                    │
+                        attempts == max ─▶ DeadLetter ─(requeue)─▶ Pending
+                                                  ▲
+                   pánico ────────────────────────┘ (sin reintentos)
+
+- Publicar directo al bus después de guardar pierde el evento si el
+  proceso muere en el medio; el outbox lo deja guardado con el cambio
+- Al menos una vez, no exactamente una: se marca Delivered DESPUÉS de
+  entregar. Caída entre las dos cosas → se entrega de nuevo
+- Mientras haya una transacción abierta el relay no lee: lo pendiente
+  todavía puede deshacerse
+- spawn: un error de storage termina la task con `Err` en el
+  JoinHandle; un suscriptor que entra en pánico no la termina
+- Atómico con la entidad solo si comparten backend (en memoria con en
+  memoria); un outbox en archivo con repositorios en memoria tiene la
+  misma advertencia de unit_of_work.rs: sin two-phase commit
+- Backends: InMemoryRepository y JsonFileRepository (Outbox::open), o
+  cualquiera que implemente Repository + Transactional
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::UnitOfWork;
+    use crate::modules_demo::shared::json_file::tests::TempPath;
+    use crate::modules_demo::shared::testing::FaultyRepository;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_rollback_discards_enqueued_events() {
+        let mut outbox = Outbox::in_memory();
+        outbox.enqueue("before").unwrap();
+
+        let result: RepoResult<()> = UnitOfWork::new(&mut outbox).run(|outbox| {
+            outbox.enqueue("lost")?;
+            Err(RepositoryError::Storage("boom".to_string()))
+        });
+        assert!(result.is_err());
+
+        UnitOfWork::new(&mut outbox)
+            .run(|outbox| outbox.enqueue("kept"))
+            .unwrap();
+
+        let events: Vec<_> = outbox.pending().unwrap().iter().map(|m| m.event).collect();
+        assert_eq!(events, ["before", "kept"]);
+    }
+
+    #[test]
+    fn test_rollback_keeps_events_enqueued_outside_the_unit_of_work() {
+        let mut outbox = Outbox::in_memory();
+        let other = outbox.clone();
+
+        let result: RepoResult<()> = UnitOfWork::new(&mut outbox).run(|outbox| {
+            outbox.enqueue("lost")?;
+            other.enqueue("independent")?;
+            Err(RepositoryError::Storage("boom".to_string()))
+        });
+        assert!(result.is_err());
+
+        let events: Vec<_> = other.pending().unwrap().iter().map(|m| m.event).collect();
+        assert_eq!(events, ["independent"]);
+    }
+
+    #[test]
+    fn test_relay_retries_then_dead_letters() {
+        let outbox = Outbox::in_memory();
+        outbox.enqueue("ok").unwrap();
+        let poison = outbox.enqueue("poison").unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&seen);
+        let mut relay = OutboxRelay::new(outbox.clone(), move |event: &&str| {
+            log.lock().unwrap().push(*event);
+            match *event {
+                "poison" => Err("cannot handle".to_string()),
+                _ => Ok(()),
+            }
+        })
+        .with_max_attempts(3);
+
+        let first = relay.run_once().unwrap();
+        assert_eq!((first.delivered, first.failed), (1, 1));
+        relay.run_once().unwrap();
+        let last = relay.run_once().unwrap();
+        assert_eq!(last.dead_lettered, 1);
+        assert_eq!(relay.run_once().unwrap(), RelayReport::default());
+
+        // "ok" una sola vez; "poison" hasta agotar los intentos
+        assert_eq!(*seen.lock().unwrap(), ["ok", "poison", "poison", "poison"]);
+        let dead = outbox.dead_letters().unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 3);
+        assert_eq!(dead[0].last_error.as_deref(), Some("cannot handle"));
+
+        outbox.requeue(poison).unwrap();
+        assert_eq!(outbox.pending().unwrap()[0].attempts, 0);
+        assert_eq!(outbox.purge_delivered().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_panicking_subscriber_dead_letters_only_its_message() {
+        let outbox = Outbox::in_memory();
+        let bug = outbox.enqueue("bug").unwrap();
+        outbox.enqueue("ok").unwrap();
+
+        let mut relay = OutboxRelay::new(outbox.clone(), |event: &&str| match *event {
+            "bug" => panic!("index out of bounds"),
+            _ => Ok(()),
+        });
+
+        let report = relay.run_once().unwrap();
+        assert_eq!((report.delivered, report.dead_lettered), (1, 1));
+
+        let dead = outbox.dead_letters().unwrap();
+        assert_eq!((dead[0].id, dead[0].attempts), (bug, 1));
+        assert_eq!(
+            dead[0].last_error.as_deref(),
+            Some("subscriber panicked: index out of bounds")
+        );
+    }
+
+    #[test]
+    fn test_relay_waits_for_open_transaction() {
+        let mut outbox = Outbox::in_memory();
+        let mut relay = OutboxRelay::new(outbox.clone(), |_: &u32| Ok(()));
+
+        outbox.begin().unwrap();
+        outbox.enqueue(1).unwrap();
+        assert_eq!(relay.run_once().unwrap().delivered, 0);
+        outbox.commit().unwrap();
+
+        assert_eq!(relay.run_once().unwrap().delivered, 1);
+    }
+
+    #[test]
+    fn test_file_outbox_survives_restart() {
+        let path = TempPath::new("outbox");
+        {
+            let mut outbox = Outbox::open(&path.0).unwrap();
+            UnitOfWork::new(&mut outbox)
+                .run(|outbox| outbox.enqueue("welcome alice".to_string()))
+                .unwrap();
+            // El proceso muere antes de que el relay corra
+        }
+
+        let outbox = Outbox::<String>::open(&path.0).unwrap();
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&delivered);
+        let mut relay = OutboxRelay::new(outbox.clone(), move |event: &String| {
+            sink.lock().unwrap().push(event.clone());
+            Ok(())
+        });
+        relay.run_once().unwrap();
+
+        assert_eq!(*delivered.lock().unwrap(), ["welcome alice"]);
+        // Los ids siguen después de los ya guardados
+        assert_eq!(outbox.enqueue("next".to_string()).unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_background_relay_publishes_to_bus() {
+        let outbox = Outbox::in_memory();
+        let bus = EventBus::new();
+        let (done, mut received) = tokio::sync::mpsc::unbounded_channel();
+        bus.subscribe(move |n: &u32| done.send(*n).unwrap());
+
+        let relay = OutboxRelay::to_bus(outbox.clone(), bus).spawn(Duration::from_millis(1));
+        outbox.enqueue(1).unwrap();
+        outbox.enqueue(2).unwrap();
+
+        assert_eq!(received.recv().await, Some(1));
+        assert_eq!(received.recv().await, Some(2));
+        relay.abort();
+    }
+
+    #[tokio::test]
+    async fn test_background_relay_survives_panics_and_reports_storage_errors() {
+        let (store, faults) = FaultyRepository::new();
+        let outbox = Outbox::with_store(store).unwrap();
+        let (done, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let relay = OutboxRelay::new(outbox.clone(), move |n: &u32| {
+            assert_ne!(*n, 1, "cannot handle 1");
+            done.send(*n).map_err(|e| e.to_string())
+        })
+        .spawn(Duration::from_millis(1));
+
+        outbox.enqueue(1).unwrap();
+        outbox.enqueue(2).unwrap();
+        assert_eq!(received.recv().await, Some(2));
+        assert_eq!(outbox.dead_letters().unwrap().len(), 1);
+
+        faults.fail_reads(true);
+        assert!(matches!(
+            relay.await.unwrap(),
+            Err(RepositoryError::Storage(_))
+        ));
+    }
+}
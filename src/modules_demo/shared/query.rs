@@ -0,0 +1,299 @@
+// Query: filtros, orden multi-clave y paginación sobre cualquier Repository
+// Resultados deterministas: el id desempata siempre, aunque el backend no tenga orden
+
+use super::repository::Entity;
+use std::cmp::Ordering;
+
+type Predicate<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+type Comparator<T> = Box<dyn Fn(&T, &T) -> Ordering + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Consulta construida por partes:
+///
+/// ```text
+/// Query::new()
+///     .filter(|u: &User| u.email.ends_with("@corp.com"))
+///     .sort_by(|u| u.name.clone(), SortOrder::Asc)
+///     .limit(50)
+/// ```
+///
+/// Sin `sort_by`, el orden es por id ascendente.
+pub struct Query<T: Entity> {
+    filters: Vec<Predicate<T>>,
+    sort: Vec<Comparator<T>>,
+    offset: usize,
+    limit: Option<usize>,
+    after: Option<T>,
+}
+
+/// Posición dentro de un orden: "seguir después de esta entidad".
+///
+/// Guarda la última entidad de la página, no un número de fila: si entre
+/// página y página se agregan o borran entidades, no se repite ni se
+/// saltea ninguna (keyset pagination).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor<T>(T);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Entidades que cumplen los filtros, sin contar la paginación.
+    pub total: usize,
+    /// `Some` si hay más resultados después de `items`.
+    pub next: Option<Cursor<T>>,
+}
+
+impl<T: Entity> Default for Query<T>
+where
+    T::Id: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Entity> Query<T>
+where
+    T::Id: Ord,
+{
+    pub fn new() -> Self {
+        Self {
+            filters: Vec::new(),
+            sort: Vec::new(),
+            offset: 0,
+            limit: None,
+            after: None,
+        }
+    }
+
+    /// Agrega un filtro; todos deben cumplirse (AND).
+    pub fn filter(mut self, predicate: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Filtro por igualdad de un campo: `.where_eq(|o| o.status, Confirmed)`.
+    pub fn where_eq<V>(self, field: impl Fn(&T) -> V + Send + Sync + 'static, value: V) -> Self
+    where
+        V: PartialEq + Send + Sync + 'static,
+    {
+        self.filter(move |entity| field(entity) == value)
+    }
+
+    /// Agrega una clave de orden; las siguientes desempatan a las anteriores.
+    pub fn sort_by<K: Ord>(
+        mut self,
+        key: impl Fn(&T) -> K + Send + Sync + 'static,
+        order: SortOrder,
+    ) -> Self {
+        self.sort.push(Box::new(move |a, b| {
+            let ordering = key(a).cmp(&key(b));
+            match order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        }));
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Empieza después del cursor de una página anterior.
+    pub fn after(mut self, cursor: Cursor<T>) -> Self {
+        self.after = Some(cursor.0);
+        self
+    }
+
+    pub fn matches(&self, entity: &T) -> bool {
+        self.filters.iter().all(|predicate| predicate(entity))
+    }
+
+    /// Cuántas entidades cumplen los filtros (ignora orden y paginación).
+    pub fn count(&self, entities: impl IntoIterator<Item = T>) -> usize {
+        entities.into_iter().filter(|e| self.matches(e)).count()
+    }
+
+    /// Ejecuta la consulta sobre entidades en cualquier orden.
+    pub fn run(&self, entities: impl IntoIterator<Item = T>) -> Page<T> {
+        let mut matching: Vec<T> = entities.into_iter().filter(|e| self.matches(e)).collect();
+        let total = matching.len();
+        matching.sort_by(|a, b| self.compare(a, b));
+
+        let start = match &self.after {
+            Some(last) => matching.partition_point(|e| self.compare(e, last) != Ordering::Greater),
+            None => 0,
+        };
+        let mut rest = matching.into_iter().skip(start).skip(self.offset);
+
+        let items: Vec<T> = match self.limit {
+            Some(limit) => rest.by_ref().take(limit).collect(),
+            None => rest.by_ref().collect(),
+        };
+        let next = match (rest.next(), items.last()) {
+            (Some(_), Some(last)) => Some(Cursor(last.clone())),
+            _ => None,
+        };
+
+        Page { items, total, next }
+    }
+
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.sort
+            .iter()
+            .map(|comparator| comparator(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.id().cmp(&b.id()))
+    }
+}
+
+/*
+CÓMO SE EJECUTA:
+
+  repo.iter()  (HashMap, archivo, SQLite: orden cualquiera)
+     │ filter: todos los predicados
+     ▼
+  matching ──────────────────────────▶ total
+     │ sort: clave 1, clave 2, ..., id  ← orden total y estable
+     ▼
+  [a b c d e f g h]
+         ▲ after(cursor = c): partition_point, búsqueda binaria
+           │ offset, limit(3)
+           ▼
+         [d e f] ── next = Cursor(f), porque queda g
+
+- offset: simple, pero si se inserta algo antes, la página "corre"
+- cursor: sigue después de la última entidad vista, aunque cambien datos
+- Corre en memoria sobre iter(): sirve igual para todos los backends
+  (SQLite podría traducir filtros a WHERE, pero un closure no es SQL)
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Person {
+        id: u64,
+        name: &'static str,
+        age: u32,
+    }
+
+    impl Entity for Person {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.id
+        }
+    }
+
+    fn people() -> Vec<Person> {
+        let raw = [
+            (5, "Eve", 30),
+            (1, "Ann", 30),
+            (4, "Bob", 25),
+            (2, "Cid", 40),
+            (3, "Dan", 25),
+        ];
+        raw.into_iter()
+            .map(|(id, name, age)| Person { id, name, age })
+            .collect()
+    }
+
+    fn ids(page: &Page<Person>) -> Vec<u64> {
+        page.items.iter().map(|p| p.id).collect()
+    }
+
+    #[test]
+    fn test_default_order_is_by_id() {
+        let page = Query::new().run(people());
+
+        assert_eq!(ids(&page), vec![1, 2, 3, 4, 5]);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn test_filters_and_multi_key_sort() {
+        let query = Query::new()
+            .filter(|p: &Person| p.age < 40)
+            .sort_by(|p| p.age, SortOrder::Desc)
+            .sort_by(|p| p.name, SortOrder::Asc);
+
+        let page = query.run(people());
+
+        // age 30: Ann, Eve; age 25: Bob, Dan
+        assert_eq!(ids(&page), vec![1, 5, 4, 3]);
+        assert_eq!(page.total, 4);
+        assert_eq!(query.count(people()), 4);
+    }
+
+    #[test]
+    fn test_where_eq() {
+        let page = Query::new().where_eq(|p: &Person| p.age, 25).run(people());
+        assert_eq!(ids(&page), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_offset_and_limit() {
+        let page = Query::new().offset(1).limit(2).run(people());
+
+        assert_eq!(ids(&page), vec![2, 3]);
+        assert_eq!(page.total, 5);
+        assert!(page.next.is_some());
+
+        let past_end = Query::<Person>::new().offset(10).run(people());
+        assert!(past_end.items.is_empty());
+    }
+
+    #[test]
+    fn test_cursor_walks_every_entity_once() {
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let mut query = Query::new()
+                .sort_by(|p: &Person| p.age, SortOrder::Asc)
+                .limit(2);
+            if let Some(cursor) = cursor {
+                query = query.after(cursor);
+            }
+            let page = query.run(people());
+            seen.extend(ids(&page));
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, vec![3, 4, 1, 5, 2]);
+    }
+
+    #[test]
+    fn test_cursor_is_stable_when_data_changes() {
+        let first = Query::new().limit(2).run(people());
+        let cursor = first.next.unwrap();
+
+        // Entre páginas: se borra el 1 (ya visto) y se agrega el 0
+        let mut changed: Vec<Person> = people().into_iter().filter(|p| p.id != 1).collect();
+        changed.push(Person {
+            id: 0,
+            name: "New",
+            age: 20,
+        });
+
+        let second = Query::new().limit(2).after(cursor).run(changed);
+        assert_eq!(ids(&second), vec![3, 4]);
+    }
+}
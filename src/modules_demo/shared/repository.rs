@@ -0,0 +1,514 @@
+// Repository genérico: abstracción de persistencia compartida
+// Los servicios se escriben una vez contra el trait y el backend se intercambia
+
+use super::index::SecondaryIndexes;
+use super::query::{Page, Query};
+use super::unit_of_work::Transactional;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use thiserror::Error;
+
+// ============================================================
+// ENTITY
+// ============================================================
+
+/// Toda entidad persistible expone su identificador.
+pub trait Entity: Clone {
+    type Id: Copy + Eq + Hash + Debug;
+
+    fn id(&self) -> Self::Id;
+
+    /// Valores que no pueden repetirse entre entidades: el equivalente a
+    /// un `UNIQUE` del esquema. Cada par es (restricción, valor).
+    fn unique_keys(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// Índices secundarios no únicos, el equivalente a un `CREATE INDEX`:
+    /// (índice, valor). Varias entidades comparten valor.
+    fn index_keys(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// Versión para control de concurrencia optimista: cuántas veces se
+    /// guardó (ver `Repository::update_versioned`). Sin versionar, 0.
+    fn version(&self) -> u64 {
+        0
+    }
+
+    /// La misma entidad con otra versión; la asigna el repositorio.
+    fn with_version(self, _version: u64) -> Self {
+        self
+    }
+}
+
+/// La entidad a guardar en un update condicional, con la versión
+/// siguiente, o el error si `stored` ya no es la versión que se leyó.
+pub(crate) fn next_version<T: Entity>(stored: Option<&T>, entity: &T) -> RepoResult<T> {
+    let stored = stored.ok_or_else(|| RepositoryError::NotFound(format!("{:?}", entity.id())))?;
+    if stored.version() != entity.version() {
+        return Err(RepositoryError::VersionConflict {
+            id: format!("{:?}", entity.id()),
+            expected: entity.version(),
+            actual: stored.version(),
+        });
+    }
+    Ok(entity.clone().with_version(entity.version() + 1))
+}
+
+pub(super) fn has_key(keys: Vec<(&'static str, String)>, index: &str, key: &str) -> bool {
+    keys.iter().any(|(i, k)| *i == index && k == key)
+}
+
+// ============================================================
+// ERRORES
+// ============================================================
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RepositoryError {
+    #[error("entity {0} not found")]
+    NotFound(String),
+
+    #[error("entity {0} already exists")]
+    AlreadyExists(String),
+
+    /// Otra entidad ya tiene ese valor único (p.ej. "users.email").
+    #[error("unique constraint violated: {0}")]
+    UniqueViolation(String),
+
+    /// La entidad referencia a otra que no existe (o se borra una referenciada).
+    #[error("foreign key constraint violated: {0}")]
+    ForeignKeyViolation(String),
+
+    /// Otro escritor la guardó después de que se leyó (optimistic locking).
+    #[error("entity {id} was modified: expected version {expected}, found {actual}")]
+    VersionConflict {
+        id: String,
+        expected: u64,
+        actual: u64,
+    },
+
+    #[error("storage failure: {0}")]
+    Storage(String),
+
+    /// Los datos persistidos existen pero no son legibles/confiables.
+    #[error("corrupted data: {0}")]
+    Corrupted(String),
+}
+
+pub type RepoResult<T> = Result<T, RepositoryError>;
+
+// ============================================================
+// TRAIT
+// ============================================================
+
+/// CRUD genérico sobre entidades identificadas por `Id`.
+///
+/// Las lecturas devuelven valores owned (no `&T`) para que backends
+/// que no viven en memoria (archivos, SQL) puedan implementarlo.
+pub trait Repository<T, Id>
+where
+    T: Entity<Id = Id>,
+    Id: Copy + Debug,
+{
+    /// Inserta o reemplaza (upsert).
+    fn save(&mut self, entity: T) -> RepoResult<()>;
+
+    fn find_by_id(&self, id: Id) -> RepoResult<Option<T>>;
+
+    /// Elimina y devuelve la entidad, `None` si no existía.
+    fn delete(&mut self, id: Id) -> RepoResult<Option<T>>;
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = T> + '_>>;
+
+    // Métodos con implementación por defecto
+
+    /// Inserta solo si el id no existe todavía.
+    fn insert(&mut self, entity: T) -> RepoResult<()> {
+        if self.exists(entity.id())? {
+            return Err(RepositoryError::AlreadyExists(format!("{:?}", entity.id())));
+        }
+        self.save(entity)
+    }
+
+    /// Reemplaza solo si el id ya existe.
+    fn update(&mut self, entity: T) -> RepoResult<()> {
+        if !self.exists(entity.id())? {
+            return Err(RepositoryError::NotFound(format!("{:?}", entity.id())));
+        }
+        self.save(entity)
+    }
+
+    /// Reemplaza solo si nadie la guardó desde que se leyó: la versión
+    /// guardada tiene que ser `entity.version()`. Devuelve lo guardado,
+    /// con la versión siguiente. `save` sigue pisando sin mirar.
+    fn update_versioned(&mut self, entity: T) -> RepoResult<T> {
+        let next = next_version(self.find_by_id(entity.id())?.as_ref(), &entity)?;
+        self.save(next.clone())?;
+        Ok(next)
+    }
+
+    fn find_all(&self) -> RepoResult<Vec<T>> {
+        Ok(self.iter()?.collect())
+    }
+
+    fn exists(&self, id: Id) -> RepoResult<bool> {
+        Ok(self.find_by_id(id)?.is_some())
+    }
+
+    fn count(&self) -> RepoResult<usize> {
+        Ok(self.iter()?.count())
+    }
+
+    /// Busca por un índice único (`Entity::unique_keys`). Por defecto
+    /// recorre todo; los backends con índices lo resuelven en O(1).
+    fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        Ok(self.iter()?.find(|e| has_key(e.unique_keys(), index, key)))
+    }
+
+    /// Busca por un índice multi-valor (`Entity::index_keys`).
+    fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        Ok(self
+            .iter()?
+            .filter(|e| has_key(e.index_keys(), index, key))
+            .collect())
+    }
+
+    /// Filtra, ordena y pagina (ver query.rs). Orden determinista en
+    /// cualquier backend.
+    fn query(&self, query: &Query<T>) -> RepoResult<Page<T>>
+    where
+        Id: Ord,
+    {
+        Ok(query.run(self.iter()?))
+    }
+
+    fn count_where(&self, query: &Query<T>) -> RepoResult<usize>
+    where
+        Id: Ord,
+    {
+        Ok(query.count(self.iter()?))
+    }
+}
+
+// ============================================================
+// IMPLEMENTACIÓN EN MEMORIA
+// ============================================================
+
+pub struct InMemoryRepository<T: Entity> {
+    storage: HashMap<T::Id, T>,
+    indexes: SecondaryIndexes<T>,
+    /// Copia tomada en `begin`: rollback la restaura, commit la descarta.
+    snapshot: Option<Snapshot<T>>,
+}
+
+type Snapshot<T> = (HashMap<<T as Entity>::Id, T>, SecondaryIndexes<T>);
+
+impl<T: Entity> InMemoryRepository<T> {
+    pub fn new() -> Self {
+        Self {
+            storage: HashMap::new(),
+            indexes: SecondaryIndexes::new(),
+            snapshot: None,
+        }
+    }
+}
+
+impl<T: Entity> Default for InMemoryRepository<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Entity> Repository<T, T::Id> for InMemoryRepository<T> {
+    fn save(&mut self, entity: T) -> RepoResult<()> {
+        self.indexes.insert(&entity)?;
+        self.storage.insert(entity.id(), entity);
+        Ok(())
+    }
+
+    fn find_by_id(&self, id: T::Id) -> RepoResult<Option<T>> {
+        Ok(self.storage.get(&id).cloned())
+    }
+
+    fn delete(&mut self, id: T::Id) -> RepoResult<Option<T>> {
+        self.indexes.remove(id);
+        Ok(self.storage.remove(&id))
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = T> + '_>> {
+        Ok(Box::new(self.storage.values().cloned()))
+    }
+
+    // Overrides: O(1) en lugar de recorrer el iterador
+    fn exists(&self, id: T::Id) -> RepoResult<bool> {
+        Ok(self.storage.contains_key(&id))
+    }
+
+    fn count(&self) -> RepoResult<usize> {
+        Ok(self.storage.len())
+    }
+
+    fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        Ok(self
+            .indexes
+            .unique_id(index, key)
+            .and_then(|id| self.storage.get(&id).cloned()))
+    }
+
+    fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        Ok(self
+            .indexes
+            .ids(index, key)
+            .iter()
+            .filter_map(|id| self.storage.get(id).cloned())
+            .collect())
+    }
+}
+
+impl<T: Entity> Transactional for InMemoryRepository<T> {
+    fn begin(&mut self) -> RepoResult<()> {
+        if self.snapshot.is_some() {
+            return Err(RepositoryError::Storage(
+                "transaction already in progress".to_string(),
+            ));
+        }
+        self.snapshot = Some((self.storage.clone(), self.indexes.clone()));
+        Ok(())
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.snapshot
+            .take()
+            .map(drop)
+            .ok_or_else(|| RepositoryError::Storage("no transaction in progress".to_string()))
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        let (storage, indexes) = self
+            .snapshot
+            .take()
+            .ok_or_else(|| RepositoryError::Storage("no transaction in progress".to_string()))?;
+        self.storage = storage;
+        self.indexes = indexes;
+        Ok(())
+    }
+}
+
+/*
+DISEÑO:
+
+1. Repository<T, Id> define el contrato mínimo (save/find/delete/iter)
+   - insert, update, find_all, exists, count tienen default
+   - Un backend nuevo implementa 4 métodos y obtiene el resto
+
+2. Entity desacopla "qué es el id" del repositorio
+   - InMemoryRepository<T> funciona para cualquier T: Entity
+
+3. Lecturas owned + RepoResult
+   - InMemory nunca falla, pero un backend en disco sí
+   - El contrato es el mismo para todos los backends
+
+4. Entity::unique_keys / index_keys declaran los índices de la entidad
+   - SQLite los tiene en el esquema (UNIQUE, CREATE INDEX)
+   - Los backends genéricos los mantienen con SecondaryIndexes (index.rs):
+     find_unique / find_indexed en O(1) y el mismo
+     RepositoryError::UniqueViolation en todos
+
+5. Entity::version + update_versioned: concurrencia optimista
+
+     editor A: lee v3 ──────────── update_versioned(v3) → guarda v4
+     editor B: lee v3 ─────────────────── update_versioned(v3) → VersionConflict
+                                                                  { expected 3, actual 4 }
+
+   - Nadie bloquea mientras se edita; el segundo en guardar se entera
+   - El chequeo y la escritura no pueden separarse: &mut self en los
+     backends en memoria, un UPDATE ... WHERE version = ? en SQLite
+
+Los repositorios de cada dominio (UserRepository, OrderRepository)
+son traits que extienden Repository con búsquedas específicas.
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Item {
+        id: u64,
+        name: String,
+    }
+
+    impl Entity for Item {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.id
+        }
+    }
+
+    fn item(id: u64, name: &str) -> Item {
+        Item {
+            id,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_crud() {
+        let mut repo = InMemoryRepository::new();
+        repo.save(item(1, "a")).unwrap();
+        repo.save(item(2, "b")).unwrap();
+
+        assert_eq!(repo.count().unwrap(), 2);
+        assert!(repo.exists(1).unwrap());
+        assert_eq!(repo.find_by_id(2).unwrap(), Some(item(2, "b")));
+
+        repo.save(item(2, "b2")).unwrap();
+        assert_eq!(repo.find_by_id(2).unwrap().unwrap().name, "b2");
+
+        assert_eq!(repo.delete(1).unwrap(), Some(item(1, "a")));
+        assert!(!repo.exists(1).unwrap());
+        assert_eq!(repo.find_all().unwrap(), vec![item(2, "b2")]);
+    }
+
+    #[test]
+    fn test_insert_rejects_duplicate() {
+        let mut repo = InMemoryRepository::new();
+        repo.insert(item(1, "a")).unwrap();
+
+        let result = repo.insert(item(1, "other"));
+        assert_eq!(result, Err(RepositoryError::AlreadyExists("1".to_string())));
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Account {
+        id: u64,
+        email: String,
+    }
+
+    impl Entity for Account {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.id
+        }
+
+        fn unique_keys(&self) -> Vec<(&'static str, String)> {
+            vec![("accounts.email", self.email.clone())]
+        }
+    }
+
+    #[test]
+    fn test_unique_keys_are_enforced() {
+        let account = |id: u64, email: &str| Account {
+            id,
+            email: email.to_string(),
+        };
+        let mut repo = InMemoryRepository::new();
+        repo.save(account(1, "a@test.com")).unwrap();
+
+        let result = repo.save(account(2, "a@test.com"));
+        assert_eq!(
+            result,
+            Err(RepositoryError::UniqueViolation(
+                "accounts.email".to_string()
+            ))
+        );
+
+        // Re-guardar la misma entidad no choca consigo misma
+        repo.save(account(1, "a@test.com")).unwrap();
+        assert_eq!(repo.count().unwrap(), 1);
+
+        // El índice sigue al update y al delete
+        repo.save(account(1, "b@test.com")).unwrap();
+        assert_eq!(repo.find_unique("accounts.email", "a@test.com"), Ok(None));
+        repo.save(account(2, "a@test.com")).unwrap();
+        assert_eq!(
+            repo.find_unique("accounts.email", "a@test.com"),
+            Ok(Some(account(2, "a@test.com")))
+        );
+        repo.delete(2).unwrap();
+        assert_eq!(repo.find_unique("accounts.email", "a@test.com"), Ok(None));
+    }
+
+    #[test]
+    fn test_rollback_restores_snapshot() {
+        let mut repo = InMemoryRepository::new();
+        repo.save(item(1, "a")).unwrap();
+
+        repo.begin().unwrap();
+        repo.save(item(1, "changed")).unwrap();
+        repo.save(item(2, "new")).unwrap();
+        repo.rollback().unwrap();
+        assert_eq!(repo.find_all().unwrap(), vec![item(1, "a")]);
+
+        repo.begin().unwrap();
+        assert!(repo.begin().is_err());
+        repo.delete(1).unwrap();
+        repo.commit().unwrap();
+        assert_eq!(repo.count().unwrap(), 0);
+        assert!(repo.commit().is_err());
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Doc {
+        id: u64,
+        text: &'static str,
+        version: u64,
+    }
+
+    impl Entity for Doc {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.id
+        }
+
+        fn version(&self) -> u64 {
+            self.version
+        }
+
+        fn with_version(self, version: u64) -> Self {
+            Self { version, ..self }
+        }
+    }
+
+    #[test]
+    fn test_update_versioned_rejects_stale_copy() {
+        let mut repo = InMemoryRepository::new();
+        let doc = |text, version| Doc {
+            id: 1,
+            text,
+            version,
+        };
+        repo.insert(doc("draft", 1)).unwrap();
+
+        // Los dos editores leyeron la versión 1
+        let saved = repo.update_versioned(doc("from A", 1)).unwrap();
+        assert_eq!(saved.version, 2);
+
+        assert_eq!(
+            repo.update_versioned(doc("from B", 1)),
+            Err(RepositoryError::VersionConflict {
+                id: "1".to_string(),
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(repo.find_by_id(1).unwrap(), Some(doc("from A", 2)));
+        assert!(matches!(
+            repo.update_versioned(Doc { id: 9, ..saved }),
+            Err(RepositoryError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_update_requires_existing() {
+        let mut repo: InMemoryRepository<Item> = InMemoryRepository::new();
+
+        let result = repo.update(item(7, "x"));
+        assert_eq!(result, Err(RepositoryError::NotFound("7".to_string())));
+    }
+}
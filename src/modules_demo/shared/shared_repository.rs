@@ -0,0 +1,333 @@
+// SharedRepository: un repositorio con varios dueños (servicios, un relay, ...)
+// Participa de un UnitOfWork una sola vez aunque lo incluyan varios de ellos
+
+use super::repository::{Entity, RepoResult, Repository, RepositoryError, has_key};
+use super::unit_of_work::Transactional;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Cualquier backend que pueda compartirse: Repository + Transactional.
+pub trait SharedStore<T: Entity>: Repository<T, T::Id> + Transactional + Send {}
+
+impl<T: Entity, R> SharedStore<T> for R where R: Repository<T, T::Id> + Transactional + Send {}
+
+/// Escrituras de la transacción abierta, todavía sin confirmar:
+/// id → valor nuevo (`None` = borrado).
+type Writes<T> = HashMap<<T as Entity>::Id, Option<T>>;
+
+struct State<T: Entity> {
+    store: Box<dyn SharedStore<T>>,
+    /// Cuántos participantes de un UnitOfWork hicieron `begin`
+    depth: u32,
+    /// Alguno pidió rollback: el último en salir deshace en lugar de confirmar
+    rollback_only: bool,
+    /// Solo las ven los clones que participan; llegan al backend en el
+    /// último commit
+    writes: Writes<T>,
+}
+
+/// Clonar es barato: todos los clones usan el mismo backend (como
+/// `SqliteDatabase`, pero para cualquier Repository transaccional).
+///
+/// Lo que escribe un clone que hizo `begin` queda aparte hasta el commit:
+/// un rollback descarta solo eso, no lo que escribieron mientras tanto
+/// los clones que no participan (otro servicio, el relay).
+pub struct SharedRepository<T: Entity> {
+    state: Arc<Mutex<State<T>>>,
+    /// Este clone hizo `begin` y todavía no cerró
+    joined: bool,
+}
+
+impl<T: Entity> Clone for SharedRepository<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+            joined: false,
+        }
+    }
+}
+
+impl<T: Entity> SharedRepository<T> {
+    pub fn new(store: impl SharedStore<T> + 'static) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                store: Box::new(store),
+                depth: 0,
+                rollback_only: false,
+                writes: HashMap::new(),
+            })),
+            joined: false,
+        }
+    }
+
+    /// Corre `f` con acceso exclusivo al backend. Dentro de la transacción
+    /// de este clone, `f` ve (y escribe) sus cambios sin confirmar.
+    pub fn with<R>(
+        &self,
+        f: impl FnOnce(&mut dyn Repository<T, T::Id>) -> RepoResult<R>,
+    ) -> RepoResult<R> {
+        let mut state = self.lock()?;
+        let State { store, writes, .. } = &mut *state;
+        if self.joined {
+            f(&mut Pending {
+                store: store.as_mut(),
+                writes,
+            })
+        } else {
+            f(store.as_mut())
+        }
+    }
+
+    /// Corre `f` sobre lo confirmado, o devuelve `None` si hay un
+    /// UnitOfWork abierto. Chequeo y lectura bajo el mismo lock: nadie
+    /// puede abrir ni confirmar una transacción en el medio.
+    pub fn with_committed<R>(
+        &self,
+        f: impl FnOnce(&dyn Repository<T, T::Id>) -> RepoResult<R>,
+    ) -> RepoResult<Option<R>> {
+        let state = self.lock()?;
+        if state.depth > 0 {
+            return Ok(None);
+        }
+        f(state.store.as_ref()).map(Some)
+    }
+
+    fn lock(&self) -> RepoResult<MutexGuard<'_, State<T>>> {
+        self.state
+            .lock()
+            .map_err(|_| RepositoryError::Storage("shared repository lock poisoned".to_string()))
+    }
+}
+
+// Solo el primer begin abre y solo el último commit escribe
+impl<T: Entity> Transactional for SharedRepository<T> {
+    fn begin(&mut self) -> RepoResult<()> {
+        let mut state = self.lock()?;
+        if state.depth == 0 {
+            state.writes.clear();
+            state.rollback_only = false;
+        }
+        state.depth += 1;
+        drop(state);
+        self.joined = true;
+        Ok(())
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        let mut state = self.close()?;
+        match (state.depth, state.rollback_only) {
+            (0, false) => {
+                let writes = std::mem::take(&mut state.writes);
+                flush(state.store.as_mut(), writes)
+            }
+            (0, true) => {
+                state.writes.clear();
+                Err(RepositoryError::Storage(
+                    "transaction was rolled back by another participant".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        let mut state = self.close()?;
+        state.rollback_only = true;
+        if state.depth == 0 {
+            state.writes.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<T: Entity> SharedRepository<T> {
+    fn close(&mut self) -> RepoResult<MutexGuard<'_, State<T>>> {
+        self.joined = false;
+        let mut state = self.lock()?;
+        state.depth = state
+            .depth
+            .checked_sub(1)
+            .ok_or_else(|| RepositoryError::Storage("no transaction in progress".to_string()))?;
+        Ok(state)
+    }
+}
+
+// Todas las escrituras en una transacción del backend: en archivo, una
+// sola escritura; si alguna falla, ninguna
+fn flush<T: Entity>(store: &mut dyn SharedStore<T>, writes: Writes<T>) -> RepoResult<()> {
+    store.begin()?;
+    let applied = writes
+        .into_iter()
+        .try_for_each(|(id, entity)| match entity {
+            Some(entity) => store.save(entity),
+            None => store.delete(id).map(drop),
+        });
+    match applied {
+        Ok(()) => store.commit(),
+        Err(e) => {
+            store.rollback()?;
+            Err(e)
+        }
+    }
+}
+
+// ============================================================
+// VISTA DE UN PARTICIPANTE: backend + sus escrituras pendientes
+// ============================================================
+
+struct Pending<'a, T: Entity> {
+    store: &'a mut dyn SharedStore<T>,
+    writes: &'a mut Writes<T>,
+}
+
+impl<T: Entity> Pending<'_, T> {
+    fn pending(&self) -> impl Iterator<Item = &T> {
+        self.writes.values().flatten()
+    }
+}
+
+impl<T: Entity> Repository<T, T::Id> for Pending<'_, T> {
+    fn save(&mut self, entity: T) -> RepoResult<()> {
+        self.writes.insert(entity.id(), Some(entity));
+        Ok(())
+    }
+
+    fn find_by_id(&self, id: T::Id) -> RepoResult<Option<T>> {
+        match self.writes.get(&id) {
+            Some(written) => Ok(written.clone()),
+            None => self.store.find_by_id(id),
+        }
+    }
+
+    fn delete(&mut self, id: T::Id) -> RepoResult<Option<T>> {
+        let before = self.find_by_id(id)?;
+        self.writes.insert(id, None);
+        Ok(before)
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = T> + '_>> {
+        let stored = self
+            .store
+            .iter()?
+            .filter(|e| !self.writes.contains_key(&e.id()));
+        Ok(Box::new(stored.chain(self.pending().cloned())))
+    }
+
+    fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        if let Some(found) = self
+            .pending()
+            .find(|e| has_key(e.unique_keys(), index, key))
+        {
+            return Ok(Some(found.clone()));
+        }
+        Ok(self
+            .store
+            .find_unique(index, key)?
+            .filter(|e| !self.writes.contains_key(&e.id())))
+    }
+
+    fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        let mut found: Vec<T> = self
+            .store
+            .find_indexed(index, key)?
+            .into_iter()
+            .filter(|e| !self.writes.contains_key(&e.id()))
+            .collect();
+        found.extend(
+            self.pending()
+                .filter(|e| has_key(e.index_keys(), index, key))
+                .cloned(),
+        );
+        Ok(found)
+    }
+}
+
+/*
+VARIOS DUEÑOS, UNA TRANSACCIÓN:
+
+  UnitOfWork::new((&mut users, &mut orders))
+     users.begin  ─▶ outbox.begin  depth 0→1  store.begin()
+     orders.begin ─▶ outbox.begin  depth 1→2
+     ...
+     users.commit  ─▶ depth 2→1
+     orders.commit ─▶ depth 1→0  escrituras pendientes → store, en una
+                                 transacción del backend (o se descartan
+                                 si alguno pidió rollback)
+
+- Mismo esquema que SqliteDatabase, para InMemory/JsonFile: los usan
+  Outbox y AuditLog, que comparten varios servicios
+- Las escrituras de la transacción no tocan el backend hasta el commit:
+  un clone que no participa (el relay, otro servicio) ni las ve ni las
+  pierde si hay rollback. Deshacer con un snapshot del backend entero
+  borraría también lo que él escribió mientras tanto
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules_demo::shared::InMemoryRepository;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Row(u64);
+
+    impl Entity for Row {
+        type Id = u64;
+
+        fn id(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_nested_begin_commits_once_and_any_rollback_wins() {
+        let mut first = SharedRepository::new(InMemoryRepository::new());
+        let mut second = first.clone();
+
+        first.begin().unwrap();
+        second.begin().unwrap();
+        first.with(|repo| repo.save(Row(1))).unwrap();
+        first.commit().unwrap();
+        assert_eq!(first.with_committed(|repo| repo.count()), Ok(None));
+        second.commit().unwrap();
+        assert_eq!(first.with_committed(|repo| repo.count()), Ok(Some(1)));
+        assert_eq!(first.with(|repo| repo.count()), Ok(1));
+
+        first.begin().unwrap();
+        second.begin().unwrap();
+        second.with(|repo| repo.save(Row(2))).unwrap();
+        second.rollback().unwrap();
+        assert!(first.commit().is_err());
+        assert_eq!(first.with(|repo| repo.count()), Ok(1));
+    }
+
+    #[test]
+    fn test_rollback_keeps_writes_from_clones_outside_the_transaction() {
+        let mut inside = SharedRepository::new(InMemoryRepository::new());
+        let outside = inside.clone();
+
+        inside.begin().unwrap();
+        inside.with(|repo| repo.save(Row(1))).unwrap();
+        outside.with(|repo| repo.save(Row(2))).unwrap();
+
+        // Cada uno ve lo suyo: lo no confirmado no se filtra afuera
+        assert_eq!(inside.with(|repo| repo.count()), Ok(2));
+        assert_eq!(outside.with(|repo| repo.find_all()), Ok(vec![Row(2)]));
+
+        inside.rollback().unwrap();
+        assert_eq!(outside.with(|repo| repo.find_all()), Ok(vec![Row(2)]));
+
+        inside.begin().unwrap();
+        inside.with(|repo| repo.save(Row(3))).unwrap();
+        outside.with(|repo| repo.save(Row(4))).unwrap();
+        inside.commit().unwrap();
+
+        let mut ids: Vec<_> = outside
+            .with(|repo| repo.find_all())
+            .unwrap()
+            .into_iter()
+            .map(|row| row.0)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, [2, 3, 4]);
+    }
+}
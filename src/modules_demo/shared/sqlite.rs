@@ -0,0 +1,362 @@
+// Base SQLite compartida: conexión, migraciones versionadas y traducción de errores
+// Los repositorios SQLite de cada dominio usan la misma base (y así sus foreign keys)
+
+use super::money::{Currency, Money};
+use super::repository::{RepoResult, RepositoryError};
+use super::unit_of_work::Transactional;
+use rusqlite::types::Type;
+use rusqlite::{Connection, Row, ffi};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// ============================================================
+// MIGRACIONES
+// ============================================================
+
+/// Un paso del esquema. Se aplica una sola vez, identificado por `name`.
+///
+/// Cada dominio declara las suyas; los nombres llevan el prefijo de la
+/// tabla ("users_001_...") para que no choquen entre dominios.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+// ============================================================
+// DATABASE
+// ============================================================
+
+/// Conexión compartida. Clonar es barato: todos los clones usan la misma base.
+#[derive(Clone)]
+pub struct SqliteDatabase {
+    session: Arc<Mutex<Session>>,
+}
+
+struct Session {
+    conn: Connection,
+    /// Cuántos participantes de un UnitOfWork hicieron `begin` sobre esta base
+    depth: u32,
+    /// Alguno pidió rollback: el último en salir deshace en lugar de confirmar
+    rollback_only: bool,
+}
+
+impl SqliteDatabase {
+    pub fn open(path: impl AsRef<Path>) -> RepoResult<Self> {
+        Self::configure(Connection::open(path).map_err(sql_error)?)
+    }
+
+    /// Base efímera: vive mientras viva algún clone.
+    pub fn open_in_memory() -> RepoResult<Self> {
+        Self::configure(Connection::open_in_memory().map_err(sql_error)?)
+    }
+
+    fn configure(conn: Connection) -> RepoResult<Self> {
+        // SQLite trae las foreign keys apagadas por compatibilidad
+        conn.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE IF NOT EXISTS schema_migrations (
+                 name       TEXT PRIMARY KEY,
+                 applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+             );",
+        )
+        .map_err(sql_error)?;
+
+        Ok(Self {
+            session: Arc::new(Mutex::new(Session {
+                conn,
+                depth: 0,
+                rollback_only: false,
+            })),
+        })
+    }
+
+    /// Aplica, en orden, las migraciones que todavía no corrieron.
+    /// Todo en una transacción: si una falla, no queda ninguna a medias.
+    /// Devuelve cuántas aplicó.
+    pub fn migrate(&self, migrations: &[Migration]) -> RepoResult<usize> {
+        self.transaction(|tx| {
+            let mut applied = 0;
+            for migration in migrations {
+                let done: bool = tx.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM schema_migrations WHERE name = ?1)",
+                    [migration.name],
+                    |row| row.get(0),
+                )?;
+                if done {
+                    continue;
+                }
+
+                tx.execute_batch(migration.sql)?;
+                tx.execute(
+                    "INSERT INTO schema_migrations (name) VALUES (?1)",
+                    [migration.name],
+                )?;
+                applied += 1;
+            }
+            Ok(applied)
+        })
+    }
+
+    pub fn applied_migrations(&self) -> RepoResult<Vec<String>> {
+        self.with_connection(|conn| {
+            let mut stmt = conn.prepare("SELECT name FROM schema_migrations ORDER BY rowid")?;
+            let names = stmt.query_map([], |row| row.get(0))?;
+            names.collect()
+        })
+    }
+
+    /// Ejecuta `f` con la conexión tomada.
+    pub fn with_connection<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> rusqlite::Result<T>,
+    ) -> RepoResult<T> {
+        f(&self.lock()?.conn).map_err(sql_error)
+    }
+
+    /// Ejecuta `f` de forma atómica: todo o nada.
+    ///
+    /// Es un SAVEPOINT, no un BEGIN: funciona igual suelto que dentro de
+    /// la transacción de un UnitOfWork.
+    pub fn transaction<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> rusqlite::Result<T>,
+    ) -> RepoResult<T> {
+        let mut session = self.lock()?;
+        let savepoint = session.conn.savepoint().map_err(sql_error)?;
+        let value = f(&savepoint).map_err(sql_error)?;
+        savepoint.commit().map_err(sql_error)?;
+        Ok(value)
+    }
+
+    fn lock(&self) -> RepoResult<MutexGuard<'_, Session>> {
+        self.session
+            .lock()
+            .map_err(|_| RepositoryError::Storage("sqlite connection poisoned".to_string()))
+    }
+}
+
+// Varios repositorios comparten la base, así que cada uno hace su `begin`:
+// solo el primero abre la transacción y solo el último la cierra.
+impl Transactional for SqliteDatabase {
+    fn begin(&mut self) -> RepoResult<()> {
+        let mut session = self.lock()?;
+        if session.depth == 0 {
+            session.conn.execute_batch("BEGIN").map_err(sql_error)?;
+            session.rollback_only = false;
+        }
+        session.depth += 1;
+        Ok(())
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        let mut session = self.lock()?;
+        session.depth = session
+            .depth
+            .checked_sub(1)
+            .ok_or_else(|| RepositoryError::Storage("no transaction in progress".to_string()))?;
+
+        match (session.depth, session.rollback_only) {
+            (0, false) => session.conn.execute_batch("COMMIT").map_err(sql_error),
+            (0, true) => {
+                session.conn.execute_batch("ROLLBACK").map_err(sql_error)?;
+                Err(RepositoryError::Storage(
+                    "transaction was rolled back by another participant".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        let mut session = self.lock()?;
+        session.depth = session
+            .depth
+            .checked_sub(1)
+            .ok_or_else(|| RepositoryError::Storage("no transaction in progress".to_string()))?;
+        session.rollback_only = true;
+
+        if session.depth == 0 {
+            session.conn.execute_batch("ROLLBACK").map_err(sql_error)?;
+        }
+        Ok(())
+    }
+}
+
+// ============================================================
+// CONVERSIONES
+// ============================================================
+
+/// Traduce un error de SQLite al idioma del Repository: las restricciones
+/// del esquema se vuelven errores tipados, no strings opacos.
+pub fn sql_error(e: rusqlite::Error) -> RepositoryError {
+    match &e {
+        rusqlite::Error::SqliteFailure(failure, message)
+            if failure.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            // "UNIQUE constraint failed: users.email" → "users.email"
+            let message = message.clone().unwrap_or_else(|| e.to_string());
+            let detail = match message.split_once(": ") {
+                Some((_, detail)) => detail.to_string(),
+                None => message,
+            };
+
+            match failure.extended_code {
+                ffi::SQLITE_CONSTRAINT_PRIMARYKEY => RepositoryError::AlreadyExists(detail),
+                ffi::SQLITE_CONSTRAINT_UNIQUE => RepositoryError::UniqueViolation(detail),
+                ffi::SQLITE_CONSTRAINT_FOREIGNKEY => RepositoryError::ForeignKeyViolation(detail),
+                _ => RepositoryError::Storage(e.to_string()),
+            }
+        }
+        rusqlite::Error::FromSqlConversionFailure(..)
+        | rusqlite::Error::InvalidColumnType(..)
+        | rusqlite::Error::IntegralValueOutOfRange(..) => RepositoryError::Corrupted(e.to_string()),
+        _ => RepositoryError::Storage(e.to_string()),
+    }
+}
+
+/// Valor guardado que el dominio no acepta (termina como `Corrupted`).
+pub fn invalid_column(index: usize, reason: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(index, Type::Text, reason.into().into())
+}
+
+/// Money ocupa dos columnas: monto en unidades menores y código de moneda.
+pub fn money_column(row: &Row<'_>, amount: usize, currency: usize) -> rusqlite::Result<Money> {
+    let code: String = row.get(currency)?;
+    let currency_value: Currency = code
+        .parse()
+        .map_err(|_| invalid_column(currency, format!("unknown currency {code}")))?;
+
+    Ok(Money::new(row.get(amount)?, currency_value))
+}
+
+/*
+UNA BASE, VARIOS REPOSITORIOS:
+
+  SqliteDatabase (Arc<Mutex<Connection>>)
+     ├── SqliteUserRepository     → users
+     ├── SqliteOrderRepository    → orders, order_items, order_history
+     └── SqlitePaymentRepository  → payments
+
+  payments.order_id ──FK──▶ orders.id
+  orders.user_id    ──FK──▶ users.id
+
+- Las FKs solo funcionan si las tablas están en la MISMA base: por eso
+  los repositorios comparten la conexión en lugar de abrir la suya
+- Cada repositorio migra su propio esquema al construirse
+  (schema_migrations recuerda qué corrió; migrar dos veces es un no-op)
+- UnitOfWork: cada repositorio hace begin/commit sobre la base
+  compartida; un contador evita BEGIN anidados (el primero abre, el
+  último confirma, cualquier rollback marca todo como rollback_only)
+- Errores de restricción → RepositoryError tipado:
+    PRIMARY KEY → AlreadyExists
+    UNIQUE      → UniqueViolation("users.email")
+    FOREIGN KEY → ForeignKeyViolation
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &[Migration] = &[
+        Migration {
+            name: "notes_001_create",
+            sql: "CREATE TABLE notes (id INTEGER PRIMARY KEY, text TEXT NOT NULL UNIQUE);",
+        },
+        Migration {
+            name: "notes_002_add_owner",
+            sql: "CREATE TABLE owners (id INTEGER PRIMARY KEY);
+                  ALTER TABLE notes ADD COLUMN owner_id INTEGER REFERENCES owners(id);",
+        },
+    ];
+
+    fn insert_note(db: &SqliteDatabase, id: i64, text: &str, owner: Option<i64>) -> RepoResult<()> {
+        db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO notes (id, text, owner_id) VALUES (?1, ?2, ?3)",
+                rusqlite::params![id, text, owner],
+            )
+        })
+        .map(drop)
+    }
+
+    #[test]
+    fn test_migrations_apply_once_and_in_order() {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+
+        assert_eq!(db.migrate(&NOTES[..1]).unwrap(), 1);
+        // Una versión nueva del código trae una migración más
+        assert_eq!(db.migrate(NOTES).unwrap(), 1);
+        assert_eq!(db.migrate(NOTES).unwrap(), 0);
+
+        assert_eq!(
+            db.applied_migrations().unwrap(),
+            vec!["notes_001_create", "notes_002_add_owner"]
+        );
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        let broken = [
+            NOTES[0],
+            Migration {
+                name: "notes_002_broken",
+                sql: "ALTER TABLE missing ADD COLUMN x INTEGER;",
+            },
+        ];
+
+        assert!(db.migrate(&broken).is_err());
+        assert!(db.applied_migrations().unwrap().is_empty());
+        assert_eq!(db.migrate(NOTES).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_shared_transaction_commits_once() {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        db.migrate(NOTES).unwrap();
+        let count = || -> i64 {
+            db.with_connection(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM notes", [], |r| r.get(0))
+            })
+            .unwrap()
+        };
+
+        // Dos repositorios sobre la misma base: un solo BEGIN/COMMIT
+        let (mut a, mut b) = (db.clone(), db.clone());
+        a.begin().unwrap();
+        b.begin().unwrap();
+        insert_note(&db, 1, "a", None).unwrap();
+        a.commit().unwrap();
+        b.commit().unwrap();
+        assert_eq!(count(), 1);
+
+        // Un rollback de cualquiera deshace todo
+        a.begin().unwrap();
+        b.begin().unwrap();
+        insert_note(&db, 2, "b", None).unwrap();
+        b.rollback().unwrap();
+        assert!(a.commit().is_err());
+        assert_eq!(count(), 1);
+    }
+
+    #[test]
+    fn test_constraint_errors_are_typed() {
+        let db = SqliteDatabase::open_in_memory().unwrap();
+        db.migrate(NOTES).unwrap();
+        insert_note(&db, 1, "a", None).unwrap();
+
+        assert!(matches!(
+            insert_note(&db, 1, "b", None),
+            Err(RepositoryError::AlreadyExists(_))
+        ));
+        assert_eq!(
+            insert_note(&db, 2, "a", None),
+            Err(RepositoryError::UniqueViolation("notes.text".to_string()))
+        );
+        assert!(matches!(
+            insert_note(&db, 3, "c", Some(99)),
+            Err(RepositoryError::ForeignKeyViolation(_))
+        ));
+    }
+}
@@ -0,0 +1,161 @@
+// Utilidades de test compartidas por los dominios
+// Solo se compila con cfg(test)
+
+/// Corre los mismos tests contra varios backends. Cada test es una
+/// función genérica que recibe lo que construye el setup del backend:
+///
+/// ```text
+/// backend_tests! {
+///     [test_create_user, test_update_email]
+///     in_memory => UserService::new(),
+///     sqlite => sqlite_service(),
+/// }
+/// ```
+///
+/// Genera `in_memory::test_create_user`, `sqlite::test_create_user`, etc.
+///
+/// Con `async` delante de la lista, los tests y los setups son async y se
+/// generan como `#[tokio::test]`:
+///
+/// ```text
+/// backend_tests! {
+///     async [test_create_user]
+///     in_memory => in_memory_service(),
+/// }
+/// ```
+macro_rules! backend_tests {
+    (@async_backend $backend:ident, $setup:expr, [$($test:ident),+ $(,)?]) => {
+        mod $backend {
+            use super::*;
+
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    super::$test($setup.await).await;
+                }
+            )+
+        }
+    };
+    (@backend $backend:ident, $setup:expr, [$($test:ident),+ $(,)?]) => {
+        mod $backend {
+            use super::*;
+
+            $(
+                #[test]
+                fn $test() {
+                    super::$test($setup);
+                }
+            )+
+        }
+    };
+    (async $tests:tt $($backend:ident => $setup:expr),+ $(,)?) => {
+        $(
+            $crate::modules_demo::shared::testing::backend_tests!(@async_backend $backend, $setup, $tests);
+        )+
+    };
+    ($tests:tt $($backend:ident => $setup:expr),+ $(,)?) => {
+        $(
+            $crate::modules_demo::shared::testing::backend_tests!(@backend $backend, $setup, $tests);
+        )+
+    };
+}
+
+pub(crate) use backend_tests;
+
+// ============================================================
+// FAULTY REPOSITORY
+// ============================================================
+
+use super::repository::{Entity, InMemoryRepository, RepoResult, Repository, RepositoryError};
+use super::unit_of_work::Transactional;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Interruptores de `FaultyRepository`. Clonables: el test se queda con
+/// una copia y rompe el backend después de dárselo al servicio.
+#[derive(Clone, Default)]
+pub(crate) struct Faults {
+    reads: Arc<AtomicBool>,
+    writes: Arc<AtomicBool>,
+}
+
+impl Faults {
+    pub(crate) fn fail_reads(&self, on: bool) {
+        self.reads.store(on, Ordering::SeqCst);
+    }
+
+    pub(crate) fn fail_writes(&self, on: bool) {
+        self.writes.store(on, Ordering::SeqCst);
+    }
+
+    fn check(flag: &AtomicBool) -> RepoResult<()> {
+        if flag.load(Ordering::SeqCst) {
+            return Err(RepositoryError::Storage("injected fault".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// En memoria, pero falla a pedido: para probar qué queda cuando el
+/// backend deja de responder a mitad de una operación.
+pub(crate) struct FaultyRepository<T: Entity> {
+    inner: InMemoryRepository<T>,
+    faults: Faults,
+}
+
+impl<T: Entity> FaultyRepository<T> {
+    pub(crate) fn new() -> (Self, Faults) {
+        let faults = Faults::default();
+        let repo = Self {
+            inner: InMemoryRepository::new(),
+            faults: faults.clone(),
+        };
+        (repo, faults)
+    }
+}
+
+impl<T: Entity> Repository<T, T::Id> for FaultyRepository<T> {
+    fn save(&mut self, entity: T) -> RepoResult<()> {
+        Faults::check(&self.faults.writes)?;
+        self.inner.save(entity)
+    }
+
+    fn find_by_id(&self, id: T::Id) -> RepoResult<Option<T>> {
+        Faults::check(&self.faults.reads)?;
+        self.inner.find_by_id(id)
+    }
+
+    fn delete(&mut self, id: T::Id) -> RepoResult<Option<T>> {
+        Faults::check(&self.faults.writes)?;
+        self.inner.delete(id)
+    }
+
+    fn iter(&self) -> RepoResult<Box<dyn Iterator<Item = T> + '_>> {
+        Faults::check(&self.faults.reads)?;
+        self.inner.iter()
+    }
+
+    fn find_unique(&self, index: &str, key: &str) -> RepoResult<Option<T>> {
+        Faults::check(&self.faults.reads)?;
+        self.inner.find_unique(index, key)
+    }
+
+    fn find_indexed(&self, index: &str, key: &str) -> RepoResult<Vec<T>> {
+        Faults::check(&self.faults.reads)?;
+        self.inner.find_indexed(index, key)
+    }
+}
+
+impl<T: Entity> Transactional for FaultyRepository<T> {
+    fn begin(&mut self) -> RepoResult<()> {
+        self.inner.begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        self.inner.commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        self.inner.rollback()
+    }
+}
@@ -0,0 +1,248 @@
+// Unit of Work: varios repositorios, un solo commit (o un solo rollback)
+// Cada backend decide cómo deshacer; el coordinador solo decide cuándo
+
+use super::repository::{RepoResult, RepositoryError};
+
+// ============================================================
+// TRAIT
+// ============================================================
+
+/// Algo que puede participar de una unidad de trabajo: un repositorio,
+/// un servicio (delega en sus repositorios) o una tupla de ellos.
+pub trait Transactional {
+    /// Empieza a acumular cambios. No se admite anidar.
+    fn begin(&mut self) -> RepoResult<()>;
+
+    /// Confirma lo acumulado desde `begin`.
+    fn commit(&mut self) -> RepoResult<()>;
+
+    /// Descarta lo acumulado desde `begin`.
+    fn rollback(&mut self) -> RepoResult<()>;
+}
+
+impl<T: Transactional + ?Sized> Transactional for &mut T {
+    fn begin(&mut self) -> RepoResult<()> {
+        (**self).begin()
+    }
+
+    fn commit(&mut self) -> RepoResult<()> {
+        (**self).commit()
+    }
+
+    fn rollback(&mut self) -> RepoResult<()> {
+        (**self).rollback()
+    }
+}
+
+// Si un begin falla, los que ya empezaron se deshacen
+fn begin_all(parts: &mut [&mut dyn Transactional]) -> RepoResult<()> {
+    for i in 0..parts.len() {
+        if let Err(e) = parts[i].begin() {
+            let _ = rollback_all(&mut parts[..i]);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+// Si un commit falla, los que faltan se deshacen (los anteriores ya
+// confirmaron: por eso todos los participantes deberían compartir backend)
+fn commit_all(parts: &mut [&mut dyn Transactional]) -> RepoResult<()> {
+    for i in 0..parts.len() {
+        if let Err(e) = parts[i].commit() {
+            let _ = rollback_all(&mut parts[i + 1..]);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+// En orden inverso, y sin cortar en el primer error: todos deben intentarlo
+fn rollback_all(parts: &mut [&mut dyn Transactional]) -> RepoResult<()> {
+    let mut first_error = None;
+    for part in parts.iter_mut().rev() {
+        if let Err(e) = part.rollback() {
+            first_error.get_or_insert(e);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+macro_rules! transactional_tuple {
+    ($($part:ident $index:tt),+) => {
+        impl<$($part: Transactional),+> Transactional for ($($part,)+) {
+            fn begin(&mut self) -> RepoResult<()> {
+                begin_all(&mut [$(&mut self.$index as &mut dyn Transactional),+])
+            }
+
+            fn commit(&mut self) -> RepoResult<()> {
+                commit_all(&mut [$(&mut self.$index as &mut dyn Transactional),+])
+            }
+
+            fn rollback(&mut self) -> RepoResult<()> {
+                rollback_all(&mut [$(&mut self.$index as &mut dyn Transactional),+])
+            }
+        }
+    };
+}
+
+transactional_tuple!(A 0, B 1);
+transactional_tuple!(A 0, B 1, C 2);
+transactional_tuple!(A 0, B 1, C 2, D 3);
+
+// ============================================================
+// UNIT OF WORK
+// ============================================================
+
+/// Corre un bloque de trabajo sobre varios participantes como una unidad:
+/// si el bloque devuelve `Ok` se confirma todo, si devuelve `Err` no
+/// queda nada.
+///
+/// ```text
+/// let placed = UnitOfWork::new((&mut orders, &mut payments)).run(|(orders, payments)| {
+///     let order = orders.create_order(user_id, lines)?;
+///     payments.authorize(orders, order.id, order.total)
+/// });
+/// ```
+pub struct UnitOfWork<P> {
+    participants: P,
+}
+
+impl<P: Transactional> UnitOfWork<P> {
+    pub fn new(participants: P) -> Self {
+        Self { participants }
+    }
+
+    pub fn run<T, E>(mut self, work: impl FnOnce(&mut P) -> Result<T, E>) -> Result<T, E>
+    where
+        E: From<RepositoryError>,
+    {
+        self.participants.begin()?;
+
+        match work(&mut self.participants) {
+            Ok(value) => {
+                self.participants.commit()?;
+                Ok(value)
+            }
+            Err(e) => {
+                // Un rollback fallido es peor que el error original:
+                // el estado quedó incierto y eso es lo que hay que reportar
+                self.participants.rollback()?;
+                Err(e)
+            }
+        }
+    }
+}
+
+/*
+CÓMO DESHACE CADA BACKEND:
+
+  UnitOfWork::run(work)
+     │ begin   → todos los participantes
+     │ work(…) → los servicios operan normalmente
+     ├─ Ok  → commit   (en orden)
+     └─ Err → rollback (en orden inverso)
+
+  InMemoryRepository  begin = snapshot del HashMap, rollback = restaurarlo
+  SqliteDatabase      begin = BEGIN (una sola vez aunque la compartan
+                      varios repositorios), rollback = ROLLBACK
+
+- Atómico de verdad solo si los participantes comparten backend
+  (todo en memoria, o todo en la misma base SQLite). Mezclar backends
+  no tiene two-phase commit: si el último commit falla, los anteriores
+  ya confirmaron.
+  JsonFileRepository  begin = copia en memoria, commit = una escritura
+                      del archivo, rollback = restaurar la copia
+- LogStructured no implementa Transactional: no compila dentro de un
+  UnitOfWork (el compilador lo impide, no un test).
+- Los ids generados dentro de una unidad deshecha no se reutilizan.
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Participante que registra lo que le pidieron.
+    #[derive(Default)]
+    struct Probe {
+        log: Vec<&'static str>,
+        fail_on: Option<&'static str>,
+    }
+
+    impl Probe {
+        fn failing(on: &'static str) -> Self {
+            Self {
+                log: Vec::new(),
+                fail_on: Some(on),
+            }
+        }
+
+        fn record(&mut self, step: &'static str) -> RepoResult<()> {
+            self.log.push(step);
+            if self.fail_on == Some(step) {
+                return Err(RepositoryError::Storage(format!("{step} failed")));
+            }
+            Ok(())
+        }
+    }
+
+    impl Transactional for Probe {
+        fn begin(&mut self) -> RepoResult<()> {
+            self.record("begin")
+        }
+
+        fn commit(&mut self) -> RepoResult<()> {
+            self.record("commit")
+        }
+
+        fn rollback(&mut self) -> RepoResult<()> {
+            self.record("rollback")
+        }
+    }
+
+    #[test]
+    fn test_ok_commits_everyone() {
+        let (mut a, mut b) = (Probe::default(), Probe::default());
+
+        let result: RepoResult<u32> = UnitOfWork::new((&mut a, &mut b)).run(|_| Ok(7));
+
+        assert_eq!(result, Ok(7));
+        assert_eq!(a.log, vec!["begin", "commit"]);
+        assert_eq!(b.log, vec!["begin", "commit"]);
+    }
+
+    #[test]
+    fn test_err_rolls_back_everyone() {
+        let (mut a, mut b) = (Probe::default(), Probe::default());
+
+        let result: RepoResult<()> = UnitOfWork::new((&mut a, &mut b))
+            .run(|_| Err(RepositoryError::NotFound("x".to_string())));
+
+        assert_eq!(result, Err(RepositoryError::NotFound("x".to_string())));
+        assert_eq!(a.log, vec!["begin", "rollback"]);
+        assert_eq!(b.log, vec!["begin", "rollback"]);
+    }
+
+    #[test]
+    fn test_failed_begin_undoes_the_ones_already_started() {
+        let (mut a, mut b, mut c) = (Probe::default(), Probe::failing("begin"), Probe::default());
+
+        let result: RepoResult<()> = UnitOfWork::new((&mut a, &mut b, &mut c)).run(|_| Ok(()));
+
+        assert!(result.is_err());
+        assert_eq!(a.log, vec!["begin", "rollback"]);
+        assert_eq!(b.log, vec!["begin"]);
+        assert!(c.log.is_empty());
+    }
+
+    #[test]
+    fn test_failed_commit_rolls_back_the_rest() {
+        let (mut a, mut b, mut c) = (Probe::default(), Probe::failing("commit"), Probe::default());
+
+        let result: RepoResult<()> = UnitOfWork::new((&mut a, &mut b, &mut c)).run(|_| Ok(()));
+
+        assert!(result.is_err());
+        assert_eq!(a.log, vec!["begin", "commit"]);
+        assert_eq!(c.log, vec!["begin", "rollback"]);
+    }
+}
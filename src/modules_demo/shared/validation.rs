@@ -0,0 +1,440 @@
+// Validación: reglas reutilizables que acumulan TODOS los errores con su ruta
+// "name: must not be blank; items[1].quantity: must be between 1 and 1000"
+
+use super::error::{ErrorCode, ErrorKind};
+use super::money::Money;
+use regex::Regex;
+use std::fmt::{self, Display};
+use std::sync::LazyLock;
+use thiserror::Error;
+
+// ============================================================
+// ERRORES
+// ============================================================
+
+/// Un campo que no cumplió una regla. `path` es la ruta desde la raíz
+/// ("email", "items[2].quantity"); `code` identifica la regla.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub path: String,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Todos los errores de una validación, en el orden en que se
+/// encontraron. Nunca vacío.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// Rutas con error, en orden (para respuestas de API y tests).
+    pub fn paths(&self) -> Vec<&str> {
+        self.errors.iter().map(|e| e.path.as_str()).collect()
+    }
+
+    pub fn for_path(&self, path: &str) -> impl Iterator<Item = &FieldError> {
+        self.errors.iter().filter(move |e| e.path == path)
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ErrorCode for ValidationErrors {
+    fn code(&self) -> &'static str {
+        "VALIDATION_FAILED"
+    }
+
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Validation
+    }
+}
+
+// ============================================================
+// REGLAS
+// ============================================================
+
+/// Una condición sobre un valor `T`, con el código y el mensaje que se
+/// reportan si no se cumple.
+pub struct Rule<T: ?Sized> {
+    code: &'static str,
+    message: String,
+    test: Box<dyn Fn(&T) -> bool + Send + Sync>,
+}
+
+impl<T: ?Sized> Rule<T> {
+    pub fn check(&self, value: &T) -> bool {
+        (self.test)(value)
+    }
+}
+
+/// Regla ad-hoc: `custom("POSITIVE", "must be positive", Money::is_positive)`.
+pub fn custom<T: ?Sized>(
+    code: &'static str,
+    message: impl Into<String>,
+    test: impl Fn(&T) -> bool + Send + Sync + 'static,
+) -> Rule<T> {
+    Rule {
+        code,
+        message: message.into(),
+        test: Box::new(test),
+    }
+}
+
+/// Algo más que espacios.
+pub fn not_blank() -> Rule<str> {
+    custom("REQUIRED", "must not be blank", |s: &str| {
+        !s.trim().is_empty()
+    })
+}
+
+/// Al menos un elemento.
+pub fn not_empty<T: 'static>() -> Rule<[T]> {
+    custom("REQUIRED", "must not be empty", |items: &[T]| {
+        !items.is_empty()
+    })
+}
+
+/// Largo en caracteres (no bytes), ambos extremos incluidos.
+pub fn length(min: usize, max: usize) -> Rule<str> {
+    custom(
+        "LENGTH",
+        format!("must be between {min} and {max} characters"),
+        move |s: &str| (min..=max).contains(&s.chars().count()),
+    )
+}
+
+/// Tope sin mínimo: para campos que ya llevan `not_blank`.
+pub fn max_length(max: usize) -> Rule<str> {
+    custom(
+        "LENGTH",
+        format!("must be at most {max} characters"),
+        move |s: &str| s.chars().count() <= max,
+    )
+}
+
+/// `min` o más (cantidades, montos).
+pub fn at_least<N>(min: N) -> Rule<N>
+where
+    N: PartialOrd + Display + Send + Sync + 'static,
+{
+    let message = format!("must be at least {min}");
+    custom("RANGE", message, move |n: &N| *n >= min)
+}
+
+/// Entre `min` y `max`, ambos incluidos.
+pub fn range<N>(min: N, max: N) -> Rule<N>
+where
+    N: PartialOrd + Display + Send + Sync + 'static,
+{
+    let message = format!("must be between {min} and {max}");
+    custom("RANGE", message, move |n: &N| *n >= min && *n <= max)
+}
+
+/// Montos: precios, cobros, reembolsos.
+pub fn positive() -> Rule<Money> {
+    custom("POSITIVE", "must be positive", Money::is_positive)
+}
+
+/// El valor completo tiene que coincidir con `regex`. `description` es
+/// lo que ve quien llenó el campo ("letters, digits and dashes").
+///
+/// # Panics
+///
+/// Si `regex` no es una expresión válida: es un error de programación,
+/// no un dato de entrada.
+pub fn pattern(regex: &str, description: &str) -> Rule<str> {
+    let regex = Regex::new(&format!("^(?:{regex})$")).expect("invalid validation pattern");
+    custom(
+        "PATTERN",
+        format!("must be {description}"),
+        move |s: &str| regex.is_match(s),
+    )
+}
+
+/// Algo@algo.tld, sin espacios: lo mismo que pedían los chequeos
+/// sueltos ('@' y '.'), pero en el orden correcto.
+pub fn email() -> Rule<str> {
+    static EMAIL: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s.]+$").expect("valid regex"));
+
+    custom("EMAIL", "must be a valid email address", |s: &str| {
+        EMAIL.is_match(s)
+    })
+}
+
+// ============================================================
+// VALIDATOR
+// ============================================================
+
+/// Lo implementan los modelos: describe sus reglas sobre un `Validator`.
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+
+    /// Corre todas las reglas y devuelve todos los errores juntos.
+    fn validated(&self) -> Result<(), ValidationErrors> {
+        let mut v = Validator::new();
+        self.validate(&mut v);
+        v.finish()
+    }
+}
+
+/// Acumula errores sin cortar en el primero. Las rutas de `nested` y
+/// `each` se arman solas: "items[1].quantity".
+#[derive(Debug, Default)]
+pub struct Validator {
+    prefix: String,
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Un campo suelto (un argumento, no un modelo):
+    /// `Validator::check("reason", &reason, &[not_blank()])?`.
+    pub fn check<T: ?Sized>(
+        field: &str,
+        value: &T,
+        rules: &[Rule<T>],
+    ) -> Result<(), ValidationErrors> {
+        let mut v = Self::new();
+        v.field(field, value, rules);
+        v.finish()
+    }
+
+    /// Corre todas las `rules` sobre `value`; cada una que falle es un
+    /// error en `field`.
+    pub fn field<T: ?Sized>(&mut self, field: &str, value: &T, rules: &[Rule<T>]) -> &mut Self {
+        for rule in rules {
+            if !rule.check(value) {
+                self.fail(field, rule.code, rule.message.clone());
+            }
+        }
+        self
+    }
+
+    /// Un modelo dentro de otro: sus errores quedan bajo "field.".
+    pub fn nested<V: Validate + ?Sized>(&mut self, field: &str, value: &V) -> &mut Self {
+        let mut child = Validator {
+            prefix: self.path(field),
+            errors: Vec::new(),
+        };
+        value.validate(&mut child);
+        self.errors.append(&mut child.errors);
+        self
+    }
+
+    /// Cada elemento de una lista, con su índice: "field[i].".
+    pub fn each<V: Validate>(&mut self, field: &str, items: &[V]) -> &mut Self {
+        for (i, item) in items.iter().enumerate() {
+            self.nested(&format!("{field}[{i}]"), item);
+        }
+        self
+    }
+
+    /// Un error que ninguna regla describe (p.ej. entre dos campos).
+    pub fn fail(
+        &mut self,
+        field: &str,
+        code: &'static str,
+        message: impl Into<String>,
+    ) -> &mut Self {
+        self.errors.push(FieldError {
+            path: self.path(field),
+            code,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn finish(self) -> Result<(), ValidationErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors {
+                errors: self.errors,
+            })
+        }
+    }
+
+    fn path(&self, field: &str) -> String {
+        if self.prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{field}", self.prefix)
+        }
+    }
+}
+
+/*
+DE CHEQUEOS SUELTOS A REGLAS:
+
+  antes                                 ahora
+  ─────                                 ─────
+  if name.is_empty() { return Err }     impl Validate for User {
+  if !email.contains('@') { ... }         v.field("name", &self.name, &[not_blank(), max_length(100)])
+  → primer error, y se corta               .field("email", &self.email, &[email()]);
+                                        }
+                                        user.validated()? → todos los errores
+
+RUTAS ANIDADAS:
+
+  NewOrder { lines: [ {qty 2}, {qty 0}, {qty 0} ] }
+
+  v.each("items", &lines)
+    └ nested("items[1]") → field("quantity") → "items[1].quantity"
+    └ nested("items[2]") → field("quantity") → "items[2].quantity"
+
+  Err(ValidationErrors) = "items[1].quantity: must be between 1 and 1000;
+                           items[2].quantity: must be between 1 and 1000"
+
+- Cada dominio envuelve ValidationErrors en su error (UserError::Invalid,
+  OrderError::Invalid, ...): el código de dominio sigue siendo estable y
+  el detalle por campo viaja en `errors()`
+- `code` de cada FieldError es el de la regla (REQUIRED, LENGTH, RANGE,
+  PATTERN, EMAIL, o el de una regla custom)
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item {
+        sku: String,
+        quantity: u32,
+    }
+
+    impl Validate for Item {
+        fn validate(&self, v: &mut Validator) {
+            v.field(
+                "sku",
+                self.sku.as_str(),
+                &[pattern("[A-Z0-9-]+", "uppercase letters, digits or dashes")],
+            )
+            .field("quantity", &self.quantity, &[range(1, 10)]);
+        }
+    }
+
+    struct Order {
+        email: String,
+        items: Vec<Item>,
+    }
+
+    impl Validate for Order {
+        fn validate(&self, v: &mut Validator) {
+            v.field("email", self.email.as_str(), &[not_blank(), email()])
+                .field("items", self.items.as_slice(), &[not_empty()])
+                .each("items", &self.items);
+        }
+    }
+
+    fn item(sku: &str, quantity: u32) -> Item {
+        Item {
+            sku: sku.to_string(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn test_collects_every_failure_with_its_path() {
+        let order = Order {
+            email: " ".to_string(),
+            items: vec![item("PEN-1", 1), item("pen 2", 0), item("INK", 11)],
+        };
+
+        let errors = order.validated().unwrap_err();
+        assert_eq!(
+            errors.paths(),
+            [
+                "email",
+                "email",
+                "items[1].sku",
+                "items[1].quantity",
+                "items[2].quantity",
+            ]
+        );
+        let codes: Vec<_> = errors.for_path("email").map(|e| e.code).collect();
+        assert_eq!(codes, ["REQUIRED", "EMAIL"]);
+        assert_eq!(
+            errors
+                .for_path("items[2].quantity")
+                .next()
+                .unwrap()
+                .to_string(),
+            "items[2].quantity: must be between 1 and 10"
+        );
+    }
+
+    #[test]
+    fn test_valid_model_passes() {
+        let order = Order {
+            email: "ana@example.com".to_string(),
+            items: vec![item("PEN-1", 3)],
+        };
+
+        assert_eq!(order.validated(), Ok(()));
+    }
+
+    #[test]
+    fn test_email_rule() {
+        let rule = email();
+
+        assert!(rule.check("test@example.com"));
+        assert!(rule.check("first.last@sub.example.org"));
+        for invalid in [
+            "invalid",
+            "a@b",
+            "a.b@c",
+            "@example.com",
+            "a b@example.com",
+            "a@example.",
+        ] {
+            assert!(!rule.check(invalid), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_length_counts_characters_and_custom_rules_run() {
+        assert!(length(1, 3).check("ñññ"));
+        assert!(!length(1, 3).check(""));
+        assert!(max_length(3).check("ñññ"));
+        assert!(!max_length(3).check("abcd"));
+        assert!(at_least(1).check(&1) && !at_least(1).check(&0));
+
+        let even = custom("EVEN", "must be even", |n: &u32| n.is_multiple_of(2));
+        let mut v = Validator::new();
+        v.field("n", &3, &[even])
+            .fail("total", "MISMATCH", "does not add up");
+        let errors = v.finish().unwrap_err();
+
+        assert_eq!(errors.paths(), ["n", "total"]);
+        assert_eq!(
+            errors.to_string(),
+            "n: must be even; total: does not add up"
+        );
+        assert_eq!(errors.code(), "VALIDATION_FAILED");
+    }
+}